@@ -0,0 +1,130 @@
+#![feature(test)]
+
+extern crate test;
+
+use based::{
+    context::Viewport,
+    dom::{Document, Element, Node},
+    parser::css::{cascader::Cascader, media::MediaEnvironment, CSSParser},
+};
+use test::Bencher;
+
+const TAGS: &[&str] = &["center", "h1", "h2", "h3", "p", "span", "a", "em"];
+const CLASSES: &[&str] = &[
+    "note", "warning", "card", "title", "muted", "wide", "lead", "meta",
+];
+
+fn element(tag: &str, attributes: Vec<(String, String)>, children: Vec<Node>) -> Node {
+    Node::Element(Element {
+        tag_name: String::from(tag),
+        attributes,
+        children,
+        ..Default::default()
+    })
+}
+
+fn attribute(name: &str, value: String) -> (String, String) {
+    (String::from(name), value)
+}
+
+//a few thousand elements, mostly runs of identical siblings like a real page's paragraphs, with
+//the odd class and id sprinkled about.
+fn document() -> Document {
+    let sections = (0..200)
+        .map(|section| {
+            let heading = vec![attribute("id", format!("section-{}", section))];
+            let mut children = vec![element("h1", heading, vec![])];
+            children.extend((0..10).map(|paragraph| {
+                let mut attributes = match paragraph {
+                    0 => vec![attribute("style", format!("width: {}px", section))],
+                    _ => vec![],
+                };
+                if paragraph % 3 == 0 {
+                    let class = CLASSES[(section + paragraph) % CLASSES.len()];
+                    attributes.push(attribute("class", format!("{} c{}", class, section)));
+                }
+                let tag = TAGS[(section + paragraph) % TAGS.len()];
+                let em = element(
+                    "em",
+                    vec![attribute("class", String::from("muted"))],
+                    vec![],
+                );
+                let inner = element(tag, vec![], vec![em]);
+                element("p", attributes, vec![inner])
+            }));
+            let class = attribute("class", String::from(CLASSES[section % CLASSES.len()]));
+            element("center", vec![class], children)
+        })
+        .collect();
+    let body = element("body", vec![], sections);
+    let mut document = Document::default();
+    document.children = vec![element("html", vec![], vec![body])];
+    document
+}
+
+//a couple of thousand rules that mostly don't apply to any given element, the way a real site's
+//stylesheet is: a few tag rules, lots of classes, some ids and plenty of descendant selectors.
+fn stylesheet() -> String {
+    let mut css = String::from("* { font-size: 16px } p { margin-bottom: 1em }\n");
+    for (i, a) in TAGS.iter().enumerate() {
+        for b in TAGS {
+            css.push_str(&format!(
+                "{a} {b} {{ color: #{i}{i}{i} }} {a} > {b} {{ margin-top: {i}px }} {a} + {b} {{ \
+                 padding-left: {i}em }}\n"
+            ));
+        }
+    }
+    for (i, class) in CLASSES.iter().enumerate() {
+        let tag = TAGS[i % TAGS.len()];
+        css.push_str(&format!(
+            ".{class} {{ color: #{i}{i}{i} }} {tag}.{class} {{ width: {i}% }} .{class} > {tag} \
+             {{ margin-top: {i}px }} center.{class} .muted {{ height: {i}px }}\n"
+        ));
+    }
+    for i in 0..400 {
+        let tag = TAGS[i % TAGS.len()];
+        let class = CLASSES[i % CLASSES.len()];
+        css.push_str(&format!(
+            ".c{i} {tag} {{ color: red }} #section-{i} + p {{ color: red }} .c{i} {{ width: {i}px \
+             }} .{class}.c{i} > {tag} em {{ height: {i}px }} #section-{i} {{ padding-top: 1px \
+             }}\n"
+        ));
+    }
+    css
+}
+
+fn styled_document() -> Document {
+    let mut document = document();
+    let mut parser = CSSParser::default();
+    parser.push_raw_css(&stylesheet());
+    document.add_styles(parser.parse_stylesheets().unwrap());
+    document
+}
+
+fn bench_cascader(b: &mut Bencher, new: fn() -> Cascader) {
+    let mut document = styled_document();
+    let viewport = Viewport::new(1080, 720);
+    let media = MediaEnvironment::new(viewport, 1.);
+    b.iter(|| {
+        let mut cascader = new();
+        cascader.cascade(
+            &mut document.children,
+            &document.style,
+            viewport,
+            media,
+            &document.fonts,
+        );
+    });
+}
+
+//with the rule map, the bloom filter and style sharing.
+#[bench]
+fn bench_full_cascade(b: &mut Bencher) {
+    bench_cascader(b, Cascader::new);
+}
+
+//every rule against every element, for comparing the one above to.
+#[bench]
+fn bench_linear_cascade(b: &mut Bencher) {
+    bench_cascader(b, Cascader::linear);
+}
@@ -0,0 +1,94 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::Client;
+use thiserror::Error;
+use url::Url;
+
+//grabs the bytes behind a url, whatever scheme it happens to be. anything that loads
+//subresources (fonts, stylesheets, etc) should go through here.
+pub async fn fetch(client: &Client, url: &Url) -> Result<Vec<u8>, FetchError> {
+    match url.scheme() {
+        "file" => {
+            let path = url
+                .to_file_path()
+                .map_err(|_| FetchError::BadFilePath(url.clone()))?;
+            Ok(tokio::fs::read(path).await?)
+        }
+        "http" | "https" => Ok(client
+            .get(url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?
+            .to_vec()),
+        "data" => decode_data_url(url),
+        _ => Err(FetchError::UnsupportedScheme(url.scheme().to_string())),
+    }
+}
+
+//data:[<mediatype>][;base64],<data>
+fn decode_data_url(url: &Url) -> Result<Vec<u8>, FetchError> {
+    //the whole thing after the scheme, since the path stops at a ? that's part of the data.
+    let url = &url.as_str()["data:".len()..];
+    let url = url.split_once('#').map_or(url, |(url, _)| url);
+    let (header, data) = url.split_once(',').ok_or(FetchError::BadDataURL)?;
+    let data = percent_decode(data)?;
+    if header.to_ascii_lowercase().ends_with(";base64") {
+        let data = data
+            .into_iter()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect::<Vec<_>>();
+        STANDARD.decode(data).map_err(|_| FetchError::BadDataURL)
+    } else {
+        Ok(data)
+    }
+}
+
+fn percent_decode(data: &str) -> Result<Vec<u8>, FetchError> {
+    let bytes = data.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            let hex = bytes
+                .get(idx + 1..idx + 3)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or(FetchError::BadDataURL)?;
+            out.push(hex);
+            idx += 3;
+        } else {
+            out.push(bytes[idx]);
+            idx += 1;
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("IO Error!: {0}")]
+    IOError(#[from] std::io::Error),
+    #[error("Request failed!: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Malformed data url!")]
+    BadDataURL,
+    #[error("Can't turn {0} into a file path!")]
+    BadFilePath(Url),
+    #[error("Don't know how to fetch from {0}:// urls!")]
+    UnsupportedScheme(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_urls() {
+        let decode = |url: &str| decode_data_url(&Url::parse(url).unwrap()).unwrap();
+        assert_eq!(decode("data:,a%20b?c=d#e"), b"a b?c=d");
+        assert_eq!(decode("data:text/plain;base64,aGk/#fragment"), b"hi?");
+        assert_eq!(decode("data:text/plain;BASE64,aG k="), b"hi");
+        assert!(decode_data_url(&Url::parse("data:nope").unwrap()).is_err());
+    }
+}
@@ -7,10 +7,19 @@ use vello::{peniko::Font, SceneBuilder};
 use crate::{
     dom::Document,
     layout::LayoutInfo,
-    parser::{css::CSSParser, html::HTMLParser},
+    parser::{
+        css::{
+            import::resolve_imports,
+            media::{ColourScheme, MediaEnvironment},
+            CSSParser, StyleLevel,
+        },
+        html::HTMLParser,
+    },
     renderer::{PageRenderer, RenderInfo},
 };
 
+pub mod fetch;
+
 #[derive(Debug)]
 pub struct Context {
     html: HTMLParser,
@@ -18,9 +27,12 @@ pub struct Context {
     document: Document,
     url: Url,
     viewport: Viewport,
+    scale_factor: f64,
+    media: MediaEnvironment,
     renderer: PageRenderer,
     fonts: Vec<Font>,
     client: Client,
+    layout_dirty: bool, //the styles or the viewport changed since the last layout
 }
 
 impl Default for Context {
@@ -30,6 +42,8 @@ impl Default for Context {
             css: CSSParser::default(),
             document: Document::default(),
             viewport: Viewport::default(),
+            scale_factor: 1.,
+            media: MediaEnvironment::default(),
             renderer: PageRenderer::default(),
             url: Url::from_directory_path(std::env::current_dir().unwrap())
                 .unwrap()
@@ -37,6 +51,7 @@ impl Default for Context {
                 .unwrap(),
             fonts: Vec::new(),
 	    client: Self::make_request_client(),
+            layout_dirty: true,
         }
     }
 }
@@ -73,19 +88,62 @@ impl Context {
 
     pub fn resize(&mut self, width: usize, height: usize) {
         self.viewport.resize(width, height);
+        self.media.update(self.viewport, self.scale_factor);
+        self.restyle();
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+        self.media.update(self.viewport, self.scale_factor);
+        self.restyle();
+    }
+
+    pub fn set_colour_scheme(&mut self, colour_scheme: ColourScheme) {
+        self.media.colour_scheme = colour_scheme;
+        self.restyle();
+    }
+
+    pub fn set_reduced_motion(&mut self, reduced_motion: bool) {
+        self.media.reduced_motion = reduced_motion;
+        self.restyle();
     }
 
-    pub fn go(&mut self) {
+    //new styles mean a new layout too, which waits for the next frame so a burst of resizes
+    //only lays out once.
+    fn restyle(&mut self) {
+        self.document.cascade(self.viewport, self.media);
+        self.layout_dirty = true;
+    }
+
+    pub fn needs_layout(&self) -> bool {
+        self.layout_dirty
+    }
+
+    pub async fn go(&mut self) {
         self.html.parse(&mut self.document).unwrap();
+        let mut user_agent = CSSParser::default();
+        user_agent.push_raw_css(&std::include_str!("../../real_shit/default.css").to_string());
+        for mut style in user_agent.parse_stylesheets().unwrap() {
+            style.set_level(StyleLevel::UserAgent);
+            self.document.add_style(style);
+        }
         self.css
-            .push_raw_css(&std::include_str!("../../real_shit/default.css").to_string());
-        self.css.push_many(self.document.find_css_sources());
-        self.document
-            .add_styles(self.css.parse_stylesheets().unwrap());
-        self.document.cascade(self.viewport);
+            .push_many(self.document.find_css_sources(&self.url));
+        self.css.fetch_sources(&self.client).await;
+        let mut styles = self.css.parse_stylesheets().unwrap_or_else(|e| {
+            tracing::warn!("couldn't parse the page's stylesheets: {}", e);
+            vec![]
+        });
+        resolve_imports(&mut styles, &self.url, &self.client).await;
+        self.document.add_styles(styles);
+        self.document.load_fonts(&self.client, &self.url).await;
+        self.restyle();
     }
 
     pub fn render(&mut self, builder: &mut SceneBuilder, render_info: RenderInfo) {
+        if self.layout_dirty {
+            self.layoutify(self.scale_factor);
+        }
         self.renderer.render(
             self.viewport,
             &self.document.children,
@@ -97,12 +155,13 @@ impl Context {
 
     pub fn layoutify(&mut self, scale_factor: f64) {
         self.document.layoutify(self.viewport, scale_factor);
+        self.layout_dirty = false;
     }
 }
 
 
 //this type is awkward, i'd like to remove it at some point.
-#[derive(Debug, Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub struct Viewport {
     pub width: usize,
     pub height: usize,
@@ -124,6 +183,7 @@ impl Viewport {
             y: 0.,
             width: self.width as f64,
             height: self.height as f64,
+            definite_height: Some(self.height as f64),
             ..Default::default()
         }
     }
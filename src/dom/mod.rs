@@ -1,7 +1,23 @@
+use reqwest::Client;
+use url::Url;
+
 use crate::{
     context::Viewport,
-    layout::{text::LaidoutText, LayoutInfo},
-    parser::css::{cascader::Cascader, CSSProps, CSSSource, Style, StyleData},
+    layout::{
+        float::Floats,
+        inline::is_replaced,
+        text::{font_set::FontSet, LaidoutText},
+        LayoutInfo,
+    },
+    parser::css::{
+        cascader::Cascader,
+        computed::ComputedStyle,
+        font_face::FontFace,
+        media::{MediaEnvironment, MediaQueryList},
+        properties::ContainerType,
+        Block, CSSError, CSSParser, CSSProps, CSSSource, CSSValue, Declaration, PseudoClass, Rule,
+        Style, StyleData,
+    },
 };
 
 #[derive(Default, Debug)]
@@ -9,6 +25,12 @@ pub struct Document {
     pub style: StyleData,
     pub document_mode: DocumentMode,
     pub children: Vec<Node>,
+    pub fonts: FontSet,
+    //whatever the last cascade() was done against, so a restyle can do it again.
+    environment: Option<(Viewport, MediaEnvironment)>,
+    //kept between cascades so only dirty elements need restyling. None means the sheets or
+    //the environment changed, so everything does.
+    cascader: Option<Cascader>,
 }
 
 pub trait DOMElement {
@@ -31,43 +53,433 @@ pub trait DOMElement {
 impl Document {
     pub fn print_tree(&self) {}
 
-    pub fn find_css_sources(&self) -> Vec<CSSSource> {
+    //in document order, <link>s get resolved against the document url here.
+    pub fn find_css_sources(&self, base: &Url) -> Vec<CSSSource> {
         let mut out = Vec::with_capacity(5);
-        Self::find_css_sources_recursive(&self.children, &mut out);
+        Self::find_css_sources_recursive(&self.children, base, &mut out);
         return out;
     }
 
-    fn find_css_sources_recursive(nodes: &Vec<Node>, out: &mut Vec<CSSSource>) {
+    fn find_css_sources_recursive(nodes: &Vec<Node>, base: &Url, out: &mut Vec<CSSSource>) {
         for node in nodes {
             if let Node::Element(el) = node {
                 if el.tag_name == "style" {
                     out.push(CSSSource::Raw(el.data.clone()));
                     continue;
                 }
-                Self::find_css_sources_recursive(&el.children, out);
+                if el.tag_name == "link" {
+                    let rel = el
+                        .get_attribute("rel")
+                        .map(|r| r.to_ascii_lowercase())
+                        .unwrap_or_default();
+                    let rel = rel.split_ascii_whitespace().collect::<Vec<_>>();
+                    //TODO: alternate stylesheets
+                    if rel.contains(&"stylesheet") && !rel.contains(&"alternate") {
+                        if let Some(url) = el.get_attribute("href").and_then(|h| base.join(h).ok())
+                        {
+                            let media = el
+                                .get_attribute("media")
+                                .and_then(|m| CSSParser::parse_component_list(m).ok())
+                                .map(|m| MediaQueryList::from_components(&m))
+                                .filter(|m| !m.queries.is_empty());
+                            out.push(match media {
+                                Some(media) => {
+                                    CSSSource::Media(Box::new(CSSSource::URL(url)), media)
+                                }
+                                None => CSSSource::URL(url),
+                            });
+                        }
+                    }
+                    continue;
+                }
+                Self::find_css_sources_recursive(&el.children, base, out);
             }
         }
     }
 
     pub fn add_styles(&mut self, styles: Vec<Style>) {
         self.style.styles.extend(styles);
+        self.cascader = None;
     }
 
     pub fn add_style(&mut self, style: Style) {
         self.style.styles.push(style);
+        self.cascader = None;
+    }
+
+    //@font-face rules inside @media blocks get loaded no matter what, the same way browsers
+    //don't unload fonts when the window changes size.
+    pub async fn load_fonts(&mut self, client: &Client, base: &Url) {
+        let mut faces = vec![];
+        for style in &self.style.styles {
+            let mut style_faces = vec![];
+            Self::find_font_faces(&style.rules, &mut style_faces);
+            let base = style.url.clone().unwrap_or(base.clone());
+            faces.extend(style_faces.into_iter().map(|f| (f, base.clone())));
+        }
+        for (face, base) in faces {
+            self.fonts.load(&face, &base, client).await;
+        }
+        self.cascader = None;
+    }
+
+    fn find_font_faces(rules: &Vec<Rule>, out: &mut Vec<FontFace>) {
+        for rule in rules {
+            match rule.value {
+                Block::FontFace(ref face) => out.push(face.clone()),
+                Block::Rules(ref rules) => Self::find_font_faces(rules, out),
+                _ => {}
+            }
+        }
+    }
+
+    //restyles whatever's been marked dirty, or everything if the sheets or the environment have
+    //changed since last time.
+    pub fn cascade(&mut self, viewport: Viewport, media: MediaEnvironment) {
+        if self.environment != Some((viewport, media)) {
+            self.cascader = None;
+        }
+        self.environment = Some((viewport, media));
+        match self.cascader {
+            Some(ref mut cascader) => cascader.restyle(&mut self.children),
+            None => {
+                let mut cascader = Cascader::default();
+                cascader.cascade(
+                    &mut self.children,
+                    &self.style,
+                    viewport,
+                    media,
+                    &self.fonts,
+                );
+                self.cascader = Some(cascader);
+            }
+        }
+    }
+
+    //nothing to do if it's never been cascaded in the first place.
+    pub fn restyle(&mut self) {
+        if let Some((viewport, media)) = self.environment {
+            self.cascade(viewport, media);
+        }
+    }
+
+    //for when the sheets themselves change.
+    fn restyle_everything(&mut self) {
+        self.cascader = None;
+        self.restyle();
+    }
+
+    //document.styleSheets. these are in the order they cascade in, so the indices below
+    //point into this.
+    pub fn style_sheets(&self) -> &Vec<Style> {
+        &self.style.styles
+    }
+
+    fn style_sheet_mut(&mut self, sheet: usize) -> Result<&mut Style, CSSError> {
+        match self.style.styles.get_mut(sheet) {
+            Some(style) => Ok(style),
+            None => do yeet CSSError::NoSuchSheet(sheet),
+        }
+    }
+
+    pub fn insert_rule(
+        &mut self,
+        sheet: usize,
+        parent: &[usize],
+        rule: &str,
+        index: usize,
+    ) -> Result<usize, CSSError> {
+        let index = self
+            .style_sheet_mut(sheet)?
+            .insert_rule(parent, &rule.to_string(), index)?;
+        self.restyle_everything();
+        Ok(index)
+    }
+
+    pub fn delete_rule(&mut self, sheet: usize, rule: &[usize]) -> Result<Rule, CSSError> {
+        let rule = self.style_sheet_mut(sheet)?.delete_rule(rule)?;
+        self.restyle_everything();
+        Ok(rule)
+    }
+
+    pub fn set_declaration(
+        &mut self,
+        sheet: usize,
+        rule: &[usize],
+        name: &str,
+        value: &str,
+        important: bool,
+    ) -> Result<(), CSSError> {
+        self.style_sheet_mut(sheet)?
+            .set_declaration(rule, name, value, important)?;
+        self.restyle_everything();
+        Ok(())
+    }
+
+    pub fn remove_declaration(
+        &mut self,
+        sheet: usize,
+        rule: &[usize],
+        name: &str,
+    ) -> Result<Option<Declaration>, CSSError> {
+        let removed = self.style_sheet_mut(sheet)?.remove_declaration(rule, name);
+        if removed.is_some() {
+            self.restyle_everything();
+        }
+        Ok(removed)
+    }
+
+    pub fn element_mut(&mut self, coordinate: &DOMCoordinate) -> Option<&mut Element> {
+        let (first, rest) = coordinate.indices.split_first()?;
+        let Some(Node::Element(element)) = self.children.get_mut(*first) else {
+            return None;
+        };
+        rest.iter()
+            .try_fold(element, |element, i| match element.children.get_mut(*i) {
+                Some(Node::Element(child)) => Some(child),
+                _ => None,
+            })
+    }
+
+    //the element and everything under it get restyled next cascade, and everything above it
+    //knows to look.
+    pub fn invalidate(&mut self, coordinate: &DOMCoordinate) {
+        let mut nodes = &mut self.children;
+        for i in &coordinate.indices {
+            let Some(Node::Element(element)) = nodes.get_mut(*i) else {
+                return;
+            };
+            element.descendants_dirty = true;
+            nodes = &mut element.children;
+        }
+        if let Some(element) = self.element_mut(coordinate) {
+            element.style_dirty = true;
+        }
+    }
+
+    fn uses_siblings(&self) -> bool {
+        self.cascader
+            .as_ref()
+            .map_or(false, Cascader::uses_siblings)
+    }
+
+    //the next element after the given spot in the parent, if there is one. that's the only
+    //sibling a + selector could change for.
+    fn invalidate_next_sibling(&mut self, parent: &DOMCoordinate, index: usize) {
+        if !self.uses_siblings() {
+            return;
+        }
+        let siblings = match self.element(parent) {
+            Some(parent) => &parent.children,
+            None if parent.indices.is_empty() => &self.children,
+            None => return,
+        };
+        let next = siblings[index.min(siblings.len())..]
+            .iter()
+            .find_map(|node| match node {
+                Node::Element(element) => Some(element.coordinate.clone()),
+                _ => None,
+            });
+        if let Some(next) = next {
+            self.invalidate(&next);
+        }
+    }
+
+    pub fn set_attribute(&mut self, coordinate: &DOMCoordinate, name: &str, value: &str) {
+        let Some(element) = self.element_mut(coordinate) else {
+            return;
+        };
+        let old = match element
+            .attributes
+            .iter_mut()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        {
+            Some((_, old)) if old == value => return,
+            Some((_, old)) => Some(std::mem::replace(old, value.to_string())),
+            None => {
+                element
+                    .attributes
+                    .push((name.to_ascii_lowercase(), value.to_string()));
+                None
+            }
+        };
+        self.invalidate_attribute(coordinate, name, old.as_deref(), Some(value));
+    }
+
+    pub fn remove_attribute(&mut self, coordinate: &DOMCoordinate, name: &str) -> Option<String> {
+        let element = self.element_mut(coordinate)?;
+        let index = element
+            .attributes
+            .iter()
+            .position(|(n, _)| n.eq_ignore_ascii_case(name))?;
+        let (_, value) = element.attributes.remove(index);
+        self.invalidate_attribute(coordinate, name, Some(&value), None);
+        Some(value)
+    }
+
+    //style="" doesn't change what anything matches, so it's only the element and what's in it.
+    fn invalidate_attribute(
+        &mut self,
+        coordinate: &DOMCoordinate,
+        name: &str,
+        old: Option<&str>,
+        new: Option<&str>,
+    ) {
+        let matters = match self.cascader {
+            Some(ref cascader) => cascader.depends_on_attribute(name, old, new),
+            None => false, //everything's getting restyled anyway
+        };
+        match matters {
+            true if name.eq_ignore_ascii_case("style") => self.invalidate(coordinate),
+            true => self.invalidate_matching(coordinate),
+            false => {}
+        }
+    }
+
+    //for when what the element itself matches might have changed. that's it and everything
+    //under it, and the sibling after it if there are any + selectors.
+    fn invalidate_matching(&mut self, coordinate: &DOMCoordinate) {
+        self.invalidate(coordinate);
+        if let Some((index, parent)) = coordinate.indices.split_last() {
+            let parent = DOMCoordinate {
+                indices: parent.to_vec(),
+            };
+            self.invalidate_next_sibling(&parent, index + 1);
+        }
+    }
+
+    //hovering and so on only happen to the one element given, so hovering over something means
+    //setting it on all its ancestors too.
+    pub fn set_state(&mut self, coordinate: &DOMCoordinate, state: PseudoClass, on: bool) {
+        let Some(element) = self.element_mut(coordinate) else {
+            return;
+        };
+        if element.state.contains(&state) == on {
+            return;
+        }
+        match on {
+            true => element.state.push(state),
+            false => element.state.retain(|s| *s != state),
+        }
+        let matters = match self.cascader {
+            Some(ref cascader) => cascader.depends_on_state(state),
+            None => false,
+        };
+        if matters {
+            self.invalidate_matching(coordinate);
+        }
+    }
+
+    //an empty parent is the document itself. the new node gets styled next cascade.
+    pub fn insert_node(
+        &mut self,
+        parent: &DOMCoordinate,
+        index: usize,
+        node: Node,
+    ) -> Option<DOMCoordinate> {
+        let siblings = match self.element_mut(parent) {
+            Some(parent) => &mut parent.children,
+            None if parent.indices.is_empty() => &mut self.children,
+            None => return None,
+        };
+        if index > siblings.len() {
+            return None;
+        }
+        siblings.insert(index, node);
+        Self::renumber(&mut siblings[index..], parent, index);
+        let mut coordinate = parent.clone();
+        coordinate.indices.push(index);
+        if let Node::Element(_) = siblings[index] {
+            self.invalidate(&coordinate);
+        }
+        self.invalidate_next_sibling(parent, index + 1);
+        Some(coordinate)
+    }
+
+    pub fn remove_node(&mut self, coordinate: &DOMCoordinate) -> Option<Node> {
+        let (index, parent) = coordinate.indices.split_last()?;
+        let parent = DOMCoordinate {
+            indices: parent.to_vec(),
+        };
+        let siblings = match self.element_mut(&parent) {
+            Some(parent) => &mut parent.children,
+            None if parent.indices.is_empty() => &mut self.children,
+            None => return None,
+        };
+        if *index >= siblings.len() {
+            return None;
+        }
+        let node = siblings.remove(*index);
+        Self::renumber(&mut siblings[*index..], &parent, *index);
+        self.invalidate_next_sibling(&parent, *index);
+        Some(node)
     }
 
-    pub fn cascade(&mut self, viewport: Viewport) {
-        Cascader::default().cascade(&mut self.children, &self.style, viewport);
+    //everything after an insert or a remove has moved over.
+    fn renumber(nodes: &mut [Node], parent: &DOMCoordinate, start: usize) {
+        for (i, node) in nodes.iter_mut().enumerate() {
+            if let Node::Element(element) = node {
+                let mut coordinate = parent.clone();
+                coordinate.indices.push(start + i);
+                Self::renumber(&mut element.children, &coordinate, 0);
+                element.coordinate = coordinate;
+            }
+        }
+    }
+
+    pub fn element(&self, coordinate: &DOMCoordinate) -> Option<&Element> {
+        let (first, rest) = coordinate.indices.split_first()?;
+        let Some(Node::Element(element)) = self.children.get(*first) else {
+            return None;
+        };
+        rest.iter()
+            .try_fold(element, |element, i| match element.children.get(*i) {
+                Some(Node::Element(child)) => Some(child),
+                _ => None,
+            })
     }
 
+    //getComputedStyle, more or less. only means anything after cascade(), and the used values
+    //only show up after layoutify().
+    pub fn computed_style(&self, coordinate: &DOMCoordinate) -> Option<ComputedStyle> {
+        self.element(coordinate).map(Element::computed_style)
+    }
+
+    //@container rules were asked about the sizes from the last layout, so any container that's
+    //come out a different size gets what's in it restyled, and it's all laid out again. only a
+    //few times, in case a style and a size keep flipping each other back and forth.
     pub fn layoutify(&mut self, viewport: Viewport, scale_factor: f64) {
-        for child in &mut self.children {
-            if let Node::Element(el) = child {
-                el.layout(Viewport::default().into_layout(), scale_factor);
+        for _ in 0..3 {
+            for child in &mut self.children {
+                if let Node::Element(el) = child {
+                    el.layout(viewport.into_layout(), &mut Floats::default(), scale_factor);
+                }
             }
+            let queries = self
+                .cascader
+                .as_ref()
+                .map_or(false, Cascader::uses_containers);
+            if !queries || !Self::find_resized(&mut self.children) {
+                break;
+            }
+            self.restyle();
         }
     }
+
+    //marks the way down to every container that isn't the size what's in it was styled for.
+    fn find_resized(nodes: &mut [Node]) -> bool {
+        let mut found = false;
+        for node in nodes {
+            if let Node::Element(el) = node {
+                let resized = el.is_size_container() && el.queried_size != el.laid_out_size();
+                if Self::find_resized(&mut el.children) || resized {
+                    el.descendants_dirty = true;
+                    found = true;
+                }
+            }
+        }
+        found
+    }
 }
 
 impl DOMElement for Document {
@@ -108,6 +520,11 @@ impl DOMElement for Document {
             data: String::new(),
             attributes,
             layout_info: LayoutInfo::default(),
+            fragments: vec![],
+            state: vec![],
+            style_dirty: true,
+            descendants_dirty: false,
+            queried_size: None,
         }));
         return coordinate;
     }
@@ -154,6 +571,36 @@ pub struct Element {
     pub children: Vec<Node>,
     pub attributes: Vec<(String, String)>,
     pub layout_info: LayoutInfo,
+    pub fragments: Vec<LayoutInfo>, //one for each line an inline ended up on
+    pub state: Vec<PseudoClass>,    //hovered, focused and so on
+    pub style_dirty: bool,          //needs restyling, along with everything under it
+    pub descendants_dirty: bool,    //something under it does
+    //what @container rules were told its size was, the last time what's in it was styled
+    pub queried_size: Option<(f64, f64)>,
+}
+
+impl Element {
+    pub fn get_attribute(&self, name: &str) -> Option<&String> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    pub fn computed_style(&self) -> ComputedStyle {
+        ComputedStyle::new(&self.css, &self.layout_info, is_replaced(self))
+    }
+
+    //its content box, if it's been laid out.
+    pub fn laid_out_size(&self) -> Option<(f64, f64)> {
+        let info = &self.layout_info;
+        info.laid_out.then_some((info.width, info.height))
+    }
+
+    //whether @container rules can ask about it.
+    pub fn is_size_container(&self) -> bool {
+        matches!(self.css.container_type, CSSValue::Value(t) if t != ContainerType::Normal)
+    }
 }
 
 impl DOMElement for Element {
@@ -174,6 +621,11 @@ impl DOMElement for Element {
             data: String::new(),
             attributes,
             layout_info: LayoutInfo::default(),
+            fragments: vec![],
+            state: vec![],
+            style_dirty: true,
+            descendants_dirty: false,
+            queried_size: None,
         }));
         return coordinate;
     }
@@ -222,3 +674,269 @@ pub enum Node {
 pub struct DOMCoordinate {
     indices: Vec<usize>,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        layout::test_util::find,
+        parser::{
+            css::{properties::Colour, CSSParser, CSSValue, PseudoClass},
+            html::HTMLParser,
+        },
+    };
+
+    fn colour(document: &Document, coordinate: &DOMCoordinate) -> String {
+        document
+            .computed_style(coordinate)
+            .unwrap()
+            .get_property_value("color")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_incremental_restyle() {
+        let mut html = HTMLParser::default();
+        html.load_from_whatever(&mut Cursor::new(
+            "<!DOCTYPE html><html><head></head><body><h1>a</h1><p>b</p><h2>c</h2></body></html>",
+        ))
+        .unwrap();
+        let mut document = Document::default();
+        html.parse(&mut document).unwrap();
+        let mut css = CSSParser::default();
+        css.push_raw_css(&String::from("p { color: red } h1 + p { color: green }"));
+        document.add_styles(css.parse_stylesheets().unwrap());
+        let viewport = Viewport::new(800, 600);
+        let media = MediaEnvironment::new(viewport, 1.);
+        document.cascade(viewport, media);
+
+        let Node::Element(ref html) = document.children[1] else {
+            panic!();
+        };
+        let Some(Node::Element(ref body)) = html.children.last() else {
+            panic!();
+        };
+        let [h1, p, h2] = [0, 1, 2].map(|i| {
+            let mut coordinate = body.coordinate.clone();
+            coordinate.indices.push(i);
+            coordinate
+        });
+        assert_eq!(colour(&document, &p), "rgb(0, 128, 0)");
+        //scribble on the styles directly, anything that gets restyled loses the scribbles.
+        for coordinate in [&h1, &p, &h2] {
+            document.element_mut(coordinate).unwrap().css.color =
+                CSSValue::Value(Colour::new(1, 2, 3, 255));
+        }
+        //nothing looks at ids, so this doesn't restyle anything.
+        document.set_attribute(&h2, "id", "shmeep");
+        document.set_attribute(&h1, "style", "color: blue");
+        document.cascade(viewport, media);
+        assert_eq!(colour(&document, &h1), "rgb(0, 0, 255)");
+        assert_eq!(colour(&document, &p), "rgb(1, 2, 3)");
+        assert_eq!(colour(&document, &h2), "rgb(1, 2, 3)");
+
+        //without the h1 in front of it, the p's only red.
+        document.remove_node(&h1).unwrap();
+        document.cascade(viewport, media);
+        assert_eq!(colour(&document, &h1), "rgb(255, 0, 0)");
+        assert_eq!(document.element(&h1).unwrap().tag_name, "p");
+        assert_eq!(document.element(&p).unwrap().coordinate, p);
+        assert_eq!(colour(&document, &p), "rgb(1, 2, 3)");
+    }
+
+    #[test]
+    fn test_descendant_selectors() {
+        let mut html = HTMLParser::default();
+        html.load_from_whatever(&mut Cursor::new(
+            "<!DOCTYPE html><html><head></head><body><center><h1><p>a</p></h1><p>b</p><p>c</p>\
+             </center><p>d</p></body></html>",
+        ))
+        .unwrap();
+        let mut document = Document::default();
+        html.parse(&mut document).unwrap();
+        let mut css = CSSParser::default();
+        css.push_raw_css(&String::from(
+            "p { color: red } body p { color: blue } center p { color: green } h1 > p { color: \
+             purple } body > * > * > p { color: #ff0 }",
+        ));
+        document.add_styles(css.parse_stylesheets().unwrap());
+        let viewport = Viewport::new(800, 600);
+        document.cascade(viewport, MediaEnvironment::new(viewport, 1.));
+
+        let Node::Element(ref html) = document.children[1] else {
+            panic!();
+        };
+        let Some(Node::Element(ref body)) = html.children.last() else {
+            panic!();
+        };
+        let at = |indices: &[usize]| {
+            let mut coordinate = body.coordinate.clone();
+            coordinate.indices.extend(indices);
+            coordinate
+        };
+        assert_eq!(colour(&document, &at(&[0, 0, 0])), "rgb(255, 255, 0)");
+        assert_eq!(colour(&document, &at(&[0, 1])), "rgb(0, 128, 0)");
+        //shared with the p before it.
+        assert_eq!(colour(&document, &at(&[0, 2])), "rgb(0, 128, 0)");
+        assert_eq!(colour(&document, &at(&[1])), "rgb(0, 0, 255)");
+    }
+
+    #[test]
+    fn test_class_invalidation() {
+        let mut html = HTMLParser::default();
+        html.load_from_whatever(&mut Cursor::new(
+            "<!DOCTYPE html><html><head></head><body><center><p>a</p></center><p>b</p><p>c</p>\
+             </body></html>",
+        ))
+        .unwrap();
+        let mut document = Document::default();
+        html.parse(&mut document).unwrap();
+        let mut css = CSSParser::default();
+        css.push_raw_css(&String::from(
+            "p { color: red } .hot p { color: green } .hot + p { color: blue } #cold { color: \
+             #010203 } center:hover > p { color: purple }",
+        ));
+        document.add_styles(css.parse_stylesheets().unwrap());
+        let viewport = Viewport::new(800, 600);
+        let media = MediaEnvironment::new(viewport, 1.);
+        document.cascade(viewport, media);
+
+        let Node::Element(ref html) = document.children[1] else {
+            panic!();
+        };
+        let Some(Node::Element(ref body)) = html.children.last() else {
+            panic!();
+        };
+        let body = body.coordinate.clone();
+        let at = |indices: &[usize]| {
+            let mut coordinate = body.clone();
+            coordinate.indices.extend(indices);
+            coordinate
+        };
+        let (center, inner, b, c) = (at(&[0]), at(&[0, 0]), at(&[1]), at(&[2]));
+        let dirty = |document: &Document| {
+            [&body, &center, &inner, &b, &c].map(|coordinate| {
+                let element = document.element(coordinate).unwrap();
+                (element.style_dirty, element.descendants_dirty)
+            })
+        };
+        let clean = [(false, false); 5];
+        //no selector looks for these.
+        document.set_attribute(&c, "class", "lukewarm");
+        document.set_attribute(&c, "id", "tepid");
+        document.set_attribute(&c, "title", "hot");
+        assert_eq!(dirty(&document), clean);
+
+        for coordinate in [&center, &inner, &b, &c] {
+            document.element_mut(coordinate).unwrap().css.color =
+                CSSValue::Value(Colour::new(1, 2, 3, 255));
+        }
+        //the center and what's in it, and the p after it for the + selector.
+        document.set_attribute(&center, "class", "big hot");
+        assert_eq!(
+            dirty(&document),
+            [
+                (false, true),
+                (true, true),
+                (false, false),
+                (true, true),
+                (false, false)
+            ]
+        );
+        document.cascade(viewport, media);
+        assert_eq!(dirty(&document), clean);
+        assert_eq!(colour(&document, &inner), "rgb(0, 128, 0)");
+        assert_eq!(colour(&document, &b), "rgb(0, 0, 255)");
+        assert_eq!(colour(&document, &c), "rgb(1, 2, 3)");
+
+        //only classes that come or go count.
+        document.set_attribute(&center, "class", "hot");
+        assert_eq!(dirty(&document), clean);
+        document.remove_attribute(&center, "class");
+        document.cascade(viewport, media);
+        assert_eq!(colour(&document, &inner), "rgb(255, 0, 0)");
+        assert_eq!(colour(&document, &b), "rgb(255, 0, 0)");
+        assert_eq!(colour(&document, &c), "rgb(1, 2, 3)");
+
+        //states work the same way.
+        document.set_state(&c, PseudoClass::Focus, true);
+        assert_eq!(dirty(&document), clean);
+        document.set_state(&center, PseudoClass::Hover, true);
+        assert!(document.element(&center).unwrap().style_dirty);
+        document.cascade(viewport, media);
+        assert_eq!(colour(&document, &inner), "rgb(128, 0, 128)");
+        document.set_state(&center, PseudoClass::Hover, false);
+        document.cascade(viewport, media);
+        assert_eq!(colour(&document, &inner), "rgb(255, 0, 0)");
+    }
+
+    #[test]
+    fn test_link_media() {
+        let mut html = HTMLParser::default();
+        html.load_from_whatever(&mut Cursor::new(
+            "<!DOCTYPE html><html><head><link rel=\"stylesheet\" href=\"narrow.css\" \
+             media=\"(max-width: 600px)\"><link rel=\"stylesheet\" href=\"all.css\" media=\"\">\
+             </head><body><p>a</p></body></html>",
+        ))
+        .unwrap();
+        let mut document = Document::default();
+        html.parse(&mut document).unwrap();
+        let base = Url::parse("https://shmeep.org/").unwrap();
+        let sources = document.find_css_sources(&base);
+        assert_eq!(sources.len(), 2);
+        assert!(matches!(&sources[1], CSSSource::URL(_)));
+        let CSSSource::Media(_, ref media) = sources[0] else {
+            panic!();
+        };
+        //stands in for whatever narrow.css would have said.
+        let mut css = CSSParser::default();
+        css.push_many(vec![CSSSource::Media(
+            Box::new(CSSSource::Raw(String::from("p { color: red }"))),
+            media.clone(),
+        )]);
+        document.add_styles(css.parse_stylesheets().unwrap());
+        let colour = |document: &Document| {
+            let p = find(&document.children, "p").unwrap();
+            p.computed_style().get_property_value("color").unwrap()
+        };
+        let viewport = Viewport::new(800, 600);
+        document.cascade(viewport, MediaEnvironment::new(viewport, 1.));
+        assert_eq!(colour(&document), "rgb(0, 0, 0)");
+        let viewport = Viewport::new(500, 600);
+        document.cascade(viewport, MediaEnvironment::new(viewport, 1.));
+        assert_eq!(colour(&document), "rgb(255, 0, 0)");
+    }
+
+    #[tokio::test]
+    async fn test_missing_file_link() {
+        let dir = std::env::temp_dir().join(format!("based-link-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("there.css"), "p { color: red }").unwrap();
+        let mut html = HTMLParser::default();
+        html.load_from_whatever(&mut Cursor::new(
+            "<!DOCTYPE html><html><head><link rel=\"stylesheet\" href=\"missing.css\">\
+             <link rel=\"stylesheet\" href=\"there.css\"></head><body><p>a</p></body></html>",
+        ))
+        .unwrap();
+        let mut document = Document::default();
+        html.parse(&mut document).unwrap();
+        let base = Url::from_file_path(dir.join("index.html")).unwrap();
+        let mut css = CSSParser::default();
+        css.push_many(document.find_css_sources(&base));
+        css.fetch_sources(&Client::new()).await;
+        //the missing one's just dropped, and the one after it still loads.
+        let styles = css.parse_stylesheets().unwrap();
+        assert_eq!(styles.len(), 1);
+        document.add_styles(styles);
+        let viewport = Viewport::new(800, 600);
+        document.cascade(viewport, MediaEnvironment::new(viewport, 1.));
+        let p = find(&document.children, "p").unwrap();
+        assert_eq!(
+            p.computed_style().get_property_value("color").unwrap(),
+            "rgb(255, 0, 0)"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+use super::{inline::is_replaced, LayoutInfo};
+use crate::{
+    dom::Element,
+    parser::css::{
+        properties::{Clear, DisplayOutside, Float},
+        CSSValue,
+    },
+};
+
+impl Element {
+    pub fn float(&self) -> Float {
+        match self.css.float {
+            CSSValue::Value(float) => float,
+            _ => Float::None,
+        }
+    }
+
+    pub fn clear(&self) -> Clear {
+        match self.css.clear {
+            CSSValue::Value(clear) => clear,
+            _ => Clear::None,
+        }
+    }
+
+    //boxless ones don't count, and absolutely positioned ones have already had theirs taken away.
+    pub fn is_floated(&self) -> bool {
+        self.float() != Float::None
+            && !matches!(
+                self.display().outside,
+                DisplayOutside::None | DisplayOutside::Contents
+            )
+    }
+
+    //floats are sized like inline-blocks, and laid out up against the origin the same way. they
+    //get moved to wherever they're put once that's been worked out.
+    pub(crate) fn layout_float(&mut self, container: LayoutInfo, scale_factor: f64) {
+        self.layout_inline_block(container, scale_factor);
+        //an image has nothing in it to be shrunk to fit, so it's just however big it is.
+        if is_replaced(self) {
+            let (width, height) = self.replaced_size(container);
+            self.layout_info.width = width;
+            self.layout_info.height = height;
+        }
+    }
+}
+
+//a float's margin box, once it's been put somewhere.
+#[derive(Debug, Clone, Copy)]
+struct Placed {
+    side: Float,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+//every float in a block formatting context so far, in the same coordinates as layout info.
+#[derive(Debug, Default)]
+pub struct Floats {
+    placed: Vec<Placed>,
+}
+
+impl Floats {
+    pub fn len(&self) -> usize {
+        self.placed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.placed.is_empty()
+    }
+
+    //forgets everything from the from'th float on, for when whatever put them there gets laid
+    //out again.
+    pub fn truncate(&mut self, from: usize) {
+        self.placed.truncate(from);
+    }
+
+    //moves everything from the from'th float on, along with whatever they're in.
+    pub fn shift(&mut self, from: usize, dy: f64) {
+        for float in &mut self.placed[from..] {
+            float.y += dy;
+        }
+    }
+
+    //what's left between left and right once the floats anywhere from top to bottom have taken
+    //their bit. something with no height still can't go where a float is.
+    pub fn available(&self, top: f64, bottom: f64, left: f64, right: f64) -> (f64, f64) {
+        self.placed
+            .iter()
+            .filter(|float| float.y + float.height > top && (float.y < bottom || float.y <= top))
+            .fold((left, right), |(left, right), float| match float.side {
+                Float::Left => (left.max(float.x + float.width), right),
+                _ => (left, right.min(float.x)),
+            })
+    }
+
+    //the closest any float ends below y, which is where there might be more room.
+    pub fn next_edge(&self, y: f64) -> Option<f64> {
+        self.placed
+            .iter()
+            .map(|float| float.y + float.height)
+            .filter(|bottom| *bottom > y)
+            .min_by(f64::total_cmp)
+    }
+
+    //how far down something has to go to be clear of the floats it's meant to go under.
+    pub fn clearance(&self, clear: Clear) -> Option<f64> {
+        self.placed
+            .iter()
+            .filter(|float| match clear {
+                Clear::None => false,
+                Clear::Left => float.side == Float::Left,
+                Clear::Right => float.side == Float::Right,
+                Clear::Both => true,
+            })
+            .map(|float| float.y + float.height)
+            .max_by(f64::total_cmp)
+    }
+
+    //the bottom of the lowest one, which a block formatting context's root grows to fit.
+    pub fn bottom(&self) -> Option<f64> {
+        self.clearance(Clear::Both)
+    }
+
+    //puts a margin box this big as far up as it can go from y, then as far over to its side as
+    //it can, without going above any float that came before it or overlapping any. left and
+    //right are the container's edges. gives back where it went.
+    pub fn place(
+        &mut self,
+        side: Float,
+        (width, height): (f64, f64),
+        y: f64,
+        left: f64,
+        right: f64,
+    ) -> (f64, f64) {
+        let mut y = self.placed.iter().map(|float| float.y).fold(y, f64::max);
+        let (x0, x1) = loop {
+            let (x0, x1) = self.available(y, y + height, left, right);
+            //one that's too wide for the container goes in anyway, once it's past everything.
+            if width <= x1 - x0 || (x0, x1) == (left, right) {
+                break (x0, x1);
+            }
+            match self.next_edge(y) {
+                Some(edge) => y = edge,
+                None => break (x0, x1),
+            }
+        };
+        let x = match side {
+            Float::Right => x1 - width,
+            _ => x0,
+        };
+        self.placed.push(Placed {
+            side,
+            x,
+            y,
+            width,
+            height,
+        });
+        (x, y)
+    }
+}
@@ -0,0 +1,1114 @@
+use std::ops::Range;
+
+use font_types::F2Dot14;
+
+use super::{
+    float::Floats,
+    text::{
+        font_set::FontChain, FontGlyph, FontMetrics, LaidoutGlyph, LaidoutText, TextLayoutifier,
+    },
+    CollapsedMargin, LayoutInfo,
+};
+use crate::{
+    dom::{Element, Node},
+    parser::css::{
+        properties::{
+            Colour, Dimensionality, DisplayInside, DisplayOutside, Float, TextAlign, VerticalAlign,
+        },
+        CSSProps, CSSValue,
+    },
+};
+
+//text and inline elements in a row, or anything else that doesn't need a block of its own.
+pub fn is_inline_level(node: &Node) -> bool {
+    match node {
+        Node::Element(el) => matches!(el.display().outside, DisplayOutside::Inline),
+        Node::DocumentType(_) | Node::PhantomBox(_) => false,
+        _ => true,
+    }
+}
+
+//things with a size of their own that sit on a line as one piece.
+pub(crate) fn is_atomic(el: &Element) -> bool {
+    is_replaced(el) || matches!(el.display().inside, DisplayInside::FlowRoot)
+}
+
+pub(crate) fn is_replaced(el: &Element) -> bool {
+    el.tag_name == "img"
+}
+
+impl Element {
+    //the content box of a replaced element. without a size from css it's whatever the attributes
+    //say, since we never actually load the image.
+    pub(crate) fn replaced_size(&self, container: LayoutInfo) -> (f64, f64) {
+        //percentages are all of the container's width, even the vertical ones.
+        let length = |d: &CSSValue<Dimensionality>| match d {
+            CSSValue::Value(d) => d.resolve(Some(container.width)).unwrap_or(0.),
+            _ => 0.,
+        };
+        let css = &self.css;
+        let attribute = |name| {
+            self.get_attribute(name)
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .unwrap_or(0.)
+        };
+        let (top, left, right, bottom) = self.border_widths();
+        let across = length(&css.padding_left) + length(&css.padding_right) + left + right;
+        let down = length(&css.padding_top) + length(&css.padding_bottom) + top + bottom;
+        let (width_basis, height_basis) = (Some(container.width), container.definite_height);
+        let width = match &css.width {
+            CSSValue::Value(width) => width.resolve(width_basis),
+            _ => None,
+        }
+        .map_or_else(
+            || attribute("width"),
+            |width| self.content_size(width, across),
+        );
+        let height = match &css.height {
+            CSSValue::Value(height) => height.resolve(height_basis),
+            _ => None,
+        }
+        .map_or_else(
+            || attribute("height"),
+            |height| self.content_size(height, down),
+        );
+        (
+            self.clamp_size(width, &css.min_width, &css.max_width, width_basis, across),
+            self.clamp_size(height, &css.min_height, &css.max_height, height_basis, down),
+        )
+    }
+}
+
+//an inline element, or the block container itself, which is box 0. that one's never opened or
+//closed, but it's what everything's aligned against and its font is the line's strut.
+#[derive(Debug)]
+struct InlineBox {
+    parent: usize,
+    metrics: FontMetrics,
+    vertical_align: VerticalAlign,
+    margin: (f64, f64, f64, f64), //top, left, right, bottom, like LayoutInfo
+    padding: (f64, f64, f64, f64),
+    border: (f64, f64, f64, f64),
+    size: Option<(f64, f64)>, //content box of an atomic one
+    baseline: Option<f64>,    //of an inline-block's last line, from the top of its content box
+}
+
+impl InlineBox {
+    //from the baseline up, and from it down.
+    fn ascent_descent(&self) -> (f64, f64) {
+        let Some((_, height)) = self.size else {
+            return (self.metrics.ascent, self.metrics.descent);
+        };
+        let top = self.margin.0 + self.border.0 + self.padding.0;
+        let bottom = self.padding.3 + self.border.3 + self.margin.3;
+        match self.baseline {
+            Some(baseline) => (top + baseline, height - baseline + bottom),
+            //no baseline of their own, so it's the bottom margin edge.
+            None => (top + height + bottom, 0.),
+        }
+    }
+
+    //what it takes up on the line. inline boxes get their half leading on both sides, line-height
+    //being normal.
+    fn extent(&self, baseline: f64) -> (f64, f64) {
+        let (ascent, descent) = self.ascent_descent();
+        let half_leading = match self.size {
+            Some(_) => 0.,
+            None => self.metrics.line_gap / 2.,
+        };
+        (
+            baseline - ascent - half_leading,
+            baseline + descent + half_leading,
+        )
+    }
+
+    fn line_height(&self) -> f64 {
+        let (ascent, descent) = self.ascent_descent();
+        ascent + descent + self.metrics.line_gap
+    }
+}
+
+#[derive(Debug)]
+enum Item {
+    Glyph(usize, FontGlyph), //the text run it's from
+    Open(usize),
+    Close(usize),
+    Atomic(usize),
+    Float(usize), //takes up no room on the line, so it's wherever it was in the source
+    Block(usize), //inside an inline, so the lines stop for it and carry on under it
+}
+
+//a float somewhere in the run. it's already been laid out, so its size is known.
+#[derive(Debug)]
+struct FloatBox {
+    side: Float,
+    size: (f64, f64),             //of its margin box
+    position: Option<(f64, f64)>, //where its margin box went, once it's been put somewhere
+}
+
+#[derive(Debug)]
+struct TextRun {
+    owner: usize,
+    text: String,
+    font: FontChain,
+    font_size: f64,
+    colour: Colour,
+    axes: Option<Vec<F2Dot14>>,
+    glyphs: Vec<LaidoutGlyph>,
+}
+
+//a run of inline-level nodes in a block container, laid out into line boxes. it's everything
+//between two blocks, so a container with both gets one of these for each run, as if they'd been
+//wrapped in anonymous blocks.
+#[derive(Debug)]
+pub struct InlineLayout<'a> {
+    container_css: &'a CSSProps,
+    container: LayoutInfo,
+    scale_factor: f64,
+    boxes: Vec<InlineBox>,
+    fragments: Vec<Vec<LayoutInfo>>, //of every box, one per line it's on
+    runs: Vec<TextRun>,
+    floats: &'a mut Floats, //everything in the block formatting context, this run's included
+    float_boxes: Vec<FloatBox>,
+    blocks: usize, //how many there are inside inlines
+    items: Vec<Item>,
+    after_space: bool, //the last thing collected was a collapsible space
+    last_baseline: Option<f64>,
+}
+
+impl<'a> InlineLayout<'a> {
+    pub fn new(
+        container_css: &'a CSSProps,
+        container: LayoutInfo,
+        floats: &'a mut Floats,
+        scale_factor: f64,
+    ) -> Self {
+        let root = InlineBox {
+            parent: 0,
+            metrics: TextLayoutifier::new(container_css, "", scale_factor).metrics(),
+            vertical_align: VerticalAlign::Baseline,
+            margin: Default::default(),
+            padding: Default::default(),
+            border: Default::default(),
+            size: None,
+            baseline: None,
+        };
+        Self {
+            container_css,
+            container,
+            scale_factor,
+            boxes: vec![root],
+            fragments: vec![vec![]],
+            runs: vec![],
+            floats,
+            float_boxes: vec![],
+            blocks: 0,
+            items: vec![],
+            after_space: true,
+            last_baseline: None,
+        }
+    }
+
+    //lays out the nodes from the top of the container's content so far. returns how tall the
+    //lines came out, and where the last one's baseline is if there were any.
+    pub fn lay_it_out(mut self, nodes: &mut [(&CSSProps, &mut Node)]) -> (f64, Option<f64>) {
+        for (_, node) in nodes.iter_mut() {
+            self.lay_out_atomics(node);
+        }
+        for (css, node) in nodes.iter() {
+            self.collect(node, css, 0);
+        }
+        let mut blocks = vec![];
+        for (_, node) in nodes.iter_mut() {
+            Self::blocks_in(node, &mut blocks);
+        }
+        let mut y = self.container.y + self.container.content_height;
+        let mut open = vec![]; //boxes carried over from the last line
+        let mut start = 0;
+        while start < self.items.len() {
+            //the lines before a block are as good as in an anonymous block of their own.
+            let stop = self.items[start..]
+                .iter()
+                .position(|item| matches!(item, Item::Block(_)))
+                .map_or(self.items.len(), |i| start + i);
+            while start < stop {
+                let (end, later) = match self.break_line(start, stop, y) {
+                    Ok(line) => line,
+                    //nothing fits beside the floats here, so it goes under the next one to end.
+                    Err(edge) => {
+                        y = edge;
+                        continue;
+                    }
+                };
+                let room = self.room(y);
+                y += self.place_line(start..end, &mut open, y, room, end == stop);
+                //floats that didn't fit next to the line go under it.
+                for float in later {
+                    self.place_float(float, y);
+                }
+                start = end;
+            }
+            if let Some(&Item::Block(block)) = self.items.get(stop) {
+                y = self.lay_out_block(blocks[block], y);
+                start = stop + 1;
+            }
+        }
+        let (mut run, mut b, mut float) = (0, 1, 0);
+        for (_, node) in nodes.iter_mut() {
+            self.write_back(node, &mut run, &mut b, &mut float);
+        }
+        (
+            y - self.container.y - self.container.content_height,
+            self.last_baseline,
+        )
+    }
+
+    //how wide it'd all be if nothing wrapped, for shrink-to-fit.
+    pub fn max_content(mut self, nodes: &mut [(&CSSProps, &mut Node)]) -> f64 {
+        for (_, node) in nodes.iter_mut() {
+            self.lay_out_atomics(node);
+        }
+        for (css, node) in nodes.iter() {
+            self.collect(node, css, 0);
+        }
+        //blocks inside inlines always start a new line, so it's the widest of what's between
+        //them, or of them.
+        let mut widest: f64 = 0.;
+        for segment in self.items.split(|item| matches!(item, Item::Block(_))) {
+            let trailing = segment
+                .iter()
+                .rev()
+                .filter(|item| !matches!(item, Item::Close(_)))
+                .take_while(|item| Self::is_space(item))
+                .map(|item| self.advance(item))
+                .sum::<f64>();
+            let width = segment.iter().map(|item| self.advance(item)).sum::<f64>() - trailing;
+            widest = widest.max(width);
+        }
+        let mut blocks = vec![];
+        for (_, node) in nodes.iter_mut() {
+            Self::blocks_in(node, &mut blocks);
+        }
+        for el in blocks {
+            widest = widest.max(el.max_content_contribution(self.container, self.scale_factor));
+        }
+        //floats would go next to it, if there was room.
+        let floats = self
+            .float_boxes
+            .iter()
+            .map(|float| float.size.0)
+            .sum::<f64>();
+        widest + floats
+    }
+
+    //the blocks inside inlines, in the same order collect() came across them.
+    fn blocks_in<'n>(node: &'n mut Node, out: &mut Vec<&'n mut Element>) {
+        let Node::Element(el) = node else {
+            return;
+        };
+        match el.display().outside {
+            DisplayOutside::None => {}
+            _ if el.is_out_of_flow() || el.is_floated() => {}
+            DisplayOutside::Block | DisplayOutside::RunIn => out.push(el),
+            _ if is_atomic(el) => {}
+            _ => {
+                for child in &mut el.children {
+                    Self::blocks_in(child, out);
+                }
+            }
+        }
+    }
+
+    //a block that split an inline, laid out in the container's flow at y. margins don't collapse
+    //with the lines around it, since there's always a line box or the container's edge in the
+    //way. returns where the lines after it start.
+    fn lay_out_block(&mut self, el: &mut Element, y: f64) -> f64 {
+        let container = LayoutInfo {
+            content_height: y - self.container.y,
+            ..self.container
+        };
+        let at = CollapsedMargin::default();
+        el.lay_out_in_flow(container, at, false, self.floats, self.scale_factor);
+        el.place_positioned(self.container, self.scale_factor);
+        if !el.layout_info.laid_out {
+            return y;
+        }
+        self.last_baseline = el.layout_info.baseline.or(self.last_baseline);
+        let margin = el.layout_info.collapsed_margin.1.resolve();
+        match el.layout_info.collapses_through {
+            true => y + margin,
+            false => {
+                let border_box = el.layout_info.border_box();
+                border_box.y + border_box.height + margin
+            }
+        }
+    }
+
+    //inline-blocks need their insides laid out before anyone knows how big they are.
+    fn lay_out_atomics(&self, node: &mut Node) {
+        let Node::Element(el) = node else {
+            return;
+        };
+        match el.display().outside {
+            DisplayOutside::None => {}
+            //it goes wherever the line is up to, or near enough. the top of the run, anyway.
+            _ if el.is_out_of_flow() => el.set_static_position(
+                self.container.x,
+                self.container.y + self.container.content_height,
+            ),
+            _ if el.is_floated() => el.layout_float(self.container, self.scale_factor),
+            //blocks get laid out once the lines before them have been.
+            DisplayOutside::Block | DisplayOutside::RunIn => {}
+            _ if is_replaced(el) => {}
+            _ if is_atomic(el) => el.layout_inline_block(self.container, self.scale_factor),
+            _ => {
+                for child in &mut el.children {
+                    self.lay_out_atomics(child);
+                }
+            }
+        }
+    }
+
+    fn collect(&mut self, node: &Node, css: &CSSProps, owner: usize) {
+        match node {
+            Node::Text(text) => self.collect_text(text, css, owner),
+            Node::LaidoutText(text) => self.collect_text(&text.text, css, owner),
+            Node::Element(el) => match el.display().outside {
+                DisplayOutside::None => {}
+                //its children are in its parent's box, but still styled like they're in it.
+                DisplayOutside::Contents => {
+                    for child in &el.children {
+                        self.collect(child, &el.css, owner);
+                    }
+                }
+                _ if el.is_out_of_flow() => {}
+                _ if el.is_floated() => {
+                    let margin_box = el.layout_info.border_box().expand(el.layout_info.margin);
+                    self.items.push(Item::Float(self.float_boxes.len()));
+                    self.float_boxes.push(FloatBox {
+                        side: el.float(),
+                        size: (margin_box.width, margin_box.height),
+                        position: None,
+                    });
+                }
+                //a block inside an inline splits it in two around it. whatever's open is closed off
+                //on the line before and opened again on the one after.
+                DisplayOutside::Block | DisplayOutside::RunIn => {
+                    self.items.push(Item::Block(self.blocks));
+                    self.blocks += 1;
+                    self.after_space = true;
+                }
+                _ => {
+                    let id = self.boxes.len();
+                    let inline_box = self.new_box(el, owner);
+                    self.boxes.push(inline_box);
+                    self.fragments.push(vec![]);
+                    if is_atomic(el) {
+                        self.items.push(Item::Atomic(id));
+                        self.after_space = false;
+                    } else {
+                        self.items.push(Item::Open(id));
+                        for child in &el.children {
+                            self.collect(child, &el.css, id);
+                        }
+                        self.items.push(Item::Close(id));
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    //white-space: normal, so every run of whitespace is one space, and there's never one at the
+    //start or straight after another, even in a different element.
+    fn collect_text(&mut self, text: &str, css: &CSSProps, owner: usize) {
+        let mut collapsed = String::with_capacity(text.len());
+        for ch in text.chars() {
+            if !ch.is_ascii_whitespace() {
+                collapsed.push(ch);
+                self.after_space = false;
+            } else if !self.after_space {
+                collapsed.push(' ');
+                self.after_space = true;
+            }
+        }
+        let text_layoutifier = TextLayoutifier::new(css, &collapsed, self.scale_factor);
+        let run = self.runs.len();
+        self.items.extend(
+            text_layoutifier
+                .shape()
+                .into_iter()
+                .map(|glyph| Item::Glyph(run, glyph)),
+        );
+        self.runs.push(TextRun {
+            owner,
+            text: String::from(text),
+            font: text_layoutifier.chain().clone(),
+            font_size: text_layoutifier.unwrap_font_size(),
+            colour: text_layoutifier.colour(),
+            axes: text_layoutifier.axes(),
+            glyphs: vec![],
+        });
+    }
+
+    fn new_box(&self, el: &Element, parent: usize) -> InlineBox {
+        //percentages are all of the container's width, even the vertical ones.
+        let length = |d: &CSSValue<Dimensionality>| match d {
+            CSSValue::Value(d) => d.resolve(Some(self.container.width)).unwrap_or(0.),
+            _ => 0.,
+        };
+        let css = &el.css;
+        let size = is_replaced(el).then(|| el.replaced_size(self.container));
+        InlineBox {
+            parent,
+            metrics: TextLayoutifier::new(css, "", self.scale_factor).metrics(),
+            vertical_align: match &css.vertical_align {
+                CSSValue::Value(v) => v.clone(),
+                _ => VerticalAlign::Baseline,
+            },
+            margin: (
+                length(&css.margin_top),
+                length(&css.margin_left),
+                length(&css.margin_right),
+                length(&css.margin_bottom),
+            ),
+            padding: (
+                length(&css.padding_top),
+                length(&css.padding_left),
+                length(&css.padding_right),
+                length(&css.padding_bottom),
+            ),
+            border: el.border_widths(),
+            //inline-blocks have already been laid out by now.
+            size: match is_replaced(el) {
+                true => size,
+                false => is_atomic(el).then_some((el.layout_info.width, el.layout_info.height)),
+            },
+            baseline: match is_replaced(el) {
+                true => None,
+                false => el.layout_info.baseline.map(|b| b - el.layout_info.y),
+            },
+        }
+    }
+
+    fn advance(&self, item: &Item) -> f64 {
+        match item {
+            Item::Glyph(_, glyph) => glyph.advance,
+            Item::Open(b) => {
+                let inline_box = &self.boxes[*b];
+                inline_box.margin.1 + inline_box.border.1 + inline_box.padding.1
+            }
+            Item::Close(b) => {
+                let inline_box = &self.boxes[*b];
+                inline_box.padding.2 + inline_box.border.2 + inline_box.margin.2
+            }
+            Item::Atomic(b) => {
+                let inline_box = &self.boxes[*b];
+                inline_box.margin.1
+                    + inline_box.border.1
+                    + inline_box.padding.1
+                    + inline_box.size.unwrap().0
+                    + inline_box.padding.2
+                    + inline_box.border.2
+                    + inline_box.margin.2
+            }
+            Item::Float(_) | Item::Block(_) => 0.,
+        }
+    }
+
+    fn is_space(item: &Item) -> bool {
+        matches!(item, Item::Glyph(_, glyph) if glyph.breakable)
+    }
+
+    //how far across a line at y starts, and how much room it's got, with the floats there taking
+    //theirs. it's as tall as the strut as far as they're concerned.
+    fn room(&self, y: f64) -> (f64, f64) {
+        let (left, right) = (self.container.x, self.container.x + self.container.width);
+        let height = self.boxes[0].line_height();
+        match self.floats.available(y, y + height, left, right) {
+            //left as it is when there's nothing in the way, so it can be told apart.
+            band if band == (left, right) => (self.container.x, self.container.width),
+            (left, right) => (left, (right - left).max(0.)),
+        }
+    }
+
+    fn place_float(&mut self, float: usize, y: f64) {
+        let (left, right) = (self.container.x, self.container.x + self.container.width);
+        let FloatBox { side, size, .. } = self.float_boxes[float];
+        let position = self.floats.place(side, size, y, left, right);
+        self.float_boxes[float].position = Some(position);
+    }
+
+    //fills the line at y, going no further than stop, up to the last place it can break before
+    //it's too wide. a word that's too wide on its own gets a line to itself and sticks out,
+    //unless it's only too wide because of floats, in which case it gives back where to try
+    //again. floats go on the line they're on if there's room left for them, and otherwise
+    //they're given back to go after it.
+    fn break_line(
+        &mut self,
+        start: usize,
+        stop: usize,
+        y: f64,
+    ) -> Result<(usize, Vec<usize>), f64> {
+        let full = self.container.width;
+        let (_, mut room) = self.room(y);
+        let mut end = stop;
+        let mut width = 0.;
+        let mut trailing = 0.; //spaces at the end don't count
+        let mut last_break = None;
+        let mut later = vec![];
+        for i in start..stop {
+            let item = &self.items[i];
+            if let Item::Float(float) = *item {
+                //one that was put down on an earlier try at this line stays there.
+                if self.float_boxes[float].position.is_some() {
+                    continue;
+                }
+                //they have to stay in order, so once one's waiting the rest do too.
+                if later.is_empty() && width - trailing + self.float_boxes[float].size.0 <= room {
+                    self.place_float(float, y);
+                    room = self.room(y).1;
+                } else {
+                    later.push(float);
+                }
+                continue;
+            }
+            width += self.advance(item);
+            trailing = match Self::is_space(item) {
+                true => trailing + self.advance(item),
+                false => 0.,
+            };
+            if let Some(at) = last_break {
+                if width - trailing > room && at <= i {
+                    end = at;
+                    break;
+                }
+            } else if width - trailing > room && room < full {
+                if let Some(edge) = self.floats.next_edge(y) {
+                    return Err(edge);
+                }
+            }
+            if Self::is_space(item) || matches!(item, Item::Atomic(_)) {
+                //anything closing straight after stays on this line with it.
+                let mut at = i + 1;
+                while let Some(Item::Close(_)) = self.items.get(at) {
+                    at += 1;
+                }
+                last_break = Some(at);
+            }
+        }
+        Ok((end, later))
+    }
+
+    //a line with nothing but collapsed spaces and empty elements on it isn't there at all.
+    fn is_phantom(&self, line: &Range<usize>) -> bool {
+        self.items[line.clone()].iter().all(|item| match item {
+            Item::Glyph(_, glyph) => glyph.breakable,
+            Item::Open(_) | Item::Close(_) => self.advance(item) == 0.,
+            Item::Atomic(_) => false,
+            Item::Float(_) | Item::Block(_) => true,
+        })
+    }
+
+    //works out where everything on the line goes vertically, then walks along it putting
+    //things down, starting at left and with width to fit into. returns the line's height.
+    fn place_line(
+        &mut self,
+        line: Range<usize>,
+        open: &mut Vec<usize>,
+        y: f64,
+        (left, width): (f64, f64),
+        last: bool,
+    ) -> f64 {
+        if self.is_phantom(&line) {
+            for item in &self.items[line] {
+                match item {
+                    Item::Open(b) => open.push(*b),
+                    Item::Close(_) => {
+                        open.pop();
+                    }
+                    _ => {}
+                }
+            }
+            return 0.;
+        }
+        let mut present = vec![false; self.boxes.len()];
+        present[0] = true;
+        for b in open.iter() {
+            present[*b] = true;
+        }
+        for item in &self.items[line.clone()] {
+            if let Item::Open(b) | Item::Atomic(b) = item {
+                present[*b] = true;
+            }
+        }
+
+        //baselines are relative to the container's, down being positive. boxes always come after
+        //their parents, so the parent's is always there first.
+        let mut baselines = vec![0.; self.boxes.len()];
+        //the top or bottom aligned box a box is in, if it's in one. those get put in once the
+        //rest of the line's worked out.
+        let mut pinned: Vec<Option<usize>> = vec![None; self.boxes.len()];
+        for b in 1..self.boxes.len() {
+            if !present[b] {
+                continue;
+            }
+            let inline_box = &self.boxes[b];
+            let parent = &self.boxes[inline_box.parent];
+            let (ascent, descent) = inline_box.ascent_descent();
+            baselines[b] = baselines[inline_box.parent]
+                + match &inline_box.vertical_align {
+                    VerticalAlign::Baseline | VerticalAlign::Top | VerticalAlign::Bottom => 0.,
+                    VerticalAlign::Sub => parent.metrics.font_size / 5.,
+                    VerticalAlign::Super => -parent.metrics.font_size / 3.,
+                    VerticalAlign::TextTop => ascent - parent.metrics.ascent,
+                    VerticalAlign::TextBottom => parent.metrics.descent - descent,
+                    VerticalAlign::Middle => (ascent - descent - parent.metrics.x_height) / 2.,
+                    VerticalAlign::Length(length) => {
+                        -length.resolve(Some(inline_box.line_height())).unwrap_or(0.)
+                    }
+                };
+            pinned[b] = match inline_box.vertical_align {
+                VerticalAlign::Top | VerticalAlign::Bottom => Some(b),
+                _ => pinned[inline_box.parent],
+            };
+        }
+
+        let extents = |baselines: &[f64], pin: Option<usize>| {
+            (0..self.boxes.len())
+                .filter(|b| present[*b] && pinned[*b] == pin)
+                .map(|b| self.boxes[b].extent(baselines[b]))
+                .fold(
+                    (f64::INFINITY, f64::NEG_INFINITY),
+                    |(top, bottom), (t, b)| (top.min(t), bottom.max(b)),
+                )
+        };
+        let (mut top, mut bottom) = extents(&baselines, None);
+        //top and bottom aligned boxes can make the line taller, but only away from the side
+        //they're stuck to.
+        let pins = (1..self.boxes.len())
+            .filter(|b| present[*b] && pinned[*b] == Some(*b))
+            .collect::<Vec<_>>();
+        for pin in &pins {
+            let (t, b) = extents(&baselines, Some(*pin));
+            match self.boxes[*pin].vertical_align {
+                VerticalAlign::Top => bottom = bottom.max(top + b - t),
+                _ => top = top.min(bottom - b + t),
+            }
+        }
+        for pin in pins {
+            let (t, b) = extents(&baselines, Some(pin));
+            let shift = match self.boxes[pin].vertical_align {
+                VerticalAlign::Top => top - t,
+                _ => bottom - b,
+            };
+            for b in 0..self.boxes.len() {
+                if pinned[b] == Some(pin) {
+                    baselines[b] += shift;
+                }
+            }
+        }
+        let baseline = y - top;
+        self.last_baseline = Some(baseline);
+
+        let trailing = self.items[line.clone()]
+            .iter()
+            .rev()
+            .filter(|item| !matches!(item, Item::Close(_)))
+            .take_while(|item| Self::is_space(item))
+            .collect::<Vec<_>>();
+        let used = self.items[line.clone()]
+            .iter()
+            .map(|item| self.advance(item))
+            .sum::<f64>()
+            - trailing.iter().map(|item| self.advance(item)).sum::<f64>();
+        let free = (width - used).max(0.);
+        //the spaces between words, which is everywhere justified text can stretch.
+        let gaps = self.items[line.clone()]
+            .iter()
+            .filter(|item| Self::is_space(item))
+            .count()
+            - trailing.len();
+        let (offset, gap) = match self.container_css.text_align.unwrap() {
+            TextAlign::Left => (0., 0.),
+            TextAlign::Center => (free / 2., 0.),
+            TextAlign::Right => (free, 0.),
+            //the last line's left alone, and so is one with only a single word on it.
+            TextAlign::Justify if last || gaps == 0 => (0., 0.),
+            TextAlign::Justify => (0., free / gaps as f64),
+        };
+        let mut x = left + offset;
+        let mut spaces = 0;
+
+        //where each box's content starts on this line, and whether it started here.
+        let mut starts = vec![(x, false); self.boxes.len()];
+        for item in &self.items[line] {
+            match item {
+                Item::Glyph(run, glyph) => {
+                    let owner = self.runs[*run].owner;
+                    self.runs[*run].glyphs.push(LaidoutGlyph {
+                        x,
+                        y: baseline + baselines[owner],
+                        glyph: *glyph,
+                    });
+                }
+                Item::Open(b) => {
+                    open.push(*b);
+                    starts[*b] = (x + self.advance(item), true);
+                }
+                Item::Close(b) => {
+                    open.pop();
+                    let fragment =
+                        self.fragment(*b, starts[*b], (x, true), baseline + baselines[*b]);
+                    self.fragments[*b].push(fragment);
+                }
+                Item::Atomic(b) => {
+                    let inline_box = &self.boxes[*b];
+                    let (ascent, _) = inline_box.ascent_descent();
+                    let (width, height) = inline_box.size.unwrap();
+                    self.fragments[*b].push(LayoutInfo {
+                        x: x + inline_box.margin.1 + inline_box.border.1 + inline_box.padding.1,
+                        y: baseline + baselines[*b] - ascent
+                            + inline_box.margin.0
+                            + inline_box.border.0
+                            + inline_box.padding.0,
+                        width,
+                        height,
+                        margin: inline_box.margin,
+                        padding: inline_box.padding,
+                        border: inline_box.border,
+                        laid_out: true,
+                        ..Default::default()
+                    });
+                }
+                Item::Float(_) | Item::Block(_) => {}
+            }
+            x += self.advance(item);
+            if Self::is_space(item) && spaces < gaps {
+                x += gap;
+                spaces += 1;
+            }
+        }
+        //whatever's still open carries on to the next line, and gets cut off here.
+        for b in open.iter() {
+            let fragment = self.fragment(*b, starts[*b], (x, false), baseline + baselines[*b]);
+            self.fragments[*b].push(fragment);
+        }
+        bottom - top
+    }
+
+    //one line's worth of an inline element. the left side's only on the line it started on and
+    //the right's only on the one it ended on.
+    fn fragment(
+        &self,
+        b: usize,
+        start: (f64, bool),
+        end: (f64, bool),
+        baseline: f64,
+    ) -> LayoutInfo {
+        let inline_box = &self.boxes[b];
+        let edges = |(top, left, right, bottom): (f64, f64, f64, f64)| {
+            (
+                top,
+                if start.1 { left } else { 0. },
+                if end.1 { right } else { 0. },
+                bottom,
+            )
+        };
+        LayoutInfo {
+            x: start.0,
+            y: baseline - inline_box.metrics.ascent,
+            width: end.0 - start.0,
+            height: inline_box.metrics.ascent + inline_box.metrics.descent,
+            margin: edges(inline_box.margin),
+            padding: edges(inline_box.padding),
+            border: edges(inline_box.border),
+            laid_out: true,
+            ..Default::default()
+        }
+    }
+
+    //hands everything back out, walking the nodes in the same order collect() did.
+    fn write_back(&mut self, node: &mut Node, run: &mut usize, b: &mut usize, float: &mut usize) {
+        match node {
+            Node::Text(_) | Node::LaidoutText(_) => {
+                let text_run = &mut self.runs[*run];
+                *node = Node::LaidoutText(LaidoutText {
+                    text: std::mem::take(&mut text_run.text),
+                    glyphs: std::mem::take(&mut text_run.glyphs),
+                    font: text_run.font.clone(),
+                    font_size: text_run.font_size,
+                    colour: text_run.colour,
+                    axes: text_run.axes.take(),
+                });
+                *run += 1;
+            }
+            Node::Element(el) => match el.display().outside {
+                DisplayOutside::None => {
+                    el.layout_info = LayoutInfo::default();
+                    el.fragments.clear();
+                }
+                DisplayOutside::Contents => {
+                    el.layout_info = LayoutInfo::default();
+                    el.fragments.clear();
+                    for child in &mut el.children {
+                        self.write_back(child, run, b, float);
+                    }
+                }
+                _ if el.is_out_of_flow() => {}
+                //it was laid out at the origin too.
+                _ if el.is_floated() => {
+                    if let Some((x, y)) = self.float_boxes[*float].position {
+                        let margin_box = el.layout_info.border_box().expand(el.layout_info.margin);
+                        el.translate(x - margin_box.x, y - margin_box.y);
+                    }
+                    *float += 1;
+                    el.place_positioned(self.container, self.scale_factor);
+                }
+                //it's been laid out already, between the lines.
+                DisplayOutside::Block | DisplayOutside::RunIn => {}
+                _ => {
+                    let fragments = std::mem::take(&mut self.fragments[*b]);
+                    *b += 1;
+                    if is_replaced(el) {
+                        el.layout_info = fragments.first().copied().unwrap_or_default();
+                        el.fragments = fragments;
+                    } else if is_atomic(el) {
+                        //it was laid out at the origin, so it's moved to wherever it ended up.
+                        if let Some(fragment) = fragments.first() {
+                            el.translate(
+                                fragment.x - el.layout_info.x,
+                                fragment.y - el.layout_info.y,
+                            );
+                        }
+                        el.fragments = vec![el.layout_info];
+                    } else {
+                        el.layout_info = fragments.first().copied().unwrap_or_default();
+                        el.fragments = fragments;
+                        for child in &mut el.children {
+                            self.write_back(child, run, b, float);
+                        }
+                    }
+                    el.place_positioned(self.container, self.scale_factor);
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::test_util::{find, laid_out};
+
+    fn glyphs<'a>(el: &'a Element) -> impl Iterator<Item = &'a LaidoutGlyph> {
+        el.children.iter().flat_map(|node| match node {
+            Node::LaidoutText(text) => text.glyphs.iter(),
+            _ => [].iter(),
+        })
+    }
+
+    #[test]
+    fn test_inline_formatting_context() {
+        let document = laid_out(
+            "<p style=\"width: 2000px\">one <b>two</b>  <span>three</span>x<sup>2</sup>\
+             <img width=\"10\" height=\"60\"></p>",
+        );
+        let p = find(&document.children, "p").unwrap();
+        let b = find(&p.children, "b").unwrap();
+        let span = find(&p.children, "span").unwrap();
+        let sup = find(&p.children, "sup").unwrap();
+        let img = find(&p.children, "img").unwrap();
+        //all on the one line, one after the other, on the same baseline.
+        let baseline = glyphs(p).next().unwrap().y;
+        assert!(glyphs(b).chain(glyphs(span)).all(|g| g.y == baseline));
+        assert!(glyphs(p).nth(3).unwrap().x < glyphs(b).next().unwrap().x);
+        assert!(b.fragments[0].x + b.fragments[0].width <= span.fragments[0].x);
+        //the two spaces between them collapsed into one.
+        let Node::LaidoutText(ref between) = p.children[2] else {
+            panic!();
+        };
+        assert_eq!(between.text, "  ");
+        assert_eq!(between.glyphs.len(), 1);
+        assert!(glyphs(sup).all(|g| g.y < baseline));
+        //images sit on the baseline, and the line's tall enough for them.
+        assert_eq!(img.layout_info.y + img.layout_info.height, baseline);
+        assert_eq!(img.layout_info.width, 10.);
+        assert!(p.layout_info.height >= 60.);
+
+        //an inline that doesn't fit gets split across lines, and only has its left padding on
+        //the first and its right padding on the last.
+        let document = laid_out(
+            "<p style=\"width: 60px\">a <span style=\"padding-left: 5px; padding-right: 7px\">\
+             bb bb bb bb bb bb</span> c</p>",
+        );
+        let span = find(&document.children, "span").unwrap();
+        let (first, last) = (span.fragments[0], *span.fragments.last().unwrap());
+        assert!(span.fragments.len() > 1);
+        assert!(first.y < last.y);
+        assert_eq!((first.padding.1, first.padding.2), (5., 0.));
+        assert_eq!((last.padding.1, last.padding.2), (0., 7.));
+    }
+
+    #[test]
+    fn test_justify() {
+        let document = laid_out(
+            "<p style=\"width: 100px; text-align: justify\">aa bb <b>cc</b> dd ee ff gg hh ii \
+             jj kk ll</p>",
+        );
+        let p = find(&document.children, "p").unwrap();
+        let all = glyphs(p)
+            .chain(glyphs(find(&p.children, "b").unwrap()))
+            .collect::<Vec<_>>();
+        let lines = {
+            let mut ys = all.iter().map(|g| g.y).collect::<Vec<_>>();
+            ys.sort_by(f64::total_cmp);
+            ys.dedup();
+            ys
+        };
+        assert!(lines.len() > 1);
+        let on = |y: f64| all.iter().filter(move |g| g.y == y);
+        //every line but the last reaches right across, spaces at the end aside.
+        let right = |y: f64| {
+            on(y)
+                .filter(|g| !g.glyph.breakable)
+                .map(|g| g.x + g.glyph.advance)
+                .fold(f64::NEG_INFINITY, f64::max)
+        };
+        for y in &lines[..lines.len() - 1] {
+            assert!((right(*y) - p.layout_info.x - 100.).abs() < 0.01);
+        }
+        //and the last one's left as it is.
+        assert!(right(*lines.last().unwrap()) < p.layout_info.x + 100. - 1.);
+        assert!(on(lines[0]).all(|g| g.x >= p.layout_info.x));
+    }
+
+    #[test]
+    fn test_block_in_inline() {
+        let document = laid_out(
+            "<p style=\"width: 300px\">a <span style=\"padding-left: 5px; padding-right: 7px\">\
+             b<span style=\"display: block; margin-top: 10px; margin-bottom: 10px\">block</span>\
+             c</span> d</p>",
+        );
+        let outer = find(&document.children, "p").unwrap();
+        let span = find(&outer.children, "span").unwrap();
+        let inner = find(&span.children, "span").unwrap();
+        //the block goes right across the container, between the lines before and after it.
+        assert_eq!(inner.layout_info.x, outer.layout_info.x);
+        assert_eq!(inner.layout_info.width, 300.);
+        let before = glyphs(span).next().unwrap();
+        let after = glyphs(span).last().unwrap();
+        assert!(before.y < inner.layout_info.y - 10.);
+        assert!(after.y > inner.layout_info.y + inner.layout_info.height + 10.);
+        assert_eq!(glyphs(outer).last().unwrap().y, after.y);
+        //the span's split in two, with its left side before the block and its right after.
+        assert_eq!(span.fragments.len(), 2);
+        let (first, last) = (span.fragments[0], span.fragments[1]);
+        assert_eq!((first.padding.1, first.padding.2), (5., 0.));
+        assert_eq!((last.padding.1, last.padding.2), (0., 7.));
+        assert_eq!(last.x, outer.layout_info.x);
+        assert!(outer.layout_info.height > inner.layout_info.height + 20.);
+    }
+
+    #[test]
+    fn test_display_types() {
+        let document = laid_out(
+            "<p style=\"width: 2000px\">a<span style=\"display: none\">hidden</span>\
+             <em style=\"display: contents; color: red\">b<b>c</b></em>\
+             <span style=\"display: inline-block; width: auto; padding-left: 3px\">\
+             d d<p>inside</p></span>e</p>\
+             <h1 style=\"display: none\">gone</h1><p style=\"display: flow-root\">f</p>",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let p = find(&body.children, "p").unwrap();
+        let baseline = glyphs(p).next().unwrap().y;
+        let hidden = find(&p.children, "span").unwrap();
+        assert!(!hidden.layout_info.laid_out);
+        assert!(matches!(hidden.children[0], Node::Text(_)));
+        //contents has no box of its own, but its children do, and they still inherit from it.
+        let em = find(&p.children, "em").unwrap();
+        let b = find(&em.children, "b").unwrap();
+        assert!(!em.layout_info.laid_out);
+        assert!(b.layout_info.laid_out);
+        assert!(glyphs(em).chain(glyphs(b)).all(|g| g.y == baseline));
+        let Node::LaidoutText(ref text) = em.children[0] else {
+            panic!();
+        };
+        assert_eq!(text.colour.real.red, 255);
+        //the inline-block's as wide as what's in it, sits on the line by its last baseline, and
+        //what's after it goes after it.
+        let inline_block = p
+            .children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Element(el) if el.tag_name == "span" => Some(el),
+                _ => None,
+            })
+            .last()
+            .unwrap();
+        let inside = find(&inline_block.children, "p").unwrap();
+        assert!(inline_block.layout_info.width > 0. && inline_block.layout_info.width < 1000.);
+        assert!(inside.layout_info.y > glyphs(inline_block).next().unwrap().y);
+        assert_eq!(glyphs(inside).next().unwrap().y, baseline);
+        assert!(
+            glyphs(p).last().unwrap().x
+                >= inline_block.layout_info.x + inline_block.layout_info.width
+        );
+        assert!(inline_block.layout_info.x - 3. > glyphs(b).last().unwrap().x);
+        //display: none takes up no room, so the flow-root's straight after the first p, with
+        //their 1em margins collapsed into one.
+        let h1 = find(&body.children, "h1").unwrap();
+        assert!(!h1.layout_info.laid_out);
+        let Some(Node::Element(flow_root)) = body
+            .children
+            .iter()
+            .rev()
+            .find(|node| matches!(node, Node::Element(el) if el.tag_name == "p"))
+        else {
+            panic!();
+        };
+        assert_eq!(
+            flow_root.layout_info.y,
+            p.layout_info.y + p.layout_info.height + 16.
+        );
+        assert!(flow_root.layout_info.height > 0.);
+    }
+
+    #[test]
+    fn test_nested_contents_and_sized_inline_block() {
+        let document = laid_out(
+            "<p style=\"width: 2000px\"><em style=\"display: contents\">\
+             <i style=\"display: contents\">a</i><b style=\"display: none\">x</b></em>\
+             <span style=\"display: inline-block; width: 50px; height: 30px\"></span>c</p>\
+             <p><em style=\"display: contents\"></em></p>",
+        );
+        let p = find(&document.children, "p").unwrap();
+        //contents inside contents still leaves the text on the p's line, and a display: none
+        //in there is gone the same as anywhere else.
+        let i = find(&p.children, "i").unwrap();
+        let b = find(&p.children, "b").unwrap();
+        assert!(!i.layout_info.laid_out && !b.layout_info.laid_out);
+        let a = glyphs(i).next().unwrap();
+        assert_eq!(a.x, p.layout_info.x);
+        //an empty inline-block still takes up its width, and sits on the baseline.
+        let span = find(&p.children, "span").unwrap();
+        assert_eq!(span.layout_info.width, 50.);
+        assert_eq!(span.layout_info.y + 30., a.y);
+        assert_eq!(
+            glyphs(p).last().unwrap().x,
+            span.layout_info.x + span.layout_info.width
+        );
+        //an empty contents doesn't make a line box.
+        let Some(Node::Element(empty)) = find(&document.children, "body")
+            .unwrap()
+            .children
+            .iter()
+            .rev()
+            .find(|node| matches!(node, Node::Element(el) if el.tag_name == "p"))
+        else {
+            panic!();
+        };
+        assert_eq!(empty.layout_info.height, 0.);
+    }
+}
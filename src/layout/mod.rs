@@ -5,16 +5,28 @@ use std::{
 
 use font_kit::font::Font;
 
-use self::text::TextLayoutifier;
+use self::{
+    float::Floats,
+    inline::{is_inline_level, InlineLayout},
+    positioned::lay_out_out_of_flow,
+};
 use crate::{
     context::Viewport,
     dom::{DOMCoordinate, Document, Element, Node},
     parser::css::{
-        properties::{Dimensionality, Display, DisplayOutside, FontFamily},
+        properties::{
+            BorderWidth, BoxSizing, Clear, Dimensionality, Display, DisplayInside, DisplayOutside,
+            FontFamily, MaxSize, Overflow,
+        },
         CSSNumber, CSSProps, CSSValue, Numeric, Unit,
     },
 };
 
+pub mod float;
+pub mod inline;
+pub mod positioned;
+#[cfg(test)]
+pub(crate) mod test_util;
 pub mod text;
 
 //three billion million trees to represent the same document is a little redundantge maybe... three billion trees no longer!!
@@ -31,38 +43,101 @@ fn pause() {
 }
 
 impl Element {
-    pub fn layout(&mut self, container: LayoutInfo, scale_factor: f64) {
+    //floats are every float in the block formatting context it's in so far.
+    pub fn layout(&mut self, container: LayoutInfo, floats: &mut Floats, scale_factor: f64) {
+        self.layout_info.laid_out = false;
+        self.fragments.clear();
         if self.tag_name == "head" {
             return;
         }
-        if let CSSValue::Value(display) = self.css.display {
-            match display.outside {
-                DisplayOutside::Block => self.layout_block(container, scale_factor),
-                a => unimplemented!("{:?}", a),
-            }
+        match self.display().outside {
+            DisplayOutside::None => {}
+            //everything but the root has been through generate_boxes() by now, so anything else
+            //is either a block or the root, which is always blockified. flow-root is a block
+            //that gets a block formatting context to itself, so no floats get in or out. run-in's
+            //just a block, like it is most places.
+            _ => self.layout_block(container, floats, scale_factor),
+        }
+        //the root's the containing block for absolutely positioned boxes with no positioned
+        //ancestor, and the viewport's the one for fixed boxes. the root's container is the
+        //viewport, so that's both.
+        if self.tag_name == "html" {
+            let viewport = LayoutInfo {
+                definite_height: Some(container.height),
+                ..container
+            };
+            lay_out_out_of_flow(&mut self.children, viewport, false, scale_factor);
+            lay_out_out_of_flow(&mut self.children, viewport, true, scale_factor);
+        }
+    }
+
+    pub fn display(&self) -> Display {
+        match self.css.display {
+            CSSValue::Value(display) => display,
+            _ => Display::default(),
         }
     }
 
-    fn layout_block(&mut self, container: LayoutInfo, scale_factor: f64) {
+    fn layout_block(&mut self, container: LayoutInfo, floats: &mut Floats, scale_factor: f64) {
+        self.layout_info.laid_out = true;
+        self.layout_info.content_height = 0.;
+        self.layout_info.baseline = None;
         self.calculate_width_block(container);
         self.calculate_pos_block(container);
-        self.go_children(scale_factor);
+        self.go_children(floats, scale_factor);
         self.calculate_height_block(container);
     }
 
     fn calculate_width_block(&mut self, container: LayoutInfo) {
-        let mut width = self.unwrap_widthwise_dimension(&self.css.width, container);
+        let (_, border_left, border_right, _) = self.border_widths();
+        let padding_left = self.unwrap_widthwise_dimension(&self.css.padding_left, container);
+        let padding_right = self.unwrap_widthwise_dimension(&self.css.padding_right, container);
+        let edges = border_left + padding_left.v() + padding_right.v() + border_right;
 
-        let mut margin_left = self.unwrap_widthwise_dimension(&self.css.margin_left, container);
-        let mut margin_right = self.unwrap_widthwise_dimension(&self.css.margin_right, container);
+        let width = match self.unwrap_widthwise_dimension(&self.css.width, container) {
+            NearlyExactDimension::Value(v) => {
+                NearlyExactDimension::Value(self.content_size(v, edges))
+            }
+            NearlyExactDimension::Auto => NearlyExactDimension::Auto,
+        };
+        let (mut width, mut margin_left, mut margin_right) =
+            self.solve_width_block(width, edges, container);
+        //too wide or too narrow and it's all worked out again like that's what it asked for. max
+        //goes first so min wins if they disagree.
+        let basis = Some(container.width);
+        if let Some(max) = self.max_size(&self.css.max_width, basis, edges) {
+            if width > max {
+                (width, margin_left, margin_right) =
+                    self.solve_width_block(NearlyExactDimension::Value(max), edges, container);
+            }
+        }
+        let min = self.min_size(&self.css.min_width, basis, edges);
+        if width < min {
+            (width, margin_left, margin_right) =
+                self.solve_width_block(NearlyExactDimension::Value(min), edges, container);
+        }
 
-        //TODO: let border_left =
-        //TODO: let border_right =
+        self.layout_info.width = width;
+        self.layout_info.padding.1 = padding_left.v();
+        self.layout_info.padding.2 = padding_right.v();
+        self.layout_info.margin.1 = margin_left;
+        self.layout_info.margin.2 = margin_right;
+        self.layout_info.border.1 = border_left;
+        self.layout_info.border.2 = border_right;
+    }
 
-        let padding_left = self.unwrap_widthwise_dimension(&self.css.padding_left, container);
-        let padding_right = self.unwrap_widthwise_dimension(&self.css.padding_right, container);
+    //the css 2.1 width equation, for a content box width that might be auto. edges are the
+    //padding and border across. gives back the width and the left and right margins.
+    fn solve_width_block(
+        &self,
+        mut width: NearlyExactDimension,
+        edges: f64,
+        container: LayoutInfo,
+    ) -> (f64, f64, f64) {
+        let mut margin_left = self.unwrap_widthwise_dimension(&self.css.margin_left, container);
+        let mut margin_right = self.unwrap_widthwise_dimension(&self.css.margin_right, container);
 
-        let total_width = margin_left.v() + /*border_left +*/ padding_left.v() + width.v() + padding_right.v() + /*border_right +*/ margin_right.v();
+        let total_width = margin_left.v() + edges + width.v() + margin_right.v();
 
         if !matches!(width, NearlyExactDimension::Auto) && total_width > container.width {
             margin_left = NearlyExactDimension::Value(0.);
@@ -105,13 +180,7 @@ impl Element {
                 }
             }
         }
-
-        self.layout_info.width = width.v();
-        self.layout_info.padding.1 = padding_left.v();
-        self.layout_info.padding.2 = padding_right.v();
-        self.layout_info.margin.1 = margin_left.v();
-        self.layout_info.margin.2 = margin_right.v();
-        //TODO: set border
+        (width.v(), margin_left.v(), margin_right.v())
     }
 
     fn calculate_pos_block(&mut self, container: LayoutInfo) {
@@ -122,7 +191,9 @@ impl Element {
             .unwrap_heightwise_dimension(&self.css.margin_bottom, container)
             .v();
 
-        //TODO: border
+        let (border_top, _, _, border_bottom) = self.border_widths();
+        self.layout_info.border.0 = border_top;
+        self.layout_info.border.3 = border_bottom;
         self.layout_info.padding.0 = self
             .unwrap_heightwise_dimension(&self.css.padding_top, container)
             .v();
@@ -130,69 +201,482 @@ impl Element {
             .unwrap_heightwise_dimension(&self.css.padding_bottom, container)
             .v();
 
-        self.layout_info.x = container.x + self.layout_info.margin.1 + /*self.layout_info.border.1 +*/ self.layout_info.padding.1;
-        self.layout_info.y = container.content_height + container.y + self.layout_info.margin.0 + /*self.layout_info.border.0 +*/ self.layout_info.padding.0;
+        self.layout_info.x = container.x
+            + self.layout_info.margin.1
+            + self.layout_info.border.1
+            + self.layout_info.padding.1;
+        self.layout_info.y = container.content_height
+            + container.y
+            + self.layout_info.margin.0
+            + self.layout_info.border.0
+            + self.layout_info.padding.0;
+        self.layout_info.definite_height = self.definite_height(container);
+    }
+
+    //an atomic inline with its own block formatting context inside. it's laid out up against
+    //the origin, and moved to wherever it lands once its line's been worked out.
+    pub fn layout_inline_block(&mut self, container: LayoutInfo, scale_factor: f64) {
+        self.layout_info = LayoutInfo {
+            laid_out: true,
+            ..Default::default()
+        };
+        self.fragments.clear();
+        //auto margins are just 0 on an inline-block.
+        let edges = [
+            &self.css.margin_left,
+            &self.css.padding_left,
+            &self.css.padding_right,
+            &self.css.margin_right,
+        ]
+        .map(|d| self.unwrap_widthwise_dimension(d, container).v());
+        let (_, border_left, border_right, _) = self.border_widths();
+        let across = edges[1] + edges[2] + border_left + border_right;
+        //shrink-to-fit, leaving out the min-content part, so a narrow container squashes it
+        //instead of it sticking out.
+        let width = match self.unwrap_widthwise_dimension(&self.css.width, container) {
+            NearlyExactDimension::Value(v) => self.content_size(v, across),
+            NearlyExactDimension::Auto => self
+                .max_content_width(container, scale_factor)
+                .min((container.width - edges[0] - edges[3] - across).max(0.)),
+        };
+        self.layout_info.width = self.clamp_size(
+            width,
+            &self.css.min_width,
+            &self.css.max_width,
+            Some(container.width),
+            across,
+        );
+        self.layout_info.margin.1 = edges[0];
+        self.layout_info.border.1 = border_left;
+        self.layout_info.padding.1 = edges[1];
+        self.layout_info.padding.2 = edges[2];
+        self.layout_info.border.2 = border_right;
+        self.layout_info.margin.2 = edges[3];
+        self.calculate_pos_block(LayoutInfo {
+            width: container.width,
+            height: container.height,
+            definite_height: container.definite_height,
+            ..Default::default()
+        });
+        self.go_children(&mut Floats::default(), scale_factor);
+        self.calculate_height_block(container);
     }
 
-    fn go_children(&mut self, scale_factor: f64) {
+    //as wide as the content would be if nothing wrapped.
+    fn max_content_width(&mut self, container: LayoutInfo, scale_factor: f64) -> f64 {
+        let mut nodes = vec![];
+        generate_boxes(&self.css, &mut self.children, &mut nodes);
+        let mut widest: f64 = 0.;
+        let mut i = 0;
+        while i < nodes.len() {
+            let run = nodes[i..]
+                .iter()
+                .take_while(|(_, child)| is_inline_level(child) || is_out_of_flow(child))
+                .count();
+            if run > 0 {
+                let mut floats = Floats::default();
+                let inline = InlineLayout::new(&self.css, container, &mut floats, scale_factor);
+                widest = widest.max(inline.max_content(&mut nodes[i..i + run]));
+                i += run;
+                continue;
+            }
+            if let Node::Element(el) = &mut *nodes[i].1 {
+                widest = widest.max(el.max_content_contribution(container, scale_factor));
+            }
+            i += 1;
+        }
+        widest
+    }
+
+    //how much room a block wants in a shrink-to-fit container, margins and all.
+    fn max_content_contribution(&mut self, container: LayoutInfo, scale_factor: f64) -> f64 {
+        let edges = [
+            &self.css.margin_left,
+            &self.css.padding_left,
+            &self.css.padding_right,
+            &self.css.margin_right,
+        ]
+        .map(|d| self.unwrap_widthwise_dimension(d, container).v());
+        let (_, border_left, border_right, _) = self.border_widths();
+        let across = edges[1] + edges[2] + border_left + border_right;
+        //percentages would depend on the width we're working out, so they count as auto, or no
+        //limit at all for min and max.
+        let width = match &self.css.width {
+            CSSValue::Value(width) => width.resolve(None),
+            _ => None,
+        }
+        .map(|width| self.content_size(width, across))
+        .unwrap_or_else(|| self.max_content_width(container, scale_factor));
+        let width = self.clamp_size(
+            width,
+            &self.css.min_width,
+            &self.css.max_width,
+            None,
+            across,
+        );
+        width + edges[0] + across + edges[3]
+    }
+
+    //moves it and everything in it.
+    pub fn translate(&mut self, dx: f64, dy: f64) {
+        self.layout_info.x += dx;
+        self.layout_info.y += dy;
+        self.layout_info.baseline = self.layout_info.baseline.map(|b| b + dy);
+        for fragment in &mut self.fragments {
+            fragment.x += dx;
+            fragment.y += dy;
+        }
         for child in &mut self.children {
-            if let Node::Element(el) = child {
-                el.layout(self.layout_info, scale_factor);
-                self.layout_info.content_height += el.layout_info.margin.0 + /*el.layout_info.border.0 +*/ el.layout_info.padding.0 + el.layout_info.height
-                    + el.layout_info.padding.3 + /*el.layout_info.border.3 +*/ el.layout_info.padding.3;
-            } else if let Node::Text(contents) = child {
-                let shmeep = self.layout_info.clone();
-                let text_layoutifier =
-                    TextLayoutifier::new(&self.css, &shmeep, contents.as_str(), scale_factor);
-                let glyphs = text_layoutifier.lay_it_out(&mut self.layout_info.content_height);
-                *child = Node::LaidoutText(glyphs);
+            match child {
+                Node::Element(el) => el.translate(dx, dy),
+                Node::LaidoutText(text) => {
+                    for glyph in &mut text.glyphs {
+                        glyph.x += dx;
+                        glyph.y += dy;
+                    }
+                }
+                _ => {}
             }
         }
     }
 
+    fn go_children(&mut self, floats: &mut Floats, scale_factor: f64) {
+        //a block formatting context keeps its floats to itself.
+        let mut own = Floats::default();
+        let floats = match self.establishes_bfc() {
+            true => &mut own,
+            false => floats,
+        };
+        //margins that have met but haven't been placed yet, since whatever's next might collapse
+        //with them too.
+        let mut pending = CollapsedMargin::default();
+        //while nothing's come between our top margin and our children's, theirs join ours.
+        let mut at_top = self.collapses_top();
+        let mut top = CollapsedMargin::new(self.layout_info.margin.0);
+        let mut nodes = vec![];
+        generate_boxes(&self.css, &mut self.children, &mut nodes);
+        let mut i = 0;
+        while i < nodes.len() {
+            let run = nodes[i..]
+                .iter()
+                .take_while(|(_, child)| is_inline_level(child) || is_out_of_flow(child))
+                .count();
+            if run > 0 {
+                let before = self.layout_info.content_height;
+                self.layout_info.content_height += pending.resolve();
+                let inline = InlineLayout::new(&self.css, self.layout_info, floats, scale_factor);
+                let (height, baseline) = inline.lay_it_out(&mut nodes[i..i + run]);
+                if height > 0. {
+                    self.layout_info.content_height += height;
+                    pending = CollapsedMargin::default();
+                    at_top = false;
+                } else {
+                    //no line boxes, so margins collapse straight past it.
+                    self.layout_info.content_height = before;
+                }
+                self.layout_info.baseline = baseline.or(self.layout_info.baseline);
+                i += run;
+                continue;
+            }
+            if let Node::Element(el) = &mut *nodes[i].1 {
+                let (y, pushed) =
+                    el.lay_out_in_flow(self.layout_info, pending, at_top, floats, scale_factor);
+                let margins = pending.adjoin(el.layout_info.collapsed_margin.0);
+                //clearance keeps its margin from collapsing with ours.
+                if at_top && !pushed {
+                    top = top.adjoin(margins);
+                }
+                let offset = y - self.layout_info.y - self.layout_info.content_height;
+                el.place_positioned(self.layout_info, scale_factor);
+                self.layout_info.baseline = el.layout_info.baseline.or(self.layout_info.baseline);
+                if el.layout_info.collapses_through && !pushed {
+                    //nothing in it to keep its margins apart, so they join whatever's next.
+                    if at_top {
+                        top = top.adjoin(el.layout_info.collapsed_margin.1);
+                    } else {
+                        pending = margins.adjoin(el.layout_info.collapsed_margin.1);
+                    }
+                } else {
+                    self.layout_info.content_height += offset
+                        + el.layout_info.border.0
+                        + el.layout_info.padding.0
+                        + el.layout_info.height
+                        + el.layout_info.padding.3
+                        + el.layout_info.border.3;
+                    pending = el.layout_info.collapsed_margin.1;
+                    at_top = false;
+                }
+            }
+            i += 1;
+        }
+        let mut bottom = CollapsedMargin::new(self.layout_info.margin.3);
+        //a height of 0 is as good as auto for this, as long as there's nothing in it. a min-height
+        //keeps them apart even then.
+        let collapses_through = at_top
+            && !self.has_min_height()
+            && (self.collapses_bottom()
+                || self.collapses_top()
+                    && self.layout_info.padding.3 == 0.
+                    && self.layout_info.border.3 == 0.
+                    && self.layout_info.definite_height == Some(0.));
+        if collapses_through {
+            top = top.adjoin(bottom);
+            bottom = top;
+        } else if self.collapses_bottom() {
+            bottom = bottom.adjoin(pending);
+        } else {
+            self.layout_info.content_height += pending.resolve();
+        }
+        //and it's tall enough for all of its floats.
+        if let Some(lowest) = own.bottom() {
+            let content_height = lowest - self.layout_info.y;
+            self.layout_info.content_height = self.layout_info.content_height.max(content_height);
+        }
+        self.layout_info.collapsed_margin = (top, bottom);
+        self.layout_info.collapses_through = collapses_through;
+    }
+
+    //lays out a block in its container's flow, and gives back where its border box starts and
+    //whether it was pushed down past floats to get there. that's under whatever's already in the
+    //container, as far down as the margins that collapse into its top margin say. those depend on
+    //what's in it, so they're guessed first and it's laid out again if the guess was wrong and
+    //there are floats for that to matter to. anything with a block formatting context of its own
+    //goes beside floats, or under them if it doesn't fit.
+    fn lay_out_in_flow(
+        &mut self,
+        container: LayoutInfo,
+        pending: CollapsedMargin,
+        at_top: bool,
+        floats: &mut Floats,
+        scale_factor: f64,
+    ) -> (f64, bool) {
+        let content = container.y + container.content_height;
+        let (left, right) = (container.x, container.x + container.width);
+        let before = floats.len();
+        let avoids_floats = self.establishes_bfc();
+        let mut margin = self.leading_margin(container);
+        let mut floor = floats.clearance(self.clear());
+        let mut height = 0.; //of its border box, the last time it was laid out
+        let mut tries = 0;
+        loop {
+            let position = |margin: CollapsedMargin, floor: Option<f64>| {
+                let y = match at_top {
+                    true => content,
+                    false => content + pending.adjoin(margin).resolve(),
+                };
+                match floor {
+                    Some(floor) if floor > y => (floor, true),
+                    _ => (y, false),
+                }
+            };
+            let (y, pushed) = position(margin, floor);
+            let band = match avoids_floats {
+                true => floats.available(y, y + height, left, right),
+                false => (left, right),
+            };
+            //it puts itself its own top margin under the container's content, so that's moved to
+            //where it'll land at y.
+            let margin_top = self.unwrap_heightwise_dimension(&self.css.margin_top, container);
+            let container = LayoutInfo {
+                x: band.0,
+                width: band.1 - band.0,
+                content_height: y - margin_top.v() - container.y,
+                ..container
+            };
+            self.layout(container, floats, scale_factor);
+            if !self.layout_info.laid_out {
+                return (y, pushed);
+            }
+            let border_box = self.layout_info.border_box();
+            let (should_be, pushed) = position(self.layout_info.collapsed_margin.0, floor);
+            tries += 1;
+            let retry = tries <= before + 2 && !floats.is_empty();
+            let fits = floats.available(y, y + border_box.height, left, right);
+            let wide = border_box.width + self.layout_info.margin.1 + self.layout_info.margin.2;
+            if retry && should_be != y {
+                margin = self.layout_info.collapsed_margin.0;
+            } else if retry && avoids_floats && fits != (left, right) && wide > fits.1 - fits.0 {
+                floor = floats.next_edge(y).or(floor);
+            } else if retry && avoids_floats && fits != band {
+                height = border_box.height;
+            } else {
+                //with no floats to care, it's just moved if it's not there already.
+                let dy = should_be - border_box.y;
+                self.translate(0., dy);
+                floats.shift(before, dy);
+                return (should_be, pushed);
+            }
+            floats.truncate(before);
+        }
+    }
+
+    //its top margin and any of its first children's that collapse into it, going by what's in
+    //it before it's laid out. it's only a guess, since there's no knowing if there'll be any line
+    //boxes until there are.
+    fn leading_margin(&mut self, container: LayoutInfo) -> CollapsedMargin {
+        let margin_top = self.unwrap_heightwise_dimension(&self.css.margin_top, container);
+        let margin = CollapsedMargin::new(margin_top.v());
+        let padding_top = self.unwrap_heightwise_dimension(&self.css.padding_top, container);
+        if self.establishes_bfc() || padding_top.v() != 0. || self.border_widths().0 != 0. {
+            return margin;
+        }
+        let mut nodes = vec![];
+        generate_boxes(&self.css, &mut self.children, &mut nodes);
+        for (_, node) in nodes {
+            match node {
+                Node::Text(text) if text.trim().is_empty() => {}
+                Node::Element(el) if el.is_out_of_flow() || el.is_floated() => {}
+                Node::Element(el) if !matches!(el.display().outside, DisplayOutside::Inline) => {
+                    return match el.clear() {
+                        Clear::None => margin.adjoin(el.leading_margin(container)),
+                        _ => margin,
+                    };
+                }
+                _ => return margin,
+            }
+        }
+        margin
+    }
+
+    //root elements, flow-roots (inline-blocks included), floats, absolutely positioned boxes and
+    //anything with overflow that isn't visible or clip keep their children's margins and floats
+    //in, and keep out the floats around them.
+    fn establishes_bfc(&self) -> bool {
+        self.tag_name == "html"
+            || matches!(self.display().inside, DisplayInside::FlowRoot)
+            || self.is_floated()
+            || self.is_out_of_flow()
+            || matches!(
+                self.css.overflow,
+                CSSValue::Value(Overflow::Hidden | Overflow::Scroll | Overflow::Auto)
+            )
+    }
+
+    //whether there's nothing between our top margin and our first child's.
+    fn collapses_top(&self) -> bool {
+        !self.establishes_bfc()
+            && self.layout_info.padding.0 == 0.
+            && self.layout_info.border.0 == 0.
+    }
+
+    //same for the bottom, which a set height gets in the way of too.
+    fn collapses_bottom(&self) -> bool {
+        !self.establishes_bfc()
+            && self.layout_info.padding.3 == 0.
+            && self.layout_info.border.3 == 0.
+            && self.layout_info.definite_height.is_none()
+    }
+
+    fn has_min_height(&self) -> bool {
+        match &self.css.min_height {
+            CSSValue::Value(min) => min.resolve(Some(1.)).is_some_and(|min| min > 0.),
+            _ => false,
+        }
+    }
+
+    //an auto height is however tall the content is, kept within min-height and max-height.
     fn calculate_height_block(&mut self, container: LayoutInfo) {
-        let height = match self.css.height {
-            CSSValue::Value(width) => match width {
-                Dimensionality::Auto => NearlyExactDimension::Auto,
-                Dimensionality::Real(v) => match v {
-                    CSSNumber::Unit(v, u) => match u {
-                        Unit::Px => NearlyExactDimension::Value(v.unwrap_f64()),
-                        a => unimplemented!("{:?}", a),
-                    },
-                    CSSNumber::Number(v) => NearlyExactDimension::Value(v.unwrap_f64()),
-                    CSSNumber::Percentage(v) => NearlyExactDimension::Auto,
-                },
-            },
-            CSSValue::Inherit => {
-                unreachable!()
+        self.layout_info.height = match self.layout_info.definite_height {
+            Some(height) => height,
+            None => {
+                let (top, _, _, bottom) = self.layout_info.padding;
+                let down = top + bottom + self.layout_info.border.0 + self.layout_info.border.3;
+                self.clamp_size(
+                    self.layout_info.content_height,
+                    &self.css.min_height,
+                    &self.css.max_height,
+                    container.definite_height,
+                    down,
+                )
             }
-            CSSValue::Initial => NearlyExactDimension::Auto,
         };
-        if let NearlyExactDimension::Value(v) = height {
-            self.layout_info.height = v;
-        } else {
-            self.layout_info.height = self.layout_info.content_height;
+    }
+
+    //the content box's height, if it's known before what's in it is. percentages only count
+    //when the container's is too, and are auto otherwise.
+    fn definite_height(&self, container: LayoutInfo) -> Option<f64> {
+        let (top, _, _, bottom) = self.layout_info.padding;
+        let down = top + bottom + self.layout_info.border.0 + self.layout_info.border.3;
+        let basis = container.definite_height;
+        let height = match &self.css.height {
+            CSSValue::Value(height) => height.resolve(basis)?,
+            _ => return None,
+        };
+        Some(self.clamp_size(
+            self.content_size(height, down),
+            &self.css.min_height,
+            &self.css.max_height,
+            basis,
+            down,
+        ))
+    }
+
+    //width and height (and their mins and maxes) are of the border box with border-box, so
+    //the padding and border along that way (edges) come off to get the content box's.
+    pub(crate) fn content_size(&self, size: f64, edges: f64) -> f64 {
+        match self.css.box_sizing.unwrap() {
+            BoxSizing::ContentBox => size,
+            BoxSizing::BorderBox => (size - edges).max(0.),
         }
     }
 
+    //max goes first, so min wins when they disagree. auto mins are 0, and percentages of
+    //nothing are 0 for min and no limit for max.
+    pub(crate) fn clamp_size(
+        &self,
+        size: f64,
+        min: &CSSValue<Dimensionality>,
+        max: &CSSValue<MaxSize>,
+        basis: Option<f64>,
+        edges: f64,
+    ) -> f64 {
+        let size = match self.max_size(max, basis, edges) {
+            Some(max) => size.min(max),
+            None => size,
+        };
+        size.max(self.min_size(min, basis, edges))
+    }
+
+    fn min_size(&self, min: &CSSValue<Dimensionality>, basis: Option<f64>, edges: f64) -> f64 {
+        match min {
+            CSSValue::Value(min) => match min.resolve(basis) {
+                Some(min) => self.content_size(min, edges),
+                None => 0.,
+            },
+            _ => 0.,
+        }
+    }
+
+    fn max_size(&self, max: &CSSValue<MaxSize>, basis: Option<f64>, edges: f64) -> Option<f64> {
+        match max {
+            CSSValue::Value(max) => max.resolve(basis).map(|max| self.content_size(max, edges)),
+            _ => None,
+        }
+    }
+
+    //top, left, right, bottom like everything else. a style of none has already made them 0.
+    pub fn border_widths(&self) -> (f64, f64, f64, f64) {
+        let width = |w: &CSSValue<BorderWidth>| match w {
+            CSSValue::Value(w) => w.px(),
+            _ => 0.,
+        };
+        (
+            width(&self.css.border_top_width),
+            width(&self.css.border_left_width),
+            width(&self.css.border_right_width),
+            width(&self.css.border_bottom_width),
+        )
+    }
+
     fn unwrap_widthwise_dimension(
         &self,
         dimension: &CSSValue<Dimensionality>,
         container: LayoutInfo,
     ) -> NearlyExactDimension {
         match dimension {
-            CSSValue::Value(width) => match width {
-                Dimensionality::Auto => NearlyExactDimension::Auto,
-                Dimensionality::Real(v) => match v {
-                    CSSNumber::Unit(v, u) => match u {
-                        Unit::Px => NearlyExactDimension::Value(v.unwrap_f64()),
-                        a => unimplemented!("{:?}", a),
-                    },
-                    CSSNumber::Number(v) => NearlyExactDimension::Value(v.unwrap_f64()),
-                    CSSNumber::Percentage(v) => {
-                        NearlyExactDimension::Value((container.width) * (v.unwrap_f64() / 100.))
-                    }
-                },
+            CSSValue::Value(width) => match width.resolve(Some(container.width)) {
+                Some(v) => NearlyExactDimension::Value(v),
+                None => NearlyExactDimension::Auto,
             },
             CSSValue::Inherit => {
                 unreachable!()
@@ -207,18 +691,9 @@ impl Element {
         container: LayoutInfo,
     ) -> NearlyExactDimension {
         match dimension {
-            CSSValue::Value(height) => match height {
-                Dimensionality::Auto => NearlyExactDimension::Auto,
-                Dimensionality::Real(v) => match v {
-                    CSSNumber::Unit(v, u) => match u {
-                        Unit::Px => NearlyExactDimension::Value(v.unwrap_f64()),
-                        a => unimplemented!("{:?}", a),
-                    },
-                    CSSNumber::Number(v) => NearlyExactDimension::Value(v.unwrap_f64()),
-                    CSSNumber::Percentage(v) => {
-                        NearlyExactDimension::Value((container.height) * (v.unwrap_f64() / 100.))
-                    }
-                },
+            CSSValue::Value(height) => match height.resolve(Some(container.height)) {
+                Some(v) => NearlyExactDimension::Value(v),
+                None => NearlyExactDimension::Auto,
             },
             CSSValue::Inherit => {
                 unreachable!()
@@ -228,6 +703,44 @@ impl Element {
     }
 }
 
+//floats and other out of flow boxes go in with inline runs, so they don't split one in two.
+//nothing in a run puts them on a line, and a run with nothing else in it has no lines.
+fn is_out_of_flow(node: &Node) -> bool {
+    match node {
+        Node::Element(el) => el.is_out_of_flow() || el.is_floated(),
+        _ => false,
+    }
+}
+
+//what actually gets laid out in a container. display: none elements aren't there at all, and
+//display: contents ones are swapped out for their children, which still get styled like
+//they're inside it.
+pub fn generate_boxes<'n>(
+    css: &'n CSSProps,
+    nodes: &'n mut [Node],
+    out: &mut Vec<(&'n CSSProps, &'n mut Node)>,
+) {
+    for node in nodes {
+        let outside = match node {
+            Node::Element(el) => Some(el.display().outside),
+            _ => None,
+        };
+        match (outside, node) {
+            (Some(DisplayOutside::None), Node::Element(el)) => {
+                el.layout_info = LayoutInfo::default();
+                el.fragments.clear();
+            }
+            (Some(DisplayOutside::Contents), Node::Element(el)) => {
+                el.layout_info = LayoutInfo::default();
+                el.fragments.clear();
+                let Element { css, children, .. } = el;
+                generate_boxes(css, children, out);
+            }
+            (_, node) => out.push((css, node)),
+        }
+    }
+}
+
 #[derive(Default, Copy, Clone, Debug)]
 pub enum NearlyExactDimension {
     #[default]
@@ -244,6 +757,34 @@ impl NearlyExactDimension {
     }
 }
 
+//adjoining margins collapse into one. the biggest positive one and the most negative one win,
+//and then they're added together.
+#[derive(Default, Copy, Clone, Debug, PartialEq)]
+pub struct CollapsedMargin {
+    positive: f64,
+    negative: f64,
+}
+
+impl CollapsedMargin {
+    pub fn new(margin: f64) -> Self {
+        Self {
+            positive: margin.max(0.),
+            negative: margin.min(0.),
+        }
+    }
+
+    pub fn adjoin(self, other: Self) -> Self {
+        Self {
+            positive: self.positive.max(other.positive),
+            negative: self.negative.min(other.negative),
+        }
+    }
+
+    pub fn resolve(self) -> f64 {
+        self.positive + self.negative
+    }
+}
+
 pub enum BuildingHeight {
     WaitingForContents,
     Value(f64),
@@ -260,6 +801,14 @@ pub struct LayoutInfo {
     pub content_height: f64,
     pub margin: (f64, f64, f64, f64), //top, left, right, bottom
     pub padding: (f64, f64, f64, f64),
+    pub border: (f64, f64, f64, f64),
+    pub laid_out: bool, //false for anything layout skipped over, like <head>
+    pub baseline: Option<f64>, //of the last line box in it, if there is one
+    //top and bottom, with whatever collapses into them
+    pub collapsed_margin: (CollapsedMargin, CollapsedMargin),
+    pub collapses_through: bool, //top and bottom margins touch, with nothing in between
+    //of the content box, when it's known before what's in it is laid out
+    pub definite_height: Option<f64>,
 }
 
 impl LayoutInfo {
@@ -269,9 +818,325 @@ impl LayoutInfo {
             y: self.y - expand.0,
             width: self.width + expand.1 + expand.2,
             height: self.height + expand.0 + expand.3,
-            content_height: self.content_height,
-            margin: self.margin,
-            padding: self.padding,
+            ..*self
         }
     }
+
+    pub fn padding_box(&self) -> LayoutInfo {
+        self.expand(self.padding)
+    }
+
+    pub fn border_box(&self) -> LayoutInfo {
+        self.padding_box().expand(self.border)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        test_util::{elements, find, laid_out},
+        *,
+    };
+
+    #[test]
+    fn test_margin_collapsing() {
+        let document = laid_out(
+            "<p style=\"margin-bottom: 30px\">a</p><p>b</p><p style=\"margin-top: -10px\">c</p>\
+             <p style=\"margin-top: 40px; margin-bottom: 0px\"></p>\
+             <p style=\"margin-top: 0px; margin-bottom: 0px\">\
+             <p style=\"margin-top: 50px; margin-bottom: 5px\">d</p></p>\
+             <p style=\"display: flow-root; margin-top: 0px\">\
+             <p style=\"margin-top: 20px\">e</p></p>",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let [a, b, c, _empty, outer, flow_root] = elements(&body.children)[..] else {
+            panic!();
+        };
+        let bottom = |el: &Element| el.layout_info.y + el.layout_info.height;
+        //body's padding keeps the first one's margin inside it.
+        assert_eq!(a.layout_info.y, body.layout_info.y + 16.);
+        //siblings: the bigger one wins, and negative ones take away.
+        assert_eq!(b.layout_info.y, bottom(a) + 30.);
+        assert_eq!(c.layout_info.y, bottom(b) + 6.);
+        //the empty one's margins, outer's and its first child's all collapse with c's.
+        let inner = elements(&outer.children)[0];
+        assert_eq!(outer.layout_info.y, bottom(c) + 50.);
+        assert_eq!(inner.layout_info.y, outer.layout_info.y);
+        assert_eq!(bottom(inner), bottom(outer));
+        assert_eq!(flow_root.layout_info.y, bottom(outer) + 5.);
+        //but a flow-root keeps its children's margins to itself.
+        let e = elements(&flow_root.children)[0];
+        assert_eq!(e.layout_info.y, flow_root.layout_info.y + 20.);
+        assert_eq!(bottom(flow_root), bottom(e) + 16.);
+    }
+
+    #[test]
+    fn test_negative_margin_collapsing() {
+        let document = laid_out(
+            "<p style=\"margin-bottom: -10px\">a</p><p style=\"margin-top: -20px\">b</p>\
+             <p style=\"margin-bottom: 30px\">c</p>\
+             <p style=\"margin-top: -40px; margin-bottom: 0px\">d</p>\
+             <p style=\"margin-top: 10px; margin-bottom: -25px\"></p><p style=\"margin-top: 5px\">\
+             <p style=\"margin-top: -30px\">e</p></p>",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let [a, b, c, d, _empty, outer] = elements(&body.children)[..] else {
+            panic!();
+        };
+        let bottom = |el: &Element| el.layout_info.y + el.layout_info.height;
+        //two negative ones, the most negative wins.
+        assert_eq!(b.layout_info.y, bottom(a) - 20.);
+        assert_eq!(c.layout_info.y, bottom(b) + 16.);
+        //a positive and a negative one add up.
+        assert_eq!(d.layout_info.y, bottom(c) - 10.);
+        //through an empty box and into a first child it's the biggest positive one plus the most
+        //negative one, out of all five.
+        let e = elements(&outer.children)[0];
+        assert_eq!(outer.layout_info.y, bottom(d) + 10. - 30.);
+        assert_eq!(e.layout_info.y, outer.layout_info.y);
+    }
+
+    #[test]
+    fn test_borders() {
+        let document = laid_out(
+            "<p style=\"border: 2px solid; border-bottom-width: 4px; padding-top: 3px; \
+             width: auto\">a</p>\
+             <p style=\"border-width: 9px; color: blue\">b<span style=\"border: 1em outset\">c\
+             </span></p>",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let [first, second] = elements(&body.children)[..] else {
+            panic!();
+        };
+        assert_eq!(first.layout_info.border, (2., 2., 2., 4.));
+        assert_eq!(first.layout_info.x, body.layout_info.x + 2.);
+        assert_eq!(first.layout_info.y, body.layout_info.y + 16. + 2. + 3.);
+        assert_eq!(first.layout_info.width, body.layout_info.width - 4.);
+        //no style, no border, however wide it's meant to be.
+        assert_eq!(second.layout_info.border, (0., 0., 0., 0.));
+        let border_box = first.layout_info.border_box();
+        assert_eq!(second.layout_info.y, border_box.y + border_box.height + 16.);
+        //an inline's border pushes what's around it over, and takes its colour from color.
+        let span = find(&second.children, "span").unwrap();
+        assert_eq!(span.layout_info.border, (16., 16., 16., 16.));
+        assert_eq!(span.css.border_top_color.unwrap().real.blue, 255);
+        let Node::LaidoutText(ref b) = second.children[0] else {
+            panic!();
+        };
+        let b = b.glyphs.last().unwrap();
+        assert_eq!(span.layout_info.x, b.x + b.glyph.advance + 16.);
+    }
+
+    #[test]
+    fn test_border_keywords_and_hidden() {
+        let document = laid_out(
+            "<p style=\"border: thick solid; border-left-style: hidden; border-right: medium \
+             dotted; border-top-width: thin; width: auto\">a</p>\
+             <p style=\"border-width: 0.5em; border-style: double; font-size: 20px\">b</p>",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let [keywords, ems] = elements(&body.children)[..] else {
+            panic!();
+        };
+        //hidden takes up no room, the same as none.
+        assert_eq!(keywords.layout_info.border, (1., 0., 3., 5.));
+        assert_eq!(keywords.layout_info.x, body.layout_info.x);
+        assert_eq!(keywords.layout_info.width, body.layout_info.width - 3.);
+        //ems are the element's own font size.
+        assert_eq!(ems.layout_info.border, (10., 10., 10., 10.));
+    }
+
+    #[test]
+    fn test_sizing_constraints() {
+        let document = laid_out(
+            "<p style=\"width: 200px; padding-left: 10px; border: 5px solid; \
+             box-sizing: border-box\"></p>\
+             <p style=\"width: auto; max-width: 100px\"></p>\
+             <p style=\"width: 50px; max-width: 40px; min-width: 60px\"></p>\
+             <p style=\"height: 200px\"><p style=\"height: 50%\"></p></p>\
+             <p style=\"height: 50%; max-height: 5px\">a</p>\
+             <p style=\"min-height: 40px\"></p><p></p><p>b</p>",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let [border_box, max, min, outer, auto, tall, empty, last] = elements(&body.children)[..]
+        else {
+            panic!();
+        };
+        //border-box takes the padding and border off what's left for the content.
+        assert_eq!(border_box.layout_info.width, 200. - 10. - 10.);
+        assert_eq!(max.layout_info.width, 100.);
+        assert_eq!(min.layout_info.width, 60.);
+        //percentage heights need a container with a height of its own, or they're auto.
+        let inner = elements(&outer.children)[0];
+        assert_eq!(inner.layout_info.height, 100.);
+        assert_eq!(auto.layout_info.height, 5.);
+        //a min-height keeps its margins apart, but an empty one's still collapse through.
+        let bottom = |el: &Element| el.layout_info.y + el.layout_info.height;
+        assert_eq!(tall.layout_info.height, 40.);
+        assert!(!tall.layout_info.collapses_through && empty.layout_info.collapses_through);
+        assert_eq!(last.layout_info.y, bottom(tall) + 16.);
+    }
+
+    #[test]
+    fn test_sizing_edge_cases() {
+        let document = laid_out(
+            "<p style=\"width: 10px; padding-left: 20px; box-sizing: border-box\"></p>\
+             <p style=\"height: 10px; min-height: 30px; max-height: 20px\"></p>\
+             <p style=\"width: auto; max-width: 25%\"></p>\
+             <p style=\"width: 10px; min-width: 50%; box-sizing: border-box; \
+             padding-right: 6px\"></p>",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let [squashed, tall, quarter, half] = elements(&body.children)[..] else {
+            panic!();
+        };
+        //padding that's wider than a border-box width leaves no room, not less than none.
+        assert_eq!(squashed.layout_info.width, 0.);
+        assert_eq!(squashed.layout_info.padding.1, 20.);
+        //min wins over both height and max-height.
+        assert_eq!(tall.layout_info.height, 30.);
+        //percentages are of the container, and border-box applies to min and max too.
+        assert_eq!(quarter.layout_info.width, body.layout_info.width / 4.);
+        assert_eq!(half.layout_info.width, body.layout_info.width / 2. - 6.);
+    }
+
+    #[test]
+    fn test_positioning() {
+        let document = laid_out(
+            "<p style=\"position: relative; top: 10px; left: 5px; margin-top: 0px\">a</p>\
+             <p style=\"position: relative; height: 100px; margin-top: 0px\">\
+             <p style=\"position: absolute; inset: auto 0px 0px auto; width: 20px; height: 10%\">\
+             </p><p style=\"position: absolute; width: auto\">b</p></p>\
+             <p style=\"position: fixed; top: 0px; left: 0px; margin-top: 0px; height: 30px\"></p>\
+             <p style=\"margin-top: 0px\">c<span style=\"position: absolute\">d</span></p>",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let [relative, container, fixed, last] = elements(&body.children)[..] else {
+            panic!();
+        };
+        //relative ones move without anything else noticing.
+        assert_eq!(relative.layout_info.x, body.layout_info.x + 5.);
+        assert_eq!(relative.layout_info.y, body.layout_info.y + 10.);
+        let bottom = |el: &Element| el.layout_info.y + el.layout_info.height;
+        assert_eq!(container.layout_info.y, bottom(relative) - 10. + 16.);
+        //absolute ones go against the nearest positioned box, or where they'd have been.
+        let [corner, shrunk] = elements(&container.children)[..] else {
+            panic!();
+        };
+        assert_eq!(
+            corner.layout_info.x,
+            container.layout_info.x + container.layout_info.width - 20.
+        );
+        //its margin's still on the bottom.
+        assert_eq!(bottom(corner) + 16., bottom(container));
+        assert_eq!(corner.layout_info.height, 10.);
+        assert_eq!(shrunk.layout_info.y, container.layout_info.y + 16.);
+        assert!(shrunk.layout_info.width > 0. && shrunk.layout_info.width < 100.);
+        //fixed ones go against the viewport, and none of them take up room.
+        assert_eq!((fixed.layout_info.x, fixed.layout_info.y), (0., 0.));
+        assert_eq!(last.layout_info.y, bottom(container) + 16.);
+        let span = find(&last.children, "span").unwrap();
+        assert!(matches!(span.display().outside, DisplayOutside::Block));
+        assert_eq!(span.layout_info.y, last.layout_info.y);
+    }
+
+    #[test]
+    fn test_overconstrained_insets() {
+        let document = laid_out(
+            "<p style=\"position: relative; width: 300px; height: 100px\">\
+             <p style=\"position: absolute; left: 10px; right: 10px; width: 400px; \
+             margin-left: auto; margin-right: auto\"></p>\
+             <p style=\"position: absolute; left: 10px; right: 10px; width: 400px; \
+             margin-left: 5px; margin-right: 5px\"></p>\
+             <p style=\"position: absolute; left: 10px; right: 10px; width: 100px; \
+             margin-left: auto; margin-right: 0px\"></p></p>",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let container = elements(&body.children)[0];
+        let [auto, set, one_auto] = elements(&container.children)[..] else {
+            panic!();
+        };
+        let x = container.layout_info.x;
+        //auto margins don't go negative to centre something too wide, the start one's 0 and
+        //the end one takes the rest.
+        assert_eq!(auto.layout_info.x, x + 10.);
+        assert_eq!(auto.layout_info.margin.1, 0.);
+        assert_eq!(auto.layout_info.margin.2, 300. - 10. - 400. - 10.);
+        //with nothing auto, right's the one that gets ignored.
+        assert_eq!(set.layout_info.x, x + 10. + 5.);
+        //a single auto margin takes up whatever's left.
+        assert_eq!(one_auto.layout_info.x, x + 300. - 10. - 100.);
+    }
+
+    #[test]
+    fn test_floats() {
+        let document = laid_out(
+            "<p style=\"margin-top: 0px\"><img style=\"float: left\" width=\"100\" height=\"50\">\
+             a</p><p style=\"clear: left; margin-top: 0px\">b</p>\
+             <p style=\"float: right; width: 50px; height: 20px; margin-top: 0px\"></p>\
+             <p style=\"display: flow-root; margin-top: 0px\">c\
+             <p style=\"float: left; height: 80px; margin-top: 0px; margin-bottom: 0px\">d</p></p>",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let [wrapped, cleared, right, flow_root] = elements(&body.children)[..] else {
+            panic!();
+        };
+        let img = find(&wrapped.children, "img").unwrap();
+        let bottom = |el: &Element| el.layout_info.y + el.layout_info.height;
+        //text goes beside a float, which sticks out of a block that isn't a flow-root.
+        assert_eq!(
+            (img.layout_info.x, img.layout_info.y),
+            (wrapped.layout_info.x, body.layout_info.y)
+        );
+        let Node::LaidoutText(ref a) = wrapped.children[1] else {
+            panic!();
+        };
+        assert_eq!(a.glyphs[0].x, img.layout_info.x + 100.);
+        assert!(bottom(wrapped) < bottom(img));
+        //clearance puts it under the float, and its margin doesn't get added on top.
+        assert_eq!(cleared.layout_info.y, bottom(img));
+        //floats go as far to their side as they can, right under what came before.
+        let right_edge = body.layout_info.x + body.layout_info.width;
+        assert_eq!(right.layout_info.x + right.layout_info.width, right_edge);
+        assert_eq!(right.layout_info.y, bottom(cleared) + 16.);
+        //a flow-root goes beside floats instead of under them, and grows to fit its own.
+        assert_eq!(flow_root.layout_info.y, right.layout_info.y);
+        assert_eq!(
+            flow_root.layout_info.x + flow_root.layout_info.width,
+            right.layout_info.x
+        );
+        let inner = elements(&flow_root.children)[0];
+        assert_eq!(bottom(flow_root), bottom(inner));
+    }
+
+    #[test]
+    fn test_clearance_next_to_floats() {
+        let document = laid_out(
+            "<p style=\"margin-top: 0px; margin-bottom: 0px\">\
+             <img style=\"float: left\" width=\"100\" height=\"50\"></p>\
+             <p style=\"clear: right; margin-top: 0px\">a</p>\
+             <p style=\"clear: both; margin-top: 80px\">b</p>\
+             <img style=\"float: left\" width=\"100\" height=\"50\">\
+             <p style=\"clear: left; margin-top: 10px\">c</p>",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let [holder, beside, past, float, cleared] = elements(&body.children)[..] else {
+            panic!();
+        };
+        let img = find(&holder.children, "img").unwrap();
+        let bottom = |el: &Element| el.layout_info.y + el.layout_info.height;
+        //clearing the other side does nothing, so it starts level with the float and its text
+        //goes around it.
+        assert_eq!(beside.layout_info.y, img.layout_info.y);
+        let Node::LaidoutText(ref a) = beside.children[0] else {
+            panic!();
+        };
+        assert_eq!(a.glyphs[0].x, img.layout_info.x + 100.);
+        //a margin that already gets it past the float needs no clearance on top.
+        assert!(bottom(beside) + 80. > bottom(img));
+        assert_eq!(past.layout_info.y, bottom(beside) + 80.);
+        //one that doesn't gets exactly enough to go under it.
+        assert!(bottom(past) + 16. < bottom(float));
+        assert_eq!(cleared.layout_info.y, bottom(float));
+    }
 }
@@ -0,0 +1,298 @@
+use super::{float::Floats, LayoutInfo};
+use crate::{
+    dom::{Element, Node},
+    parser::css::{
+        properties::{Dimensionality, DisplayOutside, Position},
+        CSSValue,
+    },
+};
+
+impl Element {
+    pub fn position(&self) -> Position {
+        match self.css.position {
+            CSSValue::Value(position) => position,
+            _ => Position::Static,
+        }
+    }
+
+    //absolutely positioned boxes take up no room where they'd otherwise be, and get laid out by
+    //their containing block once it knows how big it is. boxless ones don't count.
+    pub fn is_out_of_flow(&self) -> bool {
+        matches!(self.position(), Position::Absolute | Position::Fixed)
+            && !matches!(
+                self.display().outside,
+                DisplayOutside::None | DisplayOutside::Contents
+            )
+    }
+
+    //where an out of flow box would have gone, to fall back on when its insets are auto. it's
+    //kept in the layout info, which means it follows along when whatever it's in gets moved.
+    pub(crate) fn set_static_position(&mut self, x: f64, y: f64) {
+        self.layout_info = LayoutInfo {
+            x,
+            y,
+            ..Default::default()
+        };
+        self.fragments.clear();
+    }
+
+    //once a positioned box is where it goes in flow, anything absolutely positioned inside it is
+    //laid out against its padding box, and then a relative one gets shifted over, taking all of
+    //that with it. sticky ones are only shifted when they're painted, since it depends on the
+    //scroll.
+    pub(crate) fn place_positioned(&mut self, container: LayoutInfo, scale_factor: f64) {
+        match self.position() {
+            Position::Static | Position::Absolute | Position::Fixed => return,
+            Position::Relative | Position::Sticky => {}
+        }
+        let containing_block = self.containing_block();
+        lay_out_out_of_flow(&mut self.children, containing_block, false, scale_factor);
+        if let Position::Relative = self.position() {
+            let (dx, dy) = self.relative_offset(container);
+            self.translate(dx, dy);
+        }
+    }
+
+    //left beats right and top beats bottom when they're both set, and when neither is it stays
+    //put. percentages are of the container, if it's got a definite height for the vertical ones.
+    fn relative_offset(&self, container: LayoutInfo) -> (f64, f64) {
+        let inset = |d: &CSSValue<Dimensionality>, basis| match d {
+            CSSValue::Value(d) => d.resolve(basis),
+            _ => None,
+        };
+        let across = Some(container.width);
+        let dx = match inset(&self.css.left, across) {
+            Some(left) => left,
+            None => -inset(&self.css.right, across).unwrap_or(0.),
+        };
+        let down = container.definite_height;
+        let dy = match inset(&self.css.top, down) {
+            Some(top) => top,
+            None => -inset(&self.css.bottom, down).unwrap_or(0.),
+        };
+        (dx, dy)
+    }
+
+    //the padding box, which for an inline is its first fragment's.
+    fn containing_block(&self) -> LayoutInfo {
+        let padding_box = self.layout_info.padding_box();
+        LayoutInfo {
+            definite_height: Some(padding_box.height),
+            ..padding_box
+        }
+    }
+
+    //laid out against its containing block's padding box, with the static position already in
+    //the layout info. whatever's auto gets worked out by the css 2.1 rules, the same way across
+    //as down.
+    pub(crate) fn layout_absolute(&mut self, containing_block: LayoutInfo, scale_factor: f64) {
+        let cb = containing_block;
+        let (static_x, static_y) = (self.layout_info.x - cb.x, self.layout_info.y - cb.y);
+        self.layout_info = LayoutInfo {
+            laid_out: true,
+            ..Default::default()
+        };
+        self.fragments.clear();
+        let length = |d: &CSSValue<Dimensionality>, basis: Option<f64>| match d {
+            CSSValue::Value(d) => d.resolve(basis),
+            _ => None,
+        };
+        let (border_top, border_left, border_right, border_bottom) = self.border_widths();
+        let (across, down) = (Some(cb.width), cb.definite_height);
+        let padding = (
+            length(&self.css.padding_top, down).unwrap_or(0.),
+            length(&self.css.padding_left, across).unwrap_or(0.),
+            length(&self.css.padding_right, across).unwrap_or(0.),
+            length(&self.css.padding_bottom, down).unwrap_or(0.),
+        );
+        self.layout_info.padding = padding;
+        self.layout_info.border = (border_top, border_left, border_right, border_bottom);
+
+        //across first, since how tall it is depends on how wide.
+        let edges = padding.1 + padding.2 + border_left + border_right;
+        let width = length(&self.css.width, across).map(|width| self.content_size(width, edges));
+        let preferred = match width {
+            Some(_) => 0.,
+            None => self.max_content_width(cb, scale_factor),
+        };
+        let horizontal = Axis {
+            space: cb.width,
+            static_start: static_x,
+            start: length(&self.css.left, across),
+            end: length(&self.css.right, across),
+            margins: (
+                length(&self.css.margin_left, across),
+                length(&self.css.margin_right, across),
+            ),
+            edges,
+        };
+        let clamp = |width| {
+            self.clamp_size(
+                width,
+                &self.css.min_width,
+                &self.css.max_width,
+                across,
+                edges,
+            )
+        };
+        let (left, width, margin_left, margin_right) =
+            horizontal.solve(width, clamp, |room| preferred.min(room.max(0.)));
+        self.layout_info.width = width;
+        self.layout_info.margin.1 = margin_left;
+        self.layout_info.margin.2 = margin_right;
+        self.layout_info.x = cb.x + left + margin_left + border_left + padding.1;
+
+        let edges = padding.0 + padding.3 + border_top + border_bottom;
+        let height = length(&self.css.height, down).map(|height| self.content_size(height, edges));
+        let vertical = Axis {
+            space: cb.height,
+            static_start: static_y,
+            start: length(&self.css.top, down),
+            end: length(&self.css.bottom, down),
+            margins: (
+                length(&self.css.margin_top, across),
+                length(&self.css.margin_bottom, across),
+            ),
+            edges,
+        };
+        let clamp = |height| {
+            self.clamp_size(
+                height,
+                &self.css.min_height,
+                &self.css.max_height,
+                down,
+                edges,
+            )
+        };
+        //a height that comes from the insets is as good as a set one for what's inside.
+        self.layout_info.definite_height = match (height, vertical.start, vertical.end) {
+            (Some(height), ..) => Some(clamp(height)),
+            (None, Some(top), Some(bottom)) => {
+                let (margin_top, margin_bottom) = vertical.margins;
+                let room = cb.height - top - bottom - edges;
+                Some(clamp(
+                    (room - margin_top.unwrap_or(0.) - margin_bottom.unwrap_or(0.)).max(0.),
+                ))
+            }
+            _ => None,
+        };
+        //the insides get laid out from the top of the containing block and moved down after.
+        self.layout_info.y = cb.y + border_top + padding.0;
+        self.layout_info.content_height = 0.;
+        self.layout_info.baseline = None;
+        self.go_children(&mut Floats::default(), scale_factor);
+        let content_height = self.layout_info.content_height;
+        let clamp = |height| {
+            self.clamp_size(
+                height,
+                &self.css.min_height,
+                &self.css.max_height,
+                down,
+                edges,
+            )
+        };
+        let (top, height, margin_top, margin_bottom) =
+            vertical.solve(height, clamp, |_| content_height);
+        self.layout_info.height = height;
+        self.layout_info.margin.0 = margin_top;
+        self.layout_info.margin.3 = margin_bottom;
+        self.translate(0., top + margin_top);
+
+        let containing_block = self.containing_block();
+        lay_out_out_of_flow(&mut self.children, containing_block, false, scale_factor);
+    }
+}
+
+//one way across an absolutely positioned box. everything's relative to the containing block's
+//padding box, and None is auto.
+struct Axis {
+    space: f64,
+    static_start: f64,
+    start: Option<f64>,
+    end: Option<f64>,
+    margins: (Option<f64>, Option<f64>),
+    edges: f64, //padding and border
+}
+
+impl Axis {
+    //gives back where the margin box starts, the content box's size and the margins. an auto
+    //size is whatever fit makes of the room it's got, and one that's too big or small for its
+    //min and max gets worked out all over again like that's what it asked for.
+    fn solve(
+        &self,
+        size: Option<f64>,
+        clamp: impl Fn(f64) -> f64,
+        fit: impl Fn(f64) -> f64,
+    ) -> (f64, f64, f64, f64) {
+        let solved = self.solve_for(size, &fit);
+        let clamped = clamp(solved.1);
+        match clamped == solved.1 {
+            true => solved,
+            false => self.solve_for(Some(clamped), &fit),
+        }
+    }
+
+    fn solve_for(&self, size: Option<f64>, fit: impl Fn(f64) -> f64) -> (f64, f64, f64, f64) {
+        let space = self.space - self.edges;
+        if let (Some(start), Some(size), Some(end)) = (self.start, size, self.end) {
+            let free = space - start - size - end;
+            //too much to fit means end gets ignored, and auto margins can't split up a negative
+            //amount of room, so it all goes on the end.
+            let (margin_start, margin_end) = match self.margins {
+                (None, None) if free < 0. => (0., free),
+                (None, None) => (free / 2., free / 2.),
+                (None, Some(margin_end)) => (free - margin_end, margin_end),
+                (Some(margin_start), None) => (margin_start, free - margin_start),
+                (Some(margin_start), Some(margin_end)) => (margin_start, margin_end),
+            };
+            return (start, size, margin_start, margin_end);
+        }
+        let (margin_start, margin_end) =
+            (self.margins.0.unwrap_or(0.), self.margins.1.unwrap_or(0.));
+        let space = space - margin_start - margin_end;
+        let (start, size) = match (self.start, size, self.end) {
+            (None, None, None) => (self.static_start, fit(space - self.static_start)),
+            (None, None, Some(end)) => {
+                let size = fit(space - end);
+                (space - end - size, size)
+            }
+            (None, Some(size), None) => (self.static_start, size),
+            (Some(start), None, None) => (start, fit(space - start)),
+            (None, Some(size), Some(end)) => (space - end - size, size),
+            (Some(start), None, Some(end)) => (start, (space - start - end).max(0.)),
+            (Some(start), Some(size), None) => (start, size),
+            (Some(_), Some(_), Some(_)) => unreachable!(),
+        };
+        (start, size, margin_start, margin_end)
+    }
+}
+
+//the absolutely positioned boxes in nodes that have nothing else positioned between them and
+//whatever's laying them out, or with fixed, every fixed one anywhere in there.
+pub fn lay_out_out_of_flow(
+    nodes: &mut [Node],
+    containing_block: LayoutInfo,
+    fixed: bool,
+    scale_factor: f64,
+) {
+    for node in nodes {
+        let Node::Element(el) = node else {
+            continue;
+        };
+        if let DisplayOutside::None = el.display().outside {
+            continue;
+        }
+        match (el.position(), fixed) {
+            (Position::Absolute, false) | (Position::Fixed, true) if el.is_out_of_flow() => {
+                el.layout_absolute(containing_block, scale_factor);
+                if !fixed {
+                    continue;
+                }
+            }
+            (Position::Static, _) | (_, true) => {}
+            //it's the containing block for anything in it.
+            _ => continue,
+        }
+        lay_out_out_of_flow(&mut el.children, containing_block, fixed, scale_factor);
+    }
+}
@@ -0,0 +1,52 @@
+use std::io::Cursor;
+
+use crate::{
+    context::Viewport,
+    dom::{Document, Element, Node},
+    parser::{
+        css::{media::MediaEnvironment, CSSParser, StyleLevel},
+        html::HTMLParser,
+    },
+};
+
+//a body's worth of html with the user agent sheet, cascaded and laid out in an 800x600 window.
+pub(crate) fn laid_out(body: &str) -> Document {
+    let mut html = HTMLParser::default();
+    html.load_from_whatever(&mut Cursor::new(format!(
+        "<!DOCTYPE html><html><head></head><body>{}</body></html>",
+        body
+    )))
+    .unwrap();
+    let mut document = Document::default();
+    html.parse(&mut document).unwrap();
+    let mut user_agent = CSSParser::default();
+    user_agent.push_raw_css(&String::from(include_str!("../../real_shit/default.css")));
+    for mut style in user_agent.parse_stylesheets().unwrap() {
+        style.set_level(StyleLevel::UserAgent);
+        document.add_style(style);
+    }
+    let viewport = Viewport::new(800, 600);
+    document.cascade(viewport, MediaEnvironment::new(viewport, 1.));
+    document.layoutify(viewport, 1.);
+    document
+}
+
+//the first element with the tag, depth first.
+pub(crate) fn find<'a>(nodes: &'a [Node], tag: &str) -> Option<&'a Element> {
+    nodes.iter().find_map(|node| match node {
+        Node::Element(el) if el.tag_name == tag => Some(el),
+        Node::Element(el) => find(&el.children, tag),
+        _ => None,
+    })
+}
+
+//just the elements, without the text between them.
+pub(crate) fn elements(nodes: &[Node]) -> Vec<&Element> {
+    nodes
+        .iter()
+        .filter_map(|node| match node {
+            Node::Element(el) => Some(el),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,482 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use font_kit::{
+    family_name::FamilyName,
+    handle::Handle,
+    properties::{Properties, Style, Weight},
+    source::SystemSource,
+};
+use read_fonts::{FontRef, TableProvider};
+use reqwest::Client;
+use url::Url;
+
+use super::woff;
+use crate::{
+    context::fetch::fetch,
+    parser::css::{
+        font_face::{FontFace, FontFaceSource, FontFaceStyle},
+        properties::{FontFamily, FontStyle},
+    },
+};
+
+//all the fonts a page brought along with it through @font-face, and what every font-family's
+//resolved to so far. system fonts get read off the disk to resolve them, so that only happens
+//once per family list, weight and style, and every clone of the set shares the results.
+#[derive(Debug, Clone, Default)]
+pub struct FontSet {
+    faces: Vec<LoadedFace>,
+    resolved: Arc<RwLock<HashMap<(Vec<String>, u64, String), Option<FontChain>>>>, //style as css
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadedFace {
+    pub face: FontFace,
+    pub font: SharedFont,
+}
+
+//every face a font-family list could use, in the order they're tried for each character. an
+//@font-face one only gets the characters its unicode-range covers and it has a glyph for, and
+//an installed font gets whatever's left.
+#[derive(Debug, Clone)]
+pub struct FontChain {
+    faces: Arc<Vec<(Option<FontFace>, SharedFont)>>, //no face for installed fonts
+}
+
+impl FontChain {
+    //the first one that does spaces, which is what line heights and ems and so on come from.
+    pub fn primary(&self) -> usize {
+        self.faces
+            .iter()
+            .position(|(face, _)| face.as_ref().map_or(true, |face| face.covers(' ')))
+            .unwrap_or(0)
+    }
+
+    pub fn primary_font(&self) -> &SharedFont {
+        &self.faces[self.primary()].1
+    }
+
+    pub fn font(&self, face: usize) -> &SharedFont {
+        &self.faces[face].1
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    //whether a face is allowed to be used for a character at all.
+    pub fn covers(&self, face: usize, c: char) -> bool {
+        self.faces[face]
+            .0
+            .as_ref()
+            .map_or(true, |face| face.covers(c))
+    }
+}
+
+//a font's data, and the couple of measurements styles need from it. font-kit's fonts hang on to
+//a freetype face that belongs to the thread that loaded it, so they can't go in styles, which
+//get worked out on whichever thread's free.
+#[derive(Clone)]
+pub struct SharedFont {
+    data: Arc<Vec<u8>>,
+    index: u32,                //which font in a collection
+    x_height: Option<f64>,     //in ems
+    zero_advance: Option<f64>, //in ems
+}
+
+impl fmt::Debug for SharedFont {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedFont")
+            .field("bytes", &self.data.len())
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl SharedFont {
+    //None if it isn't a font at all.
+    pub fn new(data: Arc<Vec<u8>>, index: u32) -> Option<Self> {
+        let font = FontRef::from_index(&data, index).ok()?;
+        let units_per_em = font.head().ok()?.units_per_em() as f64;
+        let x_height = font
+            .os2()
+            .ok()
+            .and_then(|os2| os2.sx_height())
+            .filter(|x| *x > 0)
+            .map(|x| x as f64 / units_per_em);
+        let zero_advance = font
+            .cmap()
+            .ok()
+            .and_then(|cmap| cmap.map_codepoint('0'))
+            .zip(font.hmtx().ok())
+            .and_then(|(glyph, hmtx)| {
+                //glyphs past the end of the list all have the last one's advance.
+                let metrics = hmtx.h_metrics();
+                metrics
+                    .get(glyph.to_u16() as usize)
+                    .or(metrics.last())
+                    .copied()
+            })
+            .map(|metric| metric.advance() as f64 / units_per_em);
+        Some(Self {
+            x_height,
+            zero_advance,
+            data,
+            index,
+        })
+    }
+
+    pub fn from_handle(handle: &Handle) -> Option<Self> {
+        match handle {
+            Handle::Path { path, font_index } => {
+                Self::new(Arc::new(std::fs::read(path).ok()?), *font_index)
+            }
+            Handle::Memory { bytes, font_index } => Self::new(bytes.clone(), *font_index),
+        }
+    }
+
+    pub fn data(&self) -> &Arc<Vec<u8>> {
+        &self.data
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    //new() already made sure this works.
+    pub fn font_ref(&self) -> FontRef<'_> {
+        FontRef::from_index(&self.data, self.index).unwrap()
+    }
+
+    pub fn x_height(&self) -> Option<f64> {
+        self.x_height
+    }
+
+    pub fn zero_advance(&self) -> Option<f64> {
+        self.zero_advance
+    }
+}
+
+impl FontSet {
+    pub fn is_empty(&self) -> bool {
+        self.faces.is_empty()
+    }
+
+    //tries every source in order until one of them actually gives us a font, same as a browser
+    //would. faces that never load just don't end up in the set.
+    pub async fn load(&mut self, face: &FontFace, base: &Url, client: &Client) {
+        for source in &face.sources {
+            let font = match source {
+                FontFaceSource::Local(name) => Self::load_local(name),
+                FontFaceSource::Url(url, _) => match Self::load_url(url, base, client).await {
+                    Ok(font) => Some(font),
+                    Err(e) => {
+                        tracing::warn!("couldn't load font {} for {}: {}", url, face.family, e);
+                        None
+                    }
+                },
+            };
+            if let Some(font) = font {
+                //a new face could change what anything resolves to.
+                self.resolved = Default::default();
+                self.faces.push(LoadedFace {
+                    face: face.clone(),
+                    font,
+                });
+                return;
+            }
+        }
+        tracing::warn!("no usable source for @font-face {}", face.family);
+    }
+
+    fn load_local(name: &String) -> Option<SharedFont> {
+        let source = SystemSource::new();
+        let handle = source.select_by_postscript_name(name).ok().or_else(|| {
+            source
+                .select_best_match(&[FamilyName::Title(name.clone())], &Properties::new())
+                .ok()
+        })?;
+        SharedFont::from_handle(&handle)
+    }
+
+    async fn load_url(
+        url: &String,
+        base: &Url,
+        client: &Client,
+    ) -> Result<SharedFont, Box<dyn std::error::Error>> {
+        let url = base.join(url)?;
+        let data = woff::decode(fetch(client, &url).await?)?;
+        SharedFont::new(Arc::new(data), 0).ok_or_else(|| "not a font".into())
+    }
+
+    //the @font-face faces for each of the names, in order, and then the installed font that
+    //matches best, for anything they don't cover. if there's no installed font for any of them,
+    //it's the initial font-family's. None if there aren't any fonts at all.
+    pub fn resolve(&self, names: &[String], weight: f64, style: FontStyle) -> Option<FontChain> {
+        let key = (names.to_vec(), weight.to_bits(), style.to_string());
+        if let Some(chain) = self.resolved.read().unwrap().get(&key) {
+            return chain.clone();
+        }
+        let mut faces = names
+            .iter()
+            .flat_map(|name| self.select(name, weight, style))
+            .map(|loaded| (Some(loaded.face.clone()), loaded.font.clone()))
+            .collect::<Vec<_>>();
+        let installed = Self::load_system(names, weight, style).or_else(|| {
+            tracing::warn!("no usable font for {:?}, using the default", names);
+            Self::load_system(FontFamily::default().names(), 400., FontStyle::Normal)
+        });
+        faces.extend(installed.map(|font| (None, font)));
+        let chain = (!faces.is_empty()).then(|| FontChain {
+            faces: Arc::new(faces),
+        });
+        self.resolved.write().unwrap().insert(key, chain.clone());
+        chain
+    }
+
+    //serif if nothing else matches.
+    fn load_system(names: &[String], weight: f64, style: FontStyle) -> Option<SharedFont> {
+        let mut families = names
+            .iter()
+            .map(|n| match n.to_ascii_lowercase().as_str() {
+                "serif" => FamilyName::Serif,
+                "sans-serif" => FamilyName::SansSerif,
+                "monospace" => FamilyName::Monospace,
+                "cursive" => FamilyName::Cursive,
+                "fantasy" => FamilyName::Fantasy,
+                _ => FamilyName::Title(n.clone()),
+            })
+            .collect::<Vec<_>>();
+        families.push(FamilyName::Serif);
+        let style = match style {
+            FontStyle::Normal => Style::Normal,
+            FontStyle::Italic => Style::Italic,
+            FontStyle::Oblique(_) => Style::Oblique,
+        };
+        let handle = SystemSource::new()
+            .select_best_match(
+                &families,
+                Properties::new().weight(Weight(weight as f32)).style(style),
+            )
+            .ok()?;
+        SharedFont::from_handle(&handle)
+    }
+
+    //css fonts 4 font matching, minus stretch, best first: style narrows it down before weight
+    //does. a family split up by unicode-range has a face for each part, so they're all kept,
+    //and each character gets the best one that covers it.
+    pub fn select(&self, family: &str, weight: f64, style: FontStyle) -> Vec<&LoadedFace> {
+        let mut candidates = self
+            .faces
+            .iter()
+            .filter(|f| f.face.family.eq_ignore_ascii_case(family))
+            .collect::<Vec<_>>();
+        let distance = |f: &LoadedFace| {
+            (
+                style_distance(f.face.style, style),
+                weight_distance(f.face.weight, weight),
+            )
+        };
+        candidates.sort_by(|a, b| {
+            let (a, b) = (distance(a), distance(b));
+            a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+        });
+        candidates
+    }
+}
+
+//smaller is better, like weight_distance. italic goes italic, then oblique, then normal, oblique
+//goes oblique, italic, normal, and normal goes normal, oblique, italic, where a normal face is
+//oblique 0deg. oblique angles of 11deg or more look upwards first, smaller ones look downwards
+//to 0deg first, and then it's the other way and then the other side of 0deg. negative angles
+//are the same thing mirrored.
+fn style_distance(face: FontFaceStyle, desired: FontStyle) -> f64 {
+    let desired = match desired {
+        FontStyle::Italic if face == FontFaceStyle::Italic => return 0.,
+        FontStyle::Italic => 14.,
+        FontStyle::Normal => 0.,
+        FontStyle::Oblique(angle) => angle,
+    };
+    let (low, high) = match face {
+        FontFaceStyle::Italic => return 10000.,
+        FontFaceStyle::Normal if desired != 0. => return 20000.,
+        FontFaceStyle::Normal => (0., 0.),
+        FontFaceStyle::Oblique(low, high) => (low, high),
+    };
+    let (desired, low, high) = match desired < 0. {
+        true => (-desired, -high, -low),
+        false => (desired, low, high),
+    };
+    if (low..=high).contains(&desired) {
+        0.
+    } else if high < 0. {
+        2000. - high
+    } else if desired >= 11. {
+        if low > desired {
+            low - desired
+        } else {
+            1000. + desired - high
+        }
+    } else if high < desired {
+        desired - high
+    } else {
+        1000. + low - desired
+    }
+}
+
+//smaller is better. inside the range is perfect, otherwise it depends on which way the spec
+//tells us to look first for the desired weight.
+fn weight_distance((low, high): (f64, f64), desired: f64) -> f64 {
+    if (low..=high).contains(&desired) {
+        return 0.;
+    }
+    let (lighter, heavier) = (desired - high, low - desired);
+    if (400. ..=500.).contains(&desired) {
+        if low > desired && low <= 500. {
+            heavier
+        } else if high < desired {
+            1000. + lighter
+        } else {
+            2000. + heavier
+        }
+    } else if desired < 400. {
+        if high < desired {
+            lighter
+        } else {
+            1000. + heavier
+        }
+    } else if low > desired {
+        heavier
+    } else {
+        1000. + lighter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layout::text::TextLayoutifier,
+        parser::css::{properties::FontSize, CSSNumber, CSSProps, CSSValue, Numeric, Unit},
+    };
+
+    //open sans, which comes as woff2, so it goes through the decoder too.
+    fn fixture() -> SharedFont {
+        let data = include_bytes!("../../../real_shit/fonts/open-sans-regular.woff2");
+        SharedFont::new(Arc::new(woff::decode(data.to_vec()).unwrap()), 0).unwrap()
+    }
+
+    fn loaded(face: FontFace) -> LoadedFace {
+        LoadedFace {
+            face,
+            font: fixture(),
+        }
+    }
+
+    fn family(name: &str) -> FontFace {
+        FontFace {
+            family: String::from(name),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_caches() {
+        let fonts = FontSet {
+            faces: vec![loaded(family("shmeep sans"))],
+            ..Default::default()
+        };
+        let names = vec![String::from("shmeep sans"), String::from("sans-serif")];
+        let first = fonts.resolve(&names, 700., FontStyle::Normal).unwrap();
+        //clones share what's been resolved, so nothing gets read twice.
+        let again = fonts
+            .clone()
+            .resolve(&names, 700., FontStyle::Normal)
+            .unwrap();
+        assert!(Arc::ptr_eq(&first.faces, &again.faces));
+        assert_eq!(fonts.resolved.read().unwrap().len(), 1);
+        //the page's own face goes before anything installed.
+        assert!(first.faces[0].0.is_some());
+        assert_eq!(first.primary(), 0);
+        //another style has to be resolved by itself.
+        fonts.resolve(&names, 700., FontStyle::Italic).unwrap();
+        assert_eq!(fonts.resolved.read().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_style_matching() {
+        let face = |style, weight| {
+            loaded(FontFace {
+                style,
+                weight: (weight, weight),
+                ..family("shmeep")
+            })
+        };
+        let fonts = FontSet {
+            faces: vec![
+                face(FontFaceStyle::Normal, 400.),
+                face(FontFaceStyle::Italic, 400.),
+                face(FontFaceStyle::Italic, 700.),
+                face(FontFaceStyle::Oblique(20., 20.), 400.),
+                face(FontFaceStyle::Oblique(-10., -5.), 400.),
+                face(FontFaceStyle::Oblique(40., 50.), 400.),
+            ],
+            ..Default::default()
+        };
+        let order = |weight, style| {
+            fonts
+                .select("SHMEEP", weight, style)
+                .iter()
+                .map(|f| {
+                    fonts
+                        .faces
+                        .iter()
+                        .position(|g| std::ptr::eq(*f, g))
+                        .unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+        //style before weight, and then upright, oblique going up from 0deg and then the
+        //negative ones, and italic last.
+        assert_eq!(order(700., FontStyle::Normal), [0, 3, 5, 4, 2, 1]);
+        assert_eq!(order(700., FontStyle::Italic), [2, 1, 3, 5, 4, 0]);
+        //from 11deg up it looks for steeper ones before flatter ones.
+        assert_eq!(order(400., FontStyle::Oblique(30.)), [5, 3, 4, 1, 2, 0]);
+        assert_eq!(order(400., FontStyle::Oblique(-8.)), [4, 3, 5, 1, 2, 0]);
+        assert_eq!(order(400., FontStyle::Oblique(5.)), [3, 5, 4, 1, 2, 0]);
+    }
+
+    #[test]
+    fn test_unicode_range_per_character() {
+        let digits = FontFace {
+            unicode_range: vec![(0x30, 0x39)],
+            ..family("split")
+        };
+        let fonts = FontSet {
+            faces: vec![loaded(digits), loaded(family("rest"))],
+            ..Default::default()
+        };
+        let names = vec![String::from("split"), String::from("rest")];
+        let chain = fonts.resolve(&names, 400., FontStyle::Normal).unwrap();
+        assert!(chain.face_count() >= 2);
+        //it doesn't do spaces, so the other one's the primary one.
+        assert_eq!(chain.primary(), 1);
+        let css = CSSProps {
+            font_family: CSSValue::Value(FontFamily::Resolved(names, chain)),
+            font_size: CSSValue::Value(FontSize::new(CSSNumber::Unit(
+                Numeric::Number(16.),
+                Unit::Px,
+            ))),
+            ..Default::default()
+        };
+        let faces = TextLayoutifier::new(&css, "a1 b2", 1.)
+            .shape()
+            .iter()
+            .map(|glyph| glyph.face)
+            .collect::<Vec<_>>();
+        assert_eq!(faces, [1, 0, 1, 1, 0]);
+    }
+}
@@ -1,29 +1,30 @@
 use std::io::Cursor;
 
-use font_kit::font::Font;
 use font_types::F2Dot14;
 use read_fonts::{
-    tables::hmtx::Hmtx,
     types::{GlyphId, Tag},
     FontRef, TableProvider,
 };
 use vello::glyph;
 
-use super::LayoutInfo;
 use crate::{
-    layout::text::font_data::FontData,
+    layout::text::{
+        font_data::FontData,
+        font_set::{FontChain, SharedFont},
+    },
     parser::css::{
-        properties::{Colour, FontFamily, TextAlign},
+        properties::{Colour, FontFamily},
         CSSProps, CSSValue,
     },
 };
 
 pub mod font_data;
+pub mod font_set;
+pub mod woff;
 
 #[derive(Clone, Debug)]
 pub struct TextLayoutifier<'a> {
     containing_css: &'a CSSProps,
-    container: &'a LayoutInfo,
     contents: &'a str,
     scale_factor: f64,
 }
@@ -33,194 +34,152 @@ pub struct TextLayoutifier<'a> {
 //there's also the added bonus of no complete glyph shaping engine exists in pure rust, and it'd be nice to have one. not that things need to be written in rust to be usable- but it can't hurt.
 //this will need to be massively expanded upon and eventually lifted out into its own crate.
 //TODO: Presume nothing about a font
+//
+//this only shapes now, where the glyphs end up on lines is layout::inline's problem.
 impl<'a> TextLayoutifier<'a> {
-    pub fn new(
-        containing_css: &'a CSSProps,
-        container: &'a LayoutInfo,
-        contents: &'a str,
-        scale_factor: f64,
-    ) -> Self {
+    pub fn new(containing_css: &'a CSSProps, contents: &'a str, scale_factor: f64) -> Self {
         Self {
             containing_css,
-            container,
             contents,
             scale_factor,
         }
     }
 
-    pub fn lay_it_out(&self, parent_height: &mut f64) -> LaidoutText {
-        let font =
-            if let CSSValue::Value(FontFamily::Resolved(font)) = &self.containing_css.font_family {
-                font
-            } else {
-                unreachable!();
-            };
-
-        let font_raw = font.copy_font_data().unwrap();
-
-        let ot_data = FontRef::new(font_raw.as_slice()).unwrap();
-        println!("{:?}", self.contents.chars().collect::<Vec<_>>());
+    pub fn chain(&self) -> &'a FontChain {
+        if let CSSValue::Value(FontFamily::Resolved(_, chain)) = &self.containing_css.font_family {
+            chain
+        } else {
+            unreachable!();
+        }
+    }
 
-        let mut font_glyphs = Vec::new();
+    //the primary font, which everything but the glyphs themselves comes from.
+    pub fn font(&self) -> &'a SharedFont {
+        self.chain().primary_font()
+    }
 
-        let hmtx = ot_data.hmtx().unwrap();
-        let head = ot_data.head().unwrap();
-        let cmap = ot_data.cmap().unwrap();
-        let axes = if let Ok(fvar) = ot_data.fvar() {
-            let axes = fvar.axes().unwrap();
-            let mut out = Vec::with_capacity(axes.len());
-            for axis in axes {
-                if let Some(val) = self
-                    .containing_css
-                    .val_for_variable_tag(axis.axis_tag.get())
-                {
-                    let clamped =
-                        val.clamp(axis.min_value.get().to_f64(), axis.max_value.get().to_f64());
-                    let normalized = clamped / axis.max_value.get().to_f64();
-                    let twodot14 = F2Dot14::from_f32(normalized as f32);
-                    out.push(twodot14);
-                } else {
-                    //assuming that default value in a font has been clamped already... would love to be proven wrong kappa
-                    let normalized = axis.default_value.get() / axis.max_value.get();
-                    let twodot14 = normalized.to_f2dot14();
-                    out.push(twodot14);
+    //one glyph per char, each knowing how far it moves the pen along. each one comes from the
+    //first face in the chain that's allowed to do it and has a glyph for it, or the primary font
+    //if none of them do.
+    pub fn shape(&self) -> Vec<FontGlyph> {
+        let chain = self.chain();
+        let faces = (0..chain.face_count())
+            .map(|face| {
+                let ot_data = chain.font(face).font_ref();
+                let scale = (self.unwrap_font_size() * self.scale_factor)
+                    / ot_data.head().unwrap().units_per_em() as f64;
+                (ot_data.cmap().ok(), ot_data.hmtx().ok(), scale)
+            })
+            .collect::<Vec<_>>();
+        self.contents
+            .chars()
+            .map(|ch| {
+                let mut glyph = FontGlyph::default();
+                if ch.is_ascii_whitespace() {
+                    glyph.breakable = true;
                 }
-            }
-            Some(out)
-        } else {
-            None
-        };
-        //this chain is kinda just a hack to treat the last word as a word ill need to do something more real at some point.
-        for ch in self.contents.chars().chain("  ".chars()) {
-            let mut glyph = FontGlyph::default();
-            if ch.is_whitespace() {
-                glyph.breakable = true;
-            }
-            //check if newline and set broken.
-            let gid = cmap.map_codepoint(ch as u32);
-            if let Some(id) = gid {
-                glyph.id = id.to_u16();
-            }
-	    font_glyphs.push(glyph);
-	}
-        let font_size = self.unwrap_font_size();
-
-        let font_unit_scale_factor = (font_size * self.scale_factor) / head.units_per_em() as f64;
-
-        let mut glyphs = Vec::new();
-        let mut y_offset = self.container.y + self.container.content_height;
-        let mut line = Vec::new();
-        let mut wordish = Vec::new();
-        let mut glyphs_peekable = font_glyphs.iter().peekable();
-        while let Some(glyph) = glyphs_peekable.next() {
-            wordish.push(*glyph);
-            if glyph.breakable || glyph.broken {
-                let line_length =
-                    self.get_glyphs_length(&line, &hmtx, font_unit_scale_factor, true, true);
-                let word_length =
-                    self.get_glyphs_length(&wordish, &hmtx, font_unit_scale_factor, false, false);
-                if self.container.x + self.container.padding.1 - self.container.padding.2
-                    + line_length
-                    + word_length
-                    >= self.container.x + self.container.width + self.container.padding.1
-                        - self.container.padding.2
-                    || glyph.broken
-                    || glyphs_peekable.peek().is_none()
-                {
-                    let mut x_offset = match self.containing_css.text_align.unwrap() {
-                        TextAlign::Center => {
-                            self.container.x
-                                + ((self.container.width + self.container.padding.1 - line_length)
-                                    / 2.)
-                        }
-                        TextAlign::Left => self.container.x,
-                        TextAlign::Right => {
-                            self.container.x
-                                + (self.container.width + self.container.padding.1 - line_length)
-                        }
-                        TextAlign::Justify => unimplemented!("text-align: justify"),
-                    };
-                    y_offset += head.y_max() as f64 * font_unit_scale_factor;
-                    *parent_height += head.y_max() as f64 * font_unit_scale_factor; //need to find out how to make shit with lines that go below fit
-                    for letter in &line {
-                        glyphs.push(LaidoutGlyph {
-                            x: x_offset,
-                            y: y_offset,
-                            glyph: *letter,
-                        });
-                        let h_metrics = hmtx
-                            .h_metrics()
-                            .get(letter.id as usize)
-                            .expect("no metrics");
-                        x_offset += h_metrics.advance() as f64 * font_unit_scale_factor
-                            + h_metrics.side_bearing() as f64 * font_unit_scale_factor;
+                //check if newline and set broken.
+                let found = faces.iter().enumerate().find_map(|(face, (cmap, ..))| {
+                    if !chain.covers(face, ch) {
+                        return None;
+                    }
+                    let id = cmap.as_ref()?.map_codepoint(ch as u32)?;
+                    Some((face, id.to_u16()))
+                });
+                (glyph.face, glyph.id) = found.unwrap_or((chain.primary(), 0));
+                //glyphs past the end of hmtx all share the last advance.
+                let (_, hmtx, scale) = &faces[glyph.face];
+                if let Some(h_metrics) = hmtx.as_ref().map(|hmtx| hmtx.h_metrics()) {
+                    if let Some(metrics) = h_metrics.get(glyph.id as usize).or(h_metrics.last()) {
+                        glyph.advance = metrics.advance() as f64 * scale;
                     }
-                    line.clear();
                 }
-                line.extend(&wordish);
-                wordish.clear();
-            }
-        }
+                glyph
+            })
+            .collect()
+    }
 
-        LaidoutText {
-            glyphs,
-            font: font.clone(),
+    pub fn metrics(&self) -> FontMetrics {
+        let ot_data = self.font().font_ref();
+        let head = ot_data.head().unwrap();
+        let font_unit_scale_factor = self.font_unit_scale_factor();
+        //the typo metrics would be nicer, but hhea is what everyone else goes by.
+        let (ascent, descent, line_gap) = match ot_data.hhea() {
+            Ok(hhea) => (
+                hhea.ascender().to_i16() as f64,
+                -hhea.descender().to_i16() as f64,
+                hhea.line_gap().to_i16() as f64,
+            ),
+            Err(_) => (head.y_max() as f64, -head.y_min() as f64, 0.),
+        };
+        let font_size = self.unwrap_font_size() * self.scale_factor;
+        FontMetrics {
+            ascent: ascent * font_unit_scale_factor,
+            descent: descent * font_unit_scale_factor,
+            line_gap: line_gap * font_unit_scale_factor,
+            x_height: self.font().x_height().unwrap_or(0.5) * font_size,
             font_size,
-            colour: self.containing_css.color.unwrap(),
-            axes,
         }
     }
 
-    fn get_glyphs_length(
-        &self,
-        word: &Vec<FontGlyph>,
-        hmtx: &Hmtx,
-        scale_factor: f64,
-        trim_leading: bool,
-        trim_trailing: bool,
-    ) -> f64 {
-        let mut length = 0.;
-        let mut first_index: usize = 0;
-        let mut last_index: usize = word.len();
-        if trim_leading {
-            for letter in word {
-                if letter.breakable || letter.broken {
-                    first_index += 1;
-                } else {
-                    break;
-                }
-            }
-        }
-        if trim_trailing {
-            for (i, letter) in word.iter().enumerate().rev() {
-                if letter.breakable || letter.broken {
-                } else {
-                    last_index = i + 1;
-                    break;
-                }
+    pub fn axes(&self) -> Option<Vec<F2Dot14>> {
+        let ot_data = self.font().font_ref();
+        let fvar = ot_data.fvar().ok()?;
+        let axes = fvar.axes().unwrap();
+        let mut out = Vec::with_capacity(axes.len());
+        for axis in axes {
+            if let Some(val) = self
+                .containing_css
+                .val_for_variable_tag(axis.axis_tag.get())
+            {
+                let clamped =
+                    val.clamp(axis.min_value.get().to_f64(), axis.max_value.get().to_f64());
+                let normalized = clamped / axis.max_value.get().to_f64();
+                let twodot14 = F2Dot14::from_f32(normalized as f32);
+                out.push(twodot14);
+            } else {
+                //assuming that default value in a font has been clamped already... would love to be proven wrong kappa
+                let normalized = axis.default_value.get() / axis.max_value.get();
+                let twodot14 = normalized.to_f2dot14();
+                out.push(twodot14);
             }
         }
-        for letter in &word[first_index..last_index] {
-            let h_metrics = hmtx.h_metrics().get(letter.id as usize).unwrap();
-            length += h_metrics.advance() as f64 * scale_factor
-                + h_metrics.side_bearing() as f64 * scale_factor;
-        }
-        length
+        Some(out)
+    }
+
+    pub fn colour(&self) -> Colour {
+        self.containing_css.color.unwrap()
+    }
+
+    fn font_unit_scale_factor(&self) -> f64 {
+        let head = self.font().font_ref().head().unwrap();
+        (self.unwrap_font_size() * self.scale_factor) / head.units_per_em() as f64
     }
 
-    fn unwrap_font_size(&self) -> f64 {
-        if let CSSValue::Value(font_size) = self.containing_css.font_size {
-            font_size.value.unwrap().unwrap_f64()
+    pub fn unwrap_font_size(&self) -> f64 {
+        if let CSSValue::Value(font_size) = &self.containing_css.font_size {
+            font_size.px()
         } else {
             unreachable!()
         }
     }
 }
 
+//in pixels. descent's positive, going down from the baseline.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FontMetrics {
+    pub ascent: f64,
+    pub descent: f64,
+    pub line_gap: f64,
+    pub x_height: f64,
+    pub font_size: f64,
+}
+
 #[derive(Debug)]
 pub struct LaidoutText {
+    pub text: String, //what it was before, for laying it out again
     pub glyphs: Vec<LaidoutGlyph>,
-    pub font: Font,
+    pub font: FontChain,
     pub font_size: f64,
     pub colour: Colour,
     pub axes: Option<Vec<F2Dot14>>,
@@ -245,6 +204,8 @@ pub struct LaidoutGlyph {
 pub struct FontGlyph {
     //will probably jsut become pub type FontGlyph = usize; but for now keeping it as a struct in case i want to store extra data on a glyph.
     pub id: u16,
+    pub face: usize, //which of the font chain's faces it's from
+    pub advance: f64,
     pub broken: bool,
     pub breakable: bool,
 }
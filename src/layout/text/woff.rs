@@ -0,0 +1,727 @@
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+use thiserror::Error;
+
+//turns whatever a @font-face src handed us back into a plain sfnt (ttf/otf) that font-kit and
+//read-fonts can deal with. woff is just zlib per table, woff2 is brotli over everything plus a
+//couple of table transforms that have to be undone by hand.
+pub fn decode(data: Vec<u8>) -> Result<Vec<u8>, FontDecodeError> {
+    match data.get(0..4) {
+        Some(b"wOFF") => decode_woff(&data),
+        Some(b"wOF2") => decode_woff2(&data),
+        Some([0x00, 0x01, 0x00, 0x00] | b"OTTO" | b"true" | b"ttcf") => Ok(data),
+        _ => Err(FontDecodeError::UnknownFormat),
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FontDecodeError {
+    #[error("Not a font format we know about!")]
+    UnknownFormat,
+    #[error("Font data ended early!")]
+    Truncated,
+    #[error("Font data is malformed: {0}")]
+    Malformed(&'static str),
+    #[error("Decompression failed!: {0}")]
+    Decompression(#[from] std::io::Error),
+}
+
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+const GLYF: u32 = u32::from_be_bytes(*b"glyf");
+const LOCA: u32 = u32::from_be_bytes(*b"loca");
+const HMTX: u32 = u32::from_be_bytes(*b"hmtx");
+const HHEA: u32 = u32::from_be_bytes(*b"hhea");
+const HEAD: u32 = u32::from_be_bytes(*b"head");
+
+struct Reader<'a> {
+    data: &'a [u8],
+    idx: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, idx: 0 }
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], FontDecodeError> {
+        let out = self
+            .data
+            .get(self.idx..self.idx + n)
+            .ok_or(FontDecodeError::Truncated)?;
+        self.idx += n;
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, FontDecodeError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, FontDecodeError> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, FontDecodeError> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, FontDecodeError> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn base128(&mut self) -> Result<u32, FontDecodeError> {
+        let mut out: u32 = 0;
+        for i in 0..5 {
+            let b = self.u8()?;
+            if i == 0 && b == 0x80 {
+                do yeet FontDecodeError::Malformed("leading zero in UIntBase128");
+            }
+            if out & 0xFE000000 != 0 {
+                do yeet FontDecodeError::Malformed("UIntBase128 overflow");
+            }
+            out = (out << 7) | (b & 0x7F) as u32;
+            if b & 0x80 == 0 {
+                return Ok(out);
+            }
+        }
+        Err(FontDecodeError::Malformed("UIntBase128 too long"))
+    }
+
+    fn u255(&mut self) -> Result<u16, FontDecodeError> {
+        Ok(match self.u8()? {
+            253 => self.u16()?,
+            254 => self.u8()? as u16 + 253 * 2,
+            255 => self.u8()? as u16 + 253,
+            c => c as u16,
+        })
+    }
+}
+
+fn decode_woff(data: &[u8]) -> Result<Vec<u8>, FontDecodeError> {
+    let mut header = Reader::new(data);
+    header.bytes(4)?; //signature
+    let flavor = header.u32()?;
+    header.bytes(4)?; //length
+    let num_tables = header.u16()?;
+    header.bytes(30)?; //rest of the header, nothing in there we need
+    let mut tables = Vec::with_capacity(num_tables as usize);
+    for _ in 0..num_tables {
+        let tag = header.u32()?;
+        let offset = header.u32()? as usize;
+        let compressed_length = header.u32()? as usize;
+        let original_length = header.u32()? as usize;
+        header.u32()?; //checksum, recomputed when we write the sfnt back out
+        let raw = data
+            .get(offset..offset + compressed_length)
+            .ok_or(FontDecodeError::Truncated)?;
+        let table = if compressed_length < original_length {
+            let mut out = Vec::with_capacity(original_length);
+            ZlibDecoder::new(raw).read_to_end(&mut out)?;
+            out
+        } else {
+            raw.to_vec()
+        };
+        if table.len() != original_length {
+            do yeet FontDecodeError::Malformed("woff table has the wrong length");
+        }
+        tables.push((tag, table));
+    }
+    Ok(build_sfnt(flavor, tables))
+}
+
+struct Woff2Table {
+    tag: u32,
+    original_length: usize,
+    transform_length: usize,
+    transformed: bool,
+}
+
+fn decode_woff2(data: &[u8]) -> Result<Vec<u8>, FontDecodeError> {
+    let mut header = Reader::new(data);
+    header.bytes(4)?; //signature
+    let flavor = header.u32()?;
+    if flavor == u32::from_be_bytes(*b"ttcf") {
+        do yeet FontDecodeError::Malformed("woff2 collections aren't supported");
+    }
+    header.bytes(4)?; //length
+    let num_tables = header.u16()?;
+    header.bytes(6)?; //reserved, totalSfntSize
+    let compressed_size = header.u32()? as usize;
+    header.bytes(24)?; //versions, metadata and private blocks
+
+    let mut directory = Vec::with_capacity(num_tables as usize);
+    for _ in 0..num_tables {
+        let flags = header.u8()?;
+        let tag = if flags & 0x3F == 0x3F {
+            header.u32()?
+        } else {
+            u32::from_be_bytes(*KNOWN_TAGS[(flags & 0x3F) as usize])
+        };
+        let version = flags >> 6;
+        let original_length = header.base128()? as usize;
+        //glyf and loca are backwards, version 0 is the transformed one for them.
+        let transformed = if tag == GLYF || tag == LOCA {
+            version != 3
+        } else {
+            version != 0
+        };
+        let transform_length = if transformed {
+            header.base128()? as usize
+        } else {
+            original_length
+        };
+        directory.push(Woff2Table {
+            tag,
+            original_length,
+            transform_length,
+            transformed,
+        });
+    }
+
+    let compressed = header.bytes(compressed_size)?;
+    let mut stream = Vec::new();
+    brotli_decompressor::Decompressor::new(compressed, 4096).read_to_end(&mut stream)?;
+
+    let mut raw_tables = Vec::with_capacity(directory.len());
+    let mut offset = 0;
+    for table in &directory {
+        let bytes = stream
+            .get(offset..offset + table.transform_length)
+            .ok_or(FontDecodeError::Truncated)?;
+        raw_tables.push(bytes);
+        offset += table.transform_length;
+    }
+
+    let mut tables: Vec<(u32, Vec<u8>)> = Vec::with_capacity(directory.len());
+    let mut x_mins: Vec<i16> = vec![];
+    let raw = |tag| {
+        directory
+            .iter()
+            .position(|t| t.tag == tag)
+            .map(|i| raw_tables[i])
+    };
+    let glyf_idx = directory.iter().position(|t| t.tag == GLYF);
+    if let Some(i) = glyf_idx.filter(|i| directory[*i].transformed) {
+        let (glyf, loca, mins) = reconstruct_glyf(raw_tables[i])?;
+        x_mins = mins;
+        tables.push((GLYF, glyf));
+        tables.push((LOCA, loca));
+    } else if let (Some(glyf), Some(loca), Some(head)) = (raw(GLYF), raw(LOCA), raw(HEAD)) {
+        x_mins = glyf_x_mins(glyf, loca, head)?;
+    }
+    for (i, table) in directory.iter().enumerate() {
+        if tables.iter().any(|(t, _)| *t == table.tag) {
+            continue;
+        }
+        if table.tag == HMTX && table.transformed {
+            let hhea =
+                raw(HHEA).ok_or(FontDecodeError::Malformed("transformed hmtx without hhea"))?;
+            let number_of_h_metrics = Reader::new(hhea.get(34..).unwrap_or(&[])).u16()?;
+            tables.push((
+                HMTX,
+                reconstruct_hmtx(raw_tables[i], number_of_h_metrics, &x_mins)?,
+            ));
+        } else if table.transformed {
+            do yeet FontDecodeError::Malformed("unknown table transform");
+        } else {
+            let mut bytes = raw_tables[i].to_vec();
+            bytes.truncate(table.original_length);
+            if table.tag == HEAD && bytes.len() >= 12 {
+                //checksum adjustment gets fixed up once the whole font exists again
+                bytes[8..12].copy_from_slice(&[0; 4]);
+            }
+            tables.push((table.tag, bytes));
+        }
+    }
+    Ok(build_sfnt(flavor, tables))
+}
+
+//the x mins a transformed hmtx leaves out, from a glyf that wasn't transformed itself. they're
+//in each glyph's header, and empty glyphs don't have one, so theirs are 0.
+fn glyf_x_mins(glyf: &[u8], loca: &[u8], head: &[u8]) -> Result<Vec<i16>, FontDecodeError> {
+    let long_offsets = Reader::new(head.get(50..).unwrap_or(&[])).i16()? != 0;
+    let offsets = match long_offsets {
+        true => loca
+            .chunks_exact(4)
+            .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]) as usize)
+            .collect::<Vec<_>>(),
+        false => loca
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]) as usize * 2)
+            .collect::<Vec<_>>(),
+    };
+    offsets
+        .windows(2)
+        .map(|range| match range[1] > range[0] {
+            true => Reader::new(glyf.get(range[0] + 2..).unwrap_or(&[])).i16(),
+            false => Ok(0),
+        })
+        .collect()
+}
+
+//returns (glyf, loca, x_min per glyph), the x mins are for rebuilding hmtx.
+fn reconstruct_glyf(data: &[u8]) -> Result<(Vec<u8>, Vec<u8>, Vec<i16>), FontDecodeError> {
+    let mut header = Reader::new(data);
+    header.u16()?; //reserved
+    let option_flags = header.u16()?;
+    let num_glyphs = header.u16()? as usize;
+    let index_format = header.u16()?;
+    let mut sizes = [0usize; 7];
+    for size in &mut sizes {
+        *size = header.u32()? as usize;
+    }
+    let mut streams = Vec::with_capacity(7);
+    for size in sizes {
+        streams.push(header.bytes(size)?);
+    }
+    let overlap_bitmap = if option_flags & 1 != 0 {
+        Some(header.bytes((num_glyphs + 7) / 8)?)
+    } else {
+        None
+    };
+    let mut n_contours = Reader::new(streams[0]);
+    let mut n_points = Reader::new(streams[1]);
+    let mut flags = Reader::new(streams[2]);
+    let mut glyphs = Reader::new(streams[3]);
+    let mut composites = Reader::new(streams[4]);
+    let bbox_bitmap_len = 4 * ((num_glyphs + 31) / 32);
+    let bbox_bitmap = streams[5]
+        .get(..bbox_bitmap_len)
+        .ok_or(FontDecodeError::Truncated)?;
+    let mut bboxes = Reader::new(&streams[5][bbox_bitmap_len..]);
+    let mut instructions = Reader::new(streams[6]);
+
+    let mut glyf = Vec::new();
+    let mut offsets = Vec::with_capacity(num_glyphs + 1);
+    let mut x_mins = Vec::with_capacity(num_glyphs);
+    for glyph_id in 0..num_glyphs {
+        offsets.push(glyf.len());
+        let has_bbox = bbox_bitmap[glyph_id >> 3] & (0x80 >> (glyph_id & 7)) != 0;
+        let contours = n_contours.i16()?;
+        if contours == 0 {
+            if has_bbox {
+                do yeet FontDecodeError::Malformed("empty glyph with a bounding box");
+            }
+            x_mins.push(0);
+            continue;
+        }
+        if contours < 0 {
+            if !has_bbox {
+                do yeet FontDecodeError::Malformed("composite glyph without a bounding box");
+            }
+            let bbox = [bboxes.i16()?, bboxes.i16()?, bboxes.i16()?, bboxes.i16()?];
+            x_mins.push(bbox[0]);
+            let start = composites.idx;
+            let mut has_instructions = false;
+            loop {
+                let flag = composites.u16()?;
+                composites.u16()?; //glyph index
+                let mut skip = if flag & 0x0001 != 0 { 4 } else { 2 };
+                if flag & 0x0008 != 0 {
+                    skip += 2;
+                } else if flag & 0x0040 != 0 {
+                    skip += 4;
+                } else if flag & 0x0080 != 0 {
+                    skip += 8;
+                }
+                composites.bytes(skip)?;
+                has_instructions |= flag & 0x0100 != 0;
+                if flag & 0x0020 == 0 {
+                    break;
+                }
+            }
+            glyf.extend((-1i16).to_be_bytes());
+            for v in bbox {
+                glyf.extend(v.to_be_bytes());
+            }
+            glyf.extend(&composites.data[start..composites.idx]);
+            if has_instructions {
+                let length = glyphs.u255()?;
+                glyf.extend(length.to_be_bytes());
+                glyf.extend(instructions.bytes(length as usize)?);
+            }
+        } else {
+            let mut end_points = Vec::with_capacity(contours as usize);
+            let mut total = 0u16;
+            for _ in 0..contours {
+                total = total
+                    .checked_add(n_points.u255()?)
+                    .ok_or(FontDecodeError::Malformed("too many points"))?;
+                end_points.push(total.wrapping_sub(1));
+            }
+            let mut points = Vec::with_capacity(total as usize);
+            let (mut x, mut y) = (0i32, 0i32);
+            for _ in 0..total {
+                let flag = flags.u8()?;
+                let (dx, dy) = decode_triplet(flag & 0x7F, &mut glyphs)?;
+                x += dx;
+                y += dy;
+                points.push((x, y, flag & 0x80 == 0));
+            }
+            let instruction_length = glyphs.u255()?;
+            let bbox = if has_bbox {
+                [bboxes.i16()?, bboxes.i16()?, bboxes.i16()?, bboxes.i16()?]
+            } else {
+                let mut bbox = [i16::MAX, i16::MAX, i16::MIN, i16::MIN];
+                for (x, y, _) in &points {
+                    bbox[0] = bbox[0].min(*x as i16);
+                    bbox[1] = bbox[1].min(*y as i16);
+                    bbox[2] = bbox[2].max(*x as i16);
+                    bbox[3] = bbox[3].max(*y as i16);
+                }
+                bbox
+            };
+            x_mins.push(bbox[0]);
+            glyf.extend(contours.to_be_bytes());
+            for v in bbox {
+                glyf.extend(v.to_be_bytes());
+            }
+            for end in end_points {
+                glyf.extend(end.to_be_bytes());
+            }
+            glyf.extend(instruction_length.to_be_bytes());
+            glyf.extend(instructions.bytes(instruction_length as usize)?);
+            let overlaps = overlap_bitmap
+                .is_some_and(|bitmap| bitmap[glyph_id >> 3] & (0x80 >> (glyph_id & 7)) != 0);
+            //no repeat compression, just one flag per point. good enough.
+            let mut out_flags = Vec::with_capacity(points.len());
+            let mut xs = Vec::new();
+            let mut ys = Vec::new();
+            let (mut last_x, mut last_y) = (0i32, 0i32);
+            for (i, (x, y, on_curve)) in points.iter().enumerate() {
+                let mut flag = if *on_curve { 0x01 } else { 0x00 };
+                if i == 0 && overlaps {
+                    flag |= 0x40;
+                }
+                let (dx, dy) = (x - last_x, y - last_y);
+                if dx == 0 {
+                    flag |= 0x10;
+                } else if dx.abs() < 256 {
+                    flag |= 0x02 | if dx > 0 { 0x10 } else { 0 };
+                    xs.push(dx.unsigned_abs() as u8);
+                } else {
+                    xs.extend((dx as i16).to_be_bytes());
+                }
+                if dy == 0 {
+                    flag |= 0x20;
+                } else if dy.abs() < 256 {
+                    flag |= 0x04 | if dy > 0 { 0x20 } else { 0 };
+                    ys.push(dy.unsigned_abs() as u8);
+                } else {
+                    ys.extend((dy as i16).to_be_bytes());
+                }
+                out_flags.push(flag);
+                (last_x, last_y) = (*x, *y);
+            }
+            glyf.extend(out_flags);
+            glyf.extend(xs);
+            glyf.extend(ys);
+        }
+        //short loca offsets are halved, so everything has to stay even. 4 is nicer anyway.
+        while glyf.len() % 4 != 0 {
+            glyf.push(0);
+        }
+    }
+    offsets.push(glyf.len());
+
+    let mut loca = Vec::with_capacity(offsets.len() * 4);
+    for offset in offsets {
+        if index_format == 0 {
+            loca.extend(((offset / 2) as u16).to_be_bytes());
+        } else {
+            loca.extend((offset as u32).to_be_bytes());
+        }
+    }
+    Ok((glyf, loca, x_mins))
+}
+
+fn decode_triplet(flag: u8, glyphs: &mut Reader) -> Result<(i32, i32), FontDecodeError> {
+    fn with_sign(flag: u8, value: i32) -> i32 {
+        if flag & 1 != 0 {
+            value
+        } else {
+            -value
+        }
+    }
+    let flag_i = flag as i32;
+    Ok(if flag < 10 {
+        let b0 = glyphs.u8()? as i32;
+        (0, with_sign(flag, ((flag_i & 14) << 7) + b0))
+    } else if flag < 20 {
+        let b0 = glyphs.u8()? as i32;
+        (with_sign(flag, (((flag_i - 10) & 14) << 7) + b0), 0)
+    } else if flag < 84 {
+        let b0 = flag_i - 20;
+        let b1 = glyphs.u8()? as i32;
+        (
+            with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)),
+            with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (b1 & 0x0F)),
+        )
+    } else if flag < 120 {
+        let b0 = flag_i - 84;
+        let (b1, b2) = (glyphs.u8()? as i32, glyphs.u8()? as i32);
+        (
+            with_sign(flag, 1 + ((b0 / 12) << 8) + b1),
+            with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2),
+        )
+    } else if flag < 124 {
+        let (b1, b2, b3) = (
+            glyphs.u8()? as i32,
+            glyphs.u8()? as i32,
+            glyphs.u8()? as i32,
+        );
+        (
+            with_sign(flag, (b1 << 4) + (b2 >> 4)),
+            with_sign(flag >> 1, ((b2 & 0x0F) << 8) + b3),
+        )
+    } else {
+        let (b1, b2, b3, b4) = (
+            glyphs.u8()? as i32,
+            glyphs.u8()? as i32,
+            glyphs.u8()? as i32,
+            glyphs.u8()? as i32,
+        );
+        (
+            with_sign(flag, (b1 << 8) + b2),
+            with_sign(flag >> 1, (b3 << 8) + b4),
+        )
+    })
+}
+
+fn reconstruct_hmtx(
+    data: &[u8],
+    number_of_h_metrics: u16,
+    x_mins: &[i16],
+) -> Result<Vec<u8>, FontDecodeError> {
+    let mut reader = Reader::new(data);
+    let flags = reader.u8()?;
+    let num_glyphs = x_mins.len();
+    let number_of_h_metrics = number_of_h_metrics as usize;
+    let mut advances = Vec::with_capacity(number_of_h_metrics);
+    for _ in 0..number_of_h_metrics {
+        advances.push(reader.u16()?);
+    }
+    let mut lsbs = Vec::with_capacity(num_glyphs);
+    for i in 0..number_of_h_metrics {
+        lsbs.push(if flags & 1 == 0 {
+            reader.i16()?
+        } else {
+            *x_mins.get(i).unwrap_or(&0)
+        });
+    }
+    for i in number_of_h_metrics..num_glyphs {
+        lsbs.push(if flags & 2 == 0 {
+            reader.i16()?
+        } else {
+            *x_mins.get(i).unwrap_or(&0)
+        });
+    }
+    let mut out = Vec::with_capacity(number_of_h_metrics * 4 + num_glyphs * 2);
+    for (i, lsb) in lsbs.iter().enumerate() {
+        if i < number_of_h_metrics {
+            out.extend(advances[i].to_be_bytes());
+        }
+        out.extend(lsb.to_be_bytes());
+    }
+    Ok(out)
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn build_sfnt(flavor: u32, mut tables: Vec<(u32, Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    let num_tables = tables.len() as u16;
+    let entry_selector = (num_tables.max(1) as f64).log2().floor() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+    let range_shift = num_tables * 16 - search_range.min(num_tables * 16);
+
+    let mut out = Vec::new();
+    out.extend(flavor.to_be_bytes());
+    out.extend(num_tables.to_be_bytes());
+    out.extend(search_range.to_be_bytes());
+    out.extend(entry_selector.to_be_bytes());
+    out.extend(range_shift.to_be_bytes());
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, data) in &tables {
+        out.extend(tag.to_be_bytes());
+        out.extend(checksum(data).to_be_bytes());
+        out.extend((offset as u32).to_be_bytes());
+        out.extend((data.len() as u32).to_be_bytes());
+        offset += (data.len() + 3) & !3;
+    }
+    let mut head_offset = None;
+    for (tag, data) in &tables {
+        if *tag == HEAD {
+            head_offset = Some(out.len());
+        }
+        out.extend(data);
+        while out.len() % 4 != 0 {
+            out.push(0);
+        }
+    }
+    if let Some(head) = head_offset.filter(|h| out.len() >= h + 12) {
+        out[head + 8..head + 12].copy_from_slice(&[0; 4]);
+        let adjustment = 0xB1B0AFBAu32.wrapping_sub(checksum(&out));
+        out[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::ZlibEncoder, Compression};
+
+    use super::*;
+
+    #[test]
+    fn test_base128_and_255uint16() {
+        assert_eq!(Reader::new(&[0x3F]).base128().unwrap(), 63);
+        assert_eq!(Reader::new(&[0x81, 0x00]).base128().unwrap(), 128);
+        assert!(Reader::new(&[0x80, 0x01]).base128().is_err());
+        assert_eq!(Reader::new(&[252]).u255().unwrap(), 252);
+        assert_eq!(Reader::new(&[255, 3]).u255().unwrap(), 256);
+        assert_eq!(Reader::new(&[254, 0]).u255().unwrap(), 506);
+        assert_eq!(Reader::new(&[253, 0x03, 0xE8]).u255().unwrap(), 1000);
+    }
+
+    #[test]
+    fn test_woff_round_trip() {
+        let shmeep = b"shmeep shmeep shmeep shmeep shmeep".to_vec();
+        let shmop = b"shm".to_vec();
+        let expected = build_sfnt(
+            0x00010000,
+            vec![
+                (u32::from_be_bytes(*b"name"), shmeep.clone()),
+                (u32::from_be_bytes(*b"post"), shmop.clone()),
+            ],
+        );
+
+        let mut compressed = ZlibEncoder::new(Vec::new(), Compression::default());
+        compressed.write_all(&shmeep).unwrap();
+        let compressed = compressed.finish().unwrap();
+        let mut woff = Vec::new();
+        woff.extend(b"wOFF");
+        woff.extend(0x00010000u32.to_be_bytes());
+        woff.extend([0; 4]);
+        woff.extend(2u16.to_be_bytes());
+        woff.extend([0; 30]);
+        let data_start = 44 + 2 * 20;
+        for (tag, offset, stored, original) in [
+            (b"name", data_start, compressed.len(), shmeep.len()),
+            (
+                b"post",
+                data_start + compressed.len(),
+                shmop.len(),
+                shmop.len(),
+            ),
+        ] {
+            woff.extend(tag);
+            woff.extend((offset as u32).to_be_bytes());
+            woff.extend((stored as u32).to_be_bytes());
+            woff.extend((original as u32).to_be_bytes());
+            woff.extend([0; 4]);
+        }
+        woff.extend(&compressed);
+        woff.extend(&shmop);
+        assert_eq!(decode(woff).unwrap(), expected);
+    }
+
+    //brotli can store a meta-block without compressing it, so there's no need for an encoder.
+    fn uncompressed_brotli(data: &[u8]) -> Vec<u8> {
+        //a 16 bit window, not the last meta-block, four nibbles of length, uncompressed.
+        let header = ((data.len() as u32 - 1) << 4) | (1 << 20);
+        let mut out = header.to_le_bytes()[..3].to_vec();
+        out.extend(data);
+        out.push(0b11); //the last meta-block, and it's empty
+        out
+    }
+
+    #[test]
+    fn test_woff2_transformed_hmtx_with_plain_glyf() {
+        let mut head = vec![0; 54]; //short loca offsets
+        head[0..4].copy_from_slice(&0x00010000u32.to_be_bytes());
+        let mut hhea = vec![0; 36];
+        hhea[34..36].copy_from_slice(&2u16.to_be_bytes());
+        //glyph 0's empty, and 1 and 2 are nothing but headers, with x mins of 7 and -3.
+        let mut glyf = vec![0; 20];
+        glyf[2..4].copy_from_slice(&7i16.to_be_bytes());
+        glyf[12..14].copy_from_slice(&(-3i16).to_be_bytes());
+        let loca = [0u16, 0, 5, 10]
+            .iter()
+            .flat_map(|o| o.to_be_bytes())
+            .collect::<Vec<_>>();
+        //both sets of left side bearings left out, so they have to come from glyf.
+        let mut hmtx = vec![0b11];
+        hmtx.extend(500u16.to_be_bytes());
+        hmtx.extend(600u16.to_be_bytes());
+        let expected_hmtx = [
+            500u16.to_be_bytes(),
+            0i16.to_be_bytes(),
+            600u16.to_be_bytes(),
+        ]
+        .into_iter()
+        .chain([7i16.to_be_bytes(), (-3i16).to_be_bytes()])
+        .flatten()
+        .collect::<Vec<_>>();
+
+        let tables = [
+            (1, &head, None),
+            (2, &hhea, None),
+            (3 << 6 | 10, &glyf, None),
+            (3 << 6 | 11, &loca, None),
+            (1 << 6 | 3, &hmtx, Some(expected_hmtx.len())),
+        ];
+        let stream = tables
+            .iter()
+            .flat_map(|(_, data, _)| data.iter().copied())
+            .collect::<Vec<_>>();
+        let compressed = uncompressed_brotli(&stream);
+        let mut woff2 = Vec::new();
+        woff2.extend(b"wOF2");
+        woff2.extend(0x00010000u32.to_be_bytes());
+        woff2.extend([0; 4]);
+        woff2.extend((tables.len() as u16).to_be_bytes());
+        woff2.extend([0; 6]);
+        woff2.extend((compressed.len() as u32).to_be_bytes());
+        woff2.extend([0; 24]);
+        for (flags, data, original) in &tables {
+            woff2.push(*flags);
+            match original {
+                Some(original) => woff2.extend([*original as u8, data.len() as u8]),
+                None => woff2.push(data.len() as u8),
+            }
+        }
+        woff2.extend(&compressed);
+
+        let expected = build_sfnt(
+            0x00010000,
+            vec![
+                (HEAD, head.clone()),
+                (HHEA, hhea.clone()),
+                (GLYF, glyf.clone()),
+                (LOCA, loca.clone()),
+                (HMTX, expected_hmtx),
+            ],
+        );
+        assert_eq!(decode(woff2).unwrap(), expected);
+    }
+}
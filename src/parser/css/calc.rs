@@ -0,0 +1,452 @@
+use std::fmt;
+
+use super::{BlockKind, CSSNumber, CSSToken, Component, Unit};
+use crate::{layout::text::font_set::SharedFont, parser::Char};
+
+//everything a relative length could be relative to, so it can be turned into pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct LengthContext {
+    pub font_size: f64,
+    pub root_font_size: f64,
+    pub x_height: f64,     //ex
+    pub zero_advance: f64, //ch
+    pub viewport_width: f64,
+    pub viewport_height: f64,
+}
+
+impl Default for LengthContext {
+    fn default() -> Self {
+        Self::new(16., 16., None, 0., 0.)
+    }
+}
+
+impl LengthContext {
+    //without a font to measure, ex and ch fall back to half an em like the spec suggests.
+    pub fn new(
+        font_size: f64,
+        root_font_size: f64,
+        font: Option<&SharedFont>,
+        viewport_width: f64,
+        viewport_height: f64,
+    ) -> Self {
+        let em = |measure: Option<f64>| measure.map_or(font_size / 2., |m| m * font_size);
+        let x_height = em(font.and_then(SharedFont::x_height));
+        let zero_advance = em(font.and_then(SharedFont::zero_advance));
+        Self {
+            font_size,
+            root_font_size,
+            x_height,
+            zero_advance,
+            viewport_width,
+            viewport_height,
+        }
+    }
+
+    //None for units that aren't lengths at all.
+    pub fn to_px(&self, value: f64, unit: Unit) -> Option<f64> {
+        Some(match unit {
+            Unit::Px => value,
+            Unit::Cm => value * 96. / 2.54,
+            Unit::Mm => value * 96. / 25.4,
+            Unit::Q => value * 96. / 101.6,
+            Unit::In => value * 96.,
+            Unit::Pt => value * 96. / 72.,
+            Unit::Pc => value * 16.,
+            Unit::Em => value * self.font_size,
+            Unit::Rem => value * self.root_font_size,
+            Unit::Ex => value * self.x_height,
+            Unit::Ch => value * self.zero_advance,
+            Unit::Vw => value * self.viewport_width / 100.,
+            Unit::Vh => value * self.viewport_height / 100.,
+            Unit::Vmin => value * self.viewport_width.min(self.viewport_height) / 100.,
+            Unit::Vmax => value * self.viewport_width.max(self.viewport_height) / 100.,
+            Unit::Dpi | Unit::Dpcm | Unit::Dppx | Unit::X => return None,
+        })
+    }
+}
+
+//calc() and friends. subtraction is a sum with the right side multiplied by -1 and division is
+//multiplying by the inverse, so there's less to deal with when resolving.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Calc {
+    Number(f64),
+    Percentage(f64),
+    Length(f64, Unit),
+    Sum(Vec<Calc>),
+    Product(Vec<Calc>),
+    Invert(Box<Calc>),
+    Min(Vec<Calc>),
+    Max(Vec<Calc>),
+    Clamp(Box<Calc>, Box<Calc>, Box<Calc>),
+}
+
+//what a calculation ends up as. percentages count as lengths, since everywhere we use calc() so
+//far they turn into one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CalcKind {
+    Number,
+    Length,
+}
+
+impl Calc {
+    //None if it isn't a math function or it doesn't make sense, like calc(1px * 1px).
+    pub fn from_component(component: &Component) -> Option<Self> {
+        let Component::Function(name, arguments) = component else {
+            return None;
+        };
+        let calc = match name.to_ascii_lowercase().as_str() {
+            "calc" => Self::parse_sum(arguments)?,
+            "min" => Self::Min(Self::parse_list(arguments)?),
+            "max" => Self::Max(Self::parse_list(arguments)?),
+            "clamp" => {
+                let [low, value, high] = Self::parse_list(arguments)?.try_into().ok()?;
+                Self::Clamp(Box::new(low), Box::new(value), Box::new(high))
+            }
+            _ => return None,
+        };
+        calc.kind()?;
+        Some(calc)
+    }
+
+    fn parse_list(components: &[Component]) -> Option<Vec<Self>> {
+        components
+            .split(|c| matches!(c, Component::Token(CSSToken::Comma)))
+            .map(Self::parse_sum)
+            .collect()
+    }
+
+    //+ and - need whitespace around them, which the tokenizer already takes care of: `1px -2px`
+    //is two numbers in a row, not a subtraction, and gets turned away.
+    fn parse_sum(components: &[Component]) -> Option<Self> {
+        let mut terms = vec![];
+        let mut current = vec![];
+        let mut negate = false;
+        for component in components {
+            match component {
+                Component::Token(CSSToken::Whitespace) => {}
+                Component::Token(CSSToken::Delim(Char::Char(c @ ('+' | '-')))) => {
+                    terms.push(Self::term(Self::parse_product(&current)?, negate));
+                    current.clear();
+                    negate = *c == '-';
+                }
+                c => current.push(c),
+            }
+        }
+        terms.push(Self::term(Self::parse_product(&current)?, negate));
+        Some(match terms.len() {
+            1 => terms.remove(0),
+            _ => Self::Sum(terms),
+        })
+    }
+
+    fn term(value: Self, negate: bool) -> Self {
+        match negate {
+            true => Self::Product(vec![Self::Number(-1.), value]),
+            false => value,
+        }
+    }
+
+    fn parse_product(components: &[&Component]) -> Option<Self> {
+        let mut iter = components.iter();
+        let mut factors = vec![Self::parse_value(iter.next()?)?];
+        while let Some(operator) = iter.next() {
+            let value = Self::parse_value(iter.next()?)?;
+            match operator {
+                Component::Token(CSSToken::Delim(Char::Char('*'))) => factors.push(value),
+                Component::Token(CSSToken::Delim(Char::Char('/'))) => {
+                    factors.push(Self::Invert(Box::new(value)))
+                }
+                _ => return None,
+            }
+        }
+        Some(match factors.len() {
+            1 => factors.remove(0),
+            _ => Self::Product(factors),
+        })
+    }
+
+    fn parse_value(component: &Component) -> Option<Self> {
+        match component {
+            Component::Token(CSSToken::Number(n)) => Some(match n {
+                CSSNumber::Number(n) => Self::Number(n.unwrap_f64()),
+                CSSNumber::Percentage(n) => Self::Percentage(n.unwrap_f64()),
+                CSSNumber::Unit(n, u) => Self::Length(n.unwrap_f64(), *u),
+            }),
+            Component::Token(CSSToken::Ident(i)) => match i.to_ascii_lowercase().as_str() {
+                "pi" => Some(Self::Number(std::f64::consts::PI)),
+                "e" => Some(Self::Number(std::f64::consts::E)),
+                _ => None,
+            },
+            Component::Block(block) if block.kind == BlockKind::Paren => {
+                Self::parse_sum(&block.value)
+            }
+            Component::Function(..) => Self::from_component(component),
+            _ => None,
+        }
+    }
+
+    //type checking, only the bits of css values 4's typing that matter for numbers and lengths.
+    pub fn kind(&self) -> Option<CalcKind> {
+        match self {
+            Self::Number(_) => Some(CalcKind::Number),
+            Self::Percentage(_) => Some(CalcKind::Length),
+            Self::Length(_, u) => match u {
+                Unit::Dpi | Unit::Dpcm | Unit::Dppx | Unit::X => None,
+                _ => Some(CalcKind::Length),
+            },
+            Self::Sum(values) | Self::Min(values) | Self::Max(values) => {
+                Self::same_kind(values.iter())
+            }
+            Self::Clamp(low, value, high) => {
+                Self::same_kind([low.as_ref(), value.as_ref(), high.as_ref()].into_iter())
+            }
+            //you can't multiply two lengths together, at least one side has to be a number.
+            Self::Product(values) => {
+                let mut kind = CalcKind::Number;
+                for value in values {
+                    if value.kind()? == CalcKind::Length {
+                        if kind == CalcKind::Length {
+                            return None;
+                        }
+                        kind = CalcKind::Length;
+                    }
+                }
+                Some(kind)
+            }
+            Self::Invert(value) => match value.kind()? {
+                CalcKind::Number => Some(CalcKind::Number),
+                CalcKind::Length => None,
+            },
+        }
+    }
+
+    fn same_kind<'a>(mut values: impl Iterator<Item = &'a Calc>) -> Option<CalcKind> {
+        let kind = values.next()?.kind()?;
+        for value in values {
+            if value.kind()? != kind {
+                return None;
+            }
+        }
+        Some(kind)
+    }
+
+    //None when there's a percentage but nothing for it to be a percentage of.
+    pub fn resolve(&self, context: &LengthContext, basis: Option<f64>) -> Option<f64> {
+        Some(match self {
+            Self::Number(n) => *n,
+            Self::Percentage(p) => basis? * p / 100.,
+            Self::Length(n, u) => context.to_px(*n, *u)?,
+            Self::Sum(values) => values
+                .iter()
+                .map(|v| v.resolve(context, basis))
+                .sum::<Option<f64>>()?,
+            Self::Product(values) => values
+                .iter()
+                .map(|v| v.resolve(context, basis))
+                .product::<Option<f64>>()?,
+            Self::Invert(value) => 1. / value.resolve(context, basis)?,
+            Self::Min(values) => values
+                .iter()
+                .map(|v| v.resolve(context, basis))
+                .collect::<Option<Vec<_>>>()?
+                .into_iter()
+                .fold(f64::INFINITY, f64::min),
+            Self::Max(values) => values
+                .iter()
+                .map(|v| v.resolve(context, basis))
+                .collect::<Option<Vec<_>>>()?
+                .into_iter()
+                .fold(f64::NEG_INFINITY, f64::max),
+            //the minimum wins if it's bigger than the maximum.
+            Self::Clamp(low, value, high) => {
+                let low = low.resolve(context, basis)?;
+                let value = value.resolve(context, basis)?;
+                let high = high.resolve(context, basis)?;
+                value.min(high).max(low)
+            }
+        })
+    }
+
+    pub fn has_percentage(&self) -> bool {
+        match self {
+            Self::Percentage(_) => true,
+            Self::Number(_) | Self::Length(..) => false,
+            Self::Sum(values) | Self::Product(values) | Self::Min(values) | Self::Max(values) => {
+                values.iter().any(Self::has_percentage)
+            }
+            Self::Invert(value) => value.has_percentage(),
+            Self::Clamp(low, value, high) => {
+                low.has_percentage() || value.has_percentage() || high.has_percentage()
+            }
+        }
+    }
+
+    //the computed value: every length in pixels, with only the percentages left to work out
+    //during layout.
+    pub fn absolutize(&self, context: &LengthContext) -> Self {
+        let map = |values: &Vec<Calc>| values.iter().map(|v| v.absolutize(context)).collect();
+        match self {
+            Self::Length(n, u) => match context.to_px(*n, *u) {
+                Some(px) => Self::Length(px, Unit::Px),
+                None => self.clone(),
+            },
+            Self::Number(_) | Self::Percentage(_) => self.clone(),
+            Self::Sum(values) => Self::Sum(map(values)),
+            Self::Product(values) => Self::Product(map(values)),
+            Self::Min(values) => Self::Min(map(values)),
+            Self::Max(values) => Self::Max(map(values)),
+            Self::Invert(value) => Self::Invert(Box::new(value.absolutize(context))),
+            Self::Clamp(low, value, high) => Self::Clamp(
+                Box::new(low.absolutize(context)),
+                Box::new(value.absolutize(context)),
+                Box::new(high.absolutize(context)),
+            ),
+        }
+    }
+}
+
+impl Calc {
+    fn write_term(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{}", *n as f32),
+            Self::Percentage(p) => write!(f, "{}%", *p as f32),
+            Self::Length(v, u) => write!(f, "{}{}", *v as f32, u),
+            Self::Sum(terms) => {
+                for (i, term) in terms.iter().enumerate() {
+                    //subtraction is a sum with the term multiplied by -1.
+                    match term {
+                        Self::Product(factors)
+                            if i > 0
+                                && matches!(factors.first(), Some(Self::Number(n)) if *n == -1.) =>
+                        {
+                            f.write_str(" - ")?;
+                            Self::write_product(&factors[1..], f)?;
+                        }
+                        term => {
+                            if i > 0 {
+                                f.write_str(" + ")?;
+                            }
+                            term.write_term(f)?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Self::Product(factors) => Self::write_product(factors, f),
+            Self::Invert(_) => Self::write_product(std::slice::from_ref(self), f),
+            Self::Min(arguments) | Self::Max(arguments) => {
+                f.write_str(match self {
+                    Self::Min(_) => "min(",
+                    _ => "max(",
+                })?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    argument.write_term(f)?;
+                }
+                f.write_str(")")
+            }
+            Self::Clamp(low, value, high) => {
+                f.write_str("clamp(")?;
+                low.write_term(f)?;
+                f.write_str(", ")?;
+                value.write_term(f)?;
+                f.write_str(", ")?;
+                high.write_term(f)?;
+                f.write_str(")")
+            }
+        }
+    }
+
+    fn write_product(factors: &[Calc], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, factor) in factors.iter().enumerate() {
+            let factor = match factor {
+                Self::Invert(inverted) => {
+                    f.write_str(if i > 0 { " / " } else { "1 / " })?;
+                    inverted
+                }
+                factor => {
+                    if i > 0 {
+                        f.write_str(" * ")?;
+                    }
+                    factor
+                }
+            };
+            match factor {
+                Self::Sum(_) => {
+                    f.write_str("(")?;
+                    factor.write_term(f)?;
+                    f.write_str(")")?;
+                }
+                factor => factor.write_term(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+//min(), max() and clamp() are already functions, anything else needs calc() around it.
+impl fmt::Display for Calc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Min(_) | Self::Max(_) | Self::Clamp(..) => self.write_term(f),
+            _ => {
+                f.write_str("calc(")?;
+                self.write_term(f)?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::css::CSSParser;
+
+    fn calc(input: &str) -> Option<Calc> {
+        let components = CSSParser::parse_component_list(&String::from(input)).unwrap();
+        Calc::from_component(components.first()?)
+    }
+
+    #[test]
+    fn test_calc() {
+        let context = LengthContext {
+            viewport_width: 1000.,
+            viewport_height: 500.,
+            ..Default::default()
+        };
+        let resolve = |input: &str, basis| calc(input).unwrap().resolve(&context, basis);
+        assert_eq!(resolve("calc(1in - 2 * 8px)", None), Some(80.));
+        assert_eq!(resolve("calc((1em + 4px) / 2)", None), Some(10.));
+        assert_eq!(resolve("calc(50% - 10vw)", Some(300.)), Some(50.));
+        assert_eq!(resolve("calc(50% - 10vw)", None), None);
+        assert_eq!(resolve("min(10vmin, 1rem, 100px)", None), Some(16.));
+        assert_eq!(resolve("max(1pc, 72pt)", None), Some(96.));
+        assert_eq!(resolve("clamp(10px, 50%, 100px)", Some(1000.)), Some(100.));
+        assert_eq!(
+            resolve("clamp(10px, calc(2 * 3px), 100px)", None),
+            Some(10.)
+        );
+        assert!(calc("calc(1px * 2px)").is_none());
+        assert!(calc("calc(1px / 2px)").is_none());
+        assert!(calc("calc(1px + 2)").is_none());
+        assert!(calc("calc(1px -2px)").is_none());
+        let absolute = calc("calc(100% - 1em)").unwrap().absolutize(&context);
+        assert!(absolute.has_percentage());
+        assert_eq!(
+            absolute.resolve(&LengthContext::default(), Some(100.)),
+            Some(84.)
+        );
+        assert_eq!(absolute.to_string(), "calc(100% - 16px)");
+        assert_eq!(
+            calc("calc((1em + 4px) / 2)").unwrap().to_string(),
+            "calc((1em + 4px) / 2)"
+        );
+        assert_eq!(
+            calc("clamp(10px, 2 * 3px, 100px)").unwrap().to_string(),
+            "clamp(10px, 2 * 3px, 100px)"
+        );
+    }
+}
@@ -1,19 +1,104 @@
+use std::{cmp::Reverse, collections::HashSet};
+
+use rayon::prelude::*;
+
 use super::{
-    properties::{Colour, Display, FontFamily, FontSize, FontWeight, TextAlign},
-    Block, CSSNumber, CSSProps, CSSValue, Declaration, DeclarationKind, Numeric, Prelude,
-    RuleBuilder, StyleData, Unit,
+    calc::LengthContext,
+    container::ContainerCondition,
+    layer::{layer_key, LayerTree},
+    media::MediaEnvironment,
+    metadata::{CSSWideKeyword, ComputeContext, PROPERTIES},
+    rule_map::{BloomFilter, ElementKeys, RuleEntry, RuleMap},
+    variables::{compute_custom_properties, substitute, CustomProperties},
+    Block, CSSNumber, CSSParser, CSSProps, CSSValue, Combinator, Declaration, DeclarationBuilder,
+    DeclarationKind, Numeric, Prelude, PseudoClass, RuleBuilder, StyleData, Unit,
 };
 use crate::{
     context::Viewport,
     dom::{Element, Node},
+    layout::text::font_set::FontSet,
     parser::css::{properties::Dimensionality, Rule, Selector},
 };
 
+//a style rule with everything it was nested in worked out. @media, @supports and @layer are the
+//same for every element, so that's done once, but @container depends on where the element is.
+#[derive(Debug)]
+struct FlatRule {
+    rule: Rule,
+    containers: Vec<ContainerCondition>,
+}
+
+//where the elements under one parent are, as far as selectors and inheritance care. it only
+//borrows from elements that have already been styled, so every subtree gets its own and they can
+//all be styled at the same time.
+struct Level<'a> {
+    elements: Vec<ElementKeys>, //every element under the parent, in order
+    props: &'a CSSProps,        //the parent's
+    size: Option<(f64, f64)>,   //of the parent's content box, the last time it was laid out
+    parent: Option<(&'a Level<'a>, usize)>, //where the parent is
+    ancestors: BloomFilter,     //the parent and everything above it
+    root_font_size: f64,
+}
+
+impl<'a> Level<'a> {
+    fn new(
+        nodes: &[Node],
+        props: &'a CSSProps,
+        size: Option<(f64, f64)>,
+        parent: Option<(&'a Level<'a>, usize)>,
+    ) -> Self {
+        let elements = nodes
+            .iter()
+            .filter_map(|node| match node {
+                Node::Element(el) => Some(ElementKeys::of(el)),
+                _ => None,
+            })
+            .collect();
+        let (ancestors, root_font_size) = match parent {
+            Some((level, index)) => {
+                let mut ancestors = level.ancestors;
+                for hash in level.elements[index].hashes() {
+                    ancestors.insert(hash);
+                }
+                //the root element's font size is what rems are from, for everything under it.
+                let root_font_size = match (level.parent, &props.font_size) {
+                    (None, CSSValue::Value(size)) => size.px(),
+                    _ => level.root_font_size,
+                };
+                (ancestors, root_font_size)
+            }
+            None => (
+                BloomFilter::default(),
+                LengthContext::default().root_font_size,
+            ),
+        };
+        Self {
+            elements,
+            props,
+            size,
+            parent,
+            ancestors,
+            root_font_size,
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Cascader {
-    parent_prop_stack: Vec<CSSProps>,
-    parent_name_stack: Vec<String>,
-    last_sibling: String,
+    media: MediaEnvironment,
+    fonts: FontSet,
+    viewport: (f64, f64), //in css px, for vw and vh
+    root: CSSProps,       //what the root element inherits from
+    rules: Vec<FlatRule>,
+    map: RuleMap,
+    siblings: bool,   //any of the rules use a sibling combinator
+    containers: bool, //any of the rules are inside @container
+    linear: bool,     //no rule map, bloom filter or sharing
+    //every id, class and pseudo-class some selector looks for. changing any others can't
+    //restyle anything.
+    ids: HashSet<String>,
+    classes: HashSet<String>,
+    states: HashSet<PseudoClass>,
 }
 
 impl<'a> Cascader {
@@ -23,8 +108,34 @@ impl<'a> Cascader {
         }
     }
 
-    pub fn cascade(&mut self, input: &mut Vec<Node>, style: &StyleData, viewport: Viewport) {
-        self.parent_prop_stack.push(CSSProps {
+    //checks every rule against every element, which is what all the rest is meant to beat.
+    pub fn linear() -> Self {
+        Self {
+            linear: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn cascade(
+        &mut self,
+        input: &mut Vec<Node>,
+        style: &StyleData,
+        viewport: Viewport,
+        media: MediaEnvironment,
+        fonts: &FontSet,
+    ) {
+        self.media = media;
+        self.fonts = fonts.clone();
+        //the media environment's already divided the scale factor out, so vw agrees with
+        //(width: ...) in a media query.
+        self.viewport = (media.width, media.height);
+        self.flatten(style);
+        //the root element inherits initial values, apart from the size.
+        let mut initial = CSSProps::default();
+        for property in PROPERTIES {
+            (property.initial)(&mut initial);
+        }
+        self.root = CSSProps {
             width: CSSValue::Value(Dimensionality::new(CSSNumber::Unit(
                 Numeric::Integer(viewport.width as i32),
                 Unit::Px,
@@ -33,271 +144,663 @@ impl<'a> Cascader {
                 Numeric::Integer(viewport.height as i32),
                 Unit::Px,
             ))),
-            ..Default::default()
-        });
-        self.cascade_internal(input, style);
+            ..initial
+        };
+        self.style_tree(input, true);
     }
 
-    fn cascade_internal(&'a mut self, input: &'a mut Vec<Node>, style: &StyleData) {
-        println!("shmop");
-        for node in input {
-            if let Node::Element(ref mut el) = node {
-                let mut applicable_rules: Vec<Rule> = vec![];
-                for rules in &style.styles {
-                    for rule in &rules.rules {
-                        if let Prelude::Selector(selector) = &rule.prelude {
-                            if self.applicable(selector, &el.tag_name) {
-                                applicable_rules.push(rule.clone());
-                            }
+    //only what's been marked dirty since the last time, against the same sheets and
+    //environment. anything that changes those needs a whole new cascade() instead.
+    pub fn restyle(&self, input: &mut Vec<Node>) {
+        self.style_tree(input, false);
+    }
+
+    fn style_tree(&self, input: &mut Vec<Node>, everything: bool) {
+        let level = Level::new(input, &self.root, Some(self.viewport), None);
+        self.style_level(input, &level, everything);
+    }
+
+    //an element that's dirty gets restyled along with everything under it, since what it
+    //inherits might've changed. clean elements only get looked into if something under them
+    //is dirty, and their old styles are what gets inherited.
+    //
+    //everything at one level gets styled first, in order, so siblings can share. after that
+    //nothing under one element can affect anything under another, so the subtrees are handed
+    //to rayon, which has idle threads steal them. the styles come out the same whichever
+    //thread does what.
+    fn style_level(&self, input: &mut [Node], level: &Level, everything: bool) {
+        let mut previous = None; //the last element, for sharing styles with
+        let mut index = 0;
+        for i in 0..input.len() {
+            let (before, rest) = input.split_at_mut(i);
+            let Node::Element(ref mut el) = rest[0] else {
+                continue;
+            };
+            if everything || el.style_dirty {
+                let shared = previous
+                    .filter(|_| !self.linear)
+                    .and_then(|p| match before[p] {
+                        Node::Element(ref sibling)
+                            if self.can_share(sibling, el, (level, index)) =>
+                        {
+                            Some(sibling.css.clone())
                         }
-                    }
+                        _ => None,
+                    });
+                match shared {
+                    Some(css) => el.css = css,
+                    None => self.style_element(el, (level, index)),
                 }
-                let mut real_rule = RuleBuilder::new(false).build().unwrap();
-                for ref mut rule in applicable_rules {
-                    real_rule.squash(rule);
+            }
+            previous = Some(i);
+            index += 1;
+        }
+        input
+            .iter_mut()
+            .filter_map(|node| match node {
+                Node::Element(el) => Some(el),
+                _ => None,
+            })
+            .enumerate()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .for_each(|(index, el)| {
+                let restyle = everything || el.style_dirty;
+                //@container rules under it were asked about whatever size it was back then.
+                let resized = self.containers
+                    && el.is_size_container()
+                    && el.queried_size != el.laid_out_size();
+                if restyle || resized || el.descendants_dirty {
+                    el.queried_size = el.laid_out_size();
+                    let size = el.queried_size;
+                    let level = Level::new(&el.children, &el.css, size, Some((level, index)));
+                    self.style_level(&mut el.children, &level, restyle || resized);
                 }
-                self.defaulterizeificate(&mut real_rule);
-                if let Block::Declarations(declarations) = real_rule.value {
-                    for declaration in declarations.values() {
-                        self.apply(el, declaration.clone());
+                el.style_dirty = false;
+                el.descendants_dirty = false;
+            });
+    }
+
+    //siblings with the same parent, tag and attributes get the same styles, as long as every
+    //selector that looks at siblings says the same thing about both. the parent's the same, so
+    //containers are too.
+    fn can_share(&self, sibling: &Element, el: &Element, (level, index): (&Level, usize)) -> bool {
+        sibling.tag_name == el.tag_name
+            && sibling.attributes == el.attributes
+            && sibling.state == el.state
+            && self
+                .map
+                .candidates(&level.elements[index])
+                .filter(|entry| entry.siblings)
+                .all(|entry| {
+                    self.matches_entry(entry, (level, index - 1))
+                        == self.matches_entry(entry, (level, index))
+                })
+    }
+
+    fn style_element(&self, el: &mut Element, (level, index): (&Level, usize)) {
+        let mut matched = self
+            .map
+            .candidates(&level.elements[index])
+            .filter(|entry| self.matches_entry(entry, (level, index)))
+            .filter(|entry| {
+                self.rules[entry.rule]
+                    .containers
+                    .iter()
+                    .all(|c| self.container_matches(c, level))
+            })
+            .map(|entry| (entry.specificity, entry.rule))
+            .collect::<Vec<_>>();
+        //a rule that matched through more than one of its selectors gets its most specific one.
+        matched.sort_by_key(|&(specificity, rule)| (rule, Reverse(specificity)));
+        matched.dedup_by_key(|(_, rule)| *rule);
+        //then least specific first, and in the order they were written within that, since
+        //later ones win ties. origin, importance and layers beat both, and squash sees to those.
+        matched.sort();
+        let mut cascaded: Vec<&Rule> = matched
+            .iter()
+            .map(|(_, rule)| &self.rules[*rule].rule)
+            .collect();
+        //style="" is the most specific thing there is, so it always goes on last.
+        let inline =
+            el.get_attribute("style").and_then(|inline| {
+                match CSSParser::parse_inline_style(inline) {
+                    Ok(rule) => Some(rule),
+                    Err(e) => {
+                        tracing::warn!("bad style attribute {:?}: {}", inline, e);
+                        None
                     }
                 }
-                self.parent_prop_stack.push(el.css.clone());
-                self.last_sibling = el.tag_name.clone();
-                self.parent_name_stack.push(el.tag_name.clone());
-                self.cascade_internal(&mut el.children, style);
-                self.parent_prop_stack.pop();
-                self.parent_name_stack.pop();
+            });
+        cascaded.extend(inline.as_ref());
+        //start from scratch so rules that stopped applying (say, after a resize) don't
+        //leave their values behind.
+        el.css = CSSProps::default();
+        let mut real_rule = RuleBuilder::new(false).build().unwrap();
+        for rule in &cascaded {
+            real_rule.squash(rule);
+        }
+        el.css.custom = self.resolve_variables(&mut real_rule, level.props);
+        let Block::Declarations(declarations) = real_rule.value else {
+            unreachable!()
+        };
+        let parent = level.props;
+        for property in PROPERTIES {
+            let kind = declarations
+                .get(property.name)
+                .and_then(|d| self.revert(d, &cascaded, &el.css.custom));
+            match kind {
+                Some(DeclarationKind::WideKeyword(CSSWideKeyword::Inherit)) => {
+                    (property.inherit)(&mut el.css, parent)
+                }
+                Some(DeclarationKind::WideKeyword(CSSWideKeyword::Initial)) => {
+                    (property.initial)(&mut el.css)
+                }
+                //nothing declared at all acts like unset too.
+                None | Some(DeclarationKind::WideKeyword(_)) => match property.inherited {
+                    true => (property.inherit)(&mut el.css, parent),
+                    false => (property.initial)(&mut el.css),
+                },
+                Some(kind) => (property.apply)(&mut el.css, kind),
             }
         }
+        let context = ComputeContext::new(parent, &self.fonts, level.root_font_size, self.viewport);
+        for property in PROPERTIES {
+            (property.compute)(&mut el.css, &context);
+        }
     }
 
-    //me when no function overloading.....
-    pub fn defaulterizeificate(&mut self, rule: &mut Rule) {
-        if let Block::Declarations(ref mut declarations) = rule.value {
-            for declaration in declarations.values_mut() {
-                match declaration.kind {
-                    DeclarationKind::Unknown(..) => {}
-                    DeclarationKind::Color(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().color.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Colour>::default();
-                        }
-                    }
-                    DeclarationKind::TextAlign(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().text_align.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<TextAlign>::default();
-                        }
-                    }
-                    DeclarationKind::Display(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().display.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Display>::default();
-                        }
-                    }
-                    DeclarationKind::FontSize(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().font_size.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<FontSize>::default();
-                        }
-                    }
-                    DeclarationKind::FontWeight(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().font_weight.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<FontWeight>::default();
-                        } else if let CSSValue::Value(val) = v {
-                            match val {
-                                FontWeight::Normal => *val = FontWeight::Absolute(400.),
-                                FontWeight::Bold => *val = FontWeight::Absolute(700.),
-                                FontWeight::Bolder => {
-                                    if let CSSValue::Value(FontWeight::Absolute(w)) =
-                                        self.parent_prop_stack.last().unwrap().font_weight
-                                    {
-                                        if w < 350. {
-                                            *val = FontWeight::Absolute(400.);
-                                        } else if w >= 350. && w < 550. {
-                                            *val = FontWeight::Absolute(700.);
-                                        } else if w >= 550. && w < 900. {
-                                            *val = FontWeight::Absolute(900.);
-                                        }
-                                    }
-                                }
-                                FontWeight::Bolder => {
-                                    if let CSSValue::Value(FontWeight::Absolute(w)) =
-                                        self.parent_prop_stack.last().unwrap().font_weight
-                                    {
-                                        if w >= 100. && w < 550. {
-                                            *val = FontWeight::Absolute(100.);
-                                        } else if w >= 550. && w < 750. {
-                                            *val = FontWeight::Absolute(400.);
-                                        } else if w >= 750. {
-                                            *val = FontWeight::Absolute(700.);
-                                        }
-                                    }
-                                }
-                                FontWeight::Absolute(f) if *f > 1000. => {
-                                    *v = self.parent_prop_stack.last().unwrap().font_weight.clone();
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-                    DeclarationKind::BackgroundColor(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self
-                                .parent_prop_stack
-                                .last()
-                                .unwrap()
-                                .background_color
-                                .clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Colour>::default();
-                        }
-                    }
-                    DeclarationKind::Width(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().width.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Dimensionality>::default();
-                        }
-                    }
-                    DeclarationKind::Height(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().height.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Dimensionality>::default();
-                        }
-                    }
-                    DeclarationKind::MarginTop(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().margin_top.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Dimensionality>::default();
-                        }
-                    }
-                    DeclarationKind::MarginBottom(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().margin_bottom.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Dimensionality>::default();
-                        }
-                    }
-                    DeclarationKind::MarginLeft(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().margin_left.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Dimensionality>::default();
-                        }
-                    }
-                    DeclarationKind::MarginRight(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().margin_right.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Dimensionality>::default();
-                        }
-                    }
-                    DeclarationKind::PaddingTop(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().padding_top.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Dimensionality>::default();
-                        }
-                    }
-                    DeclarationKind::PaddingBottom(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self
-                                .parent_prop_stack
-                                .last()
-                                .unwrap()
-                                .padding_bottom
-                                .clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Dimensionality>::default();
-                        }
-                    }
-                    DeclarationKind::PaddingLeft(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().padding_left.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Dimensionality>::default();
-                        }
+    //whether moving elements around could change what an element's sibling matches.
+    pub fn uses_siblings(&self) -> bool {
+        self.siblings
+    }
+
+    //whether any styles depend on how big something's laid out.
+    pub fn uses_containers(&self) -> bool {
+        self.containers
+    }
+
+    pub fn depends_on_state(&self, state: PseudoClass) -> bool {
+        self.states.contains(&state)
+    }
+
+    //whether an attribute going from old to new could change what matches it. style=""
+    //always matters, ids and classes only if a selector looks for the ones that came or went,
+    //and nothing looks at any other attributes yet.
+    pub fn depends_on_attribute(&self, name: &str, old: Option<&str>, new: Option<&str>) -> bool {
+        let words = |value: Option<&str>| {
+            value
+                .unwrap_or_default()
+                .split_ascii_whitespace()
+                .map(String::from)
+                .collect::<HashSet<_>>()
+        };
+        match name.to_ascii_lowercase().as_str() {
+            "style" => true,
+            "id" => [old, new]
+                .into_iter()
+                .flatten()
+                .any(|id| self.ids.contains(id)),
+            "class" => words(old)
+                .symmetric_difference(&words(new))
+                .any(|class| self.classes.contains(class)),
+            _ => false,
+        }
+    }
+
+    //revert rolls back to whatever the origins under this declaration's would have come up
+    //with, which could be another revert. revert-layer does the same with the layers under this
+    //one, falling through to the origins under it when it runs out. with nothing underneath
+    //either one's the same as unset.
+    fn revert(
+        &self,
+        declaration: &Declaration,
+        cascaded: &[&Rule],
+        custom: &CustomProperties,
+    ) -> Option<DeclarationKind> {
+        let mut declaration = declaration.clone();
+        while let DeclarationKind::WideKeyword(
+            keyword @ (CSSWideKeyword::Revert | CSSWideKeyword::RevertLayer),
+        ) = declaration.kind
+        {
+            let mut winner: Option<&Declaration> = None;
+            for rule in cascaded {
+                let Block::Declarations(ref declarations) = rule.value else {
+                    continue;
+                };
+                if let Some(d) = declarations.get(&declaration.name) {
+                    let underneath = match keyword {
+                        CSSWideKeyword::Revert => d.level.origin() < declaration.level.origin(),
+                        _ => d.cascade_rank() < declaration.cascade_rank(),
+                    };
+                    if underneath && winner.map_or(true, |w| d >= w) {
+                        winner = Some(d);
                     }
-                    DeclarationKind::PaddingRight(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().padding_right.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<Dimensionality>::default();
-                        }
+                }
+            }
+            declaration = winner?.clone();
+            substitute_variables(&mut declaration, custom);
+        }
+        Some(declaration.kind)
+    }
+
+    //every origin has its own layers. once everything's been declared, each distinct spot in
+    //the layer order gets a number, which is what the declarations are ranked by.
+    fn flatten(&mut self, style: &StyleData) {
+        let mut trees: [LayerTree; 3] = Default::default();
+        let mut flat = vec![];
+        for sheet in &style.styles {
+            let origin = sheet.level.origin();
+            let tree = &mut trees[origin as usize];
+            self.flatten_rules(&sheet.rules, tree, &[], &[], origin, &mut flat);
+        }
+        let mut order = flat
+            .iter()
+            .map(|(_, origin, key)| (*origin, key.clone()))
+            .collect::<Vec<_>>();
+        order.sort();
+        order.dedup();
+        self.rules = flat
+            .into_iter()
+            .map(|(mut flat, origin, key)| {
+                let rank = order.binary_search(&(origin, key)).unwrap() as u32;
+                if let Block::Declarations(ref mut declarations) = flat.rule.value {
+                    for declaration in declarations.values_mut() {
+                        declaration.layer = rank;
                     }
-                    DeclarationKind::FontFamily(ref mut v) => {
-                        if let CSSValue::Inherit = v {
-                            *v = self.parent_prop_stack.last().unwrap().font_family.clone();
-                        } else if let CSSValue::Initial = v {
-                            *v = CSSValue::<FontFamily>::default();
-                        }
-                        if let CSSValue::Value(ref mut f) = v {
-                            f.resolve();
-                        }
+                }
+                flat
+            })
+            .collect();
+        self.map = RuleMap::new(self.linear);
+        for (i, flat) in self.rules.iter().enumerate() {
+            if let Prelude::Selector(ref selector) = flat.rule.prelude {
+                self.map.insert(i, selector);
+            }
+        }
+        self.siblings = self.rules.iter().any(|flat| match flat.rule.prelude {
+            Prelude::Selector(ref selector) => selector.uses_siblings(),
+            _ => false,
+        });
+        self.containers = self.rules.iter().any(|flat| !flat.containers.is_empty());
+        (self.ids, self.classes, self.states) = Default::default();
+        for flat in &self.rules {
+            let Prelude::Selector(ref selector) = flat.rule.prelude else {
+                continue;
+            };
+            for complex in selector.alternatives() {
+                for (compound, _) in complex.compounds() {
+                    for simple in compound.simples() {
+                        match simple {
+                            Selector::Id(id) => self.ids.insert(id.clone()),
+                            Selector::Class(class) => self.classes.insert(class.clone()),
+                            Selector::State(state) => self.states.insert(*state),
+                            _ => false,
+                        };
                     }
                 }
             }
         }
     }
 
-    pub fn applicable(&self, selector: &Selector, tag_name: &String) -> bool {
-        match selector {
-            Selector::Both(l, r) => {
-                return self.applicable(l, tag_name) || self.applicable(r, tag_name);
+    fn flatten_rules(
+        &self,
+        rules: &[Rule],
+        tree: &mut LayerTree,
+        layer: &[u32],
+        containers: &[ContainerCondition],
+        origin: u8,
+        out: &mut Vec<(FlatRule, u8, Vec<u32>)>,
+    ) {
+        for rule in rules {
+            match (&rule.prelude, &rule.value) {
+                (Prelude::Selector(_), Block::Declarations(_)) => {
+                    let flat = FlatRule {
+                        rule: rule.clone(),
+                        containers: containers.to_vec(),
+                    };
+                    out.push((flat, origin, layer_key(layer)));
+                }
+                (Prelude::Media(queries), Block::Rules(rules)) if queries.matches(&self.media) => {
+                    self.flatten_rules(rules, tree, layer, containers, origin, out)
+                }
+                (Prelude::Supports(condition), Block::Rules(rules)) if condition.matches() => {
+                    self.flatten_rules(rules, tree, layer, containers, origin, out)
+                }
+                (Prelude::Layer(names), Block::Rules(rules)) => {
+                    let layer = tree.declare(layer, names.first());
+                    self.flatten_rules(rules, tree, &layer, containers, origin, out)
+                }
+                (Prelude::Layer(names), _) => {
+                    for name in names {
+                        tree.declare(layer, Some(name));
+                    }
+                }
+                (Prelude::Container(condition), Block::Rules(rules)) => {
+                    let containers = [containers, std::slice::from_ref(condition)].concat();
+                    self.flatten_rules(rules, tree, layer, &containers, origin, out)
+                }
+                _ => {}
             }
-            Selector::Universal => {
-                return true;
+        }
+    }
+
+    //asks the closest ancestor that's the right kind of container. no container at all is
+    //unknown, which is false.
+    fn container_matches(&self, condition: &ContainerCondition, level: &Level) -> bool {
+        let mut level = Some(level);
+        while let Some(l) = level {
+            if condition.is_container(l.props) {
+                return condition.matches(l.props, l.size);
             }
-            Selector::Placeheld => {
-                return false;
+            level = l.parent.map(|(parent, _)| parent);
+        }
+        false
+    }
+
+    //custom properties get worked out first so that everything else using var() can be
+    //substituted and parsed for real. something that can't be substituted is invalid at
+    //computed-value time, which ends up acting like unset.
+    fn resolve_variables(&self, rule: &mut Rule, parent: &CSSProps) -> CustomProperties {
+        let parent = &parent.custom;
+        let Block::Declarations(ref mut declarations) = rule.value else {
+            return parent.clone();
+        };
+        let declared = declarations
+            .values()
+            .filter_map(|d| match d.kind {
+                DeclarationKind::Custom(ref name, ref value) => Some((name.clone(), value.clone())),
+                _ => None,
+            })
+            .collect::<CustomProperties>();
+        let custom = compute_custom_properties(parent, &declared);
+        for declaration in declarations.values_mut() {
+            substitute_variables(declaration, &custom);
+        }
+        custom
+    }
+
+    fn matches_entry(&self, entry: &RuleEntry, element: (&Level, usize)) -> bool {
+        entry
+            .ancestors
+            .iter()
+            .all(|hash| element.0.ancestors.might_contain(*hash))
+            && self.matches(&entry.compounds, element)
+    }
+
+    //right to left, from the element being styled.
+    fn matches(
+        &self,
+        compounds: &[(Selector, Option<Combinator>)],
+        (level, index): (&Level, usize),
+    ) -> bool {
+        let Some(((compound, _), rest)) = compounds.split_last() else {
+            return true;
+        };
+        if !compound.matches_keys(&level.elements[index]) {
+            return false;
+        }
+        match rest.last() {
+            None => true,
+            Some((_, Some(Combinator::Child))) => {
+                level.parent.is_some_and(|p| self.matches(rest, p))
             }
-            Selector::Type(t) => {
-                return t == tag_name;
+            Some((_, Some(Combinator::NextSibling))) => {
+                index > 0 && self.matches(rest, (level, index - 1))
             }
-            Selector::NextSibling(l, r) => {
-                return self.applicable(l, &self.last_sibling) && self.applicable(r, tag_name);
+            Some((_, Some(Combinator::Descendant))) => {
+                let mut ancestor = level.parent;
+                while let Some(a) = ancestor {
+                    if self.matches(rest, a) {
+                        return true;
+                    }
+                    ancestor = a.0.parent;
+                }
+                false
             }
-            Selector::Child(l, r) => {
-                return self
-                    .applicable(l, &self.parent_name_stack.last().unwrap_or(&String::new()))
-                    && self.applicable(r, tag_name);
+            Some((_, None)) => false,
+        }
+    }
+}
+
+//something that can't be substituted, or doesn't parse once it has been, is invalid at
+//computed-value time, which ends up acting like unset.
+fn substitute_variables(declaration: &mut Declaration, custom: &CustomProperties) {
+    if let DeclarationKind::WithVariables(ref name, ref value) = declaration.kind {
+        let mut builder = DeclarationBuilder::from_kind(name.clone());
+        declaration.kind = match substitute(value, custom) {
+            Some(substituted) => {
+                for component in substituted {
+                    builder.push_value(component);
+                }
+                //a shorthand's longhands all get the whole value, so this one's bit gets picked
+                //back out.
+                match builder.build_all() {
+                    Ok(built) => built
+                        .into_iter()
+                        .find(|built| built.name == declaration.name)
+                        .map_or(DeclarationKind::WideKeyword(CSSWideKeyword::Unset), |b| {
+                            b.kind
+                        }),
+                    Err(_) => DeclarationKind::WideKeyword(CSSWideKeyword::Unset),
+                }
             }
-            a => {
-                return false;
+            None => DeclarationKind::WideKeyword(CSSWideKeyword::Unset),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{dom::Document, layout::test_util::find, parser::html::HTMLParser};
+
+    fn computed(nodes: &[Node], out: &mut Vec<String>) {
+        for node in nodes {
+            if let Node::Element(el) = node {
+                let style = el.computed_style();
+                for property in PROPERTIES {
+                    out.push(style.get_property_value(property.name).unwrap());
+                }
+                computed(&el.children, out);
             }
         }
     }
 
-    pub fn apply(&self, element: &mut Element, declaration: Declaration) {
-        match declaration.kind {
-            DeclarationKind::Color(v) => element.css.color = v,
-            DeclarationKind::Display(v) => element.css.display = v,
-            DeclarationKind::FontSize(v) => element.css.font_size = v,
-            DeclarationKind::FontWeight(v) => element.css.font_weight = v,
-            DeclarationKind::TextAlign(v) => element.css.text_align = v,
-            DeclarationKind::BackgroundColor(v) => element.css.background_color = v,
-            DeclarationKind::Width(v) => element.css.width = v,
-            DeclarationKind::Height(v) => element.css.height = v,
-            DeclarationKind::MarginTop(v) => element.css.margin_top = v,
-            DeclarationKind::MarginBottom(v) => element.css.margin_bottom = v,
-            DeclarationKind::MarginLeft(v) => element.css.margin_left = v,
-            DeclarationKind::MarginRight(v) => element.css.margin_right = v,
-            DeclarationKind::PaddingTop(v) => element.css.padding_top = v,
-            DeclarationKind::PaddingBottom(v) => element.css.padding_bottom = v,
-            DeclarationKind::PaddingLeft(v) => element.css.padding_left = v,
-            DeclarationKind::PaddingRight(v) => element.css.padding_right = v,
-            DeclarationKind::FontFamily(v) => element.css.font_family = v,
-            DeclarationKind::Unknown(_, _) => {}
+    fn styled(body: &str, css: &str) -> Document {
+        let mut html = HTMLParser::default();
+        html.load_from_whatever(&mut Cursor::new(format!(
+            "<!DOCTYPE html><html><head></head><body>{}</body></html>",
+            body
+        )))
+        .unwrap();
+        let mut document = Document::default();
+        html.parse(&mut document).unwrap();
+        let mut parser = CSSParser::default();
+        parser.push_raw_css(&String::from(css));
+        document.add_styles(parser.parse_stylesheets().unwrap());
+        let viewport = Viewport::new(800, 600);
+        document.cascade(viewport, MediaEnvironment::new(viewport, 1.));
+        document
+    }
+
+    fn property(el: &Element, name: &str) -> String {
+        el.computed_style().get_property_value(name).unwrap()
+    }
+
+    #[test]
+    fn test_layers() {
+        let document = styled(
+            "<p>a</p><p class=\"keep\">b</p>",
+            "@layer base, theme; @layer theme { p { color: red; font-size: 10px } } \
+             @layer base { p { color: blue; font-size: 30px } } p { font-size: 20px } \
+             @layer theme { .keep { color: revert-layer } }",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let ps = body
+            .children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Element(el) => Some(el),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        //theme was declared after base, so it wins even though base's rules come later, and
+        //anything outside a layer beats both.
+        assert_eq!(property(ps[0], "color"), "rgb(255, 0, 0)");
+        assert_eq!(property(ps[0], "font-size"), "20px");
+        //revert-layer goes back to what the layers under theme said.
+        assert_eq!(property(ps[1], "color"), "rgb(0, 0, 255)");
+        assert_eq!(property(ps[1], "font-size"), "20px");
+    }
+
+    #[test]
+    fn test_container_queries() {
+        let mut document = styled(
+            "<center class=\"wide\"><p>a</p></center><center class=\"narrow\"><h2>b</h2>\
+             </center>",
+            "center { display: block; container-type: inline-size } .wide { width: 50% } \
+             .narrow { width: 200px } @container (width > 300px) { p { color: red } h2 { color: \
+             red } }",
+        );
+        let colour =
+            |document: &Document, tag| property(find(&document.children, tag).unwrap(), "color");
+        //a percentage isn't a size until it's been laid out.
+        assert_eq!(colour(&document, "p"), "rgb(0, 0, 0)");
+        assert_eq!(colour(&document, "h2"), "rgb(0, 0, 0)");
+        let viewport = Viewport::new(800, 600);
+        document.layoutify(viewport, 1.);
+        //then it's asked about the 400px it came out as.
+        assert_eq!(colour(&document, "p"), "rgb(255, 0, 0)");
+        assert_eq!(colour(&document, "h2"), "rgb(0, 0, 0)");
+        let center = find(&document.children, "center").unwrap();
+        assert_eq!(center.queried_size.map(|(width, _)| width), Some(400.));
+        assert!(!center.descendants_dirty);
+    }
+
+    #[test]
+    fn test_specificity() {
+        let document = styled(
+            "<p id=\"a\" class=\"x y\">a</p><p class=\"x\">b</p><p class=\"y\">c</p>",
+            "#a { color: red } .x.y { color: green } p { color: blue } \
+             p.x, .x { color: purple; font-size: 30px } .y { font-size: 20px !important } \
+             p { font-size: 10px }",
+        );
+        let body = find(&document.children, "body").unwrap();
+        let ps = body
+            .children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Element(el) => Some(el),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        //the id wins even though everything else comes later.
+        assert_eq!(property(ps[0], "color"), "rgb(255, 0, 0)");
+        //p.x is what matched, not .x, so it beats the plain p coming after it.
+        assert_eq!(property(ps[1], "color"), "rgb(128, 0, 128)");
+        assert_eq!(property(ps[1], "font-size"), "30px");
+        //equally specific ones go by order, and !important beats specificity.
+        assert_eq!(property(ps[2], "color"), "rgb(0, 0, 255)");
+        assert_eq!(property(ps[0], "font-size"), "20px");
+        assert_eq!(property(ps[2], "font-size"), "20px");
+    }
+
+    #[test]
+    fn test_viewport_units() {
+        let mut document = styled("<p>a</p>", "p { width: 50vw; height: 10vmax }");
+        let width = |document: &Document| property(find(&document.children, "p").unwrap(), "width");
+        assert_eq!(width(&document), "400px");
+        //at twice the scale factor, the same window is half as many css px across.
+        let viewport = Viewport::new(800, 600);
+        document.cascade(viewport, MediaEnvironment::new(viewport, 2.));
+        assert_eq!(width(&document), "200px");
+        let p = find(&document.children, "p").unwrap();
+        assert_eq!(property(p, "height"), "40px");
+    }
+
+    #[test]
+    fn test_parallel_cascade_is_deterministic() {
+        let section = "<center><h1>a</h1><p>b</p><p style=\"color: blue\">c</p><p>d</p>\
+                       <center><h2>e</h2><p>f</p></center></center>";
+        let page = format!(
+            "<!DOCTYPE html><html><head></head><body>{}</body></html>",
+            section.repeat(30)
+        );
+        let css = "center p { color: green } center > center p { font-size: 2em } h1 + p { \
+                   margin-top: 1ex } p { --x: 3px } center center p { padding-left: var(--x) } \
+                   center { container-type: inline-size; width: 50% } @container (width > \
+                   300px) { h2 { color: red } }";
+        let styled = |threads| {
+            let mut html = HTMLParser::default();
+            html.load_from_whatever(&mut Cursor::new(page.clone()))
+                .unwrap();
+            let mut document = Document::default();
+            html.parse(&mut document).unwrap();
+            let mut parser = CSSParser::default();
+            parser.push_raw_css(&String::from(css));
+            document.add_styles(parser.parse_stylesheets().unwrap());
+            let viewport = Viewport::new(800, 600);
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| document.cascade(viewport, MediaEnvironment::new(viewport, 1.)));
+            let mut out = vec![];
+            computed(&document.children, &mut out);
+            out
+        };
+        let serial = styled(1);
+        assert!(serial.contains(&String::from("rgb(0, 128, 0)")));
+        for _ in 0..4 {
+            assert_eq!(styled(4), serial);
+        }
+    }
+
+    #[test]
+    fn test_class_and_id_selectors() {
+        let mut html = HTMLParser::default();
+        html.load_from_whatever(&mut Cursor::new(
+            "<!DOCTYPE html><html><head></head><body><center id=\"main\" class=\"wide\"><p \
+             class=\"note  big\">a</p><p class=\"big\">b</p></center><p class=\"note\">c</p>\
+             </body></html>",
+        ))
+        .unwrap();
+        let mut document = Document::default();
+        html.parse(&mut document).unwrap();
+        let mut parser = CSSParser::default();
+        parser.push_raw_css(&String::from(
+            ".note { color: red } p.big { color: blue } #main .note { color: green } \
+             center.wide > .big.note { font-size: 30px } #main.narrow p { font-size: 40px }",
+        ));
+        document.add_styles(parser.parse_stylesheets().unwrap());
+        let viewport = Viewport::new(800, 600);
+        document.cascade(viewport, MediaEnvironment::new(viewport, 1.));
+        let mut out = vec![];
+        let mut styles = vec![];
+        computed(&document.children, &mut out);
+        for chunk in out.chunks(PROPERTIES.len()) {
+            let get = |name| {
+                let i = PROPERTIES.iter().position(|p| p.name == name).unwrap();
+                chunk[i].clone()
+            };
+            styles.push((get("color"), get("font-size")));
         }
+        //html, head, body, center, then the three ps.
+        let ps = &styles[4..];
+        assert_eq!(
+            ps[0],
+            (String::from("rgb(0, 128, 0)"), String::from("30px"))
+        );
+        assert_eq!(
+            ps[1],
+            (String::from("rgb(0, 0, 255)"), String::from("16px"))
+        );
+        assert_eq!(
+            ps[2],
+            (String::from("rgb(255, 0, 0)"), String::from("16px"))
+        );
     }
 }
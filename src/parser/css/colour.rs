@@ -0,0 +1,527 @@
+use super::{properties::Colour, CSSNumber, CSSToken, Component};
+use crate::parser::Char;
+
+//everything that can show up where a colour is expected. None means it wasn't a colour at all.
+pub fn parse_colour(components: &[Component]) -> Option<Colour> {
+    let mut iter = components
+        .iter()
+        .filter(|c| !matches!(c, Component::Token(CSSToken::Whitespace)));
+    let colour = match iter.next()? {
+        Component::Token(CSSToken::Ident(name)) => match name.to_ascii_lowercase().as_str() {
+            "transparent" => Colour::new(0, 0, 0, 0),
+            "currentcolor" => Colour::CURRENT,
+            name => {
+                let idx = NAMED_COLOURS.binary_search_by(|(n, _)| n.cmp(&name)).ok()?;
+                let [_, r, g, b] = NAMED_COLOURS[idx].1.to_be_bytes();
+                Colour::new(r, g, b, 0xFF)
+            }
+        },
+        Component::Token(CSSToken::Hash(hex, _)) => parse_hex(hex)?,
+        Component::Function(name, arguments) => parse_function(name, arguments)?,
+        _ => return None,
+    };
+    //anything left over means it was something like `red blue`.
+    match iter.next() {
+        Some(_) => None,
+        None => Some(colour),
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Colour> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).unwrap_or(0);
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+    //#rgb is shorthand for #rrggbb, so each digit just gets doubled up.
+    Some(match hex.len() {
+        3 => Colour::new(digit(0) * 17, digit(1) * 17, digit(2) * 17, 0xFF),
+        4 => Colour::new(digit(0) * 17, digit(1) * 17, digit(2) * 17, digit(3) * 17),
+        6 => Colour::new(pair(0), pair(2), pair(4), 0xFF),
+        8 => Colour::new(pair(0), pair(2), pair(4), pair(6)),
+        _ => return None,
+    })
+}
+
+fn parse_function(name: &str, arguments: &[Component]) -> Option<Colour> {
+    let ([first, second, third], alpha) = channels(arguments)?;
+    let alpha = match alpha {
+        Some(a) => a.alpha()?,
+        None => 1.,
+    };
+    let (r, g, b) = match name.to_ascii_lowercase().as_str() {
+        "rgb" | "rgba" => (first.rgb()?, second.rgb()?, third.rgb()?),
+        "hsl" | "hsla" => hsl_to_rgb(first.hue()?, second.fraction()?, third.fraction()?),
+        "hwb" => hwb_to_rgb(first.hue()?, second.fraction()?, third.fraction()?),
+        "lab" => lab_to_rgb(
+            first.scaled(100.)?.clamp(0., 100.),
+            second.scaled(125.)?,
+            third.scaled(125.)?,
+        ),
+        "lch" => {
+            let (a, b) = polar(second.scaled(150.)?, third.hue()?);
+            lab_to_rgb(first.scaled(100.)?.clamp(0., 100.), a, b)
+        }
+        "oklab" => oklab_to_rgb(
+            first.scaled(1.)?.clamp(0., 1.),
+            second.scaled(0.4)?,
+            third.scaled(0.4)?,
+        ),
+        "oklch" => {
+            let (a, b) = polar(second.scaled(0.4)?, third.hue()?);
+            oklab_to_rgb(first.scaled(1.)?.clamp(0., 1.), a, b)
+        }
+        _ => return None,
+    };
+    Some(from_unit_rgb(r, g, b, alpha))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Channel {
+    Number(f64),
+    Percentage(f64),
+    Angle(f64), //in degrees
+    None,
+}
+
+impl Channel {
+    //0-255 or a percentage of that, as 0-1.
+    fn rgb(self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(n / 255.),
+            Self::Percentage(p) => Some(p / 100.),
+            Self::None => Some(0.),
+            Self::Angle(_) => None,
+        }
+    }
+
+    fn hue(self) -> Option<f64> {
+        match self {
+            Self::Number(n) | Self::Angle(n) => Some(n.rem_euclid(360.)),
+            Self::None => Some(0.),
+            Self::Percentage(_) => None,
+        }
+    }
+
+    //saturation, lightness, whiteness and blackness, which are percentages but can be plain
+    //numbers in the modern syntax.
+    fn fraction(self) -> Option<f64> {
+        match self {
+            Self::Number(n) | Self::Percentage(n) => Some((n / 100.).clamp(0., 1.)),
+            Self::None => Some(0.),
+            Self::Angle(_) => None,
+        }
+    }
+
+    fn alpha(self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(n.clamp(0., 1.)),
+            Self::Percentage(p) => Some((p / 100.).clamp(0., 1.)),
+            Self::None => Some(0.),
+            Self::Angle(_) => None,
+        }
+    }
+
+    //for the lab-ish functions, where 100% maps onto some reference value.
+    fn scaled(self, full: f64) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(n),
+            Self::Percentage(p) => Some(p / 100. * full),
+            Self::None => Some(0.),
+            Self::Angle(_) => None,
+        }
+    }
+}
+
+//splits the arguments into the three colour channels and the alpha, if there is one. handles
+//both the legacy `rgb(1, 2, 3, .5)` syntax and the modern `rgb(1 2 3 / .5)` one.
+fn channels(arguments: &[Component]) -> Option<([Channel; 3], Option<Channel>)> {
+    let tokens = Component::flatten(arguments)
+        .into_iter()
+        .filter(|t| !matches!(t, CSSToken::Whitespace))
+        .collect::<Vec<_>>();
+    let legacy = tokens.contains(&CSSToken::Comma);
+    let mut values = vec![];
+    let mut alpha = None;
+    let mut slashed = false;
+    for (i, token) in tokens.iter().enumerate() {
+        if legacy {
+            //commas have to be exactly every other token.
+            if (i % 2 == 1) != (*token == CSSToken::Comma) {
+                return None;
+            }
+            if i % 2 == 1 {
+                continue;
+            }
+        }
+        let channel = match token {
+            CSSToken::Number(CSSNumber::Number(n)) => Channel::Number(n.unwrap_f64()),
+            CSSToken::Number(CSSNumber::Percentage(n)) => Channel::Percentage(n.unwrap_f64()),
+            CSSToken::Dimension(n, unit) => Channel::Angle(to_degrees(n.unwrap_f64(), unit)?),
+            CSSToken::Ident(i) if !legacy && i.eq_ignore_ascii_case("none") => Channel::None,
+            CSSToken::Delim(Char::Char('/')) if !legacy && !slashed && values.len() == 3 => {
+                slashed = true;
+                continue;
+            }
+            _ => return None,
+        };
+        if slashed {
+            if alpha.replace(channel).is_some() {
+                return None;
+            }
+        } else {
+            values.push(channel);
+        }
+    }
+    if slashed && alpha.is_none() {
+        return None;
+    }
+    if legacy && values.len() == 4 {
+        alpha = values.pop();
+    }
+    //the legacy syntax doesn't let you mix numbers and percentages for rgb, but it's not worth
+    //turning a colour away over.
+    Some((values.try_into().ok()?, alpha))
+}
+
+fn to_degrees(value: f64, unit: &str) -> Option<f64> {
+    Some(match unit.to_ascii_lowercase().as_str() {
+        "deg" => value,
+        "rad" => value.to_degrees(),
+        "grad" => value * 0.9,
+        "turn" => value * 360.,
+        _ => return None,
+    })
+}
+
+//takes 0-1 channels, anything outside of that (say, from lab colours out of srgb's gamut) just
+//gets clamped.
+fn from_unit_rgb(r: f64, g: f64, b: f64, alpha: f64) -> Colour {
+    let byte = |c: f64| (c.clamp(0., 1.) * 255.).round() as u8;
+    Colour::new(byte(r), byte(g), byte(b), byte(alpha))
+}
+
+//straight from css color 4.
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (f64, f64, f64) {
+    let f = |n: f64| {
+        let k = (n + hue / 30.) % 12.;
+        let a = saturation * lightness.min(1. - lightness);
+        lightness - a * (k - 3.).min(9. - k).clamp(-1., 1.)
+    };
+    (f(0.), f(8.), f(4.))
+}
+
+fn hwb_to_rgb(hue: f64, whiteness: f64, blackness: f64) -> (f64, f64, f64) {
+    if whiteness + blackness >= 1. {
+        let grey = whiteness / (whiteness + blackness);
+        return (grey, grey, grey);
+    }
+    let (r, g, b) = hsl_to_rgb(hue, 1., 0.5);
+    let scale = |c: f64| c * (1. - whiteness - blackness) + whiteness;
+    (scale(r), scale(g), scale(b))
+}
+
+fn polar(chroma: f64, hue: f64) -> (f64, f64) {
+    let chroma = chroma.max(0.);
+    let hue = hue.to_radians();
+    (chroma * hue.cos(), chroma * hue.sin())
+}
+
+//cie lab is relative to d50, so it goes lab -> xyz (d50) -> xyz (d65) -> linear srgb -> srgb.
+fn lab_to_rgb(lightness: f64, a: f64, b: f64) -> (f64, f64, f64) {
+    const KAPPA: f64 = 24389. / 27.;
+    const EPSILON: f64 = 216. / 24389.;
+    const D50: [f64; 3] = [0.3457 / 0.3585, 1., (1. - 0.3457 - 0.3585) / 0.3585];
+    let f1 = (lightness + 16.) / 116.;
+    let f0 = a / 500. + f1;
+    let f2 = f1 - b / 200.;
+    let x = if f0.powi(3) > EPSILON {
+        f0.powi(3)
+    } else {
+        (116. * f0 - 16.) / KAPPA
+    };
+    let y = if lightness > KAPPA * EPSILON {
+        f1.powi(3)
+    } else {
+        lightness / KAPPA
+    };
+    let z = if f2.powi(3) > EPSILON {
+        f2.powi(3)
+    } else {
+        (116. * f2 - 16.) / KAPPA
+    };
+    let d65 = multiply(
+        [
+            [
+                0.9554734527042182,
+                -0.023098536874261423,
+                0.0632593086610217,
+            ],
+            [
+                -0.028369706963208136,
+                1.0099954580058226,
+                0.021041398966943008,
+            ],
+            [
+                0.012314001688319899,
+                -0.020507696433477912,
+                1.3303659366080753,
+            ],
+        ],
+        [x * D50[0], y * D50[1], z * D50[2]],
+    );
+    let [r, g, b] = multiply(
+        [
+            [3.2409699419045226, -1.537383177570094, -0.4986107602930034],
+            [-0.9692436362808796, 1.8759675015077202, 0.04155505740717559],
+            [
+                0.05563007969699366,
+                -0.20397695888897652,
+                1.0569715142428786,
+            ],
+        ],
+        d65,
+    );
+    (gamma(r), gamma(g), gamma(b))
+}
+
+fn oklab_to_rgb(lightness: f64, a: f64, b: f64) -> (f64, f64, f64) {
+    let [l, m, s] = multiply(
+        [
+            [1., 0.3963377774, 0.2158037573],
+            [1., -0.1055613458, -0.0638541728],
+            [1., -0.0894841775, -1.2914855480],
+        ],
+        [lightness, a, b],
+    );
+    let [r, g, b] = multiply(
+        [
+            [4.0767416621, -3.3077115913, 0.2309699292],
+            [-1.2684380046, 2.6097574011, -0.3413193965],
+            [-0.0041960863, -0.7034186147, 1.7076147010],
+        ],
+        [l.powi(3), m.powi(3), s.powi(3)],
+    );
+    (gamma(r), gamma(g), gamma(b))
+}
+
+fn multiply(matrix: [[f64; 3]; 3], vector: [f64; 3]) -> [f64; 3] {
+    matrix.map(|row| row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2])
+}
+
+//linear light to the srgb transfer curve.
+fn gamma(c: f64) -> f64 {
+    let abs = c.abs();
+    if abs > 0.0031308 {
+        c.signum() * (1.055 * abs.powf(1. / 2.4) - 0.055)
+    } else {
+        12.92 * c
+    }
+}
+
+//sorted, so it can be binary searched.
+const NAMED_COLOURS: [(&str, u32); 148] = [
+    ("aliceblue", 0xF0F8FF),
+    ("antiquewhite", 0xFAEBD7),
+    ("aqua", 0x00FFFF),
+    ("aquamarine", 0x7FFFD4),
+    ("azure", 0xF0FFFF),
+    ("beige", 0xF5F5DC),
+    ("bisque", 0xFFE4C4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xFFEBCD),
+    ("blue", 0x0000FF),
+    ("blueviolet", 0x8A2BE2),
+    ("brown", 0xA52A2A),
+    ("burlywood", 0xDEB887),
+    ("cadetblue", 0x5F9EA0),
+    ("chartreuse", 0x7FFF00),
+    ("chocolate", 0xD2691E),
+    ("coral", 0xFF7F50),
+    ("cornflowerblue", 0x6495ED),
+    ("cornsilk", 0xFFF8DC),
+    ("crimson", 0xDC143C),
+    ("cyan", 0x00FFFF),
+    ("darkblue", 0x00008B),
+    ("darkcyan", 0x008B8B),
+    ("darkgoldenrod", 0xB8860B),
+    ("darkgray", 0xA9A9A9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xA9A9A9),
+    ("darkkhaki", 0xBDB76B),
+    ("darkmagenta", 0x8B008B),
+    ("darkolivegreen", 0x556B2F),
+    ("darkorange", 0xFF8C00),
+    ("darkorchid", 0x9932CC),
+    ("darkred", 0x8B0000),
+    ("darksalmon", 0xE9967A),
+    ("darkseagreen", 0x8FBC8F),
+    ("darkslateblue", 0x483D8B),
+    ("darkslategray", 0x2F4F4F),
+    ("darkslategrey", 0x2F4F4F),
+    ("darkturquoise", 0x00CED1),
+    ("darkviolet", 0x9400D3),
+    ("deeppink", 0xFF1493),
+    ("deepskyblue", 0x00BFFF),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1E90FF),
+    ("firebrick", 0xB22222),
+    ("floralwhite", 0xFFFAF0),
+    ("forestgreen", 0x228B22),
+    ("fuchsia", 0xFF00FF),
+    ("gainsboro", 0xDCDCDC),
+    ("ghostwhite", 0xF8F8FF),
+    ("gold", 0xFFD700),
+    ("goldenrod", 0xDAA520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xADFF2F),
+    ("grey", 0x808080),
+    ("honeydew", 0xF0FFF0),
+    ("hotpink", 0xFF69B4),
+    ("indianred", 0xCD5C5C),
+    ("indigo", 0x4B0082),
+    ("ivory", 0xFFFFF0),
+    ("khaki", 0xF0E68C),
+    ("lavender", 0xE6E6FA),
+    ("lavenderblush", 0xFFF0F5),
+    ("lawngreen", 0x7CFC00),
+    ("lemonchiffon", 0xFFFACD),
+    ("lightblue", 0xADD8E6),
+    ("lightcoral", 0xF08080),
+    ("lightcyan", 0xE0FFFF),
+    ("lightgoldenrodyellow", 0xFAFAD2),
+    ("lightgray", 0xD3D3D3),
+    ("lightgreen", 0x90EE90),
+    ("lightgrey", 0xD3D3D3),
+    ("lightpink", 0xFFB6C1),
+    ("lightsalmon", 0xFFA07A),
+    ("lightseagreen", 0x20B2AA),
+    ("lightskyblue", 0x87CEFA),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xB0C4DE),
+    ("lightyellow", 0xFFFFE0),
+    ("lime", 0x00FF00),
+    ("limegreen", 0x32CD32),
+    ("linen", 0xFAF0E6),
+    ("magenta", 0xFF00FF),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66CDAA),
+    ("mediumblue", 0x0000CD),
+    ("mediumorchid", 0xBA55D3),
+    ("mediumpurple", 0x9370DB),
+    ("mediumseagreen", 0x3CB371),
+    ("mediumslateblue", 0x7B68EE),
+    ("mediumspringgreen", 0x00FA9A),
+    ("mediumturquoise", 0x48D1CC),
+    ("mediumvioletred", 0xC71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xF5FFFA),
+    ("mistyrose", 0xFFE4E1),
+    ("moccasin", 0xFFE4B5),
+    ("navajowhite", 0xFFDEAD),
+    ("navy", 0x000080),
+    ("oldlace", 0xFDF5E6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6B8E23),
+    ("orange", 0xFFA500),
+    ("orangered", 0xFF4500),
+    ("orchid", 0xDA70D6),
+    ("palegoldenrod", 0xEEE8AA),
+    ("palegreen", 0x98FB98),
+    ("paleturquoise", 0xAFEEEE),
+    ("palevioletred", 0xDB7093),
+    ("papayawhip", 0xFFEFD5),
+    ("peachpuff", 0xFFDAB9),
+    ("peru", 0xCD853F),
+    ("pink", 0xFFC0CB),
+    ("plum", 0xDDA0DD),
+    ("powderblue", 0xB0E0E6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xFF0000),
+    ("rosybrown", 0xBC8F8F),
+    ("royalblue", 0x4169E1),
+    ("saddlebrown", 0x8B4513),
+    ("salmon", 0xFA8072),
+    ("sandybrown", 0xF4A460),
+    ("seagreen", 0x2E8B57),
+    ("seashell", 0xFFF5EE),
+    ("sienna", 0xA0522D),
+    ("silver", 0xC0C0C0),
+    ("skyblue", 0x87CEEB),
+    ("slateblue", 0x6A5ACD),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xFFFAFA),
+    ("springgreen", 0x00FF7F),
+    ("steelblue", 0x4682B4),
+    ("tan", 0xD2B48C),
+    ("teal", 0x008080),
+    ("thistle", 0xD8BFD8),
+    ("tomato", 0xFF6347),
+    ("turquoise", 0x40E0D0),
+    ("violet", 0xEE82EE),
+    ("wheat", 0xF5DEB3),
+    ("white", 0xFFFFFF),
+    ("whitesmoke", 0xF5F5F5),
+    ("yellow", 0xFFFF00),
+    ("yellowgreen", 0x9ACD32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::css::CSSParser;
+
+    fn colour(input: &str) -> Option<(u8, u8, u8, u8)> {
+        let c = parse_colour(&CSSParser::parse_component_list(&String::from(input)).unwrap())?;
+        Some((c.real.red, c.real.green, c.real.blue, c.real.alpha))
+    }
+
+    #[test]
+    fn test_colour_syntax() {
+        assert_eq!(colour("#ff8800"), Some((0xFF, 0x88, 0x00, 0xFF)));
+        assert_eq!(colour("#f80"), Some((0xFF, 0x88, 0x00, 0xFF)));
+        assert_eq!(colour("#f808"), Some((0xFF, 0x88, 0x00, 0x88)));
+        assert_eq!(colour("#ff880080"), Some((0xFF, 0x88, 0x00, 0x80)));
+        assert_eq!(colour("#ff88"), Some((0xFF, 0xFF, 0x88, 0x88)));
+        assert_eq!(colour("#ff8"), Some((0xFF, 0xFF, 0x88, 0xFF)));
+        assert_eq!(colour("#ff88000"), None);
+        assert_eq!(colour("RebeccaPurple"), Some((0x66, 0x33, 0x99, 0xFF)));
+        assert_eq!(colour("transparent"), Some((0, 0, 0, 0)));
+        assert_eq!(colour("rgba(0,0,0,.5)"), Some((0, 0, 0, 0x80)));
+        assert_eq!(
+            colour("rgb(255 128 0 / 50%)"),
+            Some((0xFF, 0x80, 0x00, 0x80))
+        );
+        assert_eq!(colour("rgb(300 -5 100%)"), Some((0xFF, 0x00, 0xFF, 0xFF)));
+        assert_eq!(colour("rgb(1, 2 3)"), None);
+        assert_eq!(
+            colour("hsl(120deg 100% 25%)"),
+            Some((0x00, 0x80, 0x00, 0xFF))
+        );
+        assert_eq!(
+            colour("hsla(0.5turn, 100%, 50%, 1)"),
+            Some((0x00, 0xFF, 0xFF, 0xFF))
+        );
+        assert_eq!(colour("hwb(0 0% 0%)"), Some((0xFF, 0x00, 0x00, 0xFF)));
+        assert_eq!(colour("hwb(0 60% 60%)"), Some((0x80, 0x80, 0x80, 0xFF)));
+        assert_eq!(colour("lab(100 0 0)"), Some((0xFF, 0xFF, 0xFF, 0xFF)));
+        assert_eq!(colour("lch(0% 0 0)"), Some((0, 0, 0, 0xFF)));
+        assert_eq!(colour("oklab(1 0 0)"), Some((0xFF, 0xFF, 0xFF, 0xFF)));
+        //pure srgb red, give or take the rounding in the published numbers.
+        assert_eq!(
+            colour("oklch(0.627955 0.257683 29.2339)"),
+            Some((0xFF, 0, 0, 0xFF))
+        );
+        assert_eq!(colour("red blue"), None);
+        assert!(
+            parse_colour(&CSSParser::parse_component_list(&String::from("currentColor")).unwrap())
+                .unwrap()
+                .current
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use super::{
+    metadata::{PropertyInfo, PROPERTIES},
+    properties::{BoxSizing, DisplayInside, DisplayOutside},
+    CSSProps, CSSValue, Component,
+};
+use crate::layout::LayoutInfo;
+
+//what getComputedStyle hands back for an element: computed values for everything, plus the used
+//values for the box if it's been laid out.
+#[derive(Debug, Clone)]
+pub struct ComputedStyle {
+    pub computed: CSSProps,
+    pub used: Option<LayoutInfo>,
+    pub replaced: bool,
+}
+
+impl ComputedStyle {
+    pub fn new(computed: &CSSProps, layout: &LayoutInfo, replaced: bool) -> Self {
+        Self {
+            computed: computed.clone(),
+            used: layout.laid_out.then(|| *layout),
+            replaced,
+        }
+    }
+
+    //the resolved value, like getPropertyValue(). the box's size and spacing come from layout
+    //when there's been one, everything else is the computed value.
+    pub fn get_property_value(&self, name: &str) -> Option<String> {
+        if name.starts_with("--") {
+            return self
+                .computed
+                .custom
+                .get(name)
+                .map(|v| Component::serialize(v));
+        }
+        let name = name.to_ascii_lowercase();
+        //width and height don't apply to a laid out inline that isn't replaced, its size is its
+        //lines'.
+        if matches!(name.as_str(), "width" | "height")
+            && self.used.is_some()
+            && self.is_inline_box()
+        {
+            return Some(String::from("auto"));
+        }
+        if let Some(px) = self.used_value(&name) {
+            return Some(format!("{}px", px as f32));
+        }
+        PropertyInfo::find(&name).map(|p| (p.serialize_computed)(&self.computed))
+    }
+
+    pub fn used_value(&self, name: &str) -> Option<f64> {
+        let used = self.used.as_ref()?;
+        //layout keeps the content box, but with border-box sizing the width and height are of
+        //the border box.
+        let size = match self.computed.box_sizing {
+            CSSValue::Value(BoxSizing::BorderBox) => used.border_box(),
+            _ => *used,
+        };
+        Some(match name {
+            "width" => size.width,
+            "height" => size.height,
+            "margin-top" => used.margin.0,
+            "margin-left" => used.margin.1,
+            "margin-right" => used.margin.2,
+            "margin-bottom" => used.margin.3,
+            "padding-top" => used.padding.0,
+            "padding-left" => used.padding.1,
+            "padding-right" => used.padding.2,
+            "padding-bottom" => used.padding.3,
+            _ => return None,
+        })
+    }
+
+    fn is_inline_box(&self) -> bool {
+        match self.computed.display {
+            CSSValue::Value(display) => {
+                matches!(display.outside, DisplayOutside::Inline)
+                    && !matches!(display.inside, DisplayInside::FlowRoot)
+                    && !self.replaced
+            }
+            _ => false,
+        }
+    }
+
+    //every property we know about in the order they're computed, then the custom ones sorted
+    //by name.
+    pub fn properties(&self) -> Vec<(String, String)> {
+        let mut properties = PROPERTIES
+            .iter()
+            .map(|p| {
+                let value = self.get_property_value(p.name).unwrap_or_default();
+                (p.name.to_string(), value)
+            })
+            .collect::<Vec<_>>();
+        let mut custom = self.computed.custom.keys().collect::<Vec<_>>();
+        custom.sort();
+        for name in custom {
+            properties.push((
+                name.clone(),
+                Component::serialize(&self.computed.custom[name]),
+            ));
+        }
+        properties
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::css::{properties::Display, CSSParser};
+
+    #[test]
+    fn test_resolved_values() {
+        let mut props = CSSProps::default();
+        for property in PROPERTIES {
+            (property.initial)(&mut props);
+        }
+        props.custom.insert(
+            String::from("--shmeep"),
+            CSSParser::parse_component_list(&String::from("1px +2px \"a\\\"b\"")).unwrap(),
+        );
+        let layout = LayoutInfo {
+            width: 123.5,
+            margin: (1., 2., 3., 4.),
+            ..Default::default()
+        };
+        let style = ComputedStyle::new(&props, &layout, false);
+        assert!(style.used.is_none());
+        assert_eq!(style.get_property_value("width").unwrap(), "auto");
+        assert_eq!(style.get_property_value("color").unwrap(), "rgb(0, 0, 0)");
+        assert_eq!(
+            style.get_property_value("background-color").unwrap(),
+            "rgba(0, 0, 0, 0)"
+        );
+        assert_eq!(style.get_property_value("font-size").unwrap(), "16px");
+        assert_eq!(style.get_property_value("display").unwrap(), "inline");
+        assert_eq!(
+            style.get_property_value("--shmeep").unwrap(),
+            "1px 2px \"a\\\"b\""
+        );
+        assert!(style.get_property_value("shmidth").is_none());
+
+        let layout = LayoutInfo {
+            laid_out: true,
+            ..layout
+        };
+        //an inline's size is its lines', unless it's replaced.
+        let style = ComputedStyle::new(&props, &layout, false);
+        assert_eq!(style.get_property_value("width").unwrap(), "auto");
+        assert_eq!(style.get_property_value("margin-bottom").unwrap(), "4px");
+        assert_eq!(style.properties().len(), PROPERTIES.len() + 1);
+        let style = ComputedStyle::new(&props, &layout, true);
+        assert_eq!(style.get_property_value("width").unwrap(), "123.5px");
+
+        props.display = CSSValue::Value(Display::new(DisplayOutside::Block, DisplayInside::Flow));
+        let style = ComputedStyle::new(&props, &layout, false);
+        assert_eq!(style.get_property_value("width").unwrap(), "123.5px");
+
+        //with border-box sizing it's the size that was asked for, padding and border included.
+        props.box_sizing = CSSValue::Value(BoxSizing::BorderBox);
+        let layout = LayoutInfo {
+            width: 80.,
+            height: 30.,
+            padding: (10., 10., 10., 10.),
+            border: (1., 2., 3., 4.),
+            ..layout
+        };
+        let style = ComputedStyle::new(&props, &layout, false);
+        assert_eq!(style.get_property_value("width").unwrap(), "105px");
+        assert_eq!(style.get_property_value("height").unwrap(), "55px");
+        assert_eq!(style.get_property_value("padding-left").unwrap(), "10px");
+    }
+}
@@ -0,0 +1,93 @@
+use std::fmt;
+
+use super::{
+    media::{MediaCondition, MediaValue},
+    properties::{ContainerType, Dimensionality},
+    CSSProps, CSSToken, CSSValue, Component,
+};
+
+//@container [name] <condition>. the condition's a media condition, it's just asked about the
+//nearest container instead of the viewport.
+#[derive(Debug, Clone)]
+pub struct ContainerCondition {
+    pub name: Option<String>,
+    pub condition: MediaCondition,
+}
+
+impl ContainerCondition {
+    pub fn from_components(components: &[Component]) -> Option<Self> {
+        let tokens = Component::flatten(components);
+        let start = tokens
+            .iter()
+            .position(|t| !matches!(t, CSSToken::Whitespace))
+            .unwrap_or(tokens.len());
+        let (name, rest) = match tokens.get(start) {
+            Some(CSSToken::Ident(i))
+                if !["not", "and", "or", "none"].contains(&i.to_ascii_lowercase().as_str()) =>
+            {
+                (Some(i.clone()), &tokens[start + 1..])
+            }
+            _ => (None, &tokens[start..]),
+        };
+        Some(Self {
+            name,
+            condition: MediaCondition::from_tokens(rest.to_vec())?,
+        })
+    }
+
+    //whether this is a container we'd be asking. the name has to match if there is one.
+    pub fn is_container(&self, props: &CSSProps) -> bool {
+        let CSSValue::Value(ref container_type) = props.container_type else {
+            return false;
+        };
+        *container_type != ContainerType::Normal
+            && self.name.as_ref().map_or(true, |name| {
+                props
+                    .container_name
+                    .unwrap()
+                    .names
+                    .iter()
+                    .any(|n| n == name)
+            })
+    }
+
+    //asked about the container's content box from the last layout. one that hasn't been laid
+    //out yet only has a size if it's got a definite width or height, and asking about anything
+    //else is unknown, which is false. the document lays out again once it knows better.
+    pub fn matches(&self, container: &CSSProps, laid_out: Option<(f64, f64)>) -> bool {
+        let size = |d: &CSSValue<Dimensionality>| match d {
+            CSSValue::Value(d) => d.resolve(None),
+            _ => None,
+        };
+        let width = laid_out
+            .map(|(width, _)| width)
+            .or_else(|| size(&container.width));
+        let height = match container.container_type {
+            CSSValue::Value(ContainerType::Size) => laid_out
+                .map(|(_, height)| height)
+                .or_else(|| size(&container.height)),
+            _ => None,
+        };
+        self.condition.evaluate(&|feature| {
+            Some(match feature {
+                "width" | "inline-size" => MediaValue::Length(width?),
+                "height" | "block-size" => MediaValue::Length(height?),
+                "aspect-ratio" => MediaValue::Ratio(width?, height?),
+                "orientation" => MediaValue::Ident(String::from(match height? >= width? {
+                    true => "portrait",
+                    false => "landscape",
+                })),
+                _ => return None,
+            })
+        })
+    }
+}
+
+impl fmt::Display for ContainerCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref name) = self.name {
+            write!(f, "{} ", CSSToken::Ident(name.clone()))?;
+        }
+        self.condition.fmt(f)
+    }
+}
@@ -0,0 +1,389 @@
+use std::fmt;
+
+use super::{properties::parse_angle, CSSNumber, CSSToken, Component, SimpleBlock};
+
+//what an @font-face rule says about a font. the actual loading happens over in
+//layout::text::font_set, this is just the description.
+#[derive(Debug, Clone)]
+pub struct FontFace {
+    pub family: String,
+    pub sources: Vec<FontFaceSource>,
+    pub weight: (f64, f64),
+    pub style: FontFaceStyle,
+    pub unicode_range: Vec<(u32, u32)>,
+}
+
+impl Default for FontFace {
+    fn default() -> Self {
+        Self {
+            family: String::new(),
+            sources: vec![],
+            weight: (400., 400.),
+            style: FontFaceStyle::default(),
+            unicode_range: vec![(0, 0x10FFFF)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontFaceSource {
+    Url(String, Option<String>), //url, format hint
+    Local(String),
+}
+
+//oblique takes a range of angles in degrees, which is just the one for a face that isn't
+//variable.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FontFaceStyle {
+    #[default]
+    Normal,
+    Italic,
+    Oblique(f64, f64),
+}
+
+//descriptors left at their defaults don't get written out.
+impl fmt::Display for FontFace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let default = Self::default();
+        write!(
+            f,
+            "@font-face {{ font-family: {}; src: ",
+            CSSToken::String(self.family.clone())
+        )?;
+        for (i, source) in self.sources.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            match source {
+                FontFaceSource::Url(url, format) => {
+                    write!(f, "url({})", CSSToken::String(url.clone()))?;
+                    if let Some(format) = format {
+                        write!(f, " format({})", CSSToken::String(format.clone()))?;
+                    }
+                }
+                FontFaceSource::Local(name) => {
+                    write!(f, "local({})", CSSToken::String(name.clone()))?
+                }
+            }
+        }
+        f.write_str(";")?;
+        if self.weight != default.weight {
+            write!(f, " font-weight: {} {};", self.weight.0, self.weight.1)?;
+        }
+        match self.style {
+            FontFaceStyle::Normal => {}
+            FontFaceStyle::Italic => f.write_str(" font-style: italic;")?,
+            FontFaceStyle::Oblique(low, high) if low == high => {
+                write!(f, " font-style: oblique {}deg;", low)?
+            }
+            FontFaceStyle::Oblique(low, high) => {
+                write!(f, " font-style: oblique {}deg {}deg;", low, high)?
+            }
+        }
+        if self.unicode_range != default.unicode_range {
+            f.write_str(" unicode-range: ")?;
+            for (i, (start, end)) in self.unicode_range.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                CSSToken::UnicodeRange(*start, *end).fmt(f)?;
+            }
+            f.write_str(";")?;
+        }
+        f.write_str(" }")
+    }
+}
+
+impl FontFace {
+    //returns None if the rule is missing the family or has nowhere to load from, those get
+    //ignored entirely.
+    pub fn from_blocks(blocks: &Vec<SimpleBlock>) -> Option<Self> {
+        let mut face = Self::default();
+        for block in blocks {
+            for descriptor in block
+                .value
+                .split(|c| matches!(c, Component::Token(CSSToken::Semicolon)))
+            {
+                let tokens = Component::flatten(descriptor);
+                face.apply_descriptor(trim_whitespace(&tokens));
+            }
+        }
+        if face.family.is_empty() || face.sources.is_empty() {
+            return None;
+        }
+        Some(face)
+    }
+
+    fn apply_descriptor(&mut self, tokens: &[CSSToken]) {
+        let (name, value) = match tokens {
+            [CSSToken::Ident(name), rest @ ..] => match trim_whitespace(rest) {
+                [CSSToken::Colon, value @ ..] => {
+                    (name.to_ascii_lowercase(), trim_whitespace(value))
+                }
+                _ => return,
+            },
+            _ => return,
+        };
+        match name.as_str() {
+            "font-family" => {
+                if let Some(family) = parse_family_name(value) {
+                    self.family = family;
+                }
+            }
+            "src" => {
+                self.sources = split_commas(value)
+                    .into_iter()
+                    .filter_map(|s| parse_source(trim_whitespace(s)))
+                    .collect();
+            }
+            "font-weight" => {
+                let weights = value
+                    .iter()
+                    .filter_map(|t| match t {
+                        CSSToken::Ident(i) if i.eq_ignore_ascii_case("normal") => Some(400.),
+                        CSSToken::Ident(i) if i.eq_ignore_ascii_case("bold") => Some(700.),
+                        CSSToken::Number(CSSNumber::Number(n)) => Some(n.unwrap_f64()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                match weights.as_slice() {
+                    [w] => self.weight = (*w, *w),
+                    [l, h] => self.weight = (l.min(*h), l.max(*h)),
+                    _ => {}
+                }
+            }
+            "font-style" => {
+                if let Some(style) = parse_style(value) {
+                    self.style = style;
+                }
+            }
+            "unicode-range" => {
+                let ranges = value
+                    .iter()
+                    .filter_map(|t| match t {
+                        CSSToken::UnicodeRange(l, h) => Some((*l, *h)),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                if !ranges.is_empty() {
+                    self.unicode_range = ranges;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn covers(&self, c: char) -> bool {
+        self.unicode_range
+            .iter()
+            .any(|(l, h)| (*l..=*h).contains(&(c as u32)))
+    }
+}
+
+fn trim_whitespace(tokens: &[CSSToken]) -> &[CSSToken] {
+    let start = tokens
+        .iter()
+        .position(|t| !matches!(t, CSSToken::Whitespace))
+        .unwrap_or(tokens.len());
+    let end = tokens
+        .iter()
+        .rposition(|t| !matches!(t, CSSToken::Whitespace))
+        .map_or(start, |i| i + 1);
+    &tokens[start..end]
+}
+
+fn split_commas(tokens: &[CSSToken]) -> Vec<&[CSSToken]> {
+    tokens
+        .split(|t| matches!(t, CSSToken::Comma))
+        .collect::<Vec<_>>()
+}
+
+//either one string, or a bunch of idents that get glued back together with single spaces.
+fn parse_family_name(tokens: &[CSSToken]) -> Option<String> {
+    match tokens {
+        [CSSToken::String(s)] => Some(s.clone()),
+        _ => {
+            let mut out = String::new();
+            for token in tokens {
+                match token {
+                    CSSToken::Ident(i) => {
+                        if !out.is_empty() {
+                            out.push(' ');
+                        }
+                        out.push_str(i);
+                    }
+                    CSSToken::Whitespace => {}
+                    _ => return None,
+                }
+            }
+            if out.is_empty() {
+                None
+            } else {
+                Some(out)
+            }
+        }
+    }
+}
+
+//normal, italic, or oblique with up to two angles, which default to 14deg.
+fn parse_style(tokens: &[CSSToken]) -> Option<FontFaceStyle> {
+    let tokens = tokens
+        .iter()
+        .filter(|t| !matches!(t, CSSToken::Whitespace))
+        .collect::<Vec<_>>();
+    let [CSSToken::Ident(i), angles @ ..] = tokens.as_slice() else {
+        return None;
+    };
+    let angles = angles
+        .iter()
+        .map(|t| parse_angle(t).filter(|a| a.abs() <= 90.))
+        .collect::<Option<Vec<_>>>()?;
+    Some(match (i.to_ascii_lowercase().as_str(), angles.as_slice()) {
+        ("normal", []) => FontFaceStyle::Normal,
+        ("italic", []) => FontFaceStyle::Italic,
+        ("oblique", []) => FontFaceStyle::Oblique(14., 14.),
+        ("oblique", [a]) => FontFaceStyle::Oblique(*a, *a),
+        ("oblique", [l, h]) => FontFaceStyle::Oblique(l.min(*h), l.max(*h)),
+        _ => return None,
+    })
+}
+
+fn parse_source(tokens: &[CSSToken]) -> Option<FontFaceSource> {
+    let (url, rest) = match tokens {
+        [CSSToken::Url(url), rest @ ..] => (url.clone(), rest),
+        [CSSToken::Function(f), rest @ ..] if f.eq_ignore_ascii_case("url") => {
+            match trim_whitespace(rest) {
+                [CSSToken::String(url), rest @ ..] => match trim_whitespace(rest) {
+                    [CSSToken::ParenClose, rest @ ..] => (url.clone(), rest),
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+        [CSSToken::Function(f), rest @ ..] if f.eq_ignore_ascii_case("local") => {
+            let end = rest
+                .iter()
+                .position(|t| matches!(t, CSSToken::ParenClose))?;
+            return Some(FontFaceSource::Local(parse_family_name(trim_whitespace(
+                &rest[..end],
+            ))?));
+        }
+        _ => return None,
+    };
+    let mut format = None;
+    let mut rest = trim_whitespace(rest);
+    while let [CSSToken::Function(f), inner @ ..] = rest {
+        let end = inner
+            .iter()
+            .position(|t| matches!(t, CSSToken::ParenClose))?;
+        if f.eq_ignore_ascii_case("format") {
+            match trim_whitespace(&inner[..end]) {
+                [CSSToken::String(s) | CSSToken::Ident(s)] => format = Some(s.to_ascii_lowercase()),
+                _ => return None,
+            }
+        }
+        rest = trim_whitespace(&inner[end + 1..]);
+    }
+    //we only know how to load sfnt based fonts, anything else gets skipped like the spec says.
+    if let Some(ref f) = format {
+        if ![
+            "truetype",
+            "opentype",
+            "woff",
+            "woff2",
+            "truetype-variations",
+            "opentype-variations",
+            "woff-variations",
+            "woff2-variations",
+        ]
+        .contains(&f.as_str())
+        {
+            return None;
+        }
+    }
+    Some(FontFaceSource::Url(url, format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::css::{Block, CSSParser, Prelude};
+
+    fn parse_face(input: &str) -> Option<FontFace> {
+        let mut parser = CSSParser::default();
+        parser.push_raw_css(&String::from(input));
+        let styles = parser.parse_stylesheets().unwrap();
+        match (&styles[0].rules[0].prelude, &styles[0].rules[0].value) {
+            (Prelude::FontFace, Block::FontFace(face)) => Some(face.clone()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_font_face_descriptors() {
+        let face = parse_face(
+            "@font-face {
+                font-family: \"Shmeep Sans\";
+                src: local(Shmeep Sans Regular), url(fonts/shmeep.woff2) format(\"woff2\"), url('shmeep.eot') format('embedded-opentype');
+                font-weight: 100 900;
+                font-style: italic;
+                unicode-range: U+0000-00FF, U+0131, U+04??;
+            }",
+        )
+        .unwrap();
+        assert_eq!(face.family, "Shmeep Sans");
+        assert_eq!(
+            face.sources,
+            vec![
+                FontFaceSource::Local(String::from("Shmeep Sans Regular")),
+                FontFaceSource::Url(
+                    String::from("fonts/shmeep.woff2"),
+                    Some(String::from("woff2"))
+                ),
+            ]
+        );
+        assert_eq!(face.weight, (100., 900.));
+        assert_eq!(face.style, FontFaceStyle::Italic);
+        assert_eq!(
+            face.unicode_range,
+            vec![(0, 0xFF), (0x131, 0x131), (0x400, 0x4FF)]
+        );
+        assert!(face.covers('é'));
+        assert!(!face.covers('あ'));
+    }
+
+    #[test]
+    fn test_font_face_styles() {
+        let style = |style: &str| {
+            parse_face(&format!(
+                "@font-face {{ font-family: a; src: url(a.ttf); font-style: {} }}",
+                style
+            ))
+            .unwrap()
+            .style
+        };
+        assert_eq!(style("oblique"), FontFaceStyle::Oblique(14., 14.));
+        assert_eq!(style("oblique 10deg"), FontFaceStyle::Oblique(10., 10.));
+        assert_eq!(
+            style("OBLIQUE 30deg -0.25turn"),
+            FontFaceStyle::Oblique(-90., 30.)
+        );
+        //anything that doesn't parse leaves it alone.
+        assert_eq!(style("oblique 100deg"), FontFaceStyle::Normal);
+        assert_eq!(style("italic 10deg"), FontFaceStyle::Normal);
+        let face = parse_face(
+            "@font-face { font-family: a; src: url(a.ttf); font-style: oblique 0 20deg }",
+        )
+        .unwrap();
+        assert_eq!(
+            face.to_string(),
+            "@font-face { font-family: \"a\"; src: url(\"a.ttf\"); \
+             font-style: oblique 0deg 20deg; }"
+        );
+    }
+
+    #[test]
+    fn test_font_face_without_src_is_dropped() {
+        assert!(parse_face("@font-face { font-family: Shmeep; }").is_none());
+    }
+}
@@ -0,0 +1,311 @@
+use std::fmt;
+
+use futures::{future::LocalBoxFuture, FutureExt};
+use reqwest::Client;
+use url::Url;
+
+use super::{
+    font_face::FontFaceSource,
+    layer::{parse_layer_names, LayerName},
+    media::MediaQueryList,
+    supports::SupportsCondition,
+    Block, CSSParser, CSSSource, CSSToken, Component, Prelude, Rule, Style,
+};
+use crate::context::fetch::fetch;
+
+//@import url [layer] [supports(...)] [media query list];
+#[derive(Debug, Clone)]
+pub struct ImportRule {
+    pub url: String,
+    pub media: MediaQueryList,
+    pub layer: Option<Option<LayerName>>, //`layer` on its own is an anonymous one
+    pub supports: Option<SupportsCondition>,
+    pub supported: bool, //supports() doesn't depend on anything that changes, so it's checked once.
+}
+
+impl fmt::Display for ImportRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "@import url({})", CSSToken::String(self.url.clone()))?;
+        match self.layer {
+            Some(Some(ref name)) => write!(f, " layer({})", name.join("."))?,
+            Some(None) => f.write_str(" layer")?,
+            None => {}
+        }
+        if let Some(ref condition) = self.supports {
+            write!(f, " supports({})", condition)?;
+        }
+        if !self.media.queries.is_empty() {
+            write!(f, " {}", self.media)?;
+        }
+        f.write_str(";")
+    }
+}
+
+impl ImportRule {
+    pub fn from_components(components: &[Component]) -> Option<Self> {
+        let tokens = Component::flatten(components);
+        let mut rest = skip_whitespace(&tokens);
+        let url = match rest {
+            [CSSToken::Url(url) | CSSToken::String(url), after @ ..] => {
+                rest = after;
+                url.clone()
+            }
+            [CSSToken::Function(f), after @ ..] if f.eq_ignore_ascii_case("url") => {
+                match skip_whitespace(after) {
+                    [CSSToken::String(url), after @ ..] => match skip_whitespace(after) {
+                        [CSSToken::ParenClose, after @ ..] => {
+                            rest = after;
+                            url.clone()
+                        }
+                        _ => return None,
+                    },
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        rest = skip_whitespace(rest);
+        //layer() and supports() are easier to look at as components, so they're picked back out
+        //of those while the tokens just skip over them.
+        let arguments = |name: &str| {
+            components.iter().find_map(|c| match c {
+                Component::Function(f, arguments) if f.eq_ignore_ascii_case(name) => {
+                    Some(arguments)
+                }
+                _ => None,
+            })
+        };
+        let mut layer = None;
+        match rest {
+            [CSSToken::Ident(i), after @ ..] if i.eq_ignore_ascii_case("layer") => {
+                layer = Some(None);
+                rest = after;
+            }
+            [CSSToken::Function(f), after @ ..] if f.eq_ignore_ascii_case("layer") => {
+                let mut names = parse_layer_names(arguments("layer")?)?;
+                if names.len() != 1 {
+                    return None;
+                }
+                layer = Some(names.pop());
+                rest = &after[matching_paren(after)? + 1..];
+            }
+            _ => {}
+        }
+        rest = skip_whitespace(rest);
+        let mut supports = None;
+        if let [CSSToken::Function(f), after @ ..] = rest {
+            if f.eq_ignore_ascii_case("supports") {
+                supports = Some(SupportsCondition::from_arguments(arguments("supports")?)?);
+                rest = &after[matching_paren(after)? + 1..];
+            }
+        }
+        Some(Self {
+            url,
+            media: MediaQueryList::from_tokens(rest.to_vec()),
+            layer,
+            supported: supports.as_ref().map_or(true, SupportsCondition::matches),
+            supports,
+        })
+    }
+}
+
+fn skip_whitespace(tokens: &[CSSToken]) -> &[CSSToken] {
+    let start = tokens
+        .iter()
+        .position(|t| !matches!(t, CSSToken::Whitespace))
+        .unwrap_or(tokens.len());
+    &tokens[start..]
+}
+
+//index of the paren that closes one we've already gone past.
+fn matching_paren(tokens: &[CSSToken]) -> Option<usize> {
+    let mut depth = 1usize;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            CSSToken::ParenOpen | CSSToken::Function(_) => depth += 1,
+            CSSToken::ParenClose => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+//swaps every @import in the sheets for the rules it pulled in, wrapped in an @media so the
+//import's media list still gets checked during the cascade, and in an @layer if it asked for
+//one. sheets without a url of their own (inline <style>s and such) resolve against the document.
+pub async fn resolve_imports(styles: &mut Vec<Style>, document_url: &Url, client: &Client) {
+    for style in styles {
+        let base = style.url.clone().unwrap_or(document_url.clone());
+        let mut chain = vec![base.clone()];
+        resolve_imports_recursive(&mut style.rules, &base, client, &mut chain).await;
+        style.let_em_know();
+    }
+}
+
+fn resolve_imports_recursive<'a>(
+    rules: &'a mut Vec<Rule>,
+    base: &'a Url,
+    client: &'a Client,
+    chain: &'a mut Vec<Url>,
+) -> LocalBoxFuture<'a, ()> {
+    async move {
+        for rule in rules.iter_mut() {
+            //a <link media> sheet is all inside one @media, imports and all.
+            if let (Prelude::Media(_), Block::Rules(nested)) = (&rule.prelude, &mut rule.value) {
+                resolve_imports_recursive(nested, base, client, chain).await;
+                continue;
+            }
+            let Prelude::Import(ref import) = rule.prelude else {
+                continue;
+            };
+            let import = import.clone();
+            *rule = Rule::default();
+            if !import.supported {
+                continue;
+            }
+            let url = match base.join(&import.url) {
+                Ok(url) => url,
+                Err(e) => {
+                    tracing::warn!("bad @import url {}: {}", import.url, e);
+                    continue;
+                }
+            };
+            //only the chain of sheets importing this one counts, the same sheet showing up in
+            //two separate branches is fine.
+            if chain.contains(&url) {
+                tracing::warn!("@import cycle at {}, skipping", url);
+                continue;
+            }
+            let text = match fetch(client, &url).await {
+                Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                Err(e) => {
+                    tracing::warn!("couldn't fetch @import {}: {}", url, e);
+                    continue;
+                }
+            };
+            let mut parser = CSSParser::default();
+            parser.push_many(vec![CSSSource::Fetched(url.clone(), text)]);
+            let mut imported = match parser.parse_stylesheets() {
+                Ok(mut styles) if !styles.is_empty() => styles.remove(0).rules,
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::warn!("couldn't parse @import {}: {}", url, e);
+                    continue;
+                }
+            };
+            absolutize_font_faces(&mut imported, &url);
+            chain.push(url.clone());
+            resolve_imports_recursive(&mut imported, &url, client, chain).await;
+            chain.pop();
+            if let Some(layer) = import.layer {
+                imported = vec![Rule {
+                    prelude: Prelude::Layer(layer.into_iter().collect()),
+                    value: Block::Rules(imported),
+                }];
+            }
+            *rule = Rule {
+                prelude: Prelude::Media(import.media),
+                value: Block::Rules(imported),
+            };
+        }
+    }
+    .boxed_local()
+}
+
+//font urls are relative to the sheet they're written in, which gets lost once its rules are
+//spliced into someone else's.
+fn absolutize_font_faces(rules: &mut Vec<Rule>, base: &Url) {
+    for rule in rules {
+        match rule.value {
+            Block::FontFace(ref mut face) => {
+                for source in &mut face.sources {
+                    if let FontFaceSource::Url(ref mut url, _) = source {
+                        if let Ok(joined) = base.join(url) {
+                            *url = joined.to_string();
+                        }
+                    }
+                }
+            }
+            Block::Rules(ref mut rules) => absolutize_font_faces(rules, base),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Vec<Rule> {
+        let mut parser = CSSParser::default();
+        parser.push_raw_css(&String::from(input));
+        parser.parse_stylesheets().unwrap().remove(0).rules
+    }
+
+    #[test]
+    fn test_import_preludes() {
+        let rules = parse(
+            "@import url(\"shmeep.css\") screen and (min-width: 600px);
+            @import 'shmop.css' supports(display: block);
+            @import url(nope.css) supports(shmeeping: yes);
+            @layer a;
+            @import url(layered.css) layer(a.b) supports(not (display: grid)) print;
+            p { color: red; }
+            @import url(too-late.css);",
+        );
+        let imports = rules
+            .iter()
+            .filter_map(|r| match r.prelude {
+                Prelude::Import(ref i) => Some(i),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(imports.len(), 4);
+        assert_eq!(imports[0].url, "shmeep.css");
+        assert_eq!(imports[0].media.queries.len(), 1);
+        assert!(imports[0].supported);
+        assert_eq!(imports[1].url, "shmop.css");
+        assert!(imports[1].media.queries.is_empty());
+        assert!(imports[1].supported);
+        assert!(!imports[2].supported);
+        assert_eq!(
+            imports[3].layer,
+            Some(Some(vec![String::from("a"), String::from("b")]))
+        );
+        assert!(imports[3].supported);
+        assert_eq!(
+            imports[3].to_string(),
+            "@import url(\"layered.css\") layer(a.b) supports(not (display: grid)) print;"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_cycle() {
+        let dir = std::env::temp_dir().join(format!("based-import-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.css"), "@import url(b.css); a { color: red; }").unwrap();
+        std::fs::write(dir.join("b.css"), "@import url(a.css); b { color: blue; }").unwrap();
+        let url = Url::from_file_path(dir.join("a.css")).unwrap();
+        let mut parser = CSSParser::default();
+        parser.push_url(&url);
+        let mut styles = parser.parse_stylesheets().unwrap();
+        resolve_imports(&mut styles, &url, &Client::new()).await;
+        //a imports b, b's import of a gets dropped instead of going around forever.
+        match &styles[0].rules[0] {
+            Rule {
+                prelude: Prelude::Media(_),
+                value: Block::Rules(rules),
+            } => {
+                assert!(matches!(rules[0].prelude, Prelude::None));
+                assert!(matches!(rules[1].prelude, Prelude::Selector(_)));
+            }
+            a => panic!("{:?}", a),
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use super::{CSSToken, Component};
+use crate::parser::Char;
+
+//`a.b` is layer b inside layer a.
+pub type LayerName = Vec<String>;
+
+//a comma separated list of layer names, which is empty for an anonymous @layer block. None if
+//any of them isn't a name.
+pub fn parse_layer_names(components: &[Component]) -> Option<Vec<LayerName>> {
+    let mut names = vec![];
+    let mut working: LayerName = vec![];
+    let mut dotted = false; //last thing was a dot, so the name isn't finished
+    for component in components {
+        let Component::Token(token) = component else {
+            return None;
+        };
+        match token {
+            CSSToken::Whitespace if !dotted => {}
+            CSSToken::Ident(i) if dotted || working.is_empty() => {
+                if working.is_empty() && ["initial", "inherit", "unset"].contains(&i.as_str()) {
+                    return None;
+                }
+                working.push(i.clone());
+                dotted = false;
+            }
+            CSSToken::Delim(Char::Char('.')) if !dotted && !working.is_empty() => dotted = true,
+            CSSToken::Comma if !dotted && !working.is_empty() => {
+                names.push(std::mem::take(&mut working))
+            }
+            _ => return None,
+        }
+    }
+    //a dot or a comma with nothing after it
+    if dotted || (working.is_empty() && !names.is_empty()) {
+        return None;
+    }
+    if !working.is_empty() {
+        names.push(working);
+    }
+    Some(names)
+}
+
+//every layer that's been declared so far, in the order they showed up. siblings are numbered
+//by when they were first declared, so a layer is found by the path of numbers down to it.
+//anonymous layers can't be named again, so every one of them is new.
+#[derive(Debug, Default)]
+pub struct LayerTree {
+    children: HashMap<Vec<u32>, Vec<Option<String>>>,
+}
+
+impl LayerTree {
+    //declares the layer if it isn't already, along with anything it's nested in.
+    pub fn declare(&mut self, parent: &[u32], name: Option<&LayerName>) -> Vec<u32> {
+        let mut path = parent.to_vec();
+        let Some(name) = name else {
+            let siblings = self.children.entry(path.clone()).or_default();
+            siblings.push(None);
+            path.push(siblings.len() as u32 - 1);
+            return path;
+        };
+        for part in name {
+            let siblings = self.children.entry(path.clone()).or_default();
+            let index = match siblings.iter().position(|s| s.as_ref() == Some(part)) {
+                Some(index) => index,
+                None => {
+                    siblings.push(Some(part.clone()));
+                    siblings.len() - 1
+                }
+            };
+            path.push(index as u32);
+        }
+        path
+    }
+}
+
+//where a rule's declarations sit among the layers, lowest first. rules that aren't in a layer
+//at some level come after every layer at that level, which is what the u32::MAX on the end is
+//for, so [0, MAX] (layer 0's own rules) beats [0, 1] but loses to [1, ...].
+pub fn layer_key(path: &[u32]) -> Vec<u32> {
+    let mut key = path.to_vec();
+    key.push(u32::MAX);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::css::CSSParser;
+
+    fn names(input: &str) -> Option<Vec<LayerName>> {
+        parse_layer_names(&CSSParser::parse_component_list(&String::from(input)).unwrap())
+    }
+
+    #[test]
+    fn test_layer_names_and_order() {
+        assert_eq!(
+            names(" reset, base.type , x").unwrap(),
+            vec![vec!["reset"], vec!["base", "type"], vec!["x"]]
+        );
+        assert!(names("").unwrap().is_empty());
+        assert!(names("a. b").is_none());
+        assert!(names("a,,b").is_none());
+        assert!(names("a,").is_none());
+        assert!(names(",").is_none());
+        assert!(names("a b").is_none());
+        assert!(names("initial").is_none());
+
+        let mut tree = LayerTree::default();
+        let base = vec![String::from("base")];
+        let reset = vec![String::from("reset")];
+        let nested = vec![String::from("base"), String::from("type")];
+        assert_eq!(tree.declare(&[], Some(&base)), vec![0]);
+        assert_eq!(tree.declare(&[], Some(&reset)), vec![1]);
+        assert_eq!(tree.declare(&[], Some(&nested)), vec![0, 0]);
+        assert_eq!(tree.declare(&[], None), vec![2]);
+        assert_eq!(tree.declare(&[], None), vec![3]);
+        assert_eq!(tree.declare(&[1], Some(&base)), vec![1, 0]);
+        //declaring it again doesn't move it.
+        assert_eq!(tree.declare(&[], Some(&base)), vec![0]);
+        assert!(layer_key(&[0, 0]) < layer_key(&[0]));
+        assert!(layer_key(&[0]) < layer_key(&[1, 0]));
+        assert!(layer_key(&[1]) < layer_key(&[]));
+    }
+}
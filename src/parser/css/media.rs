@@ -0,0 +1,837 @@
+use std::fmt;
+
+use super::{CSSNumber, CSSToken, Component, Unit};
+use crate::{context::Viewport, parser::Char};
+
+//everything a media query can be asked about. width and height are in css px, not device
+//pixels, so they get divided by the scale factor on the way in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaEnvironment {
+    pub width: f64,
+    pub height: f64,
+    pub resolution: f64, //dppx
+    pub colour_scheme: ColourScheme,
+    pub reduced_motion: bool,
+}
+
+impl Default for MediaEnvironment {
+    fn default() -> Self {
+        Self {
+            width: 0.,
+            height: 0.,
+            resolution: 1.,
+            colour_scheme: ColourScheme::default(),
+            reduced_motion: false,
+        }
+    }
+}
+
+impl MediaEnvironment {
+    pub fn new(viewport: Viewport, scale_factor: f64) -> Self {
+        let mut out = Self::default();
+        out.update(viewport, scale_factor);
+        out
+    }
+
+    pub fn update(&mut self, viewport: Viewport, scale_factor: f64) {
+        let scale_factor = if scale_factor > 0. { scale_factor } else { 1. };
+        self.width = viewport.width as f64 / scale_factor;
+        self.height = viewport.height as f64 / scale_factor;
+        self.resolution = scale_factor;
+    }
+
+    fn feature(&self, name: &str) -> Option<MediaValue> {
+        Some(match name {
+            "width" | "device-width" => MediaValue::Length(self.width),
+            "height" | "device-height" => MediaValue::Length(self.height),
+            "aspect-ratio" | "device-aspect-ratio" => MediaValue::Ratio(self.width, self.height),
+            "orientation" => MediaValue::Ident(String::from(if self.height >= self.width {
+                "portrait"
+            } else {
+                "landscape"
+            })),
+            "resolution" => MediaValue::Resolution(self.resolution),
+            "prefers-color-scheme" => MediaValue::Ident(String::from(match self.colour_scheme {
+                ColourScheme::Light => "light",
+                ColourScheme::Dark => "dark",
+            })),
+            "prefers-reduced-motion" => MediaValue::Ident(String::from(if self.reduced_motion {
+                "reduce"
+            } else {
+                "no-preference"
+            })),
+            "color" => MediaValue::Number(8.),
+            "color-index" | "monochrome" | "grid" => MediaValue::Number(0.),
+            "hover" | "any-hover" => MediaValue::Ident(String::from("hover")),
+            "pointer" | "any-pointer" => MediaValue::Ident(String::from("fine")),
+            "update" => MediaValue::Ident(String::from("fast")),
+            "scan" => MediaValue::Ident(String::from("progressive")),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColourScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MediaQueryList {
+    pub queries: Vec<MediaQuery>,
+}
+
+impl MediaQueryList {
+    pub fn from_components(components: &[Component]) -> Self {
+        Self::from_tokens(Component::flatten(components))
+    }
+
+    pub fn from_tokens(tokens: Vec<CSSToken>) -> Self {
+        let mut queries = vec![];
+        let mut working = vec![];
+        let mut depth = 0usize;
+        for token in tokens.into_iter().chain([CSSToken::Comma]) {
+            match token {
+                CSSToken::ParenOpen => depth += 1,
+                CSSToken::ParenClose => depth = depth.saturating_sub(1),
+                CSSToken::Comma if depth == 0 => {
+                    let mut parser = MediaQueryParser {
+                        tokens: std::mem::take(&mut working),
+                        tokens_idx: 0,
+                    };
+                    //a query that doesn't parse becomes "not all" rather than killing the list.
+                    queries.push(parser.parse_query().unwrap_or(MediaQuery::NOT_ALL));
+                    continue;
+                }
+                CSSToken::EOF => continue,
+                _ => {}
+            }
+            working.push(token);
+        }
+        //an empty list is the same as "all"
+        if queries.len() == 1 && queries[0].is_empty() {
+            queries.clear();
+        }
+        Self { queries }
+    }
+
+    pub fn matches(&self, environment: &MediaEnvironment) -> bool {
+        self.queries.is_empty() || self.queries.iter().any(|q| q.matches(environment))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MediaQuery {
+    pub negated: bool,
+    pub media_type: MediaType,
+    pub condition: Option<MediaCondition>,
+}
+
+impl MediaQuery {
+    const NOT_ALL: MediaQuery = MediaQuery {
+        negated: true,
+        media_type: MediaType::All,
+        condition: None,
+    };
+
+    fn is_empty(&self) -> bool {
+        !self.negated && matches!(self.media_type, MediaType::All) && self.condition.is_none()
+    }
+
+    pub fn matches(&self, environment: &MediaEnvironment) -> bool {
+        let type_matches = match self.media_type {
+            MediaType::All | MediaType::Screen => true,
+            MediaType::Print | MediaType::Unknown(_) => false,
+        };
+        let condition = self
+            .condition
+            .as_ref()
+            .map_or(Some(true), |c| c.kleene(&|name| environment.feature(name)));
+        //"not" can't turn an unknown into a match either.
+        match (type_matches, condition) {
+            (false, _) => self.negated,
+            (true, Some(matches)) => matches != self.negated,
+            (true, None) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MediaType {
+    All,
+    Screen,
+    Print,
+    Unknown(String),
+}
+
+impl MediaType {
+    pub fn from_string(string: &str) -> Self {
+        match string.to_ascii_lowercase().as_str() {
+            "all" => Self::All,
+            "screen" => Self::Screen,
+            "print" => Self::Print,
+            a => Self::Unknown(a.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MediaCondition {
+    Not(Box<MediaCondition>),
+    And(Vec<MediaCondition>),
+    Or(Vec<MediaCondition>),
+    Feature(MediaFeature),
+    Unknown, //<general-enclosed>, neither true nor false
+}
+
+impl MediaCondition {
+    //a bare condition with no media type in front, which is what @container uses.
+    pub fn from_tokens(tokens: Vec<CSSToken>) -> Option<Self> {
+        let mut parser = MediaQueryParser {
+            tokens,
+            tokens_idx: 0,
+        };
+        let condition = parser.parse_condition(true)?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            CSSToken::EOF => Some(condition),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, environment: &MediaEnvironment) -> bool {
+        self.evaluate(&|name| environment.feature(name))
+    }
+
+    //the same thing asked about something other than the viewport.
+    pub fn evaluate(&self, feature: &dyn Fn(&str) -> Option<MediaValue>) -> bool {
+        self.kleene(feature) == Some(true)
+    }
+
+    //three-valued, with None for unknown, so that negating something we don't understand doesn't
+    //make it match. it only turns into false right at the end.
+    fn kleene(&self, feature: &dyn Fn(&str) -> Option<MediaValue>) -> Option<bool> {
+        match self {
+            Self::Not(c) => c.kleene(feature).map(|m| !m),
+            Self::And(cs) | Self::Or(cs) => {
+                //one false settles an and, and one true settles an or, whatever else is unknown.
+                let settles = matches!(self, Self::Or(_));
+                let results = cs.iter().map(|c| c.kleene(feature)).collect::<Vec<_>>();
+                if results.contains(&Some(settles)) {
+                    Some(settles)
+                } else if results.contains(&None) {
+                    None
+                } else {
+                    Some(!settles)
+                }
+            }
+            Self::Feature(f) => {
+                let actual = feature(&f.name)?;
+                Some(f.test(Some(actual)))
+            }
+            Self::Unknown => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MediaFeature {
+    pub name: String,
+    pub test: MediaFeatureTest,
+}
+
+impl MediaFeature {
+    pub fn matches(&self, environment: &MediaEnvironment) -> bool {
+        self.test(environment.feature(&self.name))
+    }
+
+    //None is a feature whoever's being asked doesn't have.
+    fn test(&self, actual: Option<MediaValue>) -> bool {
+        let Some(actual) = actual else {
+            return false;
+        };
+        match &self.test {
+            MediaFeatureTest::Boolean => match actual {
+                MediaValue::Ident(i) => i != "none" && i != "no-preference",
+                a => a.as_f64().is_some_and(|v| v != 0.),
+            },
+            MediaFeatureTest::Range(constraints) => constraints
+                .iter()
+                .all(|(comparison, value)| comparison.compare(&actual, value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MediaFeatureTest {
+    Boolean,
+    //every constraint reads as `<feature> <comparison> <value>`, so `(400px < width)` is stored
+    //flipped as `width > 400px`.
+    Range(Vec<(MediaComparison, MediaValue)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaComparison {
+    Equal,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl MediaComparison {
+    fn flip(self) -> Self {
+        match self {
+            Self::Equal => Self::Equal,
+            Self::Less => Self::Greater,
+            Self::LessEqual => Self::GreaterEqual,
+            Self::Greater => Self::Less,
+            Self::GreaterEqual => Self::LessEqual,
+        }
+    }
+
+    fn compare(&self, actual: &MediaValue, expected: &MediaValue) -> bool {
+        if let (MediaValue::Ident(l), MediaValue::Ident(r)) = (actual, expected) {
+            return *self == Self::Equal && l.eq_ignore_ascii_case(r);
+        }
+        let (Some(l), Some(r)) = (actual.as_f64(), expected.as_f64()) else {
+            return false;
+        };
+        match self {
+            Self::Equal => (l - r).abs() < f64::EPSILON,
+            Self::Less => l < r,
+            Self::LessEqual => l <= r,
+            Self::Greater => l > r,
+            Self::GreaterEqual => l >= r,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaValue {
+    Number(f64),
+    Length(f64),     //px
+    Resolution(f64), //dppx
+    Ratio(f64, f64),
+    Ident(String),
+}
+
+impl MediaValue {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(v) | Self::Length(v) | Self::Resolution(v) => Some(*v),
+            Self::Ratio(l, r) => Some(l / r),
+            Self::Ident(_) => None,
+        }
+    }
+
+    fn from_number(number: &CSSNumber) -> Option<Self> {
+        Some(match number {
+            CSSNumber::Number(n) => Self::Number(n.unwrap_f64()),
+            CSSNumber::Percentage(_) => return None,
+            CSSNumber::Unit(n, u) => {
+                let n = n.unwrap_f64();
+                match u {
+                    Unit::Px => Self::Length(n),
+                    //media queries resolve font relative units against the initial font size
+                    Unit::Em | Unit::Rem => Self::Length(n * 16.),
+                    Unit::Ex | Unit::Ch => Self::Length(n * 8.),
+                    Unit::In => Self::Length(n * 96.),
+                    Unit::Cm => Self::Length(n * 96. / 2.54),
+                    Unit::Mm => Self::Length(n * 96. / 25.4),
+                    Unit::Q => Self::Length(n * 96. / 101.6),
+                    Unit::Pt => Self::Length(n * 96. / 72.),
+                    Unit::Pc => Self::Length(n * 16.),
+                    Unit::Dppx | Unit::X => Self::Resolution(n),
+                    Unit::Dpi => Self::Resolution(n / 96.),
+                    Unit::Dpcm => Self::Resolution(n * 2.54 / 96.),
+                    _ => return None,
+                }
+            }
+        })
+    }
+}
+
+//lengths come back out in px since that's all we keep, but it means the same thing.
+impl fmt::Display for MediaQueryList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, query) in self.queries.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            query.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MediaQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            f.write_str("not ")?;
+        }
+        match (&self.media_type, &self.condition) {
+            (MediaType::All, Some(condition)) if !self.negated => condition.fmt(f),
+            (media_type, Some(condition)) => write!(f, "{} and {}", media_type, condition),
+            (media_type, None) => media_type.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::All => "all",
+            Self::Screen => "screen",
+            Self::Print => "print",
+            Self::Unknown(t) => t,
+        })
+    }
+}
+
+impl MediaCondition {
+    //features bring their own brackets, anything else needs some when it's inside another
+    //condition.
+    fn fmt_nested(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Feature(_) | Self::Unknown => write!(f, "{}", self),
+            _ => write!(f, "({})", self),
+        }
+    }
+}
+
+impl fmt::Display for MediaCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Not(condition) => {
+                f.write_str("not ")?;
+                condition.fmt_nested(f)
+            }
+            Self::And(conditions) | Self::Or(conditions) => {
+                let joiner = match self {
+                    Self::And(_) => " and ",
+                    _ => " or ",
+                };
+                for (i, condition) in conditions.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(joiner)?;
+                    }
+                    condition.fmt_nested(f)?;
+                }
+                Ok(())
+            }
+            Self::Feature(feature) => feature.fmt(f),
+            //there's no feature by that name, so it's false the same way.
+            Self::Unknown => f.write_str("(unknown)"),
+        }
+    }
+}
+
+impl fmt::Display for MediaFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.test {
+            MediaFeatureTest::Boolean => write!(f, "({})", self.name),
+            MediaFeatureTest::Range(constraints) => match constraints.as_slice() {
+                [(MediaComparison::Equal, value)] => write!(f, "({}: {})", self.name, value),
+                [(low, min), (high, max)] => {
+                    write!(f, "({} {} {} {} {})", min, low.flip(), self.name, high, max)
+                }
+                constraints => {
+                    for (i, (comparison, value)) in constraints.iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(" and ")?;
+                        }
+                        write!(f, "({} {} {})", self.name, comparison, value)?;
+                    }
+                    Ok(())
+                }
+            },
+        }
+    }
+}
+
+impl fmt::Display for MediaComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Equal => "=",
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+        })
+    }
+}
+
+impl fmt::Display for MediaValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Length(px) => write!(f, "{}px", px),
+            Self::Resolution(dppx) => write!(f, "{}dppx", dppx),
+            Self::Ratio(l, r) => write!(f, "{}/{}", l, r),
+            Self::Ident(i) => f.write_str(i),
+        }
+    }
+}
+
+struct MediaQueryParser {
+    tokens: Vec<CSSToken>,
+    tokens_idx: usize,
+}
+
+impl MediaQueryParser {
+    fn consume(&mut self) -> CSSToken {
+        let out = self.tokens.get(self.tokens_idx).unwrap_or(&CSSToken::EOF);
+        self.tokens_idx += 1;
+        out.clone()
+    }
+
+    fn peek(&self) -> CSSToken {
+        self.tokens
+            .get(self.tokens_idx)
+            .unwrap_or(&CSSToken::EOF)
+            .clone()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let CSSToken::Whitespace = self.peek() {
+            self.tokens_idx += 1;
+        }
+    }
+
+    fn peek_ident(&self, ident: &str) -> bool {
+        matches!(self.peek(), CSSToken::Ident(i) if i.eq_ignore_ascii_case(ident))
+    }
+
+    fn next_non_whitespace_after(&self, n: usize) -> CSSToken {
+        self.tokens[(self.tokens_idx + n).min(self.tokens.len())..]
+            .iter()
+            .find(|t| !matches!(t, CSSToken::Whitespace))
+            .unwrap_or(&CSSToken::EOF)
+            .clone()
+    }
+
+    fn parse_query(&mut self) -> Option<MediaQuery> {
+        let mut query = MediaQuery {
+            negated: false,
+            media_type: MediaType::All,
+            condition: None,
+        };
+        self.skip_whitespace();
+        if let CSSToken::EOF = self.peek() {
+            return Some(query);
+        }
+        //"not (color)" is a condition, "not screen" negates the whole query.
+        if matches!(self.peek(), CSSToken::ParenOpen)
+            || (self.peek_ident("not")
+                && matches!(self.next_non_whitespace_after(1), CSSToken::ParenOpen))
+        {
+            query.condition = Some(self.parse_condition(true)?);
+        } else {
+            if self.peek_ident("not") {
+                query.negated = true;
+                self.consume();
+            } else if self.peek_ident("only") {
+                self.consume();
+            }
+            self.skip_whitespace();
+            match self.consume() {
+                CSSToken::Ident(t)
+                    if !["not", "only", "and", "or"].contains(&t.to_ascii_lowercase().as_str()) =>
+                {
+                    query.media_type = MediaType::from_string(&t);
+                }
+                _ => return None,
+            }
+            self.skip_whitespace();
+            if self.peek_ident("and") {
+                self.consume();
+                query.condition = Some(self.parse_condition(false)?);
+            }
+        }
+        self.skip_whitespace();
+        if let CSSToken::EOF = self.peek() {
+            Some(query)
+        } else {
+            None
+        }
+    }
+
+    fn parse_condition(&mut self, allow_or: bool) -> Option<MediaCondition> {
+        self.skip_whitespace();
+        if self.peek_ident("not") {
+            self.consume();
+            return Some(MediaCondition::Not(Box::new(self.parse_in_parens()?)));
+        }
+        let mut conditions = vec![self.parse_in_parens()?];
+        let mut joiner: Option<String> = None;
+        loop {
+            self.skip_whitespace();
+            let word = match self.peek() {
+                CSSToken::Ident(i) if i.eq_ignore_ascii_case("and") => "and",
+                CSSToken::Ident(i) if i.eq_ignore_ascii_case("or") && allow_or => "or",
+                _ => break,
+            };
+            //no mixing and/or without parens
+            if joiner.as_deref().is_some_and(|j| j != word) {
+                return None;
+            }
+            joiner = Some(word.to_string());
+            self.consume();
+            conditions.push(self.parse_in_parens()?);
+        }
+        Some(match joiner.as_deref() {
+            None => conditions.pop().unwrap(),
+            Some("and") => MediaCondition::And(conditions),
+            _ => MediaCondition::Or(conditions),
+        })
+    }
+
+    fn parse_in_parens(&mut self) -> Option<MediaCondition> {
+        self.skip_whitespace();
+        if let CSSToken::ParenOpen = self.consume() {
+        } else {
+            return None;
+        }
+        self.skip_whitespace();
+        if matches!(self.peek(), CSSToken::ParenOpen) || self.peek_ident("not") {
+            let condition = self.parse_condition(true)?;
+            self.skip_whitespace();
+            return match self.consume() {
+                CSSToken::ParenClose => Some(condition),
+                _ => None,
+            };
+        }
+        let mut inner = vec![];
+        let mut depth = 0usize;
+        loop {
+            match self.consume() {
+                CSSToken::ParenClose if depth == 0 => break,
+                CSSToken::EOF => return None,
+                t => {
+                    match t {
+                        CSSToken::ParenOpen => depth += 1,
+                        CSSToken::ParenClose => depth -= 1,
+                        _ => {}
+                    }
+                    inner.push(t);
+                }
+            }
+        }
+        //min- and max- only go on range features. putting them on a discrete one isn't just
+        //unknown, it makes the whole query invalid.
+        if let Some(CSSToken::Ident(name)) =
+            inner.iter().find(|t| !matches!(t, CSSToken::Whitespace))
+        {
+            let name = name.to_ascii_lowercase();
+            let unprefixed = name.strip_prefix("min-").or(name.strip_prefix("max-"));
+            if unprefixed.is_some_and(is_discrete) {
+                return None;
+            }
+        }
+        Some(
+            Self::parse_feature(inner)
+                .map(MediaCondition::Feature)
+                .unwrap_or(MediaCondition::Unknown),
+        )
+    }
+
+    fn parse_feature(tokens: Vec<CSSToken>) -> Option<MediaFeature> {
+        let tokens = tokens
+            .into_iter()
+            .filter(|t| !matches!(t, CSSToken::Whitespace))
+            .collect::<Vec<_>>();
+        match tokens.as_slice() {
+            [CSSToken::Ident(name)] => Some(MediaFeature {
+                name: name.to_ascii_lowercase(),
+                test: MediaFeatureTest::Boolean,
+            }),
+            [CSSToken::Ident(name), CSSToken::Colon, value @ ..] => {
+                let name = name.to_ascii_lowercase();
+                let (mut value, rest) = Self::parse_value(value)?;
+                if !rest.is_empty() {
+                    return None;
+                }
+                if let MediaValue::Ident(ref mut i) = value {
+                    *i = i.to_ascii_lowercase();
+                }
+                let (comparison, name) = if let Some(n) = name.strip_prefix("min-") {
+                    (MediaComparison::GreaterEqual, n.to_string())
+                } else if let Some(n) = name.strip_prefix("max-") {
+                    (MediaComparison::LessEqual, n.to_string())
+                } else {
+                    (MediaComparison::Equal, name)
+                };
+                Some(MediaFeature {
+                    name,
+                    test: MediaFeatureTest::Range(vec![(comparison, value)]),
+                })
+            }
+            _ => Self::parse_range(tokens.as_slice()),
+        }
+    }
+
+    //<mf-name> <op> <value> | <value> <op> <mf-name> | <value> <op> <mf-name> <op> <value>
+    fn parse_range(mut tokens: &[CSSToken]) -> Option<MediaFeature> {
+        let mut atoms: Vec<RangeAtom> = vec![];
+        while !tokens.is_empty() {
+            if let Some((comparison, rest)) = Self::parse_comparison(tokens) {
+                atoms.push(RangeAtom::Comparison(comparison));
+                tokens = rest;
+            } else {
+                let (value, rest) = Self::parse_value(tokens)?;
+                atoms.push(RangeAtom::Value(value));
+                tokens = rest;
+            }
+        }
+        use RangeAtom::*;
+        let (name, constraints) = match atoms.as_slice() {
+            [Value(MediaValue::Ident(name)), Comparison(c), Value(v)] => {
+                (name.clone(), vec![(*c, v.clone())])
+            }
+            [Value(v), Comparison(c), Value(MediaValue::Ident(name))] => {
+                (name.clone(), vec![(c.flip(), v.clone())])
+            }
+            [Value(l), Comparison(lc), Value(MediaValue::Ident(name)), Comparison(rc), Value(r)] => {
+                let both_less = matches!(lc, MediaComparison::Less | MediaComparison::LessEqual)
+                    && matches!(rc, MediaComparison::Less | MediaComparison::LessEqual);
+                let both_greater =
+                    matches!(lc, MediaComparison::Greater | MediaComparison::GreaterEqual)
+                        && matches!(rc, MediaComparison::Greater | MediaComparison::GreaterEqual);
+                if !both_less && !both_greater {
+                    return None;
+                }
+                (name.clone(), vec![(lc.flip(), l.clone()), (*rc, r.clone())])
+            }
+            _ => return None,
+        };
+        Some(MediaFeature {
+            name: name.to_ascii_lowercase(),
+            test: MediaFeatureTest::Range(constraints),
+        })
+    }
+
+    fn parse_comparison(tokens: &[CSSToken]) -> Option<(MediaComparison, &[CSSToken])> {
+        match tokens {
+            [CSSToken::Delim(Char::Char('<')), CSSToken::Delim(Char::Char('=')), rest @ ..] => {
+                Some((MediaComparison::LessEqual, rest))
+            }
+            [CSSToken::Delim(Char::Char('>')), CSSToken::Delim(Char::Char('=')), rest @ ..] => {
+                Some((MediaComparison::GreaterEqual, rest))
+            }
+            [CSSToken::Delim(Char::Char('<')), rest @ ..] => Some((MediaComparison::Less, rest)),
+            [CSSToken::Delim(Char::Char('>')), rest @ ..] => Some((MediaComparison::Greater, rest)),
+            [CSSToken::Delim(Char::Char('=')), rest @ ..] => Some((MediaComparison::Equal, rest)),
+            _ => None,
+        }
+    }
+
+    fn parse_value(tokens: &[CSSToken]) -> Option<(MediaValue, &[CSSToken])> {
+        match tokens {
+            [CSSToken::Number(CSSNumber::Number(l)), CSSToken::Delim(Char::Char('/')), CSSToken::Number(CSSNumber::Number(r)), rest @ ..] => {
+                Some((MediaValue::Ratio(l.unwrap_f64(), r.unwrap_f64()), rest))
+            }
+            [CSSToken::Number(n), rest @ ..] => Some((MediaValue::from_number(n)?, rest)),
+            [CSSToken::Ident(i), rest @ ..] => Some((MediaValue::Ident(i.clone()), rest)),
+            _ => None,
+        }
+    }
+}
+
+//the features that are a choice between keywords rather than a range.
+fn is_discrete(name: &str) -> bool {
+    matches!(
+        name,
+        "orientation"
+            | "prefers-color-scheme"
+            | "prefers-reduced-motion"
+            | "hover"
+            | "any-hover"
+            | "pointer"
+            | "any-pointer"
+            | "update"
+            | "scan"
+            | "grid"
+    )
+}
+
+enum RangeAtom {
+    Comparison(MediaComparison),
+    Value(MediaValue),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::css::CSSTokenizer;
+
+    fn query(input: &str) -> MediaQueryList {
+        let mut tokenizer = CSSTokenizer::default();
+        tokenizer.load_raw(&String::from(input)).unwrap();
+        let mut tokens = Vec::new();
+        tokenizer.tokenize(&mut tokens).unwrap();
+        MediaQueryList::from_tokens(tokens)
+    }
+
+    fn environment(width: usize, height: usize, scale_factor: f64) -> MediaEnvironment {
+        MediaEnvironment::new(Viewport::new(width, height), scale_factor)
+    }
+
+    #[test]
+    fn test_media_types_and_plain_features() {
+        let env = environment(1080, 720, 1.);
+        assert!(query("").matches(&env));
+        assert!(query("screen").matches(&env));
+        assert!(!query("print").matches(&env));
+        assert!(query("not print").matches(&env));
+        assert!(query("only screen and (min-width: 600px)").matches(&env));
+        assert!(!query("screen and (max-width: 600px)").matches(&env));
+        assert!(query("print, (orientation: landscape)").matches(&env));
+        assert!(!query("(orientation: portrait)").matches(&env));
+        assert!(
+            query("(prefers-color-scheme: light) and (prefers-reduced-motion: no-preference)")
+                .matches(&env)
+        );
+        assert!(!query("(prefers-reduced-motion)").matches(&env));
+    }
+
+    #[test]
+    fn test_media_unknown_and_invalid() {
+        let env = environment(1080, 720, 1.);
+        //unknown stays unknown through not, and only and/or with a known answer can settle it.
+        assert!(!query("(shmeep)").matches(&env));
+        assert!(!query("not (shmeep)").matches(&env));
+        assert!(!query("not (shmeep: 1px)").matches(&env));
+        assert!(!query("not ((shmeep) and (width > 10px))").matches(&env));
+        assert!(query("not ((shmeep) and (width < 10px))").matches(&env));
+        assert!(query("(shmeep) or (width > 10px)").matches(&env));
+        assert!(!query("not screen and (shmeep)").matches(&env));
+        //min-/max- on a discrete feature doesn't parse at all, so not doesn't rescue it.
+        assert!(!query("(min-orientation: portrait)").matches(&env));
+        assert!(!query("not (max-hover: none)").matches(&env));
+        assert!(query("(min-orientation: portrait), screen").matches(&env));
+        assert!(query("(min-shmeep: 1px)").queries[0].condition.is_some());
+    }
+
+    #[test]
+    fn test_media_range_syntax() {
+        let env = environment(1080, 720, 1.);
+        assert!(query("(width > 600px)").matches(&env));
+        assert!(query("(600px < width)").matches(&env));
+        assert!(query("(400px <= width <= 1200px)").matches(&env));
+        assert!(!query("(400px <= width <= 700px)").matches(&env));
+        assert!(query("(aspect-ratio >= 4/3)").matches(&env));
+        assert!(query("not ((width < 600px) or (height < 600px))").matches(&env));
+    }
+
+    #[test]
+    fn test_media_scale_factor() {
+        let env = environment(1600, 1200, 2.);
+        assert!(query("(max-width: 800px)").matches(&env));
+        assert!(query("(min-resolution: 2dppx)").matches(&env));
+        assert!(query("(resolution >= 192dpi)").matches(&env));
+        assert!(!query("(min-resolution: 3x)").matches(&env));
+    }
+}
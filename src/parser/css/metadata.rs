@@ -0,0 +1,825 @@
+use std::{cell::OnceCell, fmt};
+
+use super::{
+    calc::LengthContext,
+    properties::{
+        BorderStyle, BorderWidth, BoxShadow, BoxSizing, Clear, Colour, ContainerName,
+        ContainerType, CornerRadius, Dimensionality, Display, DisplayInside, DisplayOutside, Float,
+        FontFamily, FontSize, FontStyle, FontWeight, Isolation, MaxSize, Opacity, OutlineStyle,
+        Overflow, Position, Property, TextAlign, Transform, VerticalAlign, ZIndex,
+    },
+    CSSNumber, CSSProps, CSSToken, CSSValue, Component, DeclarationKind, Numeric, Unit,
+};
+use crate::{layout::text::font_set::FontSet, parser::Char};
+
+//everything the cascader needs to know about a property without matching on it. adding a
+//property means a DeclarationKind variant, a CSSProps field and a line in PROPERTIES.
+pub struct PropertyInfo {
+    pub name: &'static str,
+    pub inherited: bool,
+    pub parse: fn(Vec<Component>) -> Option<DeclarationKind>, //None means the value's invalid
+    pub apply: fn(&mut CSSProps, DeclarationKind),
+    pub inherit: fn(&mut CSSProps, &CSSProps),
+    pub initial: fn(&mut CSSProps),
+    pub compute: fn(&mut CSSProps, &ComputeContext),
+    pub serialize_computed: fn(&CSSProps) -> String,
+    pub serialize_declared: fn(&DeclarationKind) -> Option<String>,
+}
+
+impl PropertyInfo {
+    pub fn find(name: &str) -> Option<&'static PropertyInfo> {
+        PROPERTIES.iter().find(|p| p.name == name)
+    }
+}
+
+//a property that's just a way of setting a few others at once. the declaration gets split up
+//into one for each longhand as soon as it's parsed, so nothing else ever sees it.
+pub struct ShorthandInfo {
+    pub name: &'static str,
+    pub longhands: &'static [&'static str],
+    //a value for each longhand, in the same order. None means the value's invalid
+    pub expand: fn(&[Component]) -> Option<Vec<Vec<Component>>>,
+}
+
+impl ShorthandInfo {
+    pub fn find(name: &str) -> Option<&'static ShorthandInfo> {
+        SHORTHANDS.iter().find(|s| s.name == name)
+    }
+}
+
+pub const SHORTHANDS: &[ShorthandInfo] = &[
+    ShorthandInfo {
+        name: "border-width",
+        longhands: &[
+            "border-top-width",
+            "border-right-width",
+            "border-bottom-width",
+            "border-left-width",
+        ],
+        expand: expand_sides,
+    },
+    ShorthandInfo {
+        name: "border-style",
+        longhands: &[
+            "border-top-style",
+            "border-right-style",
+            "border-bottom-style",
+            "border-left-style",
+        ],
+        expand: expand_sides,
+    },
+    ShorthandInfo {
+        name: "border-color",
+        longhands: &[
+            "border-top-color",
+            "border-right-color",
+            "border-bottom-color",
+            "border-left-color",
+        ],
+        expand: expand_sides,
+    },
+    ShorthandInfo {
+        name: "border-top",
+        longhands: &["border-top-width", "border-top-style", "border-top-color"],
+        expand: expand_line::<BorderStyle>,
+    },
+    ShorthandInfo {
+        name: "border-right",
+        longhands: &[
+            "border-right-width",
+            "border-right-style",
+            "border-right-color",
+        ],
+        expand: expand_line::<BorderStyle>,
+    },
+    ShorthandInfo {
+        name: "border-bottom",
+        longhands: &[
+            "border-bottom-width",
+            "border-bottom-style",
+            "border-bottom-color",
+        ],
+        expand: expand_line::<BorderStyle>,
+    },
+    ShorthandInfo {
+        name: "border-left",
+        longhands: &[
+            "border-left-width",
+            "border-left-style",
+            "border-left-color",
+        ],
+        expand: expand_line::<BorderStyle>,
+    },
+    ShorthandInfo {
+        name: "border",
+        longhands: &[
+            "border-top-width",
+            "border-right-width",
+            "border-bottom-width",
+            "border-left-width",
+            "border-top-style",
+            "border-right-style",
+            "border-bottom-style",
+            "border-left-style",
+            "border-top-color",
+            "border-right-color",
+            "border-bottom-color",
+            "border-left-color",
+        ],
+        expand: |components| {
+            let values = expand_line::<BorderStyle>(components)?;
+            Some(
+                values
+                    .into_iter()
+                    .flat_map(|v| [v.clone(), v.clone(), v.clone(), v])
+                    .collect(),
+            )
+        },
+    },
+    ShorthandInfo {
+        name: "border-radius",
+        longhands: &[
+            "border-top-left-radius",
+            "border-top-right-radius",
+            "border-bottom-right-radius",
+            "border-bottom-left-radius",
+        ],
+        expand: expand_radii,
+    },
+    ShorthandInfo {
+        name: "outline",
+        longhands: &["outline-width", "outline-style", "outline-color"],
+        expand: expand_line::<OutlineStyle>,
+    },
+    ShorthandInfo {
+        name: "inset",
+        longhands: &["top", "right", "bottom", "left"],
+        expand: expand_sides,
+    },
+];
+
+//top, right, bottom, left, with anything left off copied from the side across from it.
+fn expand_sides(components: &[Component]) -> Option<Vec<Vec<Component>>> {
+    let values = Component::split_values(components);
+    let [top, right, bottom, left] = match values.as_slice() {
+        [all] => [all, all, all, all],
+        [vertical, horizontal] => [vertical, horizontal, vertical, horizontal],
+        [top, horizontal, bottom] => [top, horizontal, bottom, horizontal],
+        [top, right, bottom, left] => [top, right, bottom, left],
+        _ => return None,
+    };
+    Some(vec![
+        top.clone(),
+        right.clone(),
+        bottom.clone(),
+        left.clone(),
+    ])
+}
+
+//the horizontal radii, then a slash and the vertical ones if they're any different, each going
+//round the corners like expand_sides() does the sides.
+fn expand_radii(components: &[Component]) -> Option<Vec<Vec<Component>>> {
+    let mut halves =
+        components.split(|c| matches!(c, Component::Token(CSSToken::Delim(Char::Char('/')))));
+    let horizontal = expand_sides(halves.next()?)?;
+    let vertical = match halves.next() {
+        Some(vertical) => expand_sides(vertical)?,
+        None => horizontal.clone(),
+    };
+    if halves.next().is_some() {
+        return None;
+    }
+    let whitespace = Component::Token(CSSToken::Whitespace);
+    Some(
+        horizontal
+            .into_iter()
+            .zip(vertical)
+            .map(|(h, v)| [h, vec![whitespace.clone()], v].concat())
+            .collect(),
+    )
+}
+
+//a width, a style and a colour, in any order, with whatever's missing going back to initial.
+fn expand_line<Style: Property + Default + Clone>(
+    components: &[Component],
+) -> Option<Vec<Vec<Component>>> {
+    let mut out: [Option<Vec<Component>>; 3] = Default::default();
+    for value in Component::split_values(components) {
+        let slot = if let CSSValue::Value(_) = BorderWidth::from_components(value.clone()) {
+            0
+        } else if let CSSValue::Value(_) = Style::from_components(value.clone()) {
+            1
+        } else if let CSSValue::Value(_) = Colour::from_components(value.clone()) {
+            2
+        } else {
+            return None;
+        };
+        if out[slot].replace(value).is_some() {
+            return None;
+        }
+    }
+    let initial = || vec![Component::Token(CSSToken::Ident(String::from("initial")))];
+    Some(out.into_iter().map(|v| v.unwrap_or_else(initial)).collect())
+}
+
+//these mean the same thing for every property, so they never make it to from_components.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CSSWideKeyword {
+    Inherit,
+    Initial,
+    Unset,
+    Revert,
+    RevertLayer,
+}
+
+impl fmt::Display for CSSWideKeyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Inherit => "inherit",
+            Self::Initial => "initial",
+            Self::Unset => "unset",
+            Self::Revert => "revert",
+            Self::RevertLayer => "revert-layer",
+        })
+    }
+}
+
+impl CSSWideKeyword {
+    pub fn parse(components: &[Component]) -> Option<Self> {
+        let [Component::Token(CSSToken::Ident(i))] = components else {
+            return None;
+        };
+        match i.to_ascii_lowercase().as_str() {
+            "inherit" => Some(Self::Inherit),
+            "initial" => Some(Self::Initial),
+            "unset" => Some(Self::Unset),
+            "revert" => Some(Self::Revert),
+            "revert-layer" => Some(Self::RevertLayer),
+            _ => None,
+        }
+    }
+}
+
+pub struct ComputeContext<'a> {
+    pub parent: &'a CSSProps,
+    pub fonts: &'a FontSet,
+    pub root_font_size: f64,
+    pub viewport: (f64, f64),
+    lengths: OnceCell<LengthContext>,
+}
+
+impl<'a> ComputeContext<'a> {
+    pub fn new(
+        parent: &'a CSSProps,
+        fonts: &'a FontSet,
+        root_font_size: f64,
+        viewport: (f64, f64),
+    ) -> Self {
+        Self {
+            parent,
+            fonts,
+            root_font_size,
+            viewport,
+            lengths: OnceCell::new(),
+        }
+    }
+
+    pub fn lengths_for(&self, props: &CSSProps) -> LengthContext {
+        let font_size = match &props.font_size {
+            CSSValue::Value(size) => size.px(),
+            _ => self.root_font_size,
+        };
+        let font = match &props.font_family {
+            CSSValue::Value(FontFamily::Resolved(_, chain)) => Some(chain.primary_font()),
+            _ => None,
+        };
+        LengthContext::new(
+            font_size,
+            self.root_font_size,
+            font,
+            self.viewport.0,
+            self.viewport.1,
+        )
+    }
+
+    //the element's own, which only gets worked out once. the font properties are at the top of
+    //PROPERTIES so they're all computed by the time anything asks for this.
+    pub fn lengths(&self, props: &CSSProps) -> &LengthContext {
+        self.lengths.get_or_init(|| self.lengths_for(props))
+    }
+}
+
+macro_rules! properties {
+    ($($name:literal => $variant:ident($field:ident: $ty:ty), inherited: $inherited:literal,
+        initial: $initial:expr, compute: $compute:expr;)*) => {
+        pub const PROPERTIES: &[PropertyInfo] = &[$(
+            PropertyInfo {
+                name: $name,
+                inherited: $inherited,
+                parse: |components| match <$ty>::from_components(components) {
+                    CSSValue::Value(v) => Some(DeclarationKind::$variant(CSSValue::Value(v))),
+                    _ => None,
+                },
+                apply: |props, kind| {
+                    if let DeclarationKind::$variant(v) = kind {
+                        props.$field = v;
+                    }
+                },
+                inherit: |props, parent| props.$field = parent.$field.clone(),
+                initial: |props| props.$field = CSSValue::Value($initial),
+                compute: $compute,
+                serialize_computed: |props| props.$field.to_string(),
+                serialize_declared: |kind| match kind {
+                    DeclarationKind::$variant(v) => Some(v.to_string()),
+                    _ => None,
+                },
+            },
+        )*];
+    };
+}
+
+//computed in this order, so anything that depends on another property goes after it.
+properties! {
+    "color" => Color(color: Colour), inherited: true, initial: Colour::BLACK,
+        compute: |props, context| {
+            //currentColor on color itself means the parent's colour.
+            if let CSSValue::Value(Colour { current: true, .. }) = props.color {
+                props.color = context.parent.color.clone();
+            }
+        };
+    "font-weight" => FontWeight(font_weight: FontWeight), inherited: true,
+        initial: FontWeight::Absolute(400.),
+        compute: |props, context| {
+            let parent = match context.parent.font_weight {
+                CSSValue::Value(FontWeight::Absolute(w)) => w,
+                _ => 400.,
+            };
+            if let CSSValue::Value(ref mut weight) = props.font_weight {
+                *weight = weight.compute(parent);
+            }
+        };
+    "font-style" => FontStyle(font_style: FontStyle), inherited: true,
+        initial: FontStyle::Normal, compute: |_, _| {};
+    "font-family" => FontFamily(font_family: FontFamily), inherited: true,
+        initial: FontFamily::default(),
+        compute: |props, context| {
+            //the weight and style pick which face we get.
+            let weight = |props: &CSSProps| match props.font_weight {
+                CSSValue::Value(FontWeight::Absolute(w)) => w,
+                _ => 400.,
+            };
+            let style = |props: &CSSProps| match props.font_style {
+                CSSValue::Value(style) => style,
+                _ => FontStyle::Normal,
+            };
+            //finding a face isn't cheap, so the same names at the same weight and style as the
+            //parent just get the parent's.
+            if let (
+                CSSValue::Value(ref family),
+                CSSValue::Value(ref parent @ FontFamily::Resolved(..)),
+            ) = (&props.font_family, &context.parent.font_family)
+            {
+                if family.names() == parent.names()
+                    && weight(props) == weight(context.parent)
+                    && style(props) == style(context.parent)
+                {
+                    props.font_family = CSSValue::Value(parent.clone());
+                    return;
+                }
+            }
+            let (weight, style) = (weight(props), style(props));
+            if let CSSValue::Value(ref mut family) = props.font_family {
+                family.resolve(context.fonts, weight, style);
+            }
+        };
+    "font-size" => FontSize(font_size: FontSize), inherited: true,
+        initial: FontSize::new(CSSNumber::Unit(Numeric::Number(16.), Unit::Px)),
+        compute: |props, context| {
+            //ems and percentages here are the parent's.
+            let parent = context.lengths_for(context.parent);
+            if let CSSValue::Value(ref mut size) = props.font_size {
+                *size = size.compute(&parent);
+            }
+        };
+    "display" => Display(display: Display), inherited: false,
+        initial: Display::new(DisplayOutside::Inline, DisplayInside::Flow),
+        compute: |props, _| {
+            //absolutely positioned and floated boxes are always blocks, whatever they were going
+            //to be.
+            let positioned = matches!(
+                props.position,
+                CSSValue::Value(Position::Absolute | Position::Fixed)
+            );
+            let floated = matches!(props.float, CSSValue::Value(Float::Left | Float::Right));
+            if positioned || floated {
+                if let CSSValue::Value(ref mut display) = props.display {
+                    *display = display.blockify();
+                }
+            }
+        };
+    "text-align" => TextAlign(text_align: TextAlign), inherited: true,
+        initial: TextAlign::Left, compute: |_, _| {};
+    "background-color" => BackgroundColor(background_color: Colour), inherited: false,
+        initial: Colour::new(0, 0, 0, 0),
+        compute: |props, _| {
+            if let CSSValue::Value(Colour { current: true, .. }) = props.background_color {
+                props.background_color = props.color.clone();
+            }
+        };
+    "width" => Width(width: Dimensionality), inherited: false, initial: Dimensionality::Auto,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.width);
+    "height" => Height(height: Dimensionality), inherited: false, initial: Dimensionality::Auto,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.height);
+    "min-width" => MinWidth(min_width: Dimensionality), inherited: false,
+        initial: Dimensionality::Auto,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.min_width);
+    "min-height" => MinHeight(min_height: Dimensionality), inherited: false,
+        initial: Dimensionality::Auto,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.min_height);
+    "max-width" => MaxWidth(max_width: MaxSize), inherited: false, initial: MaxSize::NONE,
+        compute: |props, context| {
+            let lengths = context.lengths(props);
+            if let CSSValue::Value(ref mut max) = props.max_width {
+                *max = max.compute(lengths);
+            }
+        };
+    "max-height" => MaxHeight(max_height: MaxSize), inherited: false, initial: MaxSize::NONE,
+        compute: |props, context| {
+            let lengths = context.lengths(props);
+            if let CSSValue::Value(ref mut max) = props.max_height {
+                *max = max.compute(lengths);
+            }
+        };
+    "box-sizing" => BoxSizing(box_sizing: BoxSizing), inherited: false,
+        initial: BoxSizing::ContentBox, compute: |_, _| {};
+    "position" => Position(position: Position), inherited: false, initial: Position::Static,
+        compute: |_, _| {};
+    "float" => Float(float: Float), inherited: false, initial: Float::None,
+        compute: |props, _| {
+            //absolutely positioned boxes can't float.
+            if let CSSValue::Value(Position::Absolute | Position::Fixed) = props.position {
+                props.float = CSSValue::Value(Float::None);
+            }
+        };
+    "clear" => Clear(clear: Clear), inherited: false, initial: Clear::None, compute: |_, _| {};
+    "overflow" => Overflow(overflow: Overflow), inherited: false, initial: Overflow::Visible,
+        compute: |_, _| {};
+    "top" => Top(top: Dimensionality), inherited: false, initial: Dimensionality::Auto,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.top);
+    "right" => Right(right: Dimensionality), inherited: false, initial: Dimensionality::Auto,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.right);
+    "bottom" => Bottom(bottom: Dimensionality), inherited: false, initial: Dimensionality::Auto,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.bottom);
+    "left" => Left(left: Dimensionality), inherited: false, initial: Dimensionality::Auto,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.left);
+    "z-index" => ZIndex(z_index: ZIndex), inherited: false, initial: ZIndex::Auto,
+        compute: |_, _| {};
+    "opacity" => Opacity(opacity: Opacity), inherited: false, initial: Opacity::default(),
+        compute: |_, _| {};
+    "isolation" => Isolation(isolation: Isolation), inherited: false, initial: Isolation::Auto,
+        compute: |_, _| {};
+    "transform" => Transform(transform: Transform), inherited: false,
+        initial: Transform::default(),
+        compute: |props, context| {
+            let lengths = context.lengths(props);
+            if let CSSValue::Value(ref mut transform) = props.transform {
+                *transform = transform.compute(lengths);
+            }
+        };
+    "padding-top" => PaddingTop(padding_top: Dimensionality), inherited: false, initial: ZERO,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.padding_top);
+    "padding-bottom" => PaddingBottom(padding_bottom: Dimensionality), inherited: false,
+        initial: ZERO,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.padding_bottom);
+    "padding-left" => PaddingLeft(padding_left: Dimensionality), inherited: false, initial: ZERO,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.padding_left);
+    "padding-right" => PaddingRight(padding_right: Dimensionality), inherited: false,
+        initial: ZERO,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.padding_right);
+    "margin-top" => MarginTop(margin_top: Dimensionality), inherited: false, initial: ZERO,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.margin_top);
+    "margin-bottom" => MarginBottom(margin_bottom: Dimensionality), inherited: false,
+        initial: ZERO,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.margin_bottom);
+    "margin-left" => MarginLeft(margin_left: Dimensionality), inherited: false, initial: ZERO,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.margin_left);
+    "margin-right" => MarginRight(margin_right: Dimensionality), inherited: false, initial: ZERO,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.margin_right);
+    "border-top-style" => BorderTopStyle(border_top_style: BorderStyle), inherited: false,
+        initial: BorderStyle::None, compute: |_, _| {};
+    "border-right-style" => BorderRightStyle(border_right_style: BorderStyle), inherited: false,
+        initial: BorderStyle::None, compute: |_, _| {};
+    "border-bottom-style" => BorderBottomStyle(border_bottom_style: BorderStyle), inherited: false,
+        initial: BorderStyle::None, compute: |_, _| {};
+    "border-left-style" => BorderLeftStyle(border_left_style: BorderStyle), inherited: false,
+        initial: BorderStyle::None, compute: |_, _| {};
+    "border-top-width" => BorderTopWidth(border_top_width: BorderWidth), inherited: false,
+        initial: BorderWidth::MEDIUM,
+        compute: |props, context| compute_border_width(
+            context.lengths(props),
+            props.border_top_style.unwrap(),
+            &mut props.border_top_width,
+        );
+    "border-right-width" => BorderRightWidth(border_right_width: BorderWidth), inherited: false,
+        initial: BorderWidth::MEDIUM,
+        compute: |props, context| compute_border_width(
+            context.lengths(props),
+            props.border_right_style.unwrap(),
+            &mut props.border_right_width,
+        );
+    "border-bottom-width" => BorderBottomWidth(border_bottom_width: BorderWidth), inherited: false,
+        initial: BorderWidth::MEDIUM,
+        compute: |props, context| compute_border_width(
+            context.lengths(props),
+            props.border_bottom_style.unwrap(),
+            &mut props.border_bottom_width,
+        );
+    "border-left-width" => BorderLeftWidth(border_left_width: BorderWidth), inherited: false,
+        initial: BorderWidth::MEDIUM,
+        compute: |props, context| compute_border_width(
+            context.lengths(props),
+            props.border_left_style.unwrap(),
+            &mut props.border_left_width,
+        );
+    "border-top-color" => BorderTopColor(border_top_color: Colour), inherited: false,
+        initial: Colour::CURRENT,
+        compute: |props, _| compute_colour(&props.color, &mut props.border_top_color);
+    "border-right-color" => BorderRightColor(border_right_color: Colour), inherited: false,
+        initial: Colour::CURRENT,
+        compute: |props, _| compute_colour(&props.color, &mut props.border_right_color);
+    "border-bottom-color" => BorderBottomColor(border_bottom_color: Colour), inherited: false,
+        initial: Colour::CURRENT,
+        compute: |props, _| compute_colour(&props.color, &mut props.border_bottom_color);
+    "border-left-color" => BorderLeftColor(border_left_color: Colour), inherited: false,
+        initial: Colour::CURRENT,
+        compute: |props, _| compute_colour(&props.color, &mut props.border_left_color);
+    "border-top-left-radius" => BorderTopLeftRadius(border_top_left_radius: CornerRadius),
+        inherited: false,
+        initial: CornerRadius::new(ZERO, ZERO),
+        compute: |props, context| {
+            let lengths = context.lengths(props);
+            if let CSSValue::Value(ref mut radius) = props.border_top_left_radius {
+                *radius = radius.compute(lengths);
+            }
+        };
+    "border-top-right-radius" => BorderTopRightRadius(border_top_right_radius: CornerRadius),
+        inherited: false,
+        initial: CornerRadius::new(ZERO, ZERO),
+        compute: |props, context| {
+            let lengths = context.lengths(props);
+            if let CSSValue::Value(ref mut radius) = props.border_top_right_radius {
+                *radius = radius.compute(lengths);
+            }
+        };
+    "border-bottom-right-radius" => BorderBottomRightRadius(border_bottom_right_radius: CornerRadius),
+        inherited: false,
+        initial: CornerRadius::new(ZERO, ZERO),
+        compute: |props, context| {
+            let lengths = context.lengths(props);
+            if let CSSValue::Value(ref mut radius) = props.border_bottom_right_radius {
+                *radius = radius.compute(lengths);
+            }
+        };
+    "border-bottom-left-radius" => BorderBottomLeftRadius(border_bottom_left_radius: CornerRadius),
+        inherited: false,
+        initial: CornerRadius::new(ZERO, ZERO),
+        compute: |props, context| {
+            let lengths = context.lengths(props);
+            if let CSSValue::Value(ref mut radius) = props.border_bottom_left_radius {
+                *radius = radius.compute(lengths);
+            }
+        };
+    "box-shadow" => BoxShadow(box_shadow: BoxShadow), inherited: false,
+        initial: BoxShadow::default(),
+        compute: |props, context| {
+            let (lengths, color) = (context.lengths(props), props.color.unwrap());
+            if let CSSValue::Value(ref mut shadow) = props.box_shadow {
+                *shadow = shadow.compute(lengths, &color);
+            }
+        };
+    "outline-style" => OutlineStyle(outline_style: OutlineStyle), inherited: false,
+        initial: OutlineStyle::default(), compute: |_, _| {};
+    "outline-width" => OutlineWidth(outline_width: BorderWidth), inherited: false,
+        initial: BorderWidth::MEDIUM,
+        compute: |props, context| compute_border_width(
+            context.lengths(props),
+            props.outline_style.unwrap().border_style(),
+            &mut props.outline_width,
+        );
+    "outline-color" => OutlineColor(outline_color: Colour), inherited: false,
+        initial: Colour::CURRENT,
+        compute: |props, _| compute_colour(&props.color, &mut props.outline_color);
+    "outline-offset" => OutlineOffset(outline_offset: Dimensionality), inherited: false,
+        initial: ZERO,
+        compute: |props, context| compute_length(context.lengths(props), &mut props.outline_offset);
+    "vertical-align" => VerticalAlign(vertical_align: VerticalAlign), inherited: false,
+        initial: VerticalAlign::Baseline,
+        compute: |props, context| {
+            let lengths = context.lengths(props);
+            if let CSSValue::Value(VerticalAlign::Length(ref mut length)) = props.vertical_align {
+                *length = length.compute(lengths);
+            }
+        };
+    "container-type" => ContainerType(container_type: ContainerType), inherited: false,
+        initial: ContainerType::Normal, compute: |_, _| {};
+    "container-name" => ContainerName(container_name: ContainerName), inherited: false,
+        initial: ContainerName::default(), compute: |_, _| {};
+}
+
+fn compute_border_width(
+    lengths: &LengthContext,
+    style: BorderStyle,
+    value: &mut CSSValue<BorderWidth>,
+) {
+    if let CSSValue::Value(ref mut width) = value {
+        *width = width.compute(lengths, style);
+    }
+}
+
+//currentColor is whatever color ended up as.
+fn compute_colour(color: &CSSValue<Colour>, value: &mut CSSValue<Colour>) {
+    if let CSSValue::Value(Colour { current: true, .. }) = value {
+        *value = color.clone();
+    }
+}
+
+fn compute_length(lengths: &LengthContext, value: &mut CSSValue<Dimensionality>) {
+    if let CSSValue::Value(ref mut d) = value {
+        *d = d.compute(lengths);
+    }
+}
+
+const ZERO: Dimensionality = Dimensionality::new(CSSNumber::Unit(Numeric::Integer(0), Unit::Px));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::css::{Block, CSSParser};
+
+    #[test]
+    fn test_wide_keywords_and_initials() {
+        let rule = CSSParser::parse_inline_style(&String::from(
+            "color: inherit; width: REVERT-layer; height: banana; margin-top: unset",
+        ))
+        .unwrap();
+        let Block::Declarations(declarations) = rule.value else {
+            panic!()
+        };
+        assert!(matches!(
+            declarations["color"].kind,
+            DeclarationKind::WideKeyword(CSSWideKeyword::Inherit)
+        ));
+        assert!(matches!(
+            declarations["width"].kind,
+            DeclarationKind::WideKeyword(CSSWideKeyword::RevertLayer)
+        ));
+        assert!(matches!(
+            declarations["margin-top"].kind,
+            DeclarationKind::WideKeyword(CSSWideKeyword::Unset)
+        ));
+        //bad values take the declaration down with them.
+        assert!(!declarations.contains_key("height"));
+
+        let mut props = CSSProps::default();
+        for property in PROPERTIES {
+            (property.initial)(&mut props);
+        }
+        assert!(matches!(props.width, CSSValue::Value(Dimensionality::Auto)));
+        assert_eq!(props.background_color.unwrap().real.alpha, 0);
+        assert_eq!(props.font_size.unwrap().px(), 16.);
+        assert!(PropertyInfo::find("color").unwrap().inherited);
+        assert!(!PropertyInfo::find("margin-top").unwrap().inherited);
+    }
+
+    #[test]
+    fn test_border_shorthands() {
+        let rule = CSSParser::parse_inline_style(&String::from(
+            "border: thick dashed red; border-width: 1px 2px 3px; border-left: none; \
+             border-bottom: rgb(0 0 255) var(--style)",
+        ))
+        .unwrap();
+        let Block::Declarations(declarations) = rule.value else {
+            panic!()
+        };
+        let value = |name: &str| declarations[name].value_text();
+        assert_eq!(value("border-top-width"), "1px");
+        assert_eq!(value("border-right-width"), "2px");
+        assert_eq!(value("border-left-width"), "initial");
+        assert_eq!(value("border-top-style"), "dashed");
+        assert_eq!(value("border-left-style"), "none");
+        assert_eq!(value("border-right-color"), "rgb(255, 0, 0)");
+        //the var() isn't worked out until the cascade, so every bit of it has to wait.
+        assert!(matches!(
+            declarations["border-bottom-width"].kind,
+            DeclarationKind::WithVariables(ref name, _) if name == "border-bottom"
+        ));
+        //one value too many, or two of the same kind, and the whole thing's out.
+        for bad in [
+            "border-width: 1px 2px 3px 4px 5px",
+            "border: red blue",
+            "border: 5%",
+        ] {
+            let rule = CSSParser::parse_inline_style(&String::from(bad)).unwrap();
+            let Block::Declarations(declarations) = rule.value else {
+                panic!()
+            };
+            assert!(declarations.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_radii_shadows_and_outlines() {
+        let rule = CSSParser::parse_inline_style(&String::from(
+            "border-radius: 1px 2px 3px / 10% 20%; outline: auto 2px; \
+             box-shadow: 1px 2px red, inset 3px 4px 5px -6px",
+        ))
+        .unwrap();
+        let Block::Declarations(declarations) = rule.value else {
+            panic!()
+        };
+        let value = |name: &str| declarations[name].value_text();
+        assert_eq!(value("border-top-left-radius"), "1px 10%");
+        assert_eq!(value("border-top-right-radius"), "2px 20%");
+        assert_eq!(value("border-bottom-right-radius"), "3px 10%");
+        assert_eq!(value("border-bottom-left-radius"), "2px 20%");
+        assert_eq!(value("outline-style"), "auto");
+        assert_eq!(value("outline-width"), "2px");
+        assert_eq!(value("outline-color"), "initial");
+        let DeclarationKind::BoxShadow(CSSValue::Value(ref shadow)) =
+            declarations["box-shadow"].kind
+        else {
+            panic!()
+        };
+        assert!(!shadow.shadows[0].inset && shadow.shadows[0].colour.real.red == 255);
+        assert!(shadow.shadows[1].inset && shadow.shadows[1].colour.current);
+        for bad in [
+            "box-shadow: 1px",
+            "box-shadow: 1px 2px -3px",
+            "box-shadow: 1px red 2px",
+            "border-radius: -1px",
+            "outline-style: hidden",
+        ] {
+            let rule = CSSParser::parse_inline_style(&String::from(bad)).unwrap();
+            let Block::Declarations(declarations) = rule.value else {
+                panic!()
+            };
+            assert!(declarations.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_stacking_properties() {
+        let rule = CSSParser::parse_inline_style(&String::from(
+            "z-index: -3; opacity: 150%; isolation: isolate; \
+             transform: translate(10px, 50%) rotate(90deg) scale(2)",
+        ))
+        .unwrap();
+        let Block::Declarations(declarations) = rule.value else {
+            panic!()
+        };
+        let value = |name: &str| declarations[name].value_text();
+        assert_eq!(value("z-index"), "-3");
+        assert_eq!(value("opacity"), "1");
+        assert_eq!(value("isolation"), "isolate");
+        let DeclarationKind::Transform(CSSValue::Value(ref transform)) =
+            declarations["transform"].kind
+        else {
+            panic!()
+        };
+        //the last one goes first, so (1, 0) gets scaled, turned and then moved.
+        let [a, b, c, d, e, f] = transform.matrix(100., 40.);
+        let close = |x: f64, y: f64| (x - y).abs() < 1e-9;
+        assert!(close(a, 0.) && close(b, 2.) && close(c, -2.) && close(d, 0.));
+        assert!(close(e, 10.) && close(f, 20.));
+        for bad in [
+            "z-index: 1.5",
+            "opacity: red",
+            "transform: rotate(10px)",
+            "transform: none scale(2)",
+        ] {
+            let rule = CSSParser::parse_inline_style(&String::from(bad)).unwrap();
+            let Block::Declarations(declarations) = rule.value else {
+                panic!()
+            };
+            assert!(declarations.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_font_style() {
+        let value = |style: &str| {
+            let rule = CSSParser::parse_inline_style(&format!("font-style: {}", style)).unwrap();
+            let Block::Declarations(declarations) = rule.value else {
+                panic!()
+            };
+            declarations.get("font-style").map(|d| d.value_text())
+        };
+        assert_eq!(value("ITALIC").unwrap(), "italic");
+        assert_eq!(value("oblique").unwrap(), "oblique");
+        assert_eq!(value("oblique 14deg").unwrap(), "oblique");
+        assert_eq!(value("oblique -0.125turn").unwrap(), "oblique -45deg");
+        assert!(value("oblique 91deg").is_none());
+        assert!(value("italic 10deg").is_none());
+        assert!(value("oblique 10px").is_none());
+    }
+}
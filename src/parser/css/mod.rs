@@ -1,20 +1,45 @@
 use std::{
-    cmp::Ordering, collections::HashMap, fs::File, io::Read, mem::Discriminant, num::ParseIntError,
-    path::PathBuf,
+    cmp::Ordering, collections::HashMap, fmt, fs::File, io::Read, num::ParseIntError, path::PathBuf,
 };
 
 use font_types::Tag;
-use reqwest::Url;
+use reqwest::{Client, Url};
 use thiserror::Error;
 
-use self::properties::{
-    Colour, Dimensionality, Display, FontFamily, FontSize, FontWeight, Property, TextAlign,
+use self::{
+    container::ContainerCondition,
+    font_face::FontFace,
+    import::ImportRule,
+    layer::{parse_layer_names, LayerName},
+    media::MediaQueryList,
+    metadata::{CSSWideKeyword, PropertyInfo, ShorthandInfo},
+    properties::{
+        BorderStyle, BorderWidth, BoxShadow, BoxSizing, Clear, Colour, ContainerName,
+        ContainerType, CornerRadius, Dimensionality, Display, Float, FontFamily, FontSize,
+        FontStyle, FontWeight, Isolation, MaxSize, Opacity, OutlineStyle, Overflow, Position,
+        Property, TextAlign, Transform, VerticalAlign, ZIndex,
+    },
+    rule_map::ElementKeys,
+    supports::SupportsCondition,
+    variables::contains_var,
 };
 use super::Char;
-use crate::util::approx_eq;
+use crate::{context::fetch::fetch, util::approx_eq};
 
+pub mod calc;
 pub mod cascader;
+pub mod colour;
+pub mod computed;
+pub mod container;
+pub mod font_face;
+pub mod import;
+pub mod layer;
+pub mod media;
+pub mod metadata;
 pub mod properties;
+pub mod rule_map;
+pub mod supports;
+pub mod variables;
 
 #[derive(Debug, Default)]
 pub struct CSSParser {
@@ -46,6 +71,32 @@ impl CSSParser {
         self.sources.extend(sources);
     }
 
+    //parsing itself isn't async, so anything behind a url has to be pulled down beforehand, files
+    //included. sheets that fail to load are dropped, same as a browser would.
+    pub async fn fetch_sources(&mut self, client: &Client) {
+        let mut fetched = Vec::with_capacity(self.sources.len());
+        for source in self.sources.drain(..) {
+            let (source, media) = source.split_media();
+            let source = match source {
+                CSSSource::URL(url) => match fetch(client, &url).await {
+                    Ok(bytes) => {
+                        CSSSource::Fetched(url, String::from_utf8_lossy(&bytes).into_owned())
+                    }
+                    Err(e) => {
+                        tracing::warn!("couldn't fetch stylesheet {}: {}", url, e);
+                        continue;
+                    }
+                },
+                a => a,
+            };
+            fetched.push(match media {
+                Some(media) => CSSSource::Media(Box::new(source), media),
+                None => source,
+            });
+        }
+        self.sources = fetched;
+    }
+
     fn reconsume(&mut self) {
         self.tokens_idx = self.tokens_idx.saturating_sub(1);
     }
@@ -66,15 +117,31 @@ impl CSSParser {
         for source in self.sources.to_vec() {
             self.tokens.clear();
             self.tokens_idx = 0;
+            let mut url = None;
+            let (source, media) = source.split_media();
             if let CSSSource::Local(file) = source {
                 self.tokenizer.load_from_file(&file)?;
+                url = Url::from_file_path(file).ok();
             } else if let CSSSource::Raw(css) = source {
                 self.tokenizer.load_raw(&css)?;
-            } else if let CSSSource::URL(url) = source {
-                self.tokenizer.load_from_url(&url)?;
+            } else if let CSSSource::URL(u) = source {
+                self.tokenizer.load_from_url(&u)?;
+                url = Some(u);
+            } else if let CSSSource::Fetched(u, css) = source {
+                self.tokenizer.load_raw(&css)?;
+                url = Some(u);
             }
             self.tokenizer.tokenize(&mut self.tokens)?;
-            styles.push(self.consume_list_of_rules()?);
+            let mut style = self.consume_list_of_rules()?;
+            style.url = url;
+            //the same as an @import with a media list, the whole sheet goes inside an @media.
+            if let Some(media) = media {
+                style.rules = vec![Rule {
+                    prelude: Prelude::Media(media),
+                    value: Block::Rules(style.rules),
+                }];
+            }
+            styles.push(style);
         }
         for style in &mut styles {
             style.let_em_know();
@@ -82,40 +149,119 @@ impl CSSParser {
         Ok(styles)
     }
 
-    pub fn parse_declaration_list(
-        &mut self,
-    ) -> Result<Vec<(Discriminant<DeclarationKind>, Declaration)>, CSSError> {
+    //for style="" attributes. comes back as a single rule at author level so the cascader can
+    //squash it on top of everything the stylesheets said.
+    pub fn parse_inline_style(source: &String) -> Result<Rule, CSSError> {
+        let mut parser = Self::default();
+        parser.tokenizer.load_raw(source)?;
+        parser.tokenizer.tokenize(&mut parser.tokens)?;
+        let mut declarations: HashMap<String, Declaration> = HashMap::default();
+        for (name, mut declaration) in parser.parse_declaration_list()? {
+            declaration.level = StyleLevel::Author;
+            //later ones win, unless the earlier one was !important and this one isn't.
+            if declarations
+                .get(&name)
+                .map_or(true, |old| declaration >= *old)
+            {
+                declarations.insert(name, declaration);
+            }
+        }
+        Ok(Rule {
+            prelude: Prelude::None,
+            value: Block::Declarations(declarations),
+        })
+    }
+
+    //the spec's "parse a rule", for inserting rules at runtime. anything but exactly one rule
+    //that we understand is an error.
+    pub fn parse_rule(source: &String) -> Result<Rule, CSSError> {
+        let mut parser = Self::default();
+        parser.tokenizer.load_raw(source)?;
+        parser.tokenizer.tokenize(&mut parser.tokens)?;
+        while parser.peek() == CSSToken::Whitespace {
+            parser.consume();
+        }
+        let builder = match parser.consume() {
+            CSSToken::EOF => do yeet CSSError::InvalidRule,
+            CSSToken::AtKeyword(name) => parser.consume_at_rule(name)?,
+            _ => {
+                parser.reconsume();
+                match parser.consume_qualified_rule()? {
+                    Some(builder) => builder,
+                    None => do yeet CSSError::InvalidRule,
+                }
+            }
+        };
+        while parser.peek() == CSSToken::Whitespace {
+            parser.consume();
+        }
+        if parser.peek() != CSSToken::EOF {
+            do yeet CSSError::InvalidRule;
+        }
+        let rule = builder.build()?;
+        if let Prelude::None = rule.prelude {
+            do yeet CSSError::InvalidRule;
+        }
+        Ok(rule)
+    }
+
+    //a bad declaration only takes itself down, everything up to the next semicolon gets thrown
+    //out and we keep going.
+    pub fn parse_declaration_list(&mut self) -> Result<Vec<(String, Declaration)>, CSSError> {
         let mut declarations = vec![];
         loop {
             match self.consume() {
                 CSSToken::Whitespace | CSSToken::Semicolon => {}
+                CSSToken::EOF => {
+                    break;
+                }
+                CSSToken::AtKeyword(name) => {
+                    //nothing takes at-rules in declaration lists yet, so they just get eaten.
+                    self.consume_at_rule(name)?;
+                }
                 a @ CSSToken::Ident(_) => {
                     let mut components = vec![Component::Token(a)];
-                    loop {
-                        match self.peek() {
-                            CSSToken::Semicolon | CSSToken::EOF => {
-                                break;
-                            }
-                            _ => {
-                                components.push(self.consume_component_value()?);
+                    while !matches!(self.peek(), CSSToken::Semicolon | CSSToken::EOF) {
+                        components.push(self.consume_component_value()?);
+                    }
+                    match self.consume_declaration(components) {
+                        Ok(built) => {
+                            for declaration in built {
+                                declarations.push((declaration.name.clone(), declaration));
                             }
                         }
+                        Err(e) => tracing::warn!("dropping bad declaration: {}", e),
                     }
-                    let declaration = self.consume_declaration(components)?;
-                    declarations.push((std::mem::discriminant(&declaration.kind), declaration));
-                }
-                CSSToken::EOF => {
-                    break;
                 }
                 a => {
-                    unimplemented!("{:?}", a);
+                    tracing::warn!("dropping declaration starting with {:?}", a);
+                    self.reconsume();
+                    while !matches!(self.peek(), CSSToken::Semicolon | CSSToken::EOF) {
+                        self.consume_component_value()?;
+                    }
                 }
             }
         }
         Ok(declarations)
     }
 
-    fn consume_declaration(&self, components: Vec<Component>) -> Result<Declaration, CSSError> {
+    //the spec's "parse a list of component values", for when something needs a value on its own
+    //instead of as part of a sheet.
+    pub fn parse_component_list(source: &String) -> Result<Vec<Component>, CSSError> {
+        let mut parser = Self::default();
+        parser.tokenizer.load_raw(source)?;
+        parser.tokenizer.tokenize(&mut parser.tokens)?;
+        let mut components = vec![];
+        while parser.peek() != CSSToken::EOF {
+            components.push(parser.consume_component_value()?);
+        }
+        Ok(components)
+    }
+
+    fn consume_declaration(
+        &self,
+        components: Vec<Component>,
+    ) -> Result<Vec<Declaration>, CSSError> {
         let mut builder = DeclarationBuilder::default();
         let mut iter = components.iter().peekable();
         if let Some(Component::Token(CSSToken::Ident(t))) = iter.next() {
@@ -126,50 +272,138 @@ impl CSSParser {
         }
         if let Some(Component::Token(CSSToken::Colon)) = iter.next() {
         } else {
-            do yeet CSSError::EOFReached;
+            do yeet CSSError::InvalidDeclaration;
         }
         while let Some(Component::Token(CSSToken::Whitespace)) = iter.peek() {
             iter.next();
         }
-        if let Some(Component::Token(CSSToken::EOF)) = iter.peek() {
-        } else {
-            builder.push_value(iter.next().unwrap().clone());
+        let mut value = iter
+            .take_while(|c| !matches!(c, Component::Token(CSSToken::EOF)))
+            .cloned()
+            .collect::<Vec<_>>();
+        while let Some(Component::Token(CSSToken::Whitespace)) = value.last() {
+            value.pop();
+        }
+        //`! important` with whitespace in between is still fine, so look from the back.
+        if let Some(Component::Token(CSSToken::Ident(i))) = value.last() {
+            if i.eq_ignore_ascii_case("important") {
+                let bang = value[..value.len() - 1]
+                    .iter()
+                    .rposition(|c| !matches!(c, Component::Token(CSSToken::Whitespace)));
+                if let Some(idx) = bang.filter(|idx| {
+                    matches!(
+                        value[*idx],
+                        Component::Token(CSSToken::Delim(Char::Char('!')))
+                    )
+                }) {
+                    value.truncate(idx);
+                    while let Some(Component::Token(CSSToken::Whitespace)) = value.last() {
+                        value.pop();
+                    }
+                    builder.set_important(true);
+                }
+            }
+        }
+        for component in value {
+            builder.push_value(component);
         }
-        //TODO: Handle !important
 
-        Ok(builder.build()?)
+        Ok(builder.build_all()?)
     }
 
     fn consume_list_of_rules(&mut self) -> Result<Style, CSSError> {
+        Ok(Style {
+            rules: self.consume_rules(false)?,
+            level: StyleLevel::Author,
+            url: None,
+        })
+    }
+
+    //nested is for the insides of at-rules like @media. rules that don't make sense get dropped
+    //without taking the rest of the sheet with them.
+    fn consume_rules(&mut self, nested: bool) -> Result<Vec<Rule>, CSSError> {
         let mut rules = vec![];
+        let mut imports_allowed = !nested;
         loop {
-            match self.consume() {
-                CSSToken::Whitespace => {}
+            let rule = match self.consume() {
+                CSSToken::Whitespace => continue,
                 CSSToken::EOF => {
                     break;
                 }
-                a => {
+                //leftovers from hiding css from ancient browsers in html comments.
+                CSSToken::CDO | CSSToken::CDC if !nested => continue,
+                CSSToken::AtKeyword(name) => self.consume_at_rule(name)?,
+                _ => {
                     self.reconsume();
-                    rules.push(self.consume_qualified_rule()?);
+                    match self.consume_qualified_rule()? {
+                        Some(rule) => rule,
+                        None => break,
+                    }
+                }
+            };
+            let rule = match rule.build() {
+                Ok(rule) => rule,
+                Err(e) => {
+                    tracing::warn!("dropping bad rule: {}", e);
+                    imports_allowed = false;
+                    continue;
+                }
+            };
+            match rule.prelude {
+                //@import only counts at the very top of a sheet, before any real rules.
+                Prelude::Import(_) if !imports_allowed => {}
+                Prelude::Import(_) | Prelude::None => rules.push(rule),
+                //@layer statements are allowed to say what order the imported layers go in.
+                Prelude::Layer(_) if matches!(rule.value, Block::Empty) => rules.push(rule),
+                _ => {
+                    imports_allowed = false;
+                    rules.push(rule);
                 }
             }
         }
-        Ok(Style {
-            rules,
-            level: StyleLevel::UserAgent,
-        })
+        Ok(rules)
+    }
+
+    fn consume_at_rule(&mut self, name: String) -> Result<RuleBuilder, CSSError> {
+        let mut rule_builder = RuleBuilder::new(true);
+        rule_builder.set_name(name.clone());
+        loop {
+            match self.consume() {
+                CSSToken::Semicolon | CSSToken::EOF => {
+                    break;
+                }
+                CSSToken::CurlyOpen => {
+                    let block = self.consume_simple_block(CSSToken::CurlyClose)?;
+                    rule_builder.has_block = true;
+                    match name.to_ascii_lowercase().as_str() {
+                        "media" | "supports" | "layer" | "container" => {
+                            rule_builder.append_rules(block.parse_as_rules()?)
+                        }
+                        _ => rule_builder.append_to_blocks(block),
+                    }
+                    break;
+                }
+                _ => {
+                    self.reconsume();
+                    rule_builder.append_to_prelude(self.consume_component_value()?);
+                }
+            }
+        }
+        Ok(rule_builder)
     }
 
-    fn consume_qualified_rule(&mut self) -> Result<Rule, CSSError> {
+    //running out of sheet before the block starts means there never was a rule, so there's
+    //nothing to give back.
+    fn consume_qualified_rule(&mut self) -> Result<Option<RuleBuilder>, CSSError> {
         let mut rule_builder = RuleBuilder::new(false);
         loop {
             match self.consume() {
                 CSSToken::EOF => {
-                    break;
+                    return Ok(None);
                 }
                 CSSToken::CurlyOpen => {
                     rule_builder.append_to_blocks(self.consume_simple_block(CSSToken::CurlyClose)?);
-                    break;
+                    return Ok(Some(rule_builder));
                 }
                 _ => {
                     self.reconsume();
@@ -177,34 +411,40 @@ impl CSSParser {
                 }
             }
         }
-        Ok(rule_builder.build()?)
     }
 
     fn consume_component_value(&mut self) -> Result<Component, CSSError> {
         match self.consume() {
-            CSSToken::CurlyOpen => {
-                todo!();
-            }
-            a => {
-                return Ok(Component::Token(a));
-            }
+            CSSToken::CurlyOpen => Ok(Component::Block(
+                self.consume_simple_block(CSSToken::CurlyClose)?,
+            )),
+            CSSToken::BracketOpen => Ok(Component::Block(
+                self.consume_simple_block(CSSToken::BracketClose)?,
+            )),
+            CSSToken::ParenOpen => Ok(Component::Block(
+                self.consume_simple_block(CSSToken::ParenClose)?,
+            )),
+            CSSToken::Function(name) => self.consume_function(name),
+            a => Ok(Component::Token(a)),
         }
     }
 
+    //the opening token has already been eaten. hitting the end of the sheet early is a parse
+    //error, but whatever we got so far still counts.
     fn consume_simple_block(&mut self, ending: CSSToken) -> Result<SimpleBlock, CSSError> {
-        if ending != CSSToken::CurlyClose {
-            do yeet CSSError::WrongBlockEndingToken(ending);
-        }
-        let mut out = SimpleBlock::default();
+        let mut out = SimpleBlock {
+            kind: BlockKind::from_ending(&ending)?,
+            value: vec![],
+        };
         loop {
             match self.consume() {
                 a if a == ending => {
                     break;
                 }
                 CSSToken::EOF => {
-                    do yeet CSSError::EOFReached;
+                    break;
                 }
-                a => {
+                _ => {
                     self.reconsume();
                     out.push_value(self.consume_component_value()?);
                 }
@@ -212,11 +452,28 @@ impl CSSParser {
         }
         Ok(out)
     }
+
+    fn consume_function(&mut self, name: String) -> Result<Component, CSSError> {
+        let mut arguments = vec![];
+        loop {
+            match self.consume() {
+                CSSToken::ParenClose | CSSToken::EOF => {
+                    break;
+                }
+                _ => {
+                    self.reconsume();
+                    arguments.push(self.consume_component_value()?);
+                }
+            }
+        }
+        Ok(Component::Function(name, arguments))
+    }
 }
 
 #[derive(Default, Debug)]
 pub struct CSSTokenizer {
     source: String,
+    chars: Vec<char>, //the source after preprocessing, indexing a string by chars is slow
     source_idx: usize,
 }
 
@@ -229,12 +486,34 @@ impl CSSTokenizer {
                 Char::Char('\u{0009}' | '\u{000A}' | '\u{0020}') => {
                     tokens.push(self.consume_whitespace_token()?);
                 }
-                Char::Char('A'..='Z' | 'a'..='z' | '_' | '\u{0080}'..='\u{10FFFF}') => {
-                    self.reconsume();
-                    tokens.push(self.consume_ident_like_token()?);
+                Char::Char(c @ ('"' | '\'')) => {
+                    tokens.push(self.consume_string_token(c)?);
+                }
+                Char::Char('#') => {
+                    if is_ident_code_point(self.peek())
+                        || is_valid_escape(self.peek(), self.peek_n(2))
+                    {
+                        let kind = if starts_ident(self.peek(), self.peek_n(2), self.peek_n(3)) {
+                            HashType::Id
+                        } else {
+                            HashType::Unrestricted
+                        };
+                        tokens.push(CSSToken::Hash(self.consume_ident_sequence()?, kind));
+                    } else {
+                        tokens.push(CSSToken::Delim(Char::Char('#')));
+                    }
+                }
+                Char::Char('(') => {
+                    tokens.push(CSSToken::ParenOpen);
+                }
+                Char::Char(')') => {
+                    tokens.push(CSSToken::ParenClose);
+                }
+                Char::Char('[') => {
+                    tokens.push(CSSToken::BracketOpen);
                 }
-                Char::Char(c @ ('>' | '*')) => {
-                    tokens.push(CSSToken::Delim(Char::Char(c)));
+                Char::Char(']') => {
+                    tokens.push(CSSToken::BracketClose);
                 }
                 Char::Char('{') => {
                     tokens.push(CSSToken::CurlyOpen);
@@ -242,33 +521,91 @@ impl CSSTokenizer {
                 Char::Char('}') => {
                     tokens.push(CSSToken::CurlyClose);
                 }
+                Char::Char(',') => {
+                    tokens.push(CSSToken::Comma);
+                }
                 Char::Char(':') => {
                     tokens.push(CSSToken::Colon);
                 }
                 Char::Char(';') => {
                     tokens.push(CSSToken::Semicolon);
                 }
-                Char::Char('0'..='9') => {
-                    self.reconsume();
-                    tokens.push(self.consume_numeric()?);
+                c @ Char::Char('+' | '.') => {
+                    if starts_number(c, self.peek(), self.peek_n(2)) {
+                        self.reconsume();
+                        tokens.push(self.consume_numeric()?);
+                    } else {
+                        tokens.push(CSSToken::Delim(c));
+                    }
                 }
-                Char::Char(c @ ('+' | '-' | '.')) => {
-                    if let Char::Char('0'..='9') = self.peek() {
+                c @ Char::Char('-') => {
+                    if starts_number(c, self.peek(), self.peek_n(2)) {
                         self.reconsume();
                         tokens.push(self.consume_numeric()?);
+                    } else if let (Char::Char('-'), Char::Char('>')) = (self.peek(), self.peek_n(2))
+                    {
+                        self.consume();
+                        self.consume();
+                        tokens.push(CSSToken::CDC);
+                    } else if starts_ident(c, self.peek(), self.peek_n(2)) {
+                        self.reconsume();
+                        tokens.push(self.consume_ident_like_token()?);
                     } else {
-                        tokens.push(CSSToken::Delim(Char::Char(c)));
+                        tokens.push(CSSToken::Delim(c));
                     }
                 }
-                Char::Char(',') => {
-                    tokens.push(CSSToken::Comma);
+                Char::Char('<') => {
+                    if let (Char::Char('!'), Char::Char('-'), Char::Char('-')) =
+                        (self.peek(), self.peek_n(2), self.peek_n(3))
+                    {
+                        self.consume();
+                        self.consume();
+                        self.consume();
+                        tokens.push(CSSToken::CDO);
+                    } else {
+                        tokens.push(CSSToken::Delim(Char::Char('<')));
+                    }
+                }
+                Char::Char('@') => {
+                    if starts_ident(self.peek(), self.peek_n(2), self.peek_n(3)) {
+                        tokens.push(CSSToken::AtKeyword(self.consume_ident_sequence()?));
+                    } else {
+                        tokens.push(CSSToken::Delim(Char::Char('@')));
+                    }
+                }
+                c @ Char::Char('\\') => {
+                    if is_valid_escape(c, self.peek()) {
+                        self.reconsume();
+                        tokens.push(self.consume_ident_like_token()?);
+                    } else {
+                        //parse error, but the spec still wants a delim out of it.
+                        tokens.push(CSSToken::Delim(c));
+                    }
+                }
+                Char::Char('0'..='9') => {
+                    self.reconsume();
+                    tokens.push(self.consume_numeric()?);
+                }
+                Char::Char('U' | 'u')
+                    if matches!(self.peek(), Char::Char('+'))
+                        && matches!(
+                            self.peek_n(2),
+                            Char::Char('0'..='9' | 'A'..='F' | 'a'..='f' | '?')
+                        ) =>
+                {
+                    self.consume();
+                    tokens.push(self.consume_unicode_range()?);
+                }
+                c if is_ident_start_code_point(c) => {
+                    self.reconsume();
+                    tokens.push(self.consume_ident_like_token()?);
                 }
                 Char::Eof => {
                     tokens.push(CSSToken::EOF);
                     return Ok(());
                 }
-                a => {
-                    do yeet CSSError::UnimplementedCodePoint(a);
+                c => {
+                    tokens.push(CSSToken::Delim(c));
                 }
             }
         }
@@ -277,7 +614,7 @@ impl CSSTokenizer {
     pub fn load_from_file(&mut self, path: &PathBuf) -> Result<(), CSSError> {
         self.source.clear();
         self.source_idx = 0;
-        File::open(path).unwrap().read_to_string(&mut self.source)?;
+        File::open(path)?.read_to_string(&mut self.source)?;
         Ok(())
     }
 
@@ -287,8 +624,16 @@ impl CSSTokenizer {
         Ok(())
     }
 
-    pub fn load_from_url(&mut self, _url: &Url) -> Result<(), CSSError> {
-        todo!();
+    //only file urls can be read on the spot, everything else has to go through
+    //CSSParser::fetch_sources first.
+    pub fn load_from_url(&mut self, url: &Url) -> Result<(), CSSError> {
+        if url.scheme() != "file" {
+            do yeet CSSError::UnfetchedURL(url.clone());
+        }
+        let path = url
+            .to_file_path()
+            .map_err(|_| CSSError::UnfetchedURL(url.clone()))?;
+        self.load_from_file(&path)
     }
 
     fn preprocess(&mut self) -> Result<(), CSSError> {
@@ -299,6 +644,7 @@ impl CSSTokenizer {
             .replace("\u{000C}", "\u{000A}")
             .replace("\u{0000}", "\u{FFFD}");
         //TODO: Filter out surrogates
+        self.chars = self.source.chars().collect();
         Ok(())
     }
 
@@ -306,72 +652,95 @@ impl CSSTokenizer {
         self.source_idx = self.source_idx.saturating_sub(1);
     }
 
+    //going past the end still moves us along, so reconsuming an EOF doesn't back up into the
+    //last real character.
     fn consume(&mut self) -> Char {
-        if let Some(char) = self.source.chars().nth(self.source_idx) {
-            self.source_idx += 1;
-            Char::Char(char)
-        } else {
-            Char::Eof
-        }
+        let char = self.peek();
+        self.source_idx += 1;
+        char
     }
 
     fn peek(&self) -> Char {
-        if let Some(char) = self.source.chars().nth(self.source_idx) {
-            Char::Char(char)
-        } else {
-            Char::Eof
-        }
+        self.peek_n(1)
     }
 
     fn peek_n(&self, n: usize) -> Char {
-        if let Some(char) = self.source.chars().nth(self.source_idx + n - 1) {
-            Char::Char(char)
-        } else {
-            Char::Eof
+        match self.chars.get(self.source_idx + n - 1) {
+            Some(char) => Char::Char(*char),
+            None => Char::Eof,
         }
     }
 
     fn consume_comments(&mut self) -> Result<(), CSSError> {
-        if matches!(self.peek(), Char::Char('/')) && matches!(self.peek_n(2), Char::Char('*')) {
-            while !(matches!(self.peek(), Char::Char('*'))
-                && matches!(self.peek_n(2), Char::Char('/')))
-            {
-                self.consume();
-            }
+        while matches!(self.peek(), Char::Char('/')) && matches!(self.peek_n(2), Char::Char('*')) {
             self.consume();
             self.consume();
+            loop {
+                match self.consume() {
+                    Char::Char('*') if matches!(self.peek(), Char::Char('/')) => {
+                        self.consume();
+                        break;
+                    }
+                    //unterminated comments just run to the end.
+                    Char::Eof => {
+                        self.reconsume();
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+            }
         }
         Ok(())
     }
 
+    //expects the backslash to still be there. None means it wasn't actually an escape (a
+    //backslash right before a newline), in which case nothing gets consumed.
     fn consume_escaped(&mut self) -> Result<Option<char>, CSSError> {
-        todo!();
-        /*match self.consume() {
-        Char::Char('\n') => {
-        Ok(None)
+        if !is_valid_escape(self.peek(), self.peek_n(2)) {
+            return Ok(None);
+        }
+        self.consume();
+        match self.consume() {
+            Char::Char(c) if c.is_ascii_hexdigit() => {
+                let mut hex = String::from(c);
+                while let Char::Char(c) = self.peek() {
+                    if !c.is_ascii_hexdigit() || hex.len() == 6 {
+                        break;
+                    }
+                    self.consume();
+                    hex.push(c);
+                }
+                if let Char::Char('\u{0009}' | '\u{000A}' | '\u{0020}') = self.peek() {
+                    self.consume();
+                }
+                let value = u32::from_str_radix(&hex, 16).unwrap_or(0);
+                //char::from_u32 already says no to surrogates and anything past the max.
+                Ok(Some(match value {
+                    0 => '\u{FFFD}',
+                    v => char::from_u32(v).unwrap_or('\u{FFFD}'),
+                }))
+            }
+            Char::Char(c) => Ok(Some(c)),
+            Char::Eof => {
+                self.reconsume();
+                Ok(Some('\u{FFFD}'))
+            }
         }
-        }*/
     }
 
     fn consume_ident_sequence(&mut self) -> Result<String, CSSError> {
         let mut result = String::with_capacity(10);
         loop {
             match self.consume() {
-                Char::Char(
-                    c @ ('A'..='Z' | 'a'..='z' | '0'..='9' | '\u{0080}'..='\u{10FFFF}' | '_' | '-'),
-                ) => {
-                    result.push(c);
-                },
-                Char::Char('\\') => {
+                c @ Char::Char(ch) if is_ident_code_point(c) => {
+                    result.push(ch);
+                }
+                c @ Char::Char('\\') if is_valid_escape(c, self.peek()) => {
                     self.reconsume();
                     if let Some(c) = self.consume_escaped()? {
                         result.push(c);
                     }
-                },
-		//caught this edge case while unit testing! turns out they are valuable!!!!
-		Char::Eof => {
-		    break;
-		},
+                }
                 _ => {
                     self.reconsume();
                     break;
@@ -382,90 +751,279 @@ impl CSSTokenizer {
     }
 
     fn consume_whitespace_token(&mut self) -> Result<CSSToken, CSSError> {
+        while let Char::Char('\u{0009}' | '\u{000A}' | '\u{0020}') = self.peek() {
+            self.consume();
+        }
+        Ok(CSSToken::Whitespace)
+    }
+
+    fn consume_ident_like_token(&mut self) -> Result<CSSToken, CSSError> {
+        let string = self.consume_ident_sequence()?;
+        if let Char::Char('(') = self.peek() {
+            self.consume();
+            if string.eq_ignore_ascii_case("url") {
+                while let Char::Char('\u{0009}' | '\u{000A}' | '\u{0020}') = self.peek() {
+                    self.consume();
+                }
+                //quoted urls are just a function with a string in it.
+                if let Char::Char('"' | '\'') = self.peek() {
+                    return Ok(CSSToken::Function(string));
+                }
+                return self.consume_url_token();
+            }
+            return Ok(CSSToken::Function(string));
+        }
+        Ok(CSSToken::Ident(string))
+    }
+
+    fn consume_string_token(&mut self, ending: char) -> Result<CSSToken, CSSError> {
+        let mut result = String::new();
         loop {
             match self.consume() {
-                Char::Char('\u{0009}' | '\u{000A}' | '\u{0020}') => {}
+                Char::Char(c) if c == ending => {
+                    break;
+                }
                 Char::Eof => {
+                    self.reconsume();
                     break;
                 }
-                _ => {
+                Char::Char('\u{000A}') => {
+                    //the newline isn't part of the string, it becomes whitespace after it.
+                    self.reconsume();
+                    return Ok(CSSToken::BadString);
+                }
+                Char::Char('\\') => match self.peek() {
+                    Char::Eof => {}
+                    Char::Char('\u{000A}') => {
+                        self.consume();
+                    }
+                    _ => {
+                        self.reconsume();
+                        if let Some(c) = self.consume_escaped()? {
+                            result.push(c);
+                        }
+                    }
+                },
+                Char::Char(c) => {
+                    result.push(c);
+                }
+            }
+        }
+        Ok(CSSToken::String(result))
+    }
+
+    fn consume_url_token(&mut self) -> Result<CSSToken, CSSError> {
+        let mut result = String::new();
+        loop {
+            match self.consume() {
+                Char::Char(')') => {
+                    break;
+                }
+                Char::Eof => {
                     self.reconsume();
                     break;
                 }
+                Char::Char('\u{0009}' | '\u{000A}' | '\u{0020}') => {
+                    while let Char::Char('\u{0009}' | '\u{000A}' | '\u{0020}') = self.peek() {
+                        self.consume();
+                    }
+                    match self.peek() {
+                        Char::Char(')') => {
+                            self.consume();
+                            break;
+                        }
+                        Char::Eof => break,
+                        _ => {
+                            self.consume_bad_url_remnants()?;
+                            return Ok(CSSToken::BadUrl);
+                        }
+                    }
+                }
+                Char::Char(
+                    '"'
+                    | '\''
+                    | '('
+                    | '\u{0000}'..='\u{0008}'
+                    | '\u{000B}'
+                    | '\u{000E}'..='\u{001F}'
+                    | '\u{007F}',
+                ) => {
+                    self.consume_bad_url_remnants()?;
+                    return Ok(CSSToken::BadUrl);
+                }
+                c @ Char::Char('\\') => {
+                    if is_valid_escape(c, self.peek()) {
+                        self.reconsume();
+                        if let Some(c) = self.consume_escaped()? {
+                            result.push(c);
+                        }
+                    } else {
+                        self.consume_bad_url_remnants()?;
+                        return Ok(CSSToken::BadUrl);
+                    }
+                }
+                Char::Char(c) => {
+                    result.push(c);
+                }
             }
         }
-        Ok(CSSToken::Whitespace)
+        Ok(CSSToken::Url(result))
     }
 
-    fn consume_ident_like_token(&mut self) -> Result<CSSToken, CSSError> {
-        let string = self.consume_ident_sequence()?;
-        Ok(CSSToken::Ident(string))
+    //gets us back to a sane spot after a broken url, escaped parens don't count as the end.
+    fn consume_bad_url_remnants(&mut self) -> Result<(), CSSError> {
+        loop {
+            match self.consume() {
+                Char::Char(')') => break,
+                Char::Eof => {
+                    self.reconsume();
+                    break;
+                }
+                c @ Char::Char('\\') if is_valid_escape(c, self.peek()) => {
+                    self.reconsume();
+                    self.consume_escaped()?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
     }
 
-    fn consume_numeric(&mut self) -> Result<CSSToken, CSSError> {
-        let number = self.consume_number()?;
-        if let Char::Char(c) = self.peek() {
-            if ('A'..='Z').contains(&c)
-                || ('a'..='z').contains(&c)
-                || c as u32 > '\u{0080}' as u32
-                || c == '_'
-                || c == '\\'
-            {
-                let unit = self.consume_ident_sequence()?;
-                return Ok(CSSToken::Number(CSSNumber::Unit(
-                    number,
-                    Unit::from_string(unit).unwrap_or_default(),
-                )));
-            } else if c == '%' {
+    //the `U+` has already been eaten by the time we get here.
+    fn consume_unicode_range(&mut self) -> Result<CSSToken, CSSError> {
+        let mut start = String::with_capacity(6);
+        while start.len() < 6 {
+            match self.peek() {
+                Char::Char(c @ ('0'..='9' | 'A'..='F' | 'a'..='f' | '?')) => {
+                    self.consume();
+                    start.push(c);
+                }
+                _ => break,
+            }
+        }
+        if start.contains('?') {
+            let low = u32::from_str_radix(&start.replace('?', "0"), 16).unwrap_or(0);
+            let high = u32::from_str_radix(&start.replace('?', "F"), 16).unwrap_or(0);
+            return Ok(CSSToken::UnicodeRange(low, high));
+        }
+        let low = u32::from_str_radix(&start, 16).unwrap_or(0);
+        if let (Char::Char('-'), Char::Char('0'..='9' | 'A'..='F' | 'a'..='f')) =
+            (self.peek(), self.peek_n(2))
+        {
+            self.consume();
+            let mut end = String::with_capacity(6);
+            while let Char::Char(c @ ('0'..='9' | 'A'..='F' | 'a'..='f')) = self.peek() {
+                if end.len() == 6 {
+                    break;
+                }
                 self.consume();
-                return Ok(CSSToken::Number(CSSNumber::Percentage(number)));
+                end.push(c);
             }
-        };
+            return Ok(CSSToken::UnicodeRange(
+                low,
+                u32::from_str_radix(&end, 16).unwrap_or(0),
+            ));
+        }
+        Ok(CSSToken::UnicodeRange(low, low))
+    }
+
+    fn consume_numeric(&mut self) -> Result<CSSToken, CSSError> {
+        let number = self.consume_number()?;
+        if starts_ident(self.peek(), self.peek_n(2), self.peek_n(3)) {
+            let unit = self.consume_ident_sequence()?;
+            //units we don't know (yet) still have to make it through as dimensions, they just
+            //can't be used for anything.
+            return Ok(match Unit::from_string(unit.clone()) {
+                Some(u) => CSSToken::Number(CSSNumber::Unit(number, u)),
+                None => CSSToken::Dimension(number, unit),
+            });
+        }
+        if let Char::Char('%') = self.peek() {
+            self.consume();
+            return Ok(CSSToken::Number(CSSNumber::Percentage(number)));
+        }
         Ok(CSSToken::Number(CSSNumber::Number(number)))
     }
 
     fn consume_number(&mut self) -> Result<Numeric, CSSError> {
         let mut rep = NumberRep::default();
-        if let Char::Char('+' | '-') = self.peek() {
-            rep.set_sign(match self.consume() {
-                Char::Char(c) => c,
-                Char::Eof => do yeet CSSError::EOFReached,
-            });
+        if let Char::Char(c @ ('+' | '-')) = self.peek() {
+            self.consume();
+            rep.set_sign(c);
         };
-        while let Char::Char('0'..='9') = self.peek() {
-            rep.append_to_integer(match self.consume() {
-                Char::Char(c) => c,
-                Char::Eof => do yeet CSSError::EOFReached,
-            });
+        while let Char::Char(c @ '0'..='9') = self.peek() {
+            self.consume();
+            rep.append_to_integer(c);
         }
-        if let Char::Char('.') = self.peek() {
+        if let (Char::Char('.'), Char::Char('0'..='9')) = (self.peek(), self.peek_n(2)) {
             self.consume();
-            while let Char::Char('0'..='9') = self.peek() {
-                rep.append_to_decimal(match self.consume() {
-                    Char::Char(c) => c,
-                    Char::Eof => do yeet CSSError::EOFReached,
-                });
+            while let Char::Char(c @ '0'..='9') = self.peek() {
+                self.consume();
+                rep.append_to_decimal(c);
             }
         }
-        if let Char::Char('E' | 'e') = self.peek() {
+        //the e only belongs to the number if digits follow it, otherwise it's the start of a
+        //unit like em.
+        let exponent = match (self.peek(), self.peek_n(2), self.peek_n(3)) {
+            (Char::Char('E' | 'e'), Char::Char('0'..='9'), _) => Some(None),
+            (Char::Char('E' | 'e'), Char::Char(s @ ('+' | '-')), Char::Char('0'..='9')) => {
+                Some(Some(s))
+            }
+            _ => None,
+        };
+        if let Some(sign) = exponent {
             self.consume();
-	    if let Char::Char('-' | '+') = self.peek() {
-		rep.set_e_sign(match self.consume() {
-		    Char::Char(c) => c,
-		    Char::Eof => do yeet CSSError::EOFReached,
-		});
-	    }
-            while let Char::Char('0'..='9') = self.peek() {
-                rep.append_to_exponent(match self.consume() {
-                    Char::Char(c) => c,
-                    Char::Eof => do yeet CSSError::EOFReached,
-                });
+            if let Some(sign) = sign {
+                self.consume();
+                rep.set_e_sign(sign);
+            }
+            while let Char::Char(c @ '0'..='9') = self.peek() {
+                self.consume();
+                rep.append_to_exponent(c);
             }
         }
         Ok(rep.into_numeric()?)
     }
 }
 
+fn is_ident_start_code_point(c: Char) -> bool {
+    matches!(
+        c,
+        Char::Char('A'..='Z' | 'a'..='z' | '_' | '\u{0080}'..='\u{10FFFF}')
+    )
+}
+
+fn is_ident_code_point(c: Char) -> bool {
+    is_ident_start_code_point(c) || matches!(c, Char::Char('0'..='9' | '-'))
+}
+
+fn is_valid_escape(first: Char, second: Char) -> bool {
+    first == Char::Char('\\') && second != Char::Char('\u{000A}')
+}
+
+//the spec's "would start an ident sequence" check.
+fn starts_ident(first: Char, second: Char, third: Char) -> bool {
+    match first {
+        Char::Char('-') => {
+            is_ident_start_code_point(second)
+                || second == Char::Char('-')
+                || is_valid_escape(second, third)
+        }
+        Char::Char('\\') => is_valid_escape(first, second),
+        c => is_ident_start_code_point(c),
+    }
+}
+
+fn starts_number(first: Char, second: Char, third: Char) -> bool {
+    match (first, second, third) {
+        (Char::Char('+' | '-'), Char::Char('0'..='9'), _) => true,
+        (Char::Char('+' | '-'), Char::Char('.'), Char::Char('0'..='9')) => true,
+        (Char::Char('.'), Char::Char('0'..='9'), _) => true,
+        (Char::Char('0'..='9'), _, _) => true,
+        _ => false,
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum CSSError {
     #[error("IO Failed!: {0}")]
@@ -482,12 +1040,33 @@ pub enum CSSError {
     ParseIntError(#[from] ParseIntError),
     #[error("Unexpected token {0:?}! Expected: {1:?}")]
     UnexpectedToken(CSSToken, CSSToken),
+    #[error("Stylesheet at {0} has to be fetched before it can be parsed!")]
+    UnfetchedURL(Url),
+    #[error("Declaration is missing its colon!")]
+    InvalidDeclaration,
+    #[error("{0:?} isn't a valid value for {1}!")]
+    InvalidValue(Vec<Component>, String),
+    #[error("Don't know what to do with {0:?} in a selector!")]
+    InvalidSelector(Component),
+    #[error("Expected exactly one rule!")]
+    InvalidRule,
+    #[error("Can't put that rule there!")]
+    RuleNotAllowed,
+    #[error("There's no stylesheet at {0}!")]
+    NoSuchSheet(usize),
+    #[error("There's no rule at {0:?} that can do that!")]
+    NoSuchRule(Vec<usize>),
+    #[error("That rule doesn't have declarations!")]
+    NoDeclarations,
+    #[error("Don't know any property called {0}!")]
+    UnknownProperty(String),
 }
 
 #[derive(Debug, Default)]
 pub struct Style {
     pub rules: Vec<Rule>,
     pub level: StyleLevel,
+    pub url: Option<Url>, //where the sheet came from, if it came from anywhere.
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
@@ -498,13 +1077,151 @@ pub enum StyleLevel {
     UserAgent,
 }
 
+impl StyleLevel {
+    //just the origin, without importance coming into it. what revert rolls back through.
+    fn origin(&self) -> u8 {
+        match self {
+            StyleLevel::UserAgent => 0,
+            StyleLevel::User => 1,
+            StyleLevel::Author => 2,
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for rule in &self.rules {
+            //unknown at-rules don't leave anything behind to write out.
+            if let Prelude::None = rule.prelude {
+                continue;
+            }
+            if !first {
+                f.write_str("\n")?;
+            }
+            first = false;
+            rule.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
 impl Style {
+    //rules inside @media are found by their index inside it, so [2, 0] is the first rule in
+    //the third rule.
+    pub fn rule(&self, path: &[usize]) -> Option<&Rule> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.rules.get(*first)?, |rule, i| match rule.value {
+                Block::Rules(ref rules) => rules.get(*i),
+                _ => None,
+            })
+    }
+
+    pub fn rule_mut(&mut self, path: &[usize]) -> Option<&mut Rule> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.rules.get_mut(*first)?, |rule, i| match rule.value {
+                Block::Rules(ref mut rules) => rules.get_mut(*i),
+                _ => None,
+            })
+    }
+
+    //an empty path is the sheet itself.
+    fn rules_mut(&mut self, parent: &[usize]) -> Option<&mut Vec<Rule>> {
+        if parent.is_empty() {
+            return Some(&mut self.rules);
+        }
+        match self.rule_mut(parent)?.value {
+            Block::Rules(ref mut rules) => Some(rules),
+            _ => None,
+        }
+    }
+
+    //insertRule(). @imports have to stay in front of everything else, and can't go inside
+    //anything at all.
+    pub fn insert_rule(
+        &mut self,
+        parent: &[usize],
+        source: &String,
+        index: usize,
+    ) -> Result<usize, CSSError> {
+        let rule = CSSParser::parse_rule(source)?;
+        let Some(rules) = self.rules_mut(parent) else {
+            do yeet CSSError::NoSuchRule(parent.to_vec());
+        };
+        if index > rules.len() {
+            do yeet CSSError::NoSuchRule([parent, &[index]].concat());
+        }
+        let import_like = |r: &Rule| match r.prelude {
+            Prelude::Import(_) | Prelude::None => true,
+            Prelude::Layer(_) => matches!(r.value, Block::Empty),
+            _ => false,
+        };
+        let allowed = match rule.prelude {
+            Prelude::Import(_) => parent.is_empty() && rules[..index].iter().all(import_like),
+            _ if import_like(&rule) => true,
+            _ => !rules[index..]
+                .iter()
+                .any(|r| matches!(r.prelude, Prelude::Import(_))),
+        };
+        if !allowed {
+            do yeet CSSError::RuleNotAllowed;
+        }
+        rules.insert(index, rule);
+        self.let_em_know();
+        Ok(index)
+    }
+
+    pub fn delete_rule(&mut self, path: &[usize]) -> Result<Rule, CSSError> {
+        let Some((index, parent)) = path.split_last() else {
+            do yeet CSSError::NoSuchRule(vec![]);
+        };
+        match self.rules_mut(parent) {
+            Some(rules) if *index < rules.len() => Ok(rules.remove(*index)),
+            _ => do yeet CSSError::NoSuchRule(path.to_vec()),
+        }
+    }
+
+    //these go through the sheet so the new declaration ends up with the sheet's level.
+    pub fn set_declaration(
+        &mut self,
+        path: &[usize],
+        name: &str,
+        value: &str,
+        important: bool,
+    ) -> Result<(), CSSError> {
+        let Some(rule) = self.rule_mut(path) else {
+            do yeet CSSError::NoSuchRule(path.to_vec());
+        };
+        rule.set_declaration(name, value, important)?;
+        self.let_em_know();
+        Ok(())
+    }
+
+    pub fn remove_declaration(&mut self, path: &[usize], name: &str) -> Option<Declaration> {
+        self.rule_mut(path)?.remove_declaration(name)
+    }
+
+    pub fn set_level(&mut self, level: StyleLevel) {
+        self.level = level;
+        self.let_em_know();
+    }
+
     pub fn let_em_know(&mut self) {
-        for rule in &mut self.rules {
-            if let Block::Declarations(ref mut declarations) = rule.value {
-                for (_, declaration) in declarations {
-                    declaration.level = self.level;
+        Self::let_em_know_recursive(&mut self.rules, self.level);
+    }
+
+    fn let_em_know_recursive(rules: &mut Vec<Rule>, level: StyleLevel) {
+        for rule in rules {
+            match rule.value {
+                Block::Declarations(ref mut declarations) => {
+                    for (_, declaration) in declarations {
+                        declaration.level = level;
+                    }
                 }
+                Block::Rules(ref mut rules) => Self::let_em_know_recursive(rules, level),
+                Block::FontFace(_) | Block::Empty => {}
             }
         }
     }
@@ -519,7 +1236,10 @@ pub struct StyleData {
 pub struct RuleBuilder {
     pub preludes: Vec<Component>,
     pub blocks: Vec<SimpleBlock>,
+    pub rules: Vec<Rule>,
+    pub name: String,
     pub at: bool,
+    pub has_block: bool, //@layer means something different with and without one
 }
 
 impl RuleBuilder {
@@ -527,10 +1247,21 @@ impl RuleBuilder {
         Self {
             preludes: vec![],
             blocks: vec![],
+            rules: vec![],
+            name: String::new(),
             at,
+            has_block: false,
         }
     }
 
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn append_rules(&mut self, rules: Vec<Rule>) {
+        self.rules.extend(rules);
+    }
+
     pub fn append_to_prelude(&mut self, component: Component) {
         self.preludes.push(component);
     }
@@ -541,43 +1272,220 @@ impl RuleBuilder {
 
     pub fn build(self) -> Result<Rule, CSSError> {
         if self.at {
-            todo!("at rule");
+            return Ok(match self.name.to_ascii_lowercase().as_str() {
+                "media" => Rule {
+                    prelude: Prelude::Media(MediaQueryList::from_components(&self.preludes)),
+                    value: Block::Rules(self.rules),
+                },
+                "import" => match ImportRule::from_components(&self.preludes) {
+                    Some(import) => Rule {
+                        prelude: Prelude::Import(import),
+                        value: Block::Empty,
+                    },
+                    None => Rule::default(),
+                },
+                "supports" => match SupportsCondition::from_components(&self.preludes) {
+                    Some(condition) if self.has_block => Rule {
+                        prelude: Prelude::Supports(condition),
+                        value: Block::Rules(self.rules),
+                    },
+                    _ => Rule::default(),
+                },
+                //`@layer a, b;` just declares them, `@layer a { }` and `@layer { }` have rules.
+                "layer" => match parse_layer_names(&self.preludes) {
+                    Some(names) if self.has_block && names.len() <= 1 => Rule {
+                        prelude: Prelude::Layer(names),
+                        value: Block::Rules(self.rules),
+                    },
+                    Some(names) if !self.has_block && !names.is_empty() => Rule {
+                        prelude: Prelude::Layer(names),
+                        value: Block::Empty,
+                    },
+                    _ => Rule::default(),
+                },
+                "container" => match ContainerCondition::from_components(&self.preludes) {
+                    Some(condition) if self.has_block => Rule {
+                        prelude: Prelude::Container(condition),
+                        value: Block::Rules(self.rules),
+                    },
+                    _ => Rule::default(),
+                },
+                "font-face" => match FontFace::from_blocks(&self.blocks) {
+                    Some(face) => Rule {
+                        prelude: Prelude::FontFace,
+                        value: Block::FontFace(face),
+                    },
+                    None => Rule::default(),
+                },
+                //unknown at-rules get dropped on the floor, the cascader ignores empty rules.
+                _ => Rule::default(),
+            });
+        }
+        let mut selector = Selector::Placeheld;
+        for component in self.preludes {
+            selector.append(component)?;
+        }
+        selector.trim();
+        if let Some(c) = selector.unfinished() {
+            do yeet CSSError::InvalidSelector(Component::Token(CSSToken::Delim(Char::Char(c))));
+        }
+        let mut declarations: HashMap<String, Declaration> = HashMap::default();
+        for ref mut block in self.blocks {
+            declarations.extend(block.parse_as_declarations()?)
+        }
+        Ok(Rule {
+            prelude: Prelude::Selector(selector),
+            value: Block::Declarations(declarations),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Rule {
+    pub prelude: Prelude,
+    pub value: Block,
+}
+
+//declarations come out sorted by name, the map doesn't remember what order they went in.
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.prelude, &self.value) {
+            (Prelude::Selector(selector), Block::Declarations(_)) => {
+                write!(f, "{} {{", selector)?;
+                for declaration in self.declarations() {
+                    write!(f, " {};", declaration)?;
+                }
+                f.write_str(" }")
+            }
+            (Prelude::Media(queries), Block::Rules(rules)) => match queries.queries.is_empty() {
+                true => Self::fmt_grouping(f, "@media", rules),
+                false => Self::fmt_grouping(f, &format!("@media {}", queries), rules),
+            },
+            (Prelude::Supports(condition), Block::Rules(rules)) => {
+                Self::fmt_grouping(f, &format!("@supports {}", condition), rules)
+            }
+            (Prelude::Container(condition), Block::Rules(rules)) => {
+                Self::fmt_grouping(f, &format!("@container {}", condition), rules)
+            }
+            (Prelude::Layer(names), value) => {
+                let names = names
+                    .iter()
+                    .map(|n| {
+                        n.iter()
+                            .map(|part| CSSToken::Ident(part.clone()).to_string())
+                            .collect::<Vec<_>>()
+                            .join(".")
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                match (value, names.is_empty()) {
+                    (Block::Rules(rules), true) => Self::fmt_grouping(f, "@layer", rules),
+                    (Block::Rules(rules), false) => {
+                        Self::fmt_grouping(f, &format!("@layer {}", names), rules)
+                    }
+                    _ => write!(f, "@layer {};", names),
+                }
+            }
+            (Prelude::Import(import), _) => import.fmt(f),
+            (_, Block::FontFace(face)) => face.fmt(f),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Rule {
+    //@media and friends, with every rule inside indented.
+    fn fmt_grouping(f: &mut fmt::Formatter<'_>, prelude: &str, rules: &[Rule]) -> fmt::Result {
+        write!(f, "{} {{\n", prelude)?;
+        for rule in rules {
+            if let Prelude::None = rule.prelude {
+                continue;
+            }
+            for line in rule.to_string().lines() {
+                writeln!(f, "  {}", line)?;
+            }
+        }
+        f.write_str("}")
+    }
+
+    pub fn declarations(&self) -> Vec<&Declaration> {
+        let Block::Declarations(ref declarations) = self.value else {
+            return vec![];
+        };
+        let mut out = declarations.values().collect::<Vec<_>>();
+        out.sort_by(|l, r| l.name.cmp(&r.name));
+        out
+    }
+
+    //setProperty(). an empty value takes the declaration out instead.
+    pub fn set_declaration(
+        &mut self,
+        name: &str,
+        value: &str,
+        important: bool,
+    ) -> Result<(), CSSError> {
+        let Block::Declarations(ref mut declarations) = self.value else {
+            do yeet CSSError::NoDeclarations;
+        };
+        let name = match name.starts_with("--") {
+            true => name.to_string(),
+            false => name.to_ascii_lowercase(),
+        };
+        if !name.starts_with("--") && !DeclarationBuilder::is_known(&name) {
+            do yeet CSSError::UnknownProperty(name);
+        }
+        let mut value = CSSParser::parse_component_list(&value.to_string())?;
+        while let Some(Component::Token(CSSToken::Whitespace)) = value.last() {
+            value.pop();
+        }
+        let start = value
+            .iter()
+            .position(|c| c != &Component::Token(CSSToken::Whitespace))
+            .unwrap_or(value.len());
+        if start == value.len() {
+            for longhand in DeclarationBuilder::longhands(&name) {
+                declarations.remove(&longhand);
+            }
+            return Ok(());
         }
-        let mut selector = Selector::Placeheld;
-        for component in self.preludes {
-            selector.append(component);
+        let mut builder = DeclarationBuilder::from_kind(name.clone());
+        for component in value.drain(start..) {
+            builder.push_value(component);
         }
-        let mut declarations: HashMap<Discriminant<DeclarationKind>, Declaration> =
-            HashMap::default();
-        for ref mut block in self.blocks {
-            declarations.extend(block.parse_as_declarations()?)
+        builder.set_important(important);
+        for declaration in builder.build_all()? {
+            declarations.insert(declaration.name.clone(), declaration);
         }
-        Ok(Rule {
-            prelude: Prelude::Selector(selector),
-            value: Block::Declarations(declarations),
-        })
+        Ok(())
     }
-}
 
-#[derive(Debug, Default, Clone)]
-pub struct Rule {
-    pub prelude: Prelude,
-    pub value: Block,
-}
+    pub fn remove_declaration(&mut self, name: &str) -> Option<Declaration> {
+        let Block::Declarations(ref mut declarations) = self.value else {
+            return None;
+        };
+        match name.starts_with("--") {
+            true => declarations.remove(name),
+            //a shorthand takes all its longhands with it, and gives back the first one.
+            false => DeclarationBuilder::longhands(&name.to_ascii_lowercase())
+                .iter()
+                .filter_map(|longhand| declarations.remove(longhand))
+                .reduce(|first, _| first),
+        }
+    }
 
-impl Rule {
+    //other wins ties, so rules need squashing from the least specific and earliest up.
     pub fn squash(&mut self, other: &Rule) {
         if let Block::Declarations(ref mut self_declarations) = self.value {
             if let Block::Declarations(ref other_declarations) = other.value {
-                for (discriminant, declaration) in other_declarations {
-                    if self_declarations.contains_key(discriminant) {
+                for (name, declaration) in other_declarations {
+                    if self_declarations.contains_key(name) {
                         let mutclaration =
-                            unsafe { self_declarations.get_mut(discriminant).unwrap_unchecked() };
+                            unsafe { self_declarations.get_mut(name).unwrap_unchecked() };
                         if declaration >= mutclaration {
                             *mutclaration = declaration.clone();
                         }
                     } else {
-                        self_declarations.insert(*discriminant, declaration.clone());
+                        self_declarations.insert(name.clone(), declaration.clone());
                     }
                 }
             }
@@ -590,17 +1498,26 @@ pub enum Prelude {
     #[default]
     None,
     Selector(Selector),
+    Media(MediaQueryList),
+    FontFace,
+    Import(ImportRule),
+    Supports(SupportsCondition),
+    Layer(Vec<LayerName>),
+    Container(ContainerCondition),
 }
 
 #[derive(Debug, Clone, Default)]
 pub enum Block {
     #[default]
     Empty,
-    Declarations(HashMap<Discriminant<DeclarationKind>, Declaration>),
+    Declarations(HashMap<String, Declaration>),
+    Rules(Vec<Rule>),
+    FontFace(FontFace),
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct SimpleBlock {
+    pub kind: BlockKind,
     pub value: Vec<Component>,
 }
 
@@ -609,21 +1526,45 @@ impl SimpleBlock {
         self.value.push(component);
     }
 
-    pub fn parse_as_declarations(
-        &mut self,
-    ) -> Result<Vec<(Discriminant<DeclarationKind>, Declaration)>, CSSError> {
-        let tokens = self
-            .value
-            .iter()
-            .map(|c| match c {
-                Component::Token(t) => t.clone(),
-                _ => CSSToken::Whitespace,
-            })
-            .collect::<Vec<_>>();
+    pub fn parse_as_declarations(&mut self) -> Result<Vec<(String, Declaration)>, CSSError> {
         let mut parser = CSSParser::default();
-        parser.push_pretokenized(tokens);
+        parser.push_pretokenized(Component::flatten(&self.value));
         Ok(parser.parse_declaration_list()?)
     }
+
+    pub fn parse_as_rules(&self) -> Result<Vec<Rule>, CSSError> {
+        let mut parser = CSSParser::default();
+        parser.push_pretokenized(Component::flatten(&self.value));
+        Ok(parser.consume_rules(true)?)
+    }
+}
+
+//which brackets a block was wrapped in.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum BlockKind {
+    #[default]
+    Curly,
+    Square,
+    Paren,
+}
+
+impl BlockKind {
+    fn from_ending(ending: &CSSToken) -> Result<Self, CSSError> {
+        Ok(match ending {
+            CSSToken::CurlyClose => Self::Curly,
+            CSSToken::BracketClose => Self::Square,
+            CSSToken::ParenClose => Self::Paren,
+            a => do yeet CSSError::WrongBlockEndingToken(a.clone()),
+        })
+    }
+
+    pub fn tokens(&self) -> (CSSToken, CSSToken) {
+        match self {
+            Self::Curly => (CSSToken::CurlyOpen, CSSToken::CurlyClose),
+            Self::Square => (CSSToken::BracketOpen, CSSToken::BracketClose),
+            Self::Paren => (CSSToken::ParenOpen, CSSToken::ParenClose),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -631,13 +1572,192 @@ pub enum Selector {
     Placeheld,
     Universal,
     Type(String),
+    Id(String),
+    Class(String),
+    State(PseudoClass),
+    Pending(char), //a . or : that's still waiting for the name after it
+    //simple selectors that all have to match the same element, like p.note.
+    Compound(Box<Selector>, Box<Selector>),
     Child(Box<Selector>, Box<Selector>),
     NextSibling(Box<Selector>, Box<Selector>),
+    Descendant(Box<Selector>, Box<Selector>),
     Both(Box<Selector>, Box<Selector>),
 }
 
+//the user action pseudo-classes, which are the only ones so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PseudoClass {
+    Hover,
+    Active,
+    Focus,
+}
+
+impl PseudoClass {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "hover" => Some(Self::Hover),
+            "active" => Some(Self::Active),
+            "focus" => Some(Self::Focus),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PseudoClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hover => "hover",
+            Self::Active => "active",
+            Self::Focus => "focus",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Combinator {
+    Child,
+    NextSibling,
+    Descendant,
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Placeheld => Ok(()),
+            Selector::Universal => f.write_str("*"),
+            Selector::Type(t) => f.write_str(t),
+            Selector::Id(id) => write!(f, "#{}", id),
+            Selector::Class(class) => write!(f, ".{}", class),
+            Selector::State(state) => write!(f, ":{}", state),
+            Selector::Pending(c) => write!(f, "{}", c),
+            Selector::Compound(l, r) => write!(f, "{}{}", l, r),
+            Selector::Child(l, r) => write!(f, "{} > {}", l, r),
+            Selector::NextSibling(l, r) => write!(f, "{} + {}", l, r),
+            Selector::Descendant(l, r) => write!(f, "{} {}", l, r),
+            Selector::Both(l, r) => write!(f, "{}, {}", l, r),
+        }
+    }
+}
+
 impl Selector {
-    pub fn append(&mut self, component: Component) {
+    //what the style invalidation needs to know about, so it can leave alone whatever a change
+    //couldn't possibly affect.
+    pub fn uses_siblings(&self) -> bool {
+        match self {
+            Selector::NextSibling(..) => true,
+            Selector::Child(l, r) | Selector::Descendant(l, r) | Selector::Both(l, r) => {
+                l.uses_siblings() || r.uses_siblings()
+            }
+            _ => false,
+        }
+    }
+
+    //ids, then classes and pseudo-classes, then types, for one complex selector. a list's
+    //selectors each have their own, so it's whichever one matched that counts.
+    pub fn specificity(&self) -> (u32, u32, u32) {
+        match self {
+            Selector::Id(_) => (1, 0, 0),
+            Selector::Class(_) | Selector::State(_) => (0, 1, 0),
+            Selector::Type(_) => (0, 0, 1),
+            Selector::Compound(l, r)
+            | Selector::Child(l, r)
+            | Selector::NextSibling(l, r)
+            | Selector::Descendant(l, r) => {
+                let (l, r) = (l.specificity(), r.specificity());
+                (l.0 + r.0, l.1 + r.1, l.2 + r.2)
+            }
+            Selector::Both(l, r) => l.specificity().max(r.specificity()),
+            Selector::Placeheld | Selector::Universal | Selector::Pending(_) => (0, 0, 0),
+        }
+    }
+
+    //a compound selector's simple selectors, in the order they were written.
+    pub fn simples(&self) -> Vec<&Selector> {
+        match self {
+            Selector::Compound(l, r) => [l.simples(), r.simples()].concat(),
+            s => vec![s],
+        }
+    }
+
+    //whether a compound selector matches an element that looks like this.
+    pub fn matches_keys(&self, keys: &ElementKeys) -> bool {
+        self.simples().into_iter().all(|simple| match simple {
+            Selector::Universal => true,
+            Selector::Type(t) => *t == keys.tag,
+            Selector::Id(id) => keys.id.as_ref() == Some(id),
+            Selector::Class(class) => keys.classes.contains(class),
+            Selector::State(state) => keys.states.contains(state),
+            _ => false,
+        })
+    }
+
+    //a trailing . or : with nothing after it.
+    pub fn unfinished(&self) -> Option<char> {
+        match self {
+            Selector::Pending(c) => Some(*c),
+            Selector::Compound(_, r)
+            | Selector::Child(_, r)
+            | Selector::NextSibling(_, r)
+            | Selector::Descendant(_, r)
+            | Selector::Both(_, r) => r.unfinished(),
+            _ => None,
+        }
+    }
+
+    //a selector list's complex selectors.
+    pub fn alternatives(&self) -> Vec<&Selector> {
+        match self {
+            Selector::Both(l, r) => [l.alternatives(), r.alternatives()].concat(),
+            s => vec![s],
+        }
+    }
+
+    //a complex selector's compounds left to right, each with the combinator that comes after
+    //it. the last one's the element being matched.
+    pub fn compounds(&self) -> Vec<(&Selector, Option<Combinator>)> {
+        let (l, r, combinator) = match self {
+            Selector::Child(l, r) => (l, r, Combinator::Child),
+            Selector::NextSibling(l, r) => (l, r, Combinator::NextSibling),
+            Selector::Descendant(l, r) => (l, r, Combinator::Descendant),
+            s => return vec![(s, None)],
+        };
+        let mut compounds = vec![(&**l, Some(combinator))];
+        compounds.extend(r.compounds());
+        compounds
+    }
+
+    //gives the trailing . or : its name.
+    fn finish(&self, name: String) -> Result<Selector, CSSError> {
+        let finish = |r: &Selector| r.finish(name.clone()).map(Box::new);
+        Ok(match self {
+            Selector::Pending('.') => Selector::Class(name),
+            Selector::Pending(_) => match PseudoClass::from_name(&name) {
+                Some(state) => Selector::State(state),
+                None => do yeet CSSError::InvalidSelector(Component::Token(CSSToken::Ident(name))),
+            },
+            Selector::Compound(l, r) => Selector::Compound(l.clone(), finish(r)?),
+            Selector::Child(l, r) => Selector::Child(l.clone(), finish(r)?),
+            Selector::NextSibling(l, r) => Selector::NextSibling(l.clone(), finish(r)?),
+            Selector::Descendant(l, r) => Selector::Descendant(l.clone(), finish(r)?),
+            Selector::Both(l, r) => Selector::Both(l.clone(), finish(r)?),
+            s => s.clone(),
+        })
+    }
+
+    //trailing whitespace looks like the start of a descendant combinator until something
+    //comes after it.
+    fn trim(&mut self) {
+        match self {
+            Selector::Descendant(l, r) if matches!(**r, Selector::Placeheld) => *self = *l.clone(),
+            Selector::Child(_, r)
+            | Selector::NextSibling(_, r)
+            | Selector::Descendant(_, r)
+            | Selector::Both(_, r) => r.trim(),
+            _ => {}
+        }
+    }
+
+    pub fn append(&mut self, component: Component) -> Result<(), CSSError> {
         let new_self: Selector;
         match self {
             Selector::Placeheld => {
@@ -649,25 +1769,47 @@ impl Selector {
                             }
                             CSSToken::Ident(s) => {
                                 //TODO:
-                                //Id selectors
                                 //attribute selectors
-                                //pseudo-classes
-                                //class selectors
+                                //pseudo-classes that aren't user actions
                                 new_self = Selector::Type(s);
                             }
                             CSSToken::Delim(Char::Char('*')) => {
                                 new_self = Selector::Universal;
                             }
-                            _ => panic!(),
+                            CSSToken::Hash(id, HashType::Id) => {
+                                new_self = Selector::Id(id);
+                            }
+                            CSSToken::Delim(Char::Char('.')) => {
+                                new_self = Selector::Pending('.');
+                            }
+                            CSSToken::Colon => {
+                                new_self = Selector::Pending(':');
+                            }
+                            t => do yeet CSSError::InvalidSelector(Component::Token(t)),
                         }
                     }
-                    _ => panic!(),
+                    c => do yeet CSSError::InvalidSelector(c),
                 }
             }
-            Selector::Type(_) => match component {
+            //the name has to come straight after the . or :.
+            _ if self.unfinished().is_some() => match component {
+                Component::Token(CSSToken::Ident(name)) => {
+                    new_self = self.finish(name)?;
+                }
+                c => do yeet CSSError::InvalidSelector(c),
+            },
+            Selector::Type(_)
+            | Selector::Universal
+            | Selector::Id(_)
+            | Selector::Class(_)
+            | Selector::State(_)
+            | Selector::Compound(..) => match component {
                 Component::Token(t) => match t {
                     CSSToken::Whitespace => {
-                        new_self = self.clone();
+                        new_self = Selector::Descendant(
+                            Box::new(self.clone()),
+                            Box::new(Selector::Placeheld),
+                        );
                     }
                     CSSToken::Delim(Char::Char('>')) => {
                         new_self =
@@ -683,34 +1825,80 @@ impl Selector {
                         new_self =
                             Selector::Both(Box::new(self.clone()), Box::new(Selector::Placeheld));
                     }
-                    _ => panic!("{:?}", t),
+                    //a type or * can only come first in a compound.
+                    CSSToken::Hash(id, HashType::Id) => {
+                        new_self =
+                            Selector::Compound(Box::new(self.clone()), Box::new(Selector::Id(id)));
+                    }
+                    CSSToken::Delim(Char::Char('.')) => {
+                        new_self = Selector::Compound(
+                            Box::new(self.clone()),
+                            Box::new(Selector::Pending('.')),
+                        );
+                    }
+                    CSSToken::Colon => {
+                        new_self = Selector::Compound(
+                            Box::new(self.clone()),
+                            Box::new(Selector::Pending(':')),
+                        );
+                    }
+                    t => do yeet CSSError::InvalidSelector(Component::Token(t)),
                 },
-                _ => panic!(),
+                c => do yeet CSSError::InvalidSelector(c),
+            },
+            //whitespace next to another combinator isn't a descendant combinator after all.
+            Selector::Descendant(l, r) if matches!(**r, Selector::Placeheld) => match component {
+                Component::Token(CSSToken::Delim(Char::Char('>'))) => {
+                    new_self = Selector::Child(l.clone(), r.clone());
+                }
+                Component::Token(CSSToken::Delim(Char::Char('+'))) => {
+                    new_self = Selector::NextSibling(l.clone(), r.clone());
+                }
+                Component::Token(CSSToken::Comma) => {
+                    new_self = Selector::Both(l.clone(), r.clone());
+                }
+                component => {
+                    let mut new_r = r.clone();
+                    new_r.append(component)?;
+                    new_self = Selector::Descendant(l.clone(), new_r);
+                }
             },
+            //a comma ends the whole complex selector, not just the bit after the last
+            //combinator.
+            Selector::Child(..) | Selector::NextSibling(..) | Selector::Descendant(..)
+                if component == Component::Token(CSSToken::Comma) =>
+            {
+                self.trim();
+                new_self = Selector::Both(Box::new(self.clone()), Box::new(Selector::Placeheld));
+            }
             Selector::Child(l, r) => {
                 let new_l = l.clone();
                 let mut new_r = r.clone();
-                new_r.append(component);
+                new_r.append(component)?;
                 new_self = Selector::Child(new_l, new_r);
             }
+            Selector::Descendant(l, r) => {
+                let new_l = l.clone();
+                let mut new_r = r.clone();
+                new_r.append(component)?;
+                new_self = Selector::Descendant(new_l, new_r);
+            }
             Selector::NextSibling(l, r) => {
                 let new_l = l.clone();
                 let mut new_r = r.clone();
-                new_r.append(component);
+                new_r.append(component)?;
                 new_self = Selector::NextSibling(new_l, new_r);
             }
             Selector::Both(l, r) => {
                 let new_l = l.clone();
                 let mut new_r = r.clone();
-                new_r.append(component);
+                new_r.append(component)?;
                 new_self = Selector::Both(new_l, new_r);
             }
-            Selector::Universal => {
-                new_self = self.clone();
-            } //probably just the wind...
-            _ => panic!(),
+            Selector::Pending(_) => unreachable!(), //finished off above
         }
         *self = new_self;
+        Ok(())
     }
 }
 
@@ -719,6 +1907,7 @@ pub struct DeclarationBuilder {
     kind: String,
     value: Vec<Component>,
     level: StyleLevel,
+    important: bool,
 }
 
 impl DeclarationBuilder {
@@ -727,6 +1916,7 @@ impl DeclarationBuilder {
             kind,
             value: vec![],
             level: StyleLevel::default(),
+            important: false,
         }
     }
 
@@ -742,57 +1932,129 @@ impl DeclarationBuilder {
         self.level = level;
     }
 
+    pub fn set_important(&mut self, important: bool) {
+        self.important = important;
+    }
+
+    //whether build_all() would turn this into something other than Unknown.
+    pub fn is_known(kind: &str) -> bool {
+        PropertyInfo::find(kind).is_some() || ShorthandInfo::find(kind).is_some()
+    }
+
+    //the names a declaration of this would end up setting.
+    pub fn longhands(kind: &str) -> Vec<String> {
+        match ShorthandInfo::find(kind) {
+            Some(shorthand) => shorthand.longhands.iter().map(|l| l.to_string()).collect(),
+            None => vec![kind.to_string()],
+        }
+    }
+
+    //build(), but shorthands come out as a declaration for each of their longhands.
+    pub fn build_all(self) -> Result<Vec<Declaration>, CSSError> {
+        let Some(shorthand) = ShorthandInfo::find(&self.kind) else {
+            return Ok(vec![self.build()?]);
+        };
+        let declaration = |name: &str, kind| Declaration {
+            important: self.important,
+            kind,
+            level: self.level,
+            name: name.to_string(),
+            layer: ATTACHED,
+        };
+        //the longhands can't be split out until the variables are in, so they all hang on to
+        //the whole thing and pick their bit out once they are.
+        if contains_var(&self.value) {
+            return Ok(shorthand
+                .longhands
+                .iter()
+                .map(|longhand| {
+                    let kind =
+                        DeclarationKind::WithVariables(self.kind.clone(), self.value.clone());
+                    declaration(longhand, kind)
+                })
+                .collect());
+        }
+        if let Some(keyword) = CSSWideKeyword::parse(&self.value) {
+            return Ok(shorthand
+                .longhands
+                .iter()
+                .map(|longhand| declaration(longhand, DeclarationKind::WideKeyword(keyword)))
+                .collect());
+        }
+        let Some(values) = (shorthand.expand)(&self.value) else {
+            do yeet CSSError::InvalidValue(self.value, self.kind);
+        };
+        let mut declarations = Vec::with_capacity(values.len());
+        for (longhand, value) in shorthand.longhands.iter().zip(values) {
+            let mut builder = Self::from_kind(longhand.to_string());
+            builder.value = value;
+            let built = match builder.build() {
+                Ok(built) => built,
+                Err(_) => do yeet CSSError::InvalidValue(self.value, self.kind),
+            };
+            declarations.push(declaration(longhand, built.kind));
+        }
+        Ok(declarations)
+    }
+
     pub fn build(self) -> Result<Declaration, CSSError> {
         //TODO: So much
-        let kind = match self.kind.as_str() {
-            "color" => DeclarationKind::Color(Colour::from_components(self.value)),
-            "display" => DeclarationKind::Display(Display::from_components(self.value)),
-            "font-size" => DeclarationKind::FontSize(FontSize::from_components(self.value)),
-            "font-weight" => DeclarationKind::FontWeight(FontWeight::from_components(self.value)),
-            "text-align" => DeclarationKind::TextAlign(TextAlign::from_components(self.value)),
-            "background-color" => {
-                DeclarationKind::BackgroundColor(Colour::from_components(self.value))
-            }
-            "width" => DeclarationKind::Width(Dimensionality::from_components(self.value)),
-            "height" => DeclarationKind::Height(Dimensionality::from_components(self.value)),
-            "padding-top" => {
-                DeclarationKind::PaddingTop(Dimensionality::from_components(self.value))
-            }
-            "padding-bottom" => {
-                DeclarationKind::PaddingBottom(Dimensionality::from_components(self.value))
-            }
-            "padding-left" => {
-                DeclarationKind::PaddingLeft(Dimensionality::from_components(self.value))
-            }
-            "padding-right" => {
-                DeclarationKind::PaddingRight(Dimensionality::from_components(self.value))
-            }
-            "margin-top" => DeclarationKind::MarginTop(Dimensionality::from_components(self.value)),
-            "margin-bottom" => {
-                DeclarationKind::MarginBottom(Dimensionality::from_components(self.value))
-            }
-            "margin-left" => {
-                DeclarationKind::MarginLeft(Dimensionality::from_components(self.value))
-            }
-            "margin-right" => {
-                DeclarationKind::MarginRight(Dimensionality::from_components(self.value))
-            }
-            "font-family" => DeclarationKind::FontFamily(FontFamily::from_components(self.value)),
-            _ => DeclarationKind::Unknown(self.kind, self.value),
+        //custom properties are case sensitive and never get looked at until something var()s
+        //them. anything else using var() has to wait until we know what the variables are.
+        if self.kind.starts_with("--") {
+            return Ok(Declaration {
+                important: self.important,
+                kind: DeclarationKind::Custom(self.kind.clone(), self.value),
+                level: self.level,
+                name: self.kind,
+                layer: ATTACHED,
+            });
+        }
+        if contains_var(&self.value) {
+            return Ok(Declaration {
+                important: self.important,
+                kind: DeclarationKind::WithVariables(self.kind.clone(), self.value),
+                level: self.level,
+                name: self.kind,
+                layer: ATTACHED,
+            });
+        }
+        let Some(property) = PropertyInfo::find(&self.kind) else {
+            return Ok(Declaration {
+                important: self.important,
+                kind: DeclarationKind::Unknown(self.kind.clone(), self.value),
+                level: self.level,
+                name: self.kind,
+                layer: ATTACHED,
+            });
+        };
+        let kind = match CSSWideKeyword::parse(&self.value) {
+            Some(keyword) => DeclarationKind::WideKeyword(keyword),
+            None => match (property.parse)(self.value.clone()) {
+                Some(kind) => kind,
+                None => do yeet CSSError::InvalidValue(self.value, self.kind),
+            },
         };
         Ok(Declaration {
-            important: false,
+            important: self.important,
             kind,
             level: self.level,
+            name: self.kind,
+            layer: ATTACHED,
         })
     }
 }
 
+//the layer rank of anything that never went through a sheet's layers, i.e. style="".
+const ATTACHED: u32 = u32::MAX;
+
 #[derive(Debug, Clone)]
 pub struct Declaration {
     important: bool,
     kind: DeclarationKind,
     level: StyleLevel,
+    name: String,
+    layer: u32, //filled in by the cascader, see cascade_rank
 }
 
 impl PartialEq for Declaration {
@@ -805,42 +2067,104 @@ impl PartialEq for Declaration {
 
 impl PartialOrd for Declaration {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(
-            match (self.important, other.important, self.level, other.level) {
-                (_, _, StyleLevel::UserAgent, StyleLevel::UserAgent) => Ordering::Equal,
-                (_, _, StyleLevel::User, StyleLevel::User) => Ordering::Equal,
-                (_, _, StyleLevel::Author, StyleLevel::Author) => Ordering::Equal,
-                (true, false, _, _) => Ordering::Greater,
-                (false, true, _, _) => Ordering::Less,
-                (true, true, StyleLevel::UserAgent, StyleLevel::User) => Ordering::Greater,
-                (true, true, StyleLevel::User, StyleLevel::UserAgent) => Ordering::Less,
-                (true, true, StyleLevel::UserAgent, StyleLevel::Author) => Ordering::Greater,
-                (true, true, StyleLevel::Author, StyleLevel::UserAgent) => Ordering::Less,
-                (true, true, StyleLevel::User, StyleLevel::Author) => Ordering::Greater,
-                (true, true, StyleLevel::Author, StyleLevel::User) => Ordering::Less,
-                (false, false, StyleLevel::Author, StyleLevel::User) => Ordering::Greater,
-                (false, false, StyleLevel::User, StyleLevel::Author) => Ordering::Greater,
-                (false, false, StyleLevel::Author, StyleLevel::UserAgent) => Ordering::Greater,
-                (false, false, StyleLevel::UserAgent, StyleLevel::Author) => Ordering::Less,
-                (false, false, StyleLevel::User, StyleLevel::UserAgent) => Ordering::Greater,
-                (false, false, StyleLevel::UserAgent, StyleLevel::User) => Ordering::Less,
-            },
-        )
+        Some(self.cascade_rank().cmp(&other.cascade_rank()))
+    }
+}
+
+impl fmt::Display for Declaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.value_text())?;
+        if self.important {
+            f.write_str(" !important")?;
+        }
+        Ok(())
+    }
+}
+
+impl Declaration {
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &DeclarationKind {
+        &self.kind
+    }
+
+    pub fn important(&self) -> bool {
+        self.important
+    }
+
+    pub fn level(&self) -> StyleLevel {
+        self.level
+    }
+
+    //the value on its own, the way it'd be written in a sheet.
+    pub fn value_text(&self) -> String {
+        match &self.kind {
+            DeclarationKind::Unknown(_, value)
+            | DeclarationKind::Custom(_, value)
+            | DeclarationKind::WithVariables(_, value) => Component::serialize(value),
+            DeclarationKind::WideKeyword(keyword) => keyword.to_string(),
+            kind => PropertyInfo::find(&self.name)
+                .and_then(|p| (p.serialize_declared)(kind))
+                .unwrap_or_default(),
+        }
+    }
+
+    //where this sits in the cascade's origin/importance order, then its layer's, higher wins.
+    //important flips both around. style="" isn't in a sheet, so it beats every layer whether
+    //it's important or not.
+    fn cascade_rank(&self) -> (u8, u32) {
+        let origin = match (self.important, self.level) {
+            (false, StyleLevel::UserAgent) => 0,
+            (false, StyleLevel::User) => 1,
+            (false, StyleLevel::Author) => 2,
+            (true, StyleLevel::Author) => 3,
+            (true, StyleLevel::User) => 4,
+            (true, StyleLevel::UserAgent) => 5,
+        };
+        let layer = match self.important {
+            _ if self.layer == ATTACHED => ATTACHED,
+            false => self.layer,
+            true => ATTACHED - 1 - self.layer,
+        };
+        (origin, layer)
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum DeclarationKind {
     Unknown(String, Vec<Component>),
+    Custom(String, Vec<Component>),        //--whatever
+    WithVariables(String, Vec<Component>), //has a var() in it, gets built for real later
+    WideKeyword(CSSWideKeyword),           //inherit, initial and friends
     Color(CSSValue<Colour>), // as much as i'd like to use the right spelling of colour here, it
     // should be this way to be idiomatic.
     Display(CSSValue<Display>),
     FontSize(CSSValue<FontSize>),
     FontWeight(CSSValue<FontWeight>),
+    FontStyle(CSSValue<FontStyle>),
     TextAlign(CSSValue<TextAlign>),
     BackgroundColor(CSSValue<Colour>),
     Width(CSSValue<Dimensionality>),
     Height(CSSValue<Dimensionality>),
+    MinWidth(CSSValue<Dimensionality>),
+    MinHeight(CSSValue<Dimensionality>),
+    MaxWidth(CSSValue<MaxSize>),
+    MaxHeight(CSSValue<MaxSize>),
+    BoxSizing(CSSValue<BoxSizing>),
+    Position(CSSValue<Position>),
+    Float(CSSValue<Float>),
+    Clear(CSSValue<Clear>),
+    Overflow(CSSValue<Overflow>),
+    Top(CSSValue<Dimensionality>),
+    Right(CSSValue<Dimensionality>),
+    Bottom(CSSValue<Dimensionality>),
+    Left(CSSValue<Dimensionality>),
+    ZIndex(CSSValue<ZIndex>),
+    Opacity(CSSValue<Opacity>),
+    Isolation(CSSValue<Isolation>),
+    Transform(CSSValue<Transform>),
     PaddingTop(CSSValue<Dimensionality>),
     PaddingBottom(CSSValue<Dimensionality>),
     PaddingLeft(CSSValue<Dimensionality>),
@@ -849,7 +2173,31 @@ pub enum DeclarationKind {
     MarginBottom(CSSValue<Dimensionality>),
     MarginLeft(CSSValue<Dimensionality>),
     MarginRight(CSSValue<Dimensionality>),
+    BorderTopStyle(CSSValue<BorderStyle>),
+    BorderRightStyle(CSSValue<BorderStyle>),
+    BorderBottomStyle(CSSValue<BorderStyle>),
+    BorderLeftStyle(CSSValue<BorderStyle>),
+    BorderTopWidth(CSSValue<BorderWidth>),
+    BorderRightWidth(CSSValue<BorderWidth>),
+    BorderBottomWidth(CSSValue<BorderWidth>),
+    BorderLeftWidth(CSSValue<BorderWidth>),
+    BorderTopColor(CSSValue<Colour>),
+    BorderRightColor(CSSValue<Colour>),
+    BorderBottomColor(CSSValue<Colour>),
+    BorderLeftColor(CSSValue<Colour>),
+    BorderTopLeftRadius(CSSValue<CornerRadius>),
+    BorderTopRightRadius(CSSValue<CornerRadius>),
+    BorderBottomRightRadius(CSSValue<CornerRadius>),
+    BorderBottomLeftRadius(CSSValue<CornerRadius>),
+    BoxShadow(CSSValue<BoxShadow>),
+    OutlineStyle(CSSValue<OutlineStyle>),
+    OutlineWidth(CSSValue<BorderWidth>),
+    OutlineColor(CSSValue<Colour>),
+    OutlineOffset(CSSValue<Dimensionality>),
+    ContainerType(CSSValue<ContainerType>),
+    ContainerName(CSSValue<ContainerName>),
     FontFamily(CSSValue<FontFamily>),
+    VerticalAlign(CSSValue<VerticalAlign>),
 }
 
 #[derive(Default, Debug, Clone)]
@@ -858,10 +2206,28 @@ pub struct CSSProps {
     pub display: CSSValue<Display>,
     pub font_size: CSSValue<FontSize>,
     pub font_weight: CSSValue<FontWeight>,
+    pub font_style: CSSValue<FontStyle>,
     pub text_align: CSSValue<TextAlign>,
     pub background_color: CSSValue<Colour>,
     pub width: CSSValue<Dimensionality>,
     pub height: CSSValue<Dimensionality>,
+    pub min_width: CSSValue<Dimensionality>,
+    pub min_height: CSSValue<Dimensionality>,
+    pub max_width: CSSValue<MaxSize>,
+    pub max_height: CSSValue<MaxSize>,
+    pub box_sizing: CSSValue<BoxSizing>,
+    pub position: CSSValue<Position>,
+    pub float: CSSValue<Float>,
+    pub clear: CSSValue<Clear>,
+    pub overflow: CSSValue<Overflow>,
+    pub top: CSSValue<Dimensionality>,
+    pub right: CSSValue<Dimensionality>,
+    pub bottom: CSSValue<Dimensionality>,
+    pub left: CSSValue<Dimensionality>,
+    pub z_index: CSSValue<ZIndex>,
+    pub opacity: CSSValue<Opacity>,
+    pub isolation: CSSValue<Isolation>,
+    pub transform: CSSValue<Transform>,
     pub padding_top: CSSValue<Dimensionality>,
     pub padding_bottom: CSSValue<Dimensionality>,
     pub padding_left: CSSValue<Dimensionality>,
@@ -870,7 +2236,32 @@ pub struct CSSProps {
     pub margin_bottom: CSSValue<Dimensionality>,
     pub margin_left: CSSValue<Dimensionality>,
     pub margin_right: CSSValue<Dimensionality>,
+    pub border_top_style: CSSValue<BorderStyle>,
+    pub border_right_style: CSSValue<BorderStyle>,
+    pub border_bottom_style: CSSValue<BorderStyle>,
+    pub border_left_style: CSSValue<BorderStyle>,
+    pub border_top_width: CSSValue<BorderWidth>,
+    pub border_right_width: CSSValue<BorderWidth>,
+    pub border_bottom_width: CSSValue<BorderWidth>,
+    pub border_left_width: CSSValue<BorderWidth>,
+    pub border_top_color: CSSValue<Colour>,
+    pub border_right_color: CSSValue<Colour>,
+    pub border_bottom_color: CSSValue<Colour>,
+    pub border_left_color: CSSValue<Colour>,
+    pub border_top_left_radius: CSSValue<CornerRadius>,
+    pub border_top_right_radius: CSSValue<CornerRadius>,
+    pub border_bottom_right_radius: CSSValue<CornerRadius>,
+    pub border_bottom_left_radius: CSSValue<CornerRadius>,
+    pub box_shadow: CSSValue<BoxShadow>,
+    pub outline_style: CSSValue<OutlineStyle>,
+    pub outline_width: CSSValue<BorderWidth>,
+    pub outline_color: CSSValue<Colour>,
+    pub outline_offset: CSSValue<Dimensionality>,
+    pub container_type: CSSValue<ContainerType>,
+    pub container_name: CSSValue<ContainerName>,
     pub font_family: CSSValue<FontFamily>,
+    pub vertical_align: CSSValue<VerticalAlign>,
+    pub custom: HashMap<String, Vec<Component>>, //already has every var() substituted
 }
 
 impl CSSProps {
@@ -886,13 +2277,67 @@ impl CSSProps {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Component {
-    Block(Block),
-    Function(),
+    Block(SimpleBlock),
+    Function(String, Vec<Component>),
     Token(CSSToken),
 }
 
+impl Component {
+    //the separate values in something like a shorthand are whatever's between the whitespace.
+    pub fn split_values(components: &[Component]) -> Vec<Vec<Component>> {
+        components
+            .split(|c| matches!(c, Component::Token(CSSToken::Whitespace)))
+            .filter(|value| !value.is_empty())
+            .map(<[Component]>::to_vec)
+            .collect()
+    }
+
+    //back to plain tokens, brackets and all, for the things that would rather look at a flat
+    //list than walk the tree.
+    pub fn flatten(components: &[Component]) -> Vec<CSSToken> {
+        let mut tokens = Vec::with_capacity(components.len());
+        for component in components {
+            component.flatten_into(&mut tokens);
+        }
+        tokens
+    }
+
+    pub fn serialize(components: &[Component]) -> String {
+        let tokens = Self::flatten(components);
+        let mut out = String::new();
+        for (i, token) in tokens.iter().enumerate() {
+            if i > 0 && CSSToken::would_merge(&tokens[i - 1], token) {
+                out.push_str("/**/");
+            }
+            out.push_str(&token.to_string());
+        }
+        out
+    }
+
+    fn flatten_into(&self, tokens: &mut Vec<CSSToken>) {
+        match self {
+            Component::Token(t) => tokens.push(t.clone()),
+            Component::Function(name, arguments) => {
+                tokens.push(CSSToken::Function(name.clone()));
+                for argument in arguments {
+                    argument.flatten_into(tokens);
+                }
+                tokens.push(CSSToken::ParenClose);
+            }
+            Component::Block(block) => {
+                let (open, close) = block.kind.tokens();
+                tokens.push(open);
+                for component in &block.value {
+                    component.flatten_into(tokens);
+                }
+                tokens.push(close);
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum CSSToken {
     Whitespace,
@@ -902,11 +2347,93 @@ pub enum CSSToken {
     Semicolon,
     CurlyOpen,
     CurlyClose,
+    ParenOpen,
+    ParenClose,
+    AtKeyword(String),
+    Function(String),
+    String(String),
+    Url(String),
+    UnicodeRange(u32, u32),
     Comma,
     Number(CSSNumber),
+    Dimension(Numeric, String), //a number with a unit we don't know about
+    Hash(String, HashType),
+    BadString,
+    BadUrl,
+    BracketOpen,
+    BracketClose,
+    CDO, //<!--
+    CDC, //-->
     EOF,
 }
 
+impl CSSToken {
+    //pairs that would come back as one token if they were written out right next to each
+    //other, which need an empty comment between them. straight from the table in css syntax 3.
+    fn would_merge(first: &CSSToken, second: &CSSToken) -> bool {
+        use CSSToken as T;
+        let delim = |t: &CSSToken, c: char| matches!(t, T::Delim(Char::Char(d)) if *d == c);
+        let identish = matches!(second, T::Ident(_) | T::Function(_) | T::Url(_) | T::BadUrl);
+        let numeric = matches!(second, T::Number(_) | T::Dimension(..));
+        match first {
+            T::Ident(_) => {
+                identish || numeric || delim(second, '-') || matches!(second, T::CDC | T::ParenOpen)
+            }
+            T::AtKeyword(_) | T::Hash(..) | T::Dimension(..) | T::Number(CSSNumber::Unit(..)) => {
+                identish || numeric || delim(second, '-') || matches!(second, T::CDC)
+            }
+            T::Number(CSSNumber::Number(_)) => identish || numeric || delim(second, '%'),
+            t if delim(t, '#') || delim(t, '-') => identish || numeric || delim(second, '-'),
+            t if delim(t, '@') => identish || delim(second, '-') || matches!(second, T::CDC),
+            t if delim(t, '.') || delim(t, '+') => numeric,
+            t if delim(t, '/') => delim(second, '*'),
+            _ => false,
+        }
+    }
+}
+
+//good enough to get the same tokens back out when it's tokenized again, not escaped properly.
+impl fmt::Display for CSSToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CSSToken::Whitespace => f.write_str(" "),
+            CSSToken::Delim(Char::Char(c)) => write!(f, "{}", c),
+            CSSToken::Delim(Char::Eof) | CSSToken::EOF => Ok(()),
+            CSSToken::Ident(i) => f.write_str(i),
+            CSSToken::Colon => f.write_str(":"),
+            CSSToken::Semicolon => f.write_str(";"),
+            CSSToken::CurlyOpen => f.write_str("{"),
+            CSSToken::CurlyClose => f.write_str("}"),
+            CSSToken::ParenOpen => f.write_str("("),
+            CSSToken::ParenClose => f.write_str(")"),
+            CSSToken::AtKeyword(k) => write!(f, "@{}", k),
+            CSSToken::Function(name) => write!(f, "{}(", name),
+            CSSToken::String(s) => {
+                write!(f, "\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            CSSToken::Url(u) => write!(f, "url({})", u),
+            CSSToken::UnicodeRange(start, end) if start == end => write!(f, "U+{:X}", start),
+            CSSToken::UnicodeRange(start, end) => write!(f, "U+{:X}-{:X}", start, end),
+            CSSToken::Comma => f.write_str(","),
+            CSSToken::Number(n) => n.fmt(f),
+            CSSToken::Dimension(n, unit) => write!(f, "{}{}", n, unit),
+            CSSToken::Hash(h, _) => write!(f, "#{}", h),
+            CSSToken::BadString | CSSToken::BadUrl => Ok(()),
+            CSSToken::BracketOpen => f.write_str("["),
+            CSSToken::BracketClose => f.write_str("]"),
+            CSSToken::CDO => f.write_str("<!--"),
+            CSSToken::CDC => f.write_str("-->"),
+        }
+    }
+}
+
+//whether a hash could be used as an id selector, i.e. whether what follows the # is an ident.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum HashType {
+    Id,
+    Unrestricted,
+}
+
 #[derive(Debug, Clone, PartialEq, PartialOrd, Copy)]
 pub enum CSSNumber {
     Number(Numeric),
@@ -922,6 +2449,16 @@ impl CSSNumber {
     }
 }
 
+impl fmt::Display for CSSNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CSSNumber::Number(n) => write!(f, "{}", n),
+            CSSNumber::Percentage(n) => write!(f, "{}%", n),
+            CSSNumber::Unit(n, u) => write!(f, "{}{}", n, u),
+        }
+    }
+}
+
 impl Default for CSSNumber {
     fn default() -> Self {
         Self::Number(Numeric::Integer(0))
@@ -934,6 +2471,7 @@ pub enum Unit {
     Px,
     Cm,
     Mm,
+    Q,
     In,
     Pt,
     Pc,
@@ -945,6 +2483,10 @@ pub enum Unit {
     Vh,
     Vmin,
     Vmax,
+    Dpi,
+    Dpcm,
+    Dppx,
+    X,
 }
 
 impl Unit {
@@ -953,6 +2495,7 @@ impl Unit {
             "px" => Unit::Px,
             "cm" => Unit::Cm,
             "mm" => Unit::Mm,
+            "q" => Unit::Q,
             "in" => Unit::In,
             "pt" => Unit::Pt,
             "pc" => Unit::Pc,
@@ -964,17 +2507,58 @@ impl Unit {
             "vh" => Unit::Vh,
             "vmin" => Unit::Vmin,
             "vmax" => Unit::Vmax,
+            "dpi" => Unit::Dpi,
+            "dpcm" => Unit::Dpcm,
+            "dppx" => Unit::Dppx,
+            "x" => Unit::X,
             _ => return None,
         })
     }
 }
 
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Unit::Px => "px",
+            Unit::Cm => "cm",
+            Unit::Mm => "mm",
+            Unit::Q => "q",
+            Unit::In => "in",
+            Unit::Pt => "pt",
+            Unit::Pc => "pc",
+            Unit::Em => "em",
+            Unit::Ex => "ex",
+            Unit::Ch => "ch",
+            Unit::Rem => "rem",
+            Unit::Vw => "vw",
+            Unit::Vh => "vh",
+            Unit::Vmin => "vmin",
+            Unit::Vmax => "vmax",
+            Unit::Dpi => "dpi",
+            Unit::Dpcm => "dpcm",
+            Unit::Dppx => "dppx",
+            Unit::X => "x",
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum CSSSource {
     Raw(String),
     URL(Url),
+    Fetched(Url, String), //a URL that's already been downloaded
     Local(PathBuf),
     Pretokenized(Vec<CSSToken>),
+    Media(Box<CSSSource>, MediaQueryList), //from a <link> with a media attribute
+}
+
+impl CSSSource {
+    fn split_media(self) -> (Self, Option<MediaQueryList>) {
+        match self {
+            Self::Media(source, media) => (*source, Some(media)),
+            source => (source, None),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Copy)]
@@ -993,6 +2577,15 @@ impl Numeric {
     }
 }
 
+impl fmt::Display for Numeric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Integer(i) => write!(f, "{}", i),
+            Self::Number(n) => write!(f, "{}", n),
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct NumberRep {
     sign: Sign,
@@ -1008,7 +2601,7 @@ impl NumberRep {
     }
 
     pub fn set_e_sign(&mut self, char: char) {
-	self.sign = Sign::from_char(char);
+	self.e_sign = Sign::from_char(char);
     }
 
     pub fn append_to_integer(&mut self, char: char) {
@@ -1080,6 +2673,16 @@ impl<T: Property + Default + Clone> CSSValue<T> {
     }
 }
 
+impl<T: Property + Default + Clone> fmt::Display for CSSValue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inherit => f.write_str("inherit"),
+            Self::Initial => f.write_str("initial"),
+            Self::Value(v) => v.fmt(f),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1093,6 +2696,131 @@ mod tests {
 	assert_eq!(tokenizer.source, String::from("\u{000A}shmrmep\u{000A}shmrospo\u{000A}\u{FFFD}"));
     }
 
+    #[test]
+    fn test_inline_style_important() {
+        let rule = CSSParser::parse_inline_style(&String::from(
+            "color: red ! important; display: block; color: blue; width: 10px !IMPORTANT",
+        ))
+        .unwrap();
+        let Block::Declarations(declarations) = rule.value else {
+            panic!();
+        };
+        assert_eq!(declarations.len(), 3);
+        for declaration in declarations.values() {
+            assert_eq!(declaration.level, StyleLevel::Author);
+            match &declaration.kind {
+                DeclarationKind::Color(CSSValue::Value(c)) => {
+                    assert!(declaration.important);
+                    assert_eq!(c.real.red, 0xFF);
+                }
+                DeclarationKind::Width(_) => assert!(declaration.important),
+                DeclarationKind::Display(_) => assert!(!declaration.important),
+                a => panic!("{:?}", a),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_error_recovery() {
+        let mut parser = CSSParser::default();
+        parser.push_raw_css(&String::from(
+            "p { color: red; ; 5px; width 10px; display: block }
+            .shmeep[x] { color: blue; }
+            @shmeepframes whatever { a { b: c } }
+            div { shmidth: calc(1px + (2px)); color: green",
+        ));
+        let rules = parser.parse_stylesheets().unwrap().remove(0).rules;
+        let declarations = rules
+            .iter()
+            .filter_map(|r| match (&r.prelude, &r.value) {
+                (Prelude::Selector(_), Block::Declarations(d)) => Some(d),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        //the class selector isn't something we understand, so only p and div are left.
+        assert_eq!(declarations.len(), 2);
+        assert!(declarations[0].contains_key("color") && declarations[0].contains_key("display"));
+        assert!(!declarations[0].contains_key("width"));
+        //the sheet ending without closing the block still keeps what was in it.
+        assert!(declarations[1].contains_key("color"));
+        let DeclarationKind::Unknown(_, ref value) = declarations[1]["shmidth"].kind else {
+            panic!();
+        };
+        let [Component::Function(name, arguments)] = value.as_slice() else {
+            panic!("{:?}", value);
+        };
+        assert_eq!(name, "calc");
+        assert!(
+            matches!(arguments.last(), Some(Component::Block(b)) if b.kind == BlockKind::Paren)
+        );
+    }
+
+    #[test]
+    fn test_cssom() {
+        let mut parser = CSSParser::default();
+        parser.push_raw_css(&String::from(
+            "@import url(a.css) screen;
+            p>a{width:calc(100% - 2em);color:red!important}
+            @media screen and (min-width: 400px) { p { display: inline-block } }",
+        ));
+        let mut style = parser.parse_stylesheets().unwrap().remove(0);
+        style.set_level(StyleLevel::User);
+        assert_eq!(
+            style.to_string(),
+            "@import url(\"a.css\") screen;
+p > a { color: rgb(255, 0, 0) !important; width: calc(100% - 2em); }
+@media screen and (width >= 400px) {
+  p { display: inline-block; }
+}"
+        );
+
+        //imports have to stay at the front.
+        let rule = String::from("div, * { }");
+        assert!(matches!(
+            style.insert_rule(&[], &rule, 0),
+            Err(CSSError::RuleNotAllowed)
+        ));
+        let import = String::from("@import 'b.css';");
+        assert!(matches!(
+            style.insert_rule(&[], &import, 2),
+            Err(CSSError::RuleNotAllowed)
+        ));
+        assert!(style
+            .insert_rule(&[], &String::from("p {} q {}"), 1)
+            .is_err());
+        assert_eq!(style.insert_rule(&[2], &rule, 1).unwrap(), 1);
+        assert_eq!(style.rule(&[2, 1]).unwrap().to_string(), "div, * { }");
+
+        style
+            .set_declaration(&[2, 1], "Margin-Top", " 5px ", false)
+            .unwrap();
+        style
+            .set_declaration(&[2, 1], "--shmeep", "1px +2px", true)
+            .unwrap();
+        assert!(style
+            .set_declaration(&[2, 1], "shmargin", "5px", false)
+            .is_err());
+        assert!(style
+            .set_declaration(&[2, 1], "width", "banana", false)
+            .is_err());
+        let declarations = style.rule(&[2, 1]).unwrap().declarations();
+        assert_eq!(declarations[0].to_string(), "--shmeep: 1px 2px !important");
+        assert_eq!(declarations[1].value_text(), "5px");
+        assert!(declarations.iter().all(|d| d.level() == StyleLevel::User));
+        style
+            .set_declaration(&[2, 1], "margin-top", "", false)
+            .unwrap();
+        assert!(style.remove_declaration(&[2, 1], "--shmeep").is_some());
+        assert_eq!(style.rule(&[2, 1]).unwrap().to_string(), "div, * { }");
+
+        assert!(style.delete_rule(&[2, 0]).is_ok());
+        assert!(style.delete_rule(&[2, 1]).is_err());
+        assert_eq!(
+            style.rule(&[2]).unwrap().to_string(),
+            "@media screen and (width >= 400px) {\n  div, * { }\n}"
+        );
+    }
+
     #[test]
     fn test_tokenize_ident() {
 	let input = "_shmeep_shmOp_SHMORP";
@@ -1118,6 +2846,40 @@ mod tests {
 				     CSSToken::EOF]);
     }
 
+    #[test]
+    fn test_tokenize_everything_else() {
+        let input = "#shmeep #1a [x] <!-- --> 'a\\62 c' \"bad\n 2em 3shmops url( a\\)b ) url(a b) \\31 x -->";
+        let mut tokenizer = CSSTokenizer::default();
+        tokenizer.load_raw(&String::from(input)).unwrap();
+        let mut result = Vec::new();
+        tokenizer.tokenize(&mut result).unwrap();
+        let result = result
+            .into_iter()
+            .filter(|t| *t != CSSToken::Whitespace)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            result.as_slice(),
+            &[
+                CSSToken::Hash(String::from("shmeep"), HashType::Id),
+                CSSToken::Hash(String::from("1a"), HashType::Unrestricted),
+                CSSToken::BracketOpen,
+                CSSToken::Ident(String::from("x")),
+                CSSToken::BracketClose,
+                CSSToken::CDO,
+                CSSToken::CDC,
+                CSSToken::String(String::from("abc")),
+                CSSToken::BadString,
+                CSSToken::Number(CSSNumber::Unit(Numeric::Integer(2), Unit::Em)),
+                CSSToken::Dimension(Numeric::Integer(3), String::from("shmops")),
+                CSSToken::Url(String::from("a)b")),
+                CSSToken::BadUrl,
+                CSSToken::Ident(String::from("1x")),
+                CSSToken::CDC,
+                CSSToken::EOF,
+            ]
+        );
+    }
+
     #[test]
     fn test_tokenize_floats() {
 	let input = "3.2 +3.2 -3.2 3e2 +3e2 -3e2 .3";
@@ -1,10 +1,19 @@
+use std::fmt;
+
 use colours::Rgba;
-use font_kit::{family_name::FamilyName, font::Font, properties::Properties, source::SystemSource};
 
-use super::{CSSNumber, CSSToken, CSSValue, Component};
-use crate::parser::html::Token;
+use super::{
+    calc::{Calc, CalcKind, LengthContext},
+    colour::parse_colour,
+    CSSNumber, CSSToken, CSSValue, Component, Numeric, Unit,
+};
+use crate::{
+    layout::text::font_set::{FontChain, FontSet},
+    parser::html::Token,
+};
 
-pub trait Property {
+//Display is the value serialized back into css.
+pub trait Property: fmt::Display {
     fn from_components(components: Vec<Component>) -> CSSValue<Self>
     where
         Self: Sized + Default + Clone;
@@ -13,6 +22,7 @@ pub trait Property {
 #[derive(Default, Debug, Clone, Copy)]
 pub struct Colour {
     pub real: Rgba<u8>,
+    pub current: bool, //currentColor, the cascader swaps it out for the actual colour
 }
 
 impl Colour {
@@ -24,6 +34,7 @@ impl Colour {
                 blue: b,
                 alpha: a,
             },
+            current: false,
         }
     }
 
@@ -44,6 +55,34 @@ impl Colour {
     pub const BLACK: Colour = Colour::new(0x00, 0x00, 0x00, 0xFF);
     pub const SILVER: Colour = Colour::new(0xc0, 0xc0, 0xc0, 0xFF);
     pub const GRAY: Colour = Colour::new(0x80, 0x80, 0x80, 0xFF);
+    pub const CURRENT: Colour = Colour {
+        current: true,
+        ..Colour::BLACK
+    };
+}
+
+//the way getComputedStyle gives colours back, with the alpha as short as it can be while still
+//coming back to the same byte.
+impl fmt::Display for Colour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Rgba {
+            red,
+            green,
+            blue,
+            alpha,
+        } = self.real;
+        if self.current {
+            return f.write_str("currentcolor");
+        }
+        if alpha == 255 {
+            return write!(f, "rgb({}, {}, {})", red, green, blue);
+        }
+        let mut a = (alpha as f64 / 255. * 100.).round() / 100.;
+        if (a * 255.).round() as u8 != alpha {
+            a = (alpha as f64 / 255. * 1000.).round() / 1000.;
+        }
+        write!(f, "rgba({}, {}, {}, {})", red, green, blue, a)
+    }
 }
 
 impl Property for Colour {
@@ -51,32 +90,10 @@ impl Property for Colour {
     where
         Self: Sized,
     {
-        CSSValue::Value(
-            if let Some(Component::Token(CSSToken::Ident(t))) = components.get(0) {
-                match t.as_str() {
-                    "maroon" => Self::MAROON,
-                    "red" => Self::RED,
-                    "orange" => Self::ORANGE,
-                    "yellow" => Self::YELLOW,
-                    "olive" => Self::OLIVE,
-                    "purple" => Self::PURPLE,
-                    "fuchsia" => Self::FUCHSIA,
-                    "white" => Self::WHITE,
-                    "lime" => Self::LIME,
-                    "green" => Self::GREEN,
-                    "navy" => Self::NAVY,
-                    "blue" => Self::BLUE,
-                    "aqua" => Self::AQUA,
-                    "teal" => Self::TEAL,
-                    "black" => Self::BLACK,
-                    "silver" => Self::SILVER,
-                    "gray" => Self::GRAY,
-                    _ => return CSSValue::default(),
-                }
-            } else {
-                return CSSValue::default();
-            },
-        )
+        match parse_colour(&components) {
+            Some(colour) => CSSValue::Value(colour),
+            None => CSSValue::default(),
+        }
     }
 }
 
@@ -100,6 +117,46 @@ impl Display {
     const RUN_IN: Display = Self::new(DisplayOutside::RunIn, DisplayInside::Flow);
     const LIST_ITEM: Display = unimplemented!();
     const INLINE_LIST_ITEM: Display = unimplemented!();
+
+    //what it turns into when it has to be a block, keeping what it's like inside.
+    pub fn blockify(self) -> Self {
+        match self.outside {
+            DisplayOutside::Inline | DisplayOutside::RunIn => {
+                Self::new(DisplayOutside::Block, self.inside)
+            }
+            _ => self,
+        }
+    }
+}
+
+impl fmt::Display for Display {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DisplayInside as I;
+        use DisplayOutside as O;
+        let outside = match self.outside {
+            O::None => return f.write_str("none"),
+            O::Contents => return f.write_str("contents"),
+            O::Block => "block",
+            O::Inline => "inline",
+            O::RunIn => "run-in",
+        };
+        match (self.outside, self.inside) {
+            (_, I::Flow) => f.write_str(outside),
+            (O::Block, I::FlowRoot) => f.write_str("flow-root"),
+            (O::Inline, I::FlowRoot) => f.write_str("inline-block"),
+            (_, inside) => {
+                let inside = match inside {
+                    I::Flow => "flow",
+                    I::FlowRoot => "flow-root",
+                    I::Table => "table",
+                    I::Flex => "flex",
+                    I::Grid => "grid",
+                    I::Ruby => "ruby",
+                };
+                write!(f, "{} {}", outside, inside)
+            }
+        }
+    }
 }
 
 impl Property for Display {
@@ -118,14 +175,14 @@ impl Property for Display {
                         "inline" => Self::INLINE,
                         "inline-block" => Self::INLINE_BLOCK,
                         "run-in" => Self::RUN_IN,
-                        _ => unimplemented!(),
+                        _ => return CSSValue::default(),
                     }
                 } else {
                     return CSSValue::default();
                 },
             )
         } else {
-            unimplemented!();
+            CSSValue::default()
         }
     }
 }
@@ -151,14 +208,39 @@ pub enum DisplayInside {
     Ruby,
 }
 
-#[derive(Debug, Clone, Default, Copy)]
+#[derive(Debug, Clone, Default)]
 pub struct FontSize {
-    pub value: CSSNumber,
+    pub value: Dimensionality, //never auto
 }
 
 impl FontSize {
     pub const fn new(value: CSSNumber) -> Self {
-        Self { value }
+        Self {
+            value: Dimensionality::Real(value),
+        }
+    }
+
+    //percentages and ems are relative to the parent's font size, so the context here should be
+    //the parent's. always ends up as pixels.
+    pub fn compute(&self, parent: &LengthContext) -> Self {
+        let px = self
+            .value
+            .compute(parent)
+            .resolve(Some(parent.font_size))
+            .unwrap_or(parent.font_size);
+        Self::new(CSSNumber::Unit(Numeric::Number(px as f32), Unit::Px))
+    }
+
+    pub fn px(&self) -> f64 {
+        self.value
+            .resolve(None)
+            .unwrap_or(LengthContext::default().font_size)
+    }
+}
+
+impl fmt::Display for FontSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
     }
 }
 
@@ -167,25 +249,95 @@ impl Property for FontSize {
     where
         Self: Sized,
     {
-        if let Some(Component::Token(CSSToken::Number(n))) = components.get(0) {
-            CSSValue::Value(Self { value: n.clone() })
-        } else {
-            unimplemented!();
+        //the absolute keywords are the scale from css fonts 4, medium being the usual 16px.
+        if let Some(Component::Token(CSSToken::Ident(t))) = components.get(0) {
+            let px = match t.to_ascii_lowercase().as_str() {
+                "xx-small" => 9.,
+                "x-small" => 10.,
+                "small" => 13.,
+                "medium" => 16.,
+                "large" => 18.,
+                "x-large" => 24.,
+                "xx-large" => 32.,
+                "xxx-large" => 48.,
+                "larger" => {
+                    return CSSValue::Value(Self::new(CSSNumber::Percentage(Numeric::Number(120.))))
+                }
+                "smaller" => {
+                    return CSSValue::Value(Self::new(CSSNumber::Percentage(Numeric::Number(
+                        100. / 1.2,
+                    ))))
+                }
+                _ => return CSSValue::default(),
+            };
+            return CSSValue::Value(Self::new(CSSNumber::Unit(Numeric::Number(px), Unit::Px)));
+        }
+        match Dimensionality::from_components(components) {
+            CSSValue::Value(Dimensionality::Auto) => CSSValue::default(),
+            CSSValue::Value(value) => CSSValue::Value(Self { value }),
+            _ => CSSValue::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Default, Copy)]
+#[derive(Debug, Clone, Default)]
 pub enum Dimensionality {
     #[default]
     Auto,
     Real(CSSNumber),
+    Calc(Calc), //only ever has pixels and percentages in it once it's been computed
 }
 
 impl Dimensionality {
     pub const fn new(value: CSSNumber) -> Self {
         Self::Real(value)
     }
+
+    //turns everything but percentages into pixels.
+    pub fn compute(&self, context: &LengthContext) -> Self {
+        match self {
+            Self::Real(CSSNumber::Unit(n, u)) => match context.to_px(n.unwrap_f64(), *u) {
+                Some(px) => Self::Real(CSSNumber::Unit(Numeric::Number(px as f32), Unit::Px)),
+                None => self.clone(),
+            },
+            Self::Calc(calc) => {
+                let calc = calc.absolutize(context);
+                match calc.has_percentage() {
+                    true => Self::Calc(calc),
+                    false => match calc.resolve(context, None) {
+                        Some(px) => {
+                            Self::Real(CSSNumber::Unit(Numeric::Number(px as f32), Unit::Px))
+                        }
+                        None => Self::Calc(calc),
+                    },
+                }
+            }
+            a => a.clone(),
+        }
+    }
+
+    //in pixels, with percentages taken out of basis. None means auto, which is also what a
+    //percentage of nothing comes out as.
+    pub fn resolve(&self, basis: Option<f64>) -> Option<f64> {
+        let context = LengthContext::default();
+        match self {
+            Self::Auto => None,
+            Self::Real(CSSNumber::Unit(n, u)) => context.to_px(n.unwrap_f64(), *u),
+            Self::Real(CSSNumber::Number(n)) => Some(n.unwrap_f64()),
+            Self::Real(CSSNumber::Percentage(p)) => Some(basis? * p.unwrap_f64() / 100.),
+            Self::Calc(calc) => calc.resolve(&context, basis),
+        }
+    }
+}
+
+impl fmt::Display for Dimensionality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::Real(n) => n.fmt(f),
+            Self::Calc(calc) => calc.fmt(f),
+        }
+    }
 }
 
 impl Property for Dimensionality {
@@ -193,13 +345,18 @@ impl Property for Dimensionality {
     where
         Self: Sized,
     {
-        if let Some(Component::Token(CSSToken::Number(n))) = components.get(0) {
-            CSSValue::Value(Self::Real(n.clone()))
-        } else if let Some(Component::Token(CSSToken::Ident(..))) = components.get(0) {
-            CSSValue::Value(Self::Auto) //tbf i should actually check if it's auto but for now it's
-                                        //probably fine..
-        } else {
-            unimplemented!();
+        match components.get(0) {
+            Some(Component::Token(CSSToken::Number(n))) => CSSValue::Value(Self::Real(n.clone())),
+            Some(Component::Token(CSSToken::Ident(i))) if i.eq_ignore_ascii_case("auto") => {
+                CSSValue::Value(Self::Auto)
+            }
+            Some(c @ Component::Function(..)) => match Calc::from_component(c) {
+                Some(calc) if calc.kind() == Some(CalcKind::Length) => {
+                    CSSValue::Value(Self::Calc(calc))
+                }
+                _ => CSSValue::default(),
+            },
+            _ => CSSValue::default(),
         }
     }
 }
@@ -213,6 +370,17 @@ pub enum TextAlign {
     Justify,
 }
 
+impl fmt::Display for TextAlign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::Center => "center",
+            Self::Justify => "justify",
+        })
+    }
+}
+
 impl Property for TextAlign {
     fn from_components(components: Vec<Component>) -> CSSValue<Self>
     where
@@ -233,47 +401,1106 @@ impl Property for TextAlign {
     }
 }
 
+//where an inline-level box sits on its line. everything but top and bottom is against the
+//parent's baseline, those two are against the line box itself.
+#[derive(Debug, Clone, Default)]
+pub enum VerticalAlign {
+    #[default]
+    Baseline,
+    Sub,
+    Super,
+    TextTop,
+    TextBottom,
+    Middle,
+    Top,
+    Bottom,
+    Length(Dimensionality), //raised by this much, percentages being of the line height
+}
+
+impl fmt::Display for VerticalAlign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Baseline => "baseline",
+            Self::Sub => "sub",
+            Self::Super => "super",
+            Self::TextTop => "text-top",
+            Self::TextBottom => "text-bottom",
+            Self::Middle => "middle",
+            Self::Top => "top",
+            Self::Bottom => "bottom",
+            Self::Length(length) => return length.fmt(f),
+        })
+    }
+}
+
+impl Property for VerticalAlign {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        if let Some(Component::Token(CSSToken::Ident(t))) = components.get(0) {
+            return CSSValue::Value(match t.to_ascii_lowercase().as_str() {
+                "baseline" => Self::Baseline,
+                "sub" => Self::Sub,
+                "super" => Self::Super,
+                "text-top" => Self::TextTop,
+                "text-bottom" => Self::TextBottom,
+                "middle" => Self::Middle,
+                "top" => Self::Top,
+                "bottom" => Self::Bottom,
+                _ => return CSSValue::default(),
+            });
+        }
+        match Dimensionality::from_components(components) {
+            CSSValue::Value(Dimensionality::Auto) => CSSValue::default(),
+            CSSValue::Value(length) => CSSValue::Value(Self::Length(length)),
+            _ => CSSValue::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum BorderStyle {
+    #[default]
+    None,
+    Hidden,
+    Dotted,
+    Dashed,
+    Solid,
+    Double,
+    Groove,
+    Ridge,
+    Inset,
+    Outset,
+}
+
+impl fmt::Display for BorderStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Hidden => "hidden",
+            Self::Dotted => "dotted",
+            Self::Dashed => "dashed",
+            Self::Solid => "solid",
+            Self::Double => "double",
+            Self::Groove => "groove",
+            Self::Ridge => "ridge",
+            Self::Inset => "inset",
+            Self::Outset => "outset",
+        })
+    }
+}
+
+impl Property for BorderStyle {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        let [Component::Token(CSSToken::Ident(i))] = components.as_slice() else {
+            return CSSValue::default();
+        };
+        CSSValue::Value(match i.to_ascii_lowercase().as_str() {
+            "none" => Self::None,
+            "hidden" => Self::Hidden,
+            "dotted" => Self::Dotted,
+            "dashed" => Self::Dashed,
+            "solid" => Self::Solid,
+            "double" => Self::Double,
+            "groove" => Self::Groove,
+            "ridge" => Self::Ridge,
+            "inset" => Self::Inset,
+            "outset" => Self::Outset,
+            _ => return CSSValue::default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BorderWidth {
+    pub value: Dimensionality, //never auto or a percentage
+}
+
+impl BorderWidth {
+    pub const fn new(px: f32) -> Self {
+        Self {
+            value: Dimensionality::new(CSSNumber::Unit(Numeric::Number(px), Unit::Px)),
+        }
+    }
+
+    pub const MEDIUM: BorderWidth = BorderWidth::new(3.);
+
+    //no border to speak of means no width either.
+    pub fn compute(&self, context: &LengthContext, style: BorderStyle) -> Self {
+        match style {
+            BorderStyle::None | BorderStyle::Hidden => Self::new(0.),
+            _ => Self::new(self.value.compute(context).resolve(None).unwrap_or(0.) as f32),
+        }
+    }
+
+    pub fn px(&self) -> f64 {
+        self.value.resolve(None).unwrap_or(0.)
+    }
+}
+
+impl fmt::Display for BorderWidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl Property for BorderWidth {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        if let [Component::Token(CSSToken::Ident(i))] = components.as_slice() {
+            return CSSValue::Value(match i.to_ascii_lowercase().as_str() {
+                "thin" => Self::new(1.),
+                "medium" => Self::MEDIUM,
+                "thick" => Self::new(5.),
+                _ => return CSSValue::default(),
+            });
+        }
+        match length(components, false, false) {
+            Some(value) => CSSValue::Value(Self { value }),
+            None => CSSValue::default(),
+        }
+    }
+}
+
+//a length or calc() of one, never auto, with whether percentages and negatives are alright up
+//to whatever it's for.
+fn length(components: Vec<Component>, percentages: bool, negative: bool) -> Option<Dimensionality> {
+    let value = match Dimensionality::from_components(components) {
+        CSSValue::Value(Dimensionality::Auto) => return None,
+        CSSValue::Value(value) => value,
+        _ => return None,
+    };
+    let percentage = match &value {
+        Dimensionality::Real(CSSNumber::Percentage(_)) => true,
+        Dimensionality::Calc(calc) => calc.has_percentage(),
+        _ => false,
+    };
+    if percentage && !percentages {
+        return None;
+    }
+    if !negative && value.resolve(Some(100.)).is_some_and(|v| v < 0.) {
+        return None;
+    }
+    Some(value)
+}
+
+//a corner's, across then down. percentages are of the border box's width and height.
+#[derive(Debug, Clone, Default)]
+pub struct CornerRadius {
+    pub horizontal: Dimensionality,
+    pub vertical: Dimensionality,
+}
+
+impl CornerRadius {
+    pub const fn new(horizontal: Dimensionality, vertical: Dimensionality) -> Self {
+        Self {
+            horizontal,
+            vertical,
+        }
+    }
+
+    pub fn compute(&self, context: &LengthContext) -> Self {
+        Self::new(
+            self.horizontal.compute(context),
+            self.vertical.compute(context),
+        )
+    }
+
+    pub fn resolve(&self, width: f64, height: f64) -> (f64, f64) {
+        (
+            self.horizontal.resolve(Some(width)).unwrap_or(0.),
+            self.vertical.resolve(Some(height)).unwrap_or(0.),
+        )
+    }
+}
+
+impl fmt::Display for CornerRadius {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (horizontal, vertical) = (self.horizontal.to_string(), self.vertical.to_string());
+        match horizontal == vertical {
+            true => f.write_str(&horizontal),
+            false => write!(f, "{} {}", horizontal, vertical),
+        }
+    }
+}
+
+impl Property for CornerRadius {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        let values = Component::split_values(&components)
+            .into_iter()
+            .map(|value| length(value, true, false))
+            .collect::<Option<Vec<_>>>();
+        match values.as_deref() {
+            Some([both]) => CSSValue::Value(Self::new(both.clone(), both.clone())),
+            Some([horizontal, vertical]) => {
+                CSSValue::Value(Self::new(horizontal.clone(), vertical.clone()))
+            }
+            _ => CSSValue::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Shadow {
+    pub inset: bool,
+    pub x: Dimensionality,
+    pub y: Dimensionality,
+    pub blur: Dimensionality,
+    pub spread: Dimensionality,
+    pub colour: Colour, //currentColor when there isn't one
+}
+
+//no shadows at all is none. the first one's on top.
+#[derive(Debug, Clone, Default)]
+pub struct BoxShadow {
+    pub shadows: Vec<Shadow>,
+}
+
+impl BoxShadow {
+    pub fn compute(&self, context: &LengthContext, color: &Colour) -> Self {
+        let shadows = self
+            .shadows
+            .iter()
+            .map(|shadow| Shadow {
+                inset: shadow.inset,
+                x: shadow.x.compute(context),
+                y: shadow.y.compute(context),
+                blur: shadow.blur.compute(context),
+                spread: shadow.spread.compute(context),
+                colour: match shadow.colour.current {
+                    true => *color,
+                    false => shadow.colour,
+                },
+            })
+            .collect();
+        Self { shadows }
+    }
+}
+
+//the way getComputedStyle has it, colour first and inset last.
+impl fmt::Display for BoxShadow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.shadows.is_empty() {
+            return f.write_str("none");
+        }
+        for (i, shadow) in self.shadows.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(
+                f,
+                "{} {} {} {} {}",
+                shadow.colour, shadow.x, shadow.y, shadow.blur, shadow.spread
+            )?;
+            if shadow.inset {
+                f.write_str(" inset")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Property for BoxShadow {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        if let [Component::Token(CSSToken::Ident(i))] = components.as_slice() {
+            if i.eq_ignore_ascii_case("none") {
+                return CSSValue::Value(Self::default());
+            }
+        }
+        let mut shadows = vec![];
+        for shadow in components.split(|c| matches!(c, Component::Token(CSSToken::Comma))) {
+            let mut inset = false;
+            let mut colour = None;
+            let mut lengths = vec![];
+            //the lengths all have to be next to each other.
+            let mut lengths_done = false;
+            for value in Component::split_values(shadow) {
+                if let [Component::Token(CSSToken::Ident(i))] = value.as_slice() {
+                    if i.eq_ignore_ascii_case("inset") && !inset {
+                        inset = true;
+                        lengths_done = !lengths.is_empty();
+                        continue;
+                    }
+                }
+                if let Some(length) = length(value.clone(), false, true) {
+                    if lengths_done || lengths.len() == 4 {
+                        return CSSValue::default();
+                    }
+                    lengths.push(length);
+                    continue;
+                }
+                match Colour::from_components(value) {
+                    CSSValue::Value(c) if colour.is_none() => colour = Some(c),
+                    _ => return CSSValue::default(),
+                }
+                lengths_done = !lengths.is_empty();
+            }
+            if lengths.len() < 2 {
+                return CSSValue::default();
+            }
+            let zero = || Dimensionality::new(CSSNumber::Unit(Numeric::Integer(0), Unit::Px));
+            let mut lengths = lengths.into_iter();
+            let (x, y) = (lengths.next().unwrap(), lengths.next().unwrap());
+            let blur = lengths.next().unwrap_or_else(zero);
+            if blur.resolve(None).is_some_and(|b| b < 0.) {
+                return CSSValue::default();
+            }
+            shadows.push(Shadow {
+                inset,
+                x,
+                y,
+                blur,
+                spread: lengths.next().unwrap_or_else(zero),
+                colour: colour.unwrap_or(Colour::CURRENT),
+            });
+        }
+        CSSValue::Value(Self { shadows })
+    }
+}
+
+//border-style without hidden, and with auto, which is meant to look like the platform's focus
+//rings. ours are just solid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlineStyle {
+    Auto,
+    Border(BorderStyle),
+}
+
+impl OutlineStyle {
+    pub fn border_style(self) -> BorderStyle {
+        match self {
+            Self::Auto => BorderStyle::Solid,
+            Self::Border(style) => style,
+        }
+    }
+}
+
+impl Default for OutlineStyle {
+    fn default() -> Self {
+        Self::Border(BorderStyle::None)
+    }
+}
+
+impl fmt::Display for OutlineStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::Border(style) => style.fmt(f),
+        }
+    }
+}
+
+impl Property for OutlineStyle {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        if let [Component::Token(CSSToken::Ident(i))] = components.as_slice() {
+            if i.eq_ignore_ascii_case("auto") {
+                return CSSValue::Value(Self::Auto);
+            }
+        }
+        match BorderStyle::from_components(components) {
+            CSSValue::Value(BorderStyle::Hidden) => CSSValue::default(),
+            CSSValue::Value(style) => CSSValue::Value(Self::Border(style)),
+            _ => CSSValue::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Position {
+    #[default]
+    Static,
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Static => "static",
+            Self::Relative => "relative",
+            Self::Absolute => "absolute",
+            Self::Fixed => "fixed",
+            Self::Sticky => "sticky",
+        })
+    }
+}
+
+impl Property for Position {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        let [Component::Token(CSSToken::Ident(i))] = components.as_slice() else {
+            return CSSValue::default();
+        };
+        CSSValue::Value(match i.to_ascii_lowercase().as_str() {
+            "static" => Self::Static,
+            "relative" => Self::Relative,
+            "absolute" => Self::Absolute,
+            "fixed" => Self::Fixed,
+            "sticky" => Self::Sticky,
+            _ => return CSSValue::default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Float {
+    #[default]
+    None,
+    Left,
+    Right,
+}
+
+impl fmt::Display for Float {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Left => "left",
+            Self::Right => "right",
+        })
+    }
+}
+
+impl Property for Float {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        let [Component::Token(CSSToken::Ident(i))] = components.as_slice() else {
+            return CSSValue::default();
+        };
+        CSSValue::Value(match i.to_ascii_lowercase().as_str() {
+            "none" => Self::None,
+            "left" => Self::Left,
+            "right" => Self::Right,
+            _ => return CSSValue::default(),
+        })
+    }
+}
+
+//which floats it has to go under.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Clear {
+    #[default]
+    None,
+    Left,
+    Right,
+    Both,
+}
+
+impl fmt::Display for Clear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::Both => "both",
+        })
+    }
+}
+
+impl Property for Clear {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        let [Component::Token(CSSToken::Ident(i))] = components.as_slice() else {
+            return CSSValue::default();
+        };
+        CSSValue::Value(match i.to_ascii_lowercase().as_str() {
+            "none" => Self::None,
+            "left" => Self::Left,
+            "right" => Self::Right,
+            "both" => Self::Both,
+            _ => return CSSValue::default(),
+        })
+    }
+}
+
+//anything other than visible cuts off what's in it at the padding box. nothing scrolls yet, but
+//anything other than visible and clip still gives it a block formatting context of its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Overflow {
+    #[default]
+    Visible,
+    Hidden,
+    Clip,
+    Scroll,
+    Auto,
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Visible => "visible",
+            Self::Hidden => "hidden",
+            Self::Clip => "clip",
+            Self::Scroll => "scroll",
+            Self::Auto => "auto",
+        })
+    }
+}
+
+impl Property for Overflow {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        let [Component::Token(CSSToken::Ident(i))] = components.as_slice() else {
+            return CSSValue::default();
+        };
+        CSSValue::Value(match i.to_ascii_lowercase().as_str() {
+            "visible" => Self::Visible,
+            "hidden" => Self::Hidden,
+            "clip" => Self::Clip,
+            "scroll" => Self::Scroll,
+            "auto" => Self::Auto,
+            _ => return CSSValue::default(),
+        })
+    }
+}
+
+//auto means it doesn't get a stacking context of its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ZIndex {
+    #[default]
+    Auto,
+    Integer(i32),
+}
+
+impl fmt::Display for ZIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::Integer(z) => z.fmt(f),
+        }
+    }
+}
+
+impl Property for ZIndex {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        CSSValue::Value(match components.as_slice() {
+            [Component::Token(CSSToken::Ident(i))] if i.eq_ignore_ascii_case("auto") => Self::Auto,
+            [Component::Token(CSSToken::Number(CSSNumber::Number(Numeric::Integer(z))))] => {
+                Self::Integer(*z)
+            }
+            _ => return CSSValue::default(),
+        })
+    }
+}
+
+//a number or a percentage, kept between 0 and 1.
+#[derive(Debug, Clone, Copy)]
+pub struct Opacity {
+    pub value: f64,
+}
+
+impl Default for Opacity {
+    fn default() -> Self {
+        Self { value: 1. }
+    }
+}
+
+impl fmt::Display for Opacity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl Property for Opacity {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        let value = match components.as_slice() {
+            [Component::Token(CSSToken::Number(CSSNumber::Number(n)))] => n.unwrap_f64(),
+            [Component::Token(CSSToken::Number(CSSNumber::Percentage(n)))] => n.unwrap_f64() / 100.,
+            _ => return CSSValue::default(),
+        };
+        CSSValue::Value(Self {
+            value: value.clamp(0., 1.),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Isolation {
+    #[default]
+    Auto,
+    Isolate,
+}
+
+impl fmt::Display for Isolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Auto => "auto",
+            Self::Isolate => "isolate",
+        })
+    }
+}
+
+impl Property for Isolation {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        let [Component::Token(CSSToken::Ident(i))] = components.as_slice() else {
+            return CSSValue::default();
+        };
+        CSSValue::Value(match i.to_ascii_lowercase().as_str() {
+            "auto" => Self::Auto,
+            "isolate" => Self::Isolate,
+            _ => return CSSValue::default(),
+        })
+    }
+}
+
+//the 2d ones. angles are in radians, going clockwise, and lengths can be percentages of the
+//border box.
+#[derive(Debug, Clone)]
+pub enum TransformFunction {
+    Matrix([f64; 6]),
+    Translate(Dimensionality, Dimensionality),
+    Scale(f64, f64),
+    Rotate(f64),
+    Skew(f64, f64),
+}
+
+impl TransformFunction {
+    //a, b, c, d, e, f, the same as matrix() takes them.
+    fn matrix(&self, width: f64, height: f64) -> [f64; 6] {
+        match self {
+            Self::Matrix(matrix) => *matrix,
+            Self::Translate(x, y) => [
+                1.,
+                0.,
+                0.,
+                1.,
+                x.resolve(Some(width)).unwrap_or(0.),
+                y.resolve(Some(height)).unwrap_or(0.),
+            ],
+            Self::Scale(x, y) => [*x, 0., 0., *y, 0., 0.],
+            Self::Rotate(a) => [a.cos(), a.sin(), -a.sin(), a.cos(), 0., 0.],
+            Self::Skew(x, y) => [1., y.tan(), x.tan(), 1., 0., 0.],
+        }
+    }
+}
+
+impl fmt::Display for TransformFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Matrix([a, b, c, d, e, g]) => {
+                write!(f, "matrix({}, {}, {}, {}, {}, {})", a, b, c, d, e, g)
+            }
+            Self::Translate(x, y) => write!(f, "translate({}, {})", x, y),
+            Self::Scale(x, y) => write!(f, "scale({}, {})", x, y),
+            Self::Rotate(a) => write!(f, "rotate({}deg)", a.to_degrees()),
+            Self::Skew(x, y) => write!(f, "skew({}deg, {}deg)", x.to_degrees(), y.to_degrees()),
+        }
+    }
+}
+
+//no functions at all is none. they're applied in order, each one inside the last.
+#[derive(Debug, Clone, Default)]
+pub struct Transform {
+    pub functions: Vec<TransformFunction>,
+}
+
+impl Transform {
+    pub fn compute(&self, context: &LengthContext) -> Self {
+        let functions = self
+            .functions
+            .iter()
+            .map(|function| match function {
+                TransformFunction::Translate(x, y) => {
+                    TransformFunction::Translate(x.compute(context), y.compute(context))
+                }
+                function => function.clone(),
+            })
+            .collect();
+        Self { functions }
+    }
+
+    //everything multiplied together, for a border box this big.
+    pub fn matrix(&self, width: f64, height: f64) -> [f64; 6] {
+        self.functions
+            .iter()
+            .fold([1., 0., 0., 1., 0., 0.], |m, function| {
+                let [a, b, c, d, e, f] = function.matrix(width, height);
+                [
+                    m[0] * a + m[2] * b,
+                    m[1] * a + m[3] * b,
+                    m[0] * c + m[2] * d,
+                    m[1] * c + m[3] * d,
+                    m[0] * e + m[2] * f + m[4],
+                    m[1] * e + m[3] * f + m[5],
+                ]
+            })
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.functions.is_empty() {
+            return f.write_str("none");
+        }
+        for (i, function) in self.functions.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            function.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Property for Transform {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        if let [Component::Token(CSSToken::Ident(i))] = components.as_slice() {
+            if i.eq_ignore_ascii_case("none") {
+                return CSSValue::Value(Self::default());
+            }
+        }
+        let mut functions = vec![];
+        for value in Component::split_values(&components) {
+            let [Component::Function(name, arguments)] = value.as_slice() else {
+                return CSSValue::default();
+            };
+            let arguments = arguments
+                .split(|c| matches!(c, Component::Token(CSSToken::Comma)))
+                .map(|argument| {
+                    argument
+                        .iter()
+                        .filter(|c| !matches!(c, Component::Token(CSSToken::Whitespace)))
+                        .cloned()
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            match transform_function(&name.to_ascii_lowercase(), &arguments) {
+                Some(function) => functions.push(function),
+                None => return CSSValue::default(),
+            }
+        }
+        match functions.is_empty() {
+            true => CSSValue::default(),
+            false => CSSValue::Value(Self { functions }),
+        }
+    }
+}
+
+//in degrees.
+pub fn parse_angle(token: &CSSToken) -> Option<f64> {
+    match token {
+        CSSToken::Dimension(n, unit) => {
+            let n = n.unwrap_f64();
+            match unit.to_ascii_lowercase().as_str() {
+                "deg" => Some(n),
+                "rad" => Some(n.to_degrees()),
+                "grad" => Some(n * 0.9),
+                "turn" => Some(n * 360.),
+                _ => None,
+            }
+        }
+        //a plain 0 is fine for an angle, but nothing else is.
+        CSSToken::Number(CSSNumber::Number(n)) if n.unwrap_f64() == 0. => Some(0.),
+        _ => None,
+    }
+}
+
+fn transform_function(name: &str, arguments: &[Vec<Component>]) -> Option<TransformFunction> {
+    let number = |argument: &Vec<Component>| match argument.as_slice() {
+        [Component::Token(CSSToken::Number(CSSNumber::Number(n)))] => Some(n.unwrap_f64()),
+        _ => None,
+    };
+    let angle = |argument: &Vec<Component>| match argument.as_slice() {
+        [Component::Token(token)] => parse_angle(token).map(f64::to_radians),
+        _ => None,
+    };
+    let translation = |argument: &Vec<Component>| length(argument.clone(), true, true);
+    let zero = || Dimensionality::new(CSSNumber::Unit(Numeric::Integer(0), Unit::Px));
+    use TransformFunction as T;
+    Some(match (name, arguments) {
+        ("matrix", [a, b, c, d, e, f]) => T::Matrix([
+            number(a)?,
+            number(b)?,
+            number(c)?,
+            number(d)?,
+            number(e)?,
+            number(f)?,
+        ]),
+        ("translate", [x]) => T::Translate(translation(x)?, zero()),
+        ("translate", [x, y]) => T::Translate(translation(x)?, translation(y)?),
+        ("translatex", [x]) => T::Translate(translation(x)?, zero()),
+        ("translatey", [y]) => T::Translate(zero(), translation(y)?),
+        ("scale", [both]) => T::Scale(number(both)?, number(both)?),
+        ("scale", [x, y]) => T::Scale(number(x)?, number(y)?),
+        ("scalex", [x]) => T::Scale(number(x)?, 1.),
+        ("scaley", [y]) => T::Scale(1., number(y)?),
+        ("rotate", [a]) => T::Rotate(angle(a)?),
+        ("skew", [x]) => T::Skew(angle(x)?, 0.),
+        ("skew", [x, y]) => T::Skew(angle(x)?, angle(y)?),
+        ("skewx", [x]) => T::Skew(angle(x)?, 0.),
+        ("skewy", [y]) => T::Skew(0., angle(y)?),
+        _ => return None,
+    })
+}
+
+//which box width and height (and their min and max) are the size of.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum BoxSizing {
+    #[default]
+    ContentBox,
+    BorderBox,
+}
+
+impl fmt::Display for BoxSizing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ContentBox => "content-box",
+            Self::BorderBox => "border-box",
+        })
+    }
+}
+
+impl Property for BoxSizing {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        let [Component::Token(CSSToken::Ident(i))] = components.as_slice() else {
+            return CSSValue::default();
+        };
+        CSSValue::Value(match i.to_ascii_lowercase().as_str() {
+            "content-box" => Self::ContentBox,
+            "border-box" => Self::BorderBox,
+            _ => return CSSValue::default(),
+        })
+    }
+}
+
+//max-width and max-height, which are none instead of auto when there isn't one.
+#[derive(Debug, Clone, Default)]
+pub struct MaxSize {
+    pub value: Dimensionality, //auto means none
+}
+
+impl MaxSize {
+    pub const NONE: MaxSize = MaxSize {
+        value: Dimensionality::Auto,
+    };
+
+    pub fn compute(&self, context: &LengthContext) -> Self {
+        Self {
+            value: self.value.compute(context),
+        }
+    }
+
+    //None if there's no limit, including a percentage of nothing.
+    pub fn resolve(&self, basis: Option<f64>) -> Option<f64> {
+        self.value.resolve(basis)
+    }
+}
+
+impl fmt::Display for MaxSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Dimensionality::Auto => f.write_str("none"),
+            ref value => value.fmt(f),
+        }
+    }
+}
+
+impl Property for MaxSize {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        if let [Component::Token(CSSToken::Ident(i))] = components.as_slice() {
+            if i.eq_ignore_ascii_case("none") {
+                return CSSValue::Value(Self::NONE);
+            }
+        }
+        match length(components, true, false) {
+            Some(value) => CSSValue::Value(Self { value }),
+            None => CSSValue::default(),
+        }
+    }
+}
+
+//what a container query is allowed to ask this element about.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ContainerType {
+    #[default]
+    Normal,
+    Size,
+    InlineSize,
+}
+
+impl fmt::Display for ContainerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Normal => "normal",
+            Self::Size => "size",
+            Self::InlineSize => "inline-size",
+        })
+    }
+}
+
+impl Property for ContainerType {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        let components = components
+            .iter()
+            .filter(|c| !matches!(c, Component::Token(CSSToken::Whitespace)))
+            .collect::<Vec<_>>();
+        let [Component::Token(CSSToken::Ident(i))] = components.as_slice() else {
+            return CSSValue::default();
+        };
+        CSSValue::Value(match i.to_ascii_lowercase().as_str() {
+            "normal" => Self::Normal,
+            "size" => Self::Size,
+            "inline-size" => Self::InlineSize,
+            _ => return CSSValue::default(),
+        })
+    }
+}
+
+//no names at all is `none`.
+#[derive(Debug, Clone, Default)]
+pub struct ContainerName {
+    pub names: Vec<String>,
+}
+
+impl fmt::Display for ContainerName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.names.is_empty() {
+            return f.write_str("none");
+        }
+        for (i, name) in self.names.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            CSSToken::Ident(name.clone()).fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Property for ContainerName {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        let mut names = vec![];
+        for component in components {
+            match component {
+                Component::Token(CSSToken::Whitespace) => {}
+                Component::Token(CSSToken::Ident(i)) => names.push(i),
+                _ => return CSSValue::default(),
+            }
+        }
+        match names.as_slice() {
+            [] => CSSValue::default(),
+            [none] if none.eq_ignore_ascii_case("none") => CSSValue::Value(Self::default()),
+            _ if names.iter().any(|n| {
+                ["none", "and", "not", "or"].contains(&n.to_ascii_lowercase().as_str())
+            }) =>
+            {
+                CSSValue::default()
+            }
+            _ => CSSValue::Value(Self { names }),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum FontFamily {
     Unresoved(Vec<String>),
-    Resolved(Font),
+    //names are kept around so children with a different weight or style can resolve again.
+    Resolved(Vec<String>, FontChain),
 }
 
 impl FontFamily {
-    pub fn resolve(&mut self) {
-        if let Self::Resolved(_) = self {
-            return;
-        } else if let Self::Unresoved(names) = self {
-            let mut defined_fonts = names
-                .iter()
-                .map(|n| FamilyName::Title(n.clone()))
-                .collect::<Vec<_>>();
-            defined_fonts.push(FamilyName::Serif); //final fallback.
-            let font_data = SystemSource::new()
-                .select_best_match(defined_fonts.as_slice(), &Properties::new())
-                .unwrap()
-                .load()
-                .unwrap();
-            *self = Self::Resolved(font_data);
+    pub fn names(&self) -> &Vec<String> {
+        match self {
+            Self::Unresoved(names) | Self::Resolved(names, _) => names,
+        }
+    }
+
+    //stays unresolved if there's no font to be had at all.
+    pub fn resolve(&mut self, fonts: &FontSet, weight: f64, style: FontStyle) {
+        let names = self.names().clone();
+        if let Some(font) = fonts.resolve(&names, weight, style) {
+            *self = Self::Resolved(names, font);
         }
     }
 }
 
+//generic families and plain identifiers go out as they are, anything else gets quoted.
+impl fmt::Display for FontFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, name) in self.names().iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            let plain = !name.starts_with(|c: char| c.is_ascii_digit())
+                && !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+            if plain {
+                f.write_str(name)?;
+            } else {
+                write!(f, "\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Property for FontFamily {
     fn from_components(components: Vec<Component>) -> CSSValue<Self>
     where
         Self: Sized,
     {
         let mut actual = Vec::new();
-        let mut iter = components.iter();
         let mut working = String::new();
-        while let Some(c) = iter.next() {
+        for c in &components {
             if let Component::Token(t) = c {
                 match t {
                     CSSToken::Ident(i) => {
+                        if !working.is_empty() {
+                            working.push(' ');
+                        }
                         working.push_str(i.as_str());
-                        working.push(' ');
                     }
+                    CSSToken::String(s) => working.push_str(s.as_str()),
                     CSSToken::Comma => {
                         actual.push(working.clone());
                         working.clear();
@@ -282,6 +1509,16 @@ impl Property for FontFamily {
                 }
             }
         }
+        if !working.is_empty() {
+            actual.push(working);
+        }
+        if let [only] = actual.as_slice() {
+            match only.to_ascii_lowercase().as_str() {
+                "inherit" => return CSSValue::Inherit,
+                "initial" => return CSSValue::Initial,
+                _ => {}
+            }
+        }
         CSSValue::Value(Self::Unresoved(actual))
     }
 }
@@ -301,6 +1538,18 @@ pub enum FontWeight {
     Absolute(f64), //units disallowed for font-weight, can take real ass value
 }
 
+impl fmt::Display for FontWeight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Normal => f.write_str("normal"),
+            Self::Bold => f.write_str("bold"),
+            Self::Bolder => f.write_str("bolder"),
+            Self::Lighter => f.write_str("lighter"),
+            Self::Absolute(w) => write!(f, "{}", w),
+        }
+    }
+}
+
 impl Property for FontWeight {
     fn from_components(components: Vec<Component>) -> CSSValue<Self>
     where
@@ -329,8 +1578,76 @@ impl Property for FontWeight {
     }
 }
 
+impl FontWeight {
+    //bolder and lighter go by the table in css fonts 4, relative to what the parent ended up
+    //with.
+    pub fn compute(&self, parent: f64) -> Self {
+        Self::Absolute(match self {
+            Self::Normal => 400.,
+            Self::Bold => 700.,
+            Self::Bolder if parent < 350. => 400.,
+            Self::Bolder if parent < 550. => 700.,
+            Self::Bolder => parent.max(900.),
+            Self::Lighter if parent < 550. => parent.min(100.),
+            Self::Lighter if parent < 750. => 400.,
+            Self::Lighter => 700.,
+            Self::Absolute(w) if (1. ..=1000.).contains(w) => *w,
+            Self::Absolute(_) => parent,
+        })
+    }
+}
+
 impl Default for FontWeight {
     fn default() -> Self {
         Self::Absolute(400.)
     }
 }
+
+//oblique angles are in degrees, and a plain oblique is 14 of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FontStyle {
+    #[default]
+    Normal,
+    Italic,
+    Oblique(f64),
+}
+
+impl fmt::Display for FontStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Normal => f.write_str("normal"),
+            Self::Italic => f.write_str("italic"),
+            Self::Oblique(a) if *a == 14. => f.write_str("oblique"),
+            Self::Oblique(a) => write!(f, "oblique {}deg", a),
+        }
+    }
+}
+
+impl Property for FontStyle {
+    fn from_components(components: Vec<Component>) -> CSSValue<Self>
+    where
+        Self: Sized,
+    {
+        let mut tokens = vec![];
+        for component in &components {
+            match component {
+                Component::Token(CSSToken::Whitespace) => {}
+                Component::Token(token) => tokens.push(token),
+                _ => return CSSValue::default(),
+            }
+        }
+        let [CSSToken::Ident(i), angle @ ..] = tokens.as_slice() else {
+            return CSSValue::default();
+        };
+        CSSValue::Value(match (i.to_ascii_lowercase().as_str(), angle) {
+            ("normal", []) => Self::Normal,
+            ("italic", []) => Self::Italic,
+            ("oblique", []) => Self::Oblique(14.),
+            ("oblique", [angle]) => match parse_angle(angle) {
+                Some(a) if a.abs() <= 90. => Self::Oblique(a),
+                _ => return CSSValue::default(),
+            },
+            _ => return CSSValue::default(),
+        })
+    }
+}
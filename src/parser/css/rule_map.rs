@@ -0,0 +1,287 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use super::{Combinator, PseudoClass, Selector};
+use crate::dom::Element;
+
+//one complex selector out of a rule's selector list, split up ready for matching.
+#[derive(Debug)]
+pub struct RuleEntry {
+    pub rule: usize, //which rule it came from
+    pub compounds: Vec<(Selector, Option<Combinator>)>,
+    //tags, ids and classes some ancestor has to have for this to match, which the bloom filter
+    //can rule out without walking up the tree.
+    pub ancestors: Vec<u32>,
+    pub siblings: bool, //could tell apart two elements that only differ in where they are
+    pub specificity: (u32, u32, u32),
+}
+
+//every complex selector bucketed by the most specific thing its rightmost compound needs, so
+//elements only get checked against selectors that could possibly match them. a linear one puts
+//everything in with the universal selectors, which is what the benches compare against.
+#[derive(Debug, Default)]
+pub struct RuleMap {
+    ids: HashMap<String, Vec<RuleEntry>>,
+    classes: HashMap<String, Vec<RuleEntry>>,
+    tags: HashMap<String, Vec<RuleEntry>>,
+    universal: Vec<RuleEntry>,
+    linear: bool,
+}
+
+impl RuleMap {
+    pub fn new(linear: bool) -> Self {
+        Self {
+            linear,
+            ..Default::default()
+        }
+    }
+
+    pub fn insert(&mut self, rule: usize, selector: &Selector) {
+        for complex in selector.alternatives() {
+            let compounds = complex.compounds();
+            let ancestors = match self.linear {
+                true => vec![],
+                false => compounds
+                    .iter()
+                    .filter(|(_, combinator)| {
+                        //anything before a > or a space is an ancestor, even with a + further
+                        //along.
+                        matches!(combinator, Some(Combinator::Child | Combinator::Descendant))
+                    })
+                    .flat_map(|(compound, _)| compound.simples())
+                    .filter_map(BloomFilter::hash_simple)
+                    .collect(),
+            };
+            let entry = RuleEntry {
+                rule,
+                compounds: compounds
+                    .into_iter()
+                    .map(|(compound, combinator)| (compound.clone(), combinator))
+                    .collect(),
+                ancestors,
+                siblings: complex.uses_siblings(),
+                specificity: complex.specificity(),
+            };
+            let simples = match entry.compounds.last() {
+                Some((Selector::Placeheld, _)) | None => continue, //never matches anything
+                Some((compound, _)) => compound.simples(),
+            };
+            let key = |f: fn(&Selector) -> Option<&String>| simples.iter().find_map(|s| f(s));
+            let id = key(|s| match s {
+                Selector::Id(id) => Some(id),
+                _ => None,
+            });
+            let class = key(|s| match s {
+                Selector::Class(class) => Some(class),
+                _ => None,
+            });
+            let tag = key(|s| match s {
+                Selector::Type(tag) => Some(tag),
+                _ => None,
+            });
+            let bucket = match (id, class, tag) {
+                _ if self.linear => &mut self.universal,
+                (Some(id), ..) => self.ids.entry(id.clone()).or_default(),
+                (_, Some(class), _) => self.classes.entry(class.clone()).or_default(),
+                (_, _, Some(tag)) => self.tags.entry(tag.clone()).or_default(),
+                _ => &mut self.universal,
+            };
+            bucket.push(entry);
+        }
+    }
+
+    //in no particular order, and a rule can show up more than once.
+    pub fn candidates<'a>(&'a self, keys: &ElementKeys) -> impl Iterator<Item = &'a RuleEntry> {
+        let id = keys.id.as_ref().and_then(|id| self.ids.get(id));
+        let classes = keys
+            .classes
+            .iter()
+            .filter_map(|class| self.classes.get(class))
+            .collect::<Vec<_>>();
+        id.into_iter()
+            .chain(classes)
+            .chain(self.tags.get(&keys.tag))
+            .flatten()
+            .chain(&self.universal)
+    }
+}
+
+//everything about an element selectors can look at, apart from where it is.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ElementKeys {
+    pub tag: String,
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+    pub states: Vec<PseudoClass>,
+}
+
+impl ElementKeys {
+    pub fn of(el: &Element) -> Self {
+        let mut classes = el
+            .get_attribute("class")
+            .map(|class| class.split_ascii_whitespace().map(String::from).collect())
+            .unwrap_or(vec![]);
+        classes.sort();
+        classes.dedup();
+        Self {
+            tag: el.tag_name.clone(),
+            id: el.get_attribute("id").filter(|id| !id.is_empty()).cloned(),
+            classes,
+            states: el.state.clone(),
+        }
+    }
+
+    //what goes in the bloom filter for an ancestor like this.
+    pub fn hashes(&self) -> Vec<u32> {
+        let mut simples = vec![Selector::Type(self.tag.clone())];
+        simples.extend(self.id.clone().map(Selector::Id));
+        simples.extend(self.classes.iter().cloned().map(Selector::Class));
+        simples
+            .iter()
+            .filter_map(BloomFilter::hash_simple)
+            .collect()
+    }
+}
+
+//the ancestors' tags, ids and classes, hashed twice into 4096 bits. it can say one definitely
+//isn't above the element, never that it definitely is, so it only ever saves work. it's copied
+//for every element with children, so it lives on the stack.
+#[derive(Debug, Clone, Copy)]
+pub struct BloomFilter {
+    bits: [u64; BLOOM_WORDS],
+}
+
+const BLOOM_BITS: u32 = 12;
+const BLOOM_WORDS: usize = 1 << BLOOM_BITS >> 6;
+
+impl Default for BloomFilter {
+    fn default() -> Self {
+        Self {
+            bits: [0; BLOOM_WORDS],
+        }
+    }
+}
+
+impl BloomFilter {
+    pub fn hash(tag: &str) -> u32 {
+        let mut hasher = DefaultHasher::new();
+        tag.hash(&mut hasher);
+        hasher.finish() as u32
+    }
+
+    //written out the way they'd be in a selector, so p, #p and .p don't get mixed up.
+    pub fn hash_simple(simple: &Selector) -> Option<u32> {
+        match simple {
+            Selector::Type(_) | Selector::Id(_) | Selector::Class(_) => {
+                Some(Self::hash(&simple.to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    fn slots(hash: u32) -> [usize; 2] {
+        let mask = (1 << BLOOM_BITS) - 1;
+        [
+            (hash & mask) as usize,
+            ((hash >> BLOOM_BITS) & mask) as usize,
+        ]
+    }
+
+    pub fn insert(&mut self, hash: u32) {
+        for slot in Self::slots(hash) {
+            self.bits[slot >> 6] |= 1 << (slot & 63);
+        }
+    }
+
+    pub fn might_contain(&self, hash: u32) -> bool {
+        Self::slots(hash)
+            .iter()
+            .all(|slot| self.bits[slot >> 6] & 1 << (slot & 63) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::css::{Block, CSSParser, Prelude};
+
+    fn selector(input: &str) -> Selector {
+        let mut parser = CSSParser::default();
+        parser.push_raw_css(&format!("{} {{}}", input));
+        let mut sheets = parser.parse_stylesheets().unwrap();
+        let rule = sheets[0].rules.remove(0);
+        assert!(matches!(rule.value, Block::Declarations(_)));
+        let Prelude::Selector(selector) = rule.prelude else {
+            panic!()
+        };
+        selector
+    }
+
+    fn keys(tag: &str, id: Option<&str>, classes: &[&str]) -> ElementKeys {
+        ElementKeys {
+            tag: String::from(tag),
+            id: id.map(String::from),
+            classes: classes.iter().map(|c| String::from(*c)).collect(),
+            states: vec![],
+        }
+    }
+
+    #[test]
+    fn test_rule_map_and_bloom_filter() {
+        assert_eq!(selector("center  p ,h1>p").to_string(), "center p, h1 > p");
+        assert_eq!(selector("* + h1 p").to_string(), "* + h1 p");
+        assert_eq!(selector("p.a.b #c>.d").to_string(), "p.a.b #c > .d");
+        assert_eq!(selector("a:HOVER .x:focus").to_string(), "a:hover .x:focus");
+        let mut map = RuleMap::default();
+        map.insert(0, &selector("center p, h1"));
+        map.insert(1, &selector("*"));
+        map.insert(2, &selector("body > h2 + p"));
+        map.insert(3, &selector("p.note, .warning"));
+        map.insert(4, &selector("#main.note"));
+        map.insert(5, &selector("center.wide .note > p"));
+        let rules = |keys: &ElementKeys| map.candidates(keys).map(|e| e.rule).collect::<Vec<_>>();
+        assert_eq!(rules(&keys("p", None, &[])), vec![0, 2, 5, 1]);
+        assert_eq!(rules(&keys("h1", None, &[])), vec![0, 1]);
+        assert_eq!(rules(&keys("h2", None, &[])), vec![1]);
+        //ids first, then each class, then the tag.
+        assert_eq!(
+            rules(&keys("p", Some("main"), &["note", "warning"])),
+            vec![4, 3, 3, 0, 2, 5, 1]
+        );
+        assert_eq!(rules(&keys("em", Some("other"), &[])), vec![1]);
+        let p = map.candidates(&keys("p", None, &[])).collect::<Vec<_>>();
+        assert_eq!(p[0].ancestors, vec![BloomFilter::hash("center")]);
+        //the h2's a sibling, not an ancestor.
+        assert_eq!(p[1].ancestors, vec![BloomFilter::hash("body")]);
+        assert_eq!(p[1].compounds.len(), 3);
+        assert_eq!(
+            p[2].ancestors,
+            ["center", ".wide", ".note"].map(BloomFilter::hash)
+        );
+        assert_eq!(
+            keys("p", Some("x"), &["y"]).hashes(),
+            ["p", "#x", ".y"].map(BloomFilter::hash)
+        );
+
+        //nothing's bucketed, and nothing can be ruled out ahead of time.
+        let mut linear = RuleMap::new(true);
+        linear.insert(0, &selector("#main"));
+        linear.insert(1, &selector("center p"));
+        let all = linear
+            .candidates(&keys("h1", None, &[]))
+            .collect::<Vec<_>>();
+        assert_eq!(all.iter().map(|e| e.rule).collect::<Vec<_>>(), vec![0, 1]);
+        assert!(all[1].ancestors.is_empty());
+
+        let mut bloom = BloomFilter::default();
+        assert!(!bloom.might_contain(BloomFilter::hash("body")));
+        bloom.insert(BloomFilter::hash("body"));
+        let mut nested = bloom;
+        nested.insert(BloomFilter::hash("center"));
+        assert!(nested.might_contain(BloomFilter::hash("center")));
+        assert!(nested.might_contain(BloomFilter::hash("body")));
+        assert!(!bloom.might_contain(BloomFilter::hash("center")));
+    }
+}
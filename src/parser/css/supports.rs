@@ -0,0 +1,236 @@
+use std::fmt;
+
+use super::{BlockKind, CSSToken, Component, DeclarationBuilder, Selector, SimpleBlock};
+
+//@supports, and supports() on an @import. "supported" just means we'd parse it, so the answer
+//never changes once the sheet's loaded.
+#[derive(Debug, Clone)]
+pub enum SupportsCondition {
+    Not(Box<SupportsCondition>),
+    And(Vec<SupportsCondition>),
+    Or(Vec<SupportsCondition>),
+    Declaration(String, Vec<Component>),
+    Selector(Vec<Component>),
+    Unknown(Component), //<general-enclosed>, always false
+}
+
+impl SupportsCondition {
+    pub fn from_components(components: &[Component]) -> Option<Self> {
+        let components = components
+            .iter()
+            .filter(|c| !matches!(c, Component::Token(CSSToken::Whitespace)))
+            .collect::<Vec<_>>();
+        Self::parse_condition(&components)
+    }
+
+    //what's inside supports(), which is allowed to be a bare declaration.
+    pub fn from_arguments(arguments: &[Component]) -> Option<Self> {
+        Self::parse_in_parens(&Component::Block(SimpleBlock {
+            kind: BlockKind::Paren,
+            value: arguments.to_vec(),
+        }))
+    }
+
+    //not <in-parens> | <in-parens> [and <in-parens>]* | <in-parens> [or <in-parens>]*
+    fn parse_condition(components: &[&Component]) -> Option<Self> {
+        if let [not, inner] = components {
+            if is_ident(not, "not") {
+                return Some(Self::Not(Box::new(Self::parse_in_parens(inner)?)));
+            }
+        }
+        let (first, rest) = components.split_first()?;
+        let mut conditions = vec![Self::parse_in_parens(first)?];
+        let mut joiner = None;
+        for pair in rest.chunks(2) {
+            let [word, inner] = pair else {
+                return None;
+            };
+            let word = match word {
+                w if is_ident(w, "and") => "and",
+                w if is_ident(w, "or") => "or",
+                _ => return None,
+            };
+            //no mixing and/or without parens
+            if joiner.is_some_and(|j| j != word) {
+                return None;
+            }
+            joiner = Some(word);
+            conditions.push(Self::parse_in_parens(inner)?);
+        }
+        Some(match joiner {
+            None => conditions.pop().unwrap(),
+            Some("and") => Self::And(conditions),
+            _ => Self::Or(conditions),
+        })
+    }
+
+    fn parse_in_parens(component: &Component) -> Option<Self> {
+        match component {
+            Component::Block(block) if block.kind == BlockKind::Paren => {
+                if let Some(declaration) = Self::parse_declaration(&block.value) {
+                    return Some(declaration);
+                }
+                let inner = block
+                    .value
+                    .iter()
+                    .filter(|c| !matches!(c, Component::Token(CSSToken::Whitespace)))
+                    .collect::<Vec<_>>();
+                Some(
+                    Self::parse_condition(&inner)
+                        .unwrap_or_else(|| Self::Unknown(component.clone())),
+                )
+            }
+            Component::Function(name, arguments) if name.eq_ignore_ascii_case("selector") => {
+                let mut arguments = arguments.clone();
+                trim_whitespace(&mut arguments);
+                Some(Self::Selector(arguments))
+            }
+            Component::Function(..) => Some(Self::Unknown(component.clone())),
+            _ => None,
+        }
+    }
+
+    //<ident> : <anything>, or None if it isn't one.
+    fn parse_declaration(components: &[Component]) -> Option<Self> {
+        let colon = Component::Token(CSSToken::Colon);
+        let mut inner = components
+            .iter()
+            .filter(|c| !matches!(c, Component::Token(CSSToken::Whitespace)));
+        let Some(Component::Token(CSSToken::Ident(name))) = inner.next() else {
+            return None;
+        };
+        if inner.next() != Some(&colon) {
+            return None;
+        }
+        let start = components.iter().position(|c| *c == colon)? + 1;
+        let mut value = components[start..].to_vec();
+        trim_whitespace(&mut value);
+        let name = match name.starts_with("--") {
+            true => name.clone(),
+            false => name.to_ascii_lowercase(),
+        };
+        Some(Self::Declaration(name, value))
+    }
+
+    pub fn matches(&self) -> bool {
+        match self {
+            Self::Not(c) => !c.matches(),
+            Self::And(cs) => cs.iter().all(Self::matches),
+            Self::Or(cs) => cs.iter().any(Self::matches),
+            Self::Declaration(name, value) => {
+                if value.is_empty() {
+                    return false;
+                }
+                let mut builder = DeclarationBuilder::from_kind(name.clone());
+                for component in value {
+                    builder.push_value(component.clone());
+                }
+                //var() and custom properties get the benefit of the doubt, same as browsers.
+                builder.build_all().is_ok()
+                    && (name.starts_with("--") || DeclarationBuilder::is_known(name))
+            }
+            Self::Selector(components) => {
+                let mut selector = Selector::Placeheld;
+                components
+                    .iter()
+                    .all(|c| selector.append(c.clone()).is_ok())
+                    && !matches!(selector, Selector::Placeheld)
+                    && selector.unfinished().is_none()
+            }
+            Self::Unknown(_) => false,
+        }
+    }
+
+    fn fmt_nested(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Not(_) | Self::And(_) | Self::Or(_) => write!(f, "({})", self),
+            _ => write!(f, "{}", self),
+        }
+    }
+}
+
+impl fmt::Display for SupportsCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Not(c) => {
+                f.write_str("not ")?;
+                c.fmt_nested(f)
+            }
+            Self::And(cs) | Self::Or(cs) => {
+                let joiner = match self {
+                    Self::And(_) => " and ",
+                    _ => " or ",
+                };
+                for (i, c) in cs.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(joiner)?;
+                    }
+                    c.fmt_nested(f)?;
+                }
+                Ok(())
+            }
+            Self::Declaration(name, value) => {
+                write!(f, "({}: {})", name, Component::serialize(value))
+            }
+            Self::Selector(selector) => write!(f, "selector({})", Component::serialize(selector)),
+            Self::Unknown(c) => f.write_str(&Component::serialize(std::slice::from_ref(c))),
+        }
+    }
+}
+
+fn is_ident(component: &Component, ident: &str) -> bool {
+    matches!(component, Component::Token(CSSToken::Ident(i)) if i.eq_ignore_ascii_case(ident))
+}
+
+fn trim_whitespace(components: &mut Vec<Component>) {
+    while let Some(Component::Token(CSSToken::Whitespace)) = components.last() {
+        components.pop();
+    }
+    let start = components
+        .iter()
+        .position(|c| c != &Component::Token(CSSToken::Whitespace))
+        .unwrap_or(components.len());
+    components.drain(..start);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::css::CSSParser;
+
+    fn condition(input: &str) -> Option<SupportsCondition> {
+        SupportsCondition::from_components(
+            &CSSParser::parse_component_list(&String::from(input)).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_supports_conditions() {
+        let supported = |input| condition(input).unwrap().matches();
+        assert!(supported("(display: block)"));
+        assert!(!supported("(display: grid)"));
+        assert!(!supported("(shmisplay: block)"));
+        assert!(supported("(--anything: at all)"));
+        assert!(supported("(width: var(--w))"));
+        assert!(supported("not (display: grid)"));
+        assert!(supported(
+            "(display: block) and ((color: red) or (display: grid))"
+        ));
+        assert!(!supported("(display: block) and (display: grid)"));
+        assert!(supported("selector(p > a)"));
+        assert!(supported("selector(p.a:hover)"));
+        assert!(!supported("selector(p:first-child)"));
+        assert!(!supported("selector(p.)"));
+        assert!(!supported("font-tech(color-COLRv1)"));
+        assert!(!supported("(shmeep)"));
+        //mixing without parens, or nothing in parens at all, doesn't parse.
+        assert!(condition("(a: b) and (c: d) or (e: f)").is_none());
+        assert!(condition("display: block").is_none());
+        assert_eq!(
+            condition("not ((display:block) and (color : red))")
+                .unwrap()
+                .to_string(),
+            "not ((display: block) and (color: red))"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{CSSToken, Component, SimpleBlock};
+
+pub type CustomProperties = HashMap<String, Vec<Component>>;
+
+pub fn contains_var(value: &[Component]) -> bool {
+    value.iter().any(|c| match c {
+        Component::Function(f, _) if f.eq_ignore_ascii_case("var") => true,
+        Component::Function(_, arguments) => contains_var(arguments),
+        Component::Block(block) => contains_var(&block.value),
+        Component::Token(_) => false,
+    })
+}
+
+//the custom properties an element ends up with: everything inherited from the parent, with the
+//ones declared on the element itself on top. every var() in the result is already substituted.
+pub fn compute_custom_properties(
+    parent: &CustomProperties,
+    declared: &CustomProperties,
+) -> CustomProperties {
+    let mut resolver = Resolver {
+        parent,
+        declared,
+        computed: parent.clone(),
+        done: HashSet::new(),
+        stack: vec![],
+        cyclic: HashSet::new(),
+    };
+    for name in declared.keys() {
+        resolver.resolve(name);
+    }
+    resolver.computed
+}
+
+//None means invalid at computed-value time, the caller decides what that turns into.
+pub fn substitute(value: &[Component], custom: &CustomProperties) -> Option<Vec<Component>> {
+    substitute_with(value, &mut |name| custom.get(name).cloned())
+}
+
+struct Resolver<'a> {
+    parent: &'a CustomProperties,
+    declared: &'a CustomProperties,
+    computed: CustomProperties,
+    done: HashSet<String>,
+    stack: Vec<String>,
+    cyclic: HashSet<String>,
+}
+
+impl<'a> Resolver<'a> {
+    fn resolve(&mut self, name: &str) -> Option<Vec<Component>> {
+        let declared = self.declared;
+        let Some(value) = declared.get(name) else {
+            return self.parent.get(name).cloned();
+        };
+        if self.done.contains(name) {
+            return self.computed.get(name).cloned();
+        }
+        if let Some(start) = self.stack.iter().position(|n| n == name) {
+            //everything from where we first saw this one is part of the loop.
+            self.cyclic.extend(self.stack[start..].iter().cloned());
+            return None;
+        }
+        let keyword = match value.as_slice() {
+            [Component::Token(CSSToken::Ident(i))] => Some(i.to_ascii_lowercase()),
+            _ => None,
+        };
+        self.stack.push(name.to_string());
+        let resolved = match keyword.as_deref() {
+            Some("initial") => None,
+            Some("inherit" | "unset") => self.parent.get(name).cloned(),
+            _ => substitute_with(value, &mut |n| self.resolve(n)),
+        };
+        self.stack.pop();
+        self.done.insert(name.to_string());
+        //loops make every property in them guaranteed-invalid, and so does anything else that
+        //fails to substitute. that's invalid at computed-value time, which for a custom
+        //property means its initial value, not the parent's.
+        let resolved = match self.cyclic.contains(name) {
+            true => None,
+            false => resolved,
+        };
+        match resolved {
+            Some(ref value) => self.computed.insert(name.to_string(), value.clone()),
+            None => self.computed.remove(name),
+        };
+        resolved
+    }
+}
+
+fn substitute_with(
+    value: &[Component],
+    lookup: &mut dyn FnMut(&str) -> Option<Vec<Component>>,
+) -> Option<Vec<Component>> {
+    let mut out = Vec::with_capacity(value.len());
+    for component in value {
+        match component {
+            Component::Function(f, arguments) if f.eq_ignore_ascii_case("var") => {
+                let (name, fallback) = var_arguments(arguments)?;
+                match lookup(name) {
+                    Some(v) => out.extend(v),
+                    None => out.extend(substitute_with(fallback?, lookup)?),
+                }
+            }
+            //var()s can hide inside other functions, calc(var(--a) * 2) and such.
+            Component::Function(f, arguments) => {
+                out.push(Component::Function(
+                    f.clone(),
+                    substitute_with(arguments, lookup)?,
+                ));
+            }
+            Component::Block(block) => {
+                out.push(Component::Block(SimpleBlock {
+                    kind: block.kind,
+                    value: substitute_with(&block.value, lookup)?,
+                }));
+            }
+            c => out.push(c.clone()),
+        }
+    }
+    Some(out)
+}
+
+//var(--name) or var(--name, fallback), the fallback can be empty.
+fn var_arguments(arguments: &[Component]) -> Option<(&str, Option<&[Component]>)> {
+    let mut iter = arguments
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !matches!(c, Component::Token(CSSToken::Whitespace)));
+    let name = match iter.next()? {
+        (_, Component::Token(CSSToken::Ident(name))) if name.starts_with("--") => name.as_str(),
+        _ => return None,
+    };
+    match iter.next() {
+        None => Some((name, None)),
+        Some((i, Component::Token(CSSToken::Comma))) => Some((name, Some(&arguments[i + 1..]))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::css::CSSParser;
+
+    fn components(input: &str) -> Vec<Component> {
+        CSSParser::parse_component_list(&String::from(input)).unwrap()
+    }
+
+    fn declared(pairs: &[(&str, &str)]) -> CustomProperties {
+        pairs
+            .iter()
+            .map(|(n, v)| (n.to_string(), components(v)))
+            .collect()
+    }
+
+    #[test]
+    fn test_var_substitution_and_fallbacks() {
+        let parent = declared(&[("--shmeep", "10px"), ("--nope", "3px")]);
+        let custom = compute_custom_properties(
+            &parent,
+            &declared(&[("--shmop", "var(--shmeep)"), ("--nope", "var(--missing)")]),
+        );
+        assert_eq!(custom["--shmop"], components("10px"));
+        //nothing to fall back on, so it's guaranteed-invalid, even though the parent has one.
+        assert!(!custom.contains_key("--nope"));
+        assert_eq!(
+            substitute(&components("var(--missing, var(--shmop))"), &custom).unwrap(),
+            components(" 10px")
+        );
+        assert!(substitute(&components("var(--missing)"), &custom).is_none());
+        assert_eq!(
+            substitute(&components("calc(var(--shmop) * 2)"), &custom).unwrap(),
+            components("calc(10px * 2)")
+        );
+    }
+
+    #[test]
+    fn test_var_cycles() {
+        let parent = declared(&[("--a", "1px")]);
+        let custom = compute_custom_properties(
+            &parent,
+            &declared(&[
+                ("--a", "var(--b)"),
+                ("--b", "var(--a)"),
+                ("--c", "var(--b, 2px)"),
+            ]),
+        );
+        assert!(!custom.contains_key("--a"));
+        assert!(!custom.contains_key("--b"));
+        assert_eq!(custom["--c"], components(" 2px"));
+    }
+}
@@ -115,6 +115,7 @@ impl HTMLParser {
                 TokenizationState::AfterAttributeValueQuoted => {
                     self.tokenize_after_attribute_value_quoted()?
                 }
+                TokenizationState::SelfClosingStartTag => self.tokenize_self_closing_start_tag()?,
                 a => {
                     do yeet ParserError::UnimplementedTokenizationState(*a);
                 }
@@ -129,6 +130,21 @@ impl HTMLParser {
         self.open_elements.last().cloned()
     }
 
+    //pops everything up to and including the innermost open element with that name. an end tag
+    //for something that isn't open (a stray one, or one that was misnested and already got
+    //closed) gets ignored, instead of closing body and html along with everything else.
+    fn close_element(&mut self, name: &str, document: &mut Document) {
+        let open = self.open_elements.iter().rposition(|element| {
+            document
+                .get_element_for_coordinate(element.coordinate.clone())
+                .tag_name
+                == name
+        });
+        if let Some(index) = open {
+            self.open_elements.truncate(index);
+        }
+    }
+
     fn emit(&mut self, token: Token) -> Result<(), ParserError> {
         self.tokens_available = true;
         if let Token::StartTag { ref name, .. } = token {
@@ -281,6 +297,19 @@ impl HTMLParser {
             Token::StartTag { ref name, .. } if name == "style" || name == "noframes" => {
                 self.generic_parsing_algorithm(token, true, document)?;
             }
+            Token::StartTag { name, attributes }
+                if name == "base"
+                    || name == "basefont"
+                    || name == "bgsound"
+                    || name == "link"
+                    || name == "meta" =>
+            {
+                //void elements, nothing can go inside so they never get pushed to the stack.
+                //TODO: meta charset/http-equiv
+                document
+                    .get_element_for_coordinate(self.current_element().unwrap().coordinate)
+                    .insert_element(name, attributes);
+            }
             Token::EndTag { ref name } if name == "head" => {
                 let _ = self.open_elements.pop();
                 self.insertion_mode = InsertionMode::AfterHead;
@@ -368,24 +397,26 @@ impl HTMLParser {
             {
                 //TODO: Generate implied end tags
                 //TODO: Check for element in scope
-                while let Some(element) = self.open_elements.pop() {
-                    let element_name = &document
-                        .get_element_for_coordinate(element.coordinate)
-                        .tag_name;
-                    if element_name == &name {
-                        break;
-                    }
-                }
+                self.close_element(&name, document);
             }
-            Token::EndTag { name } if name == "p" || name == "center" => {
-                while let Some(element) = self.open_elements.pop() {
-                    let element_name = &document
-                        .get_element_for_coordinate(element.coordinate)
-                        .tag_name;
-                    if element_name == &name {
-                        break;
-                    }
-                }
+            Token::StartTag { name, attributes } if PHRASING.contains(&name.as_str()) => {
+                //TODO: a, b and friends are formatting elements, and should be going through the
+                //list of active formatting elements and the adoption agency.
+                let coordinate = document
+                    .get_element_for_coordinate(self.current_element().unwrap().coordinate)
+                    .insert_element(name, attributes);
+                self.open_elements.push(OpenElement { coordinate });
+            }
+            Token::StartTag { name, attributes } if name == "img" => {
+                //void, so it's never open.
+                document
+                    .get_element_for_coordinate(self.current_element().unwrap().coordinate)
+                    .insert_element(name, attributes);
+            }
+            Token::EndTag { name }
+                if name == "p" || name == "center" || PHRASING.contains(&name.as_str()) =>
+            {
+                self.close_element(&name, document);
             }
             Token::EndTag { name } if name == "body" => {
                 //TODO: Check for body tag in scope
@@ -1364,6 +1395,26 @@ impl HTMLParser {
         Ok(())
     }
 
+    fn tokenize_self_closing_start_tag(&mut self) -> Result<(), ParserError> {
+        match self.consume() {
+            Char::Char('>') => {
+                //TODO: acknowledge the self-closing flag. void elements don't need it, and for
+                //anything else it's meant to be ignored anyway.
+                self.tokenization_state = TokenizationState::Data;
+                self.emit_current()?;
+            }
+            Char::Char(_) => {
+                self.reconsume(TokenizationState::BeforeAttributeName);
+                do yeet ParsingError::UnexpectedSoidusInTag;
+            }
+            Char::Eof => {
+                self.emit(Token::EOF)?;
+                do yeet ParsingError::EofInTag;
+            }
+        }
+        Ok(())
+    }
+
     fn current_tag_is_appropriate(&self) -> Result<bool, ParserError> {
         Ok(if let Token::EndTag { ref name } = self.current_token {
             name
@@ -1630,6 +1681,11 @@ pub enum ParsingError {
     UnknownNamedCharacterReference,
 }
 
+//inline elements that don't need anything special done when they open or close.
+const PHRASING: &[&str] = &[
+    "a", "abbr", "b", "cite", "code", "em", "i", "small", "span", "strong", "sub", "sup", "u",
+];
+
 #[derive(Default, Debug, Clone)]
 struct OpenElement {
     coordinate: DOMCoordinate,
@@ -1641,6 +1697,7 @@ mod tests {
     use std::io::Cursor;
 
     use super::*;
+    use crate::{layout::test_util::find, parser::css::CSSSource};
 
     #[inline(always)]
     //many such cases
@@ -1726,5 +1783,33 @@ mod tests {
     }
 
     //parser tests go here
+    #[test]
+    fn test_parse_link_in_head() {
+	let input = "<!DOCTYPE html><html><head><link rel=\"stylesheet\" href=\"shmeep.css\"><link rel=\"icon\" href=\"shmop.png\"><title>x</title></head><body></body></html>";
+	let mut parser = HTMLParser::default();
+	parser.load_from_whatever(&mut Cursor::new(input)).unwrap();
+	let mut document = Document::default();
+	parser.parse(&mut document).unwrap();
+	let base = url::Url::parse("https://shmeep.org/a/b.html").unwrap();
+	let sources = document.find_css_sources(&base);
+	assert_eq!(sources.len(), 1);
+	assert!(matches!(&sources[0], CSSSource::URL(url) if url.as_str() == "https://shmeep.org/a/shmeep.css"));
+    }
+
+    #[test]
+    fn test_stray_and_misnested_end_tags() {
+	let input = "<!DOCTYPE html><html><head></head><body><p>a</span>b<b><i>c</b>d</i>e</p></body></html>";
+	let mut parser = HTMLParser::default();
+	parser.load_from_whatever(&mut Cursor::new(input)).unwrap();
+	let mut document = Document::default();
+	parser.parse(&mut document).unwrap();
+	//neither end tag closes anything that isn't theirs, so everything's still in the p.
+	let body = find(&document.children, "body").unwrap();
+	let p = find(&body.children, "p").unwrap();
+	assert_eq!(body.children.len(), 1);
+	let b = find(&p.children, "b").unwrap();
+	assert!(find(&b.children, "i").is_some());
+	assert!(matches!(p.children.last(), Some(Node::Text(text)) if text.ends_with('e')));
+    }
 
 }
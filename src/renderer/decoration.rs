@@ -0,0 +1,368 @@
+use std::f64::consts::PI;
+
+use vello::{
+    kurbo::{Affine, Arc, BezPath, Cap, Join, Point, Rect, Stroke, Vec2},
+    peniko::{BlendMode, BrushRef, Color, Compose, Fill, Mix},
+    SceneBuilder,
+};
+
+use crate::{
+    layout::LayoutInfo,
+    parser::css::{
+        properties::{BorderStyle, Colour, CornerRadius, Dimensionality, Shadow},
+        CSSProps, CSSValue,
+    },
+};
+
+//top left, top right, bottom right, bottom left, each across then down.
+pub type Radii = [Vec2; 4];
+
+//one side of a border or an outline.
+#[derive(Debug, Clone, Copy)]
+pub struct Edge {
+    pub width: f64,
+    pub style: BorderStyle,
+    pub colour: Colour,
+}
+
+pub fn colour(colour: Colour) -> Color {
+    let colour = colour.real;
+    Color::rgba8(colour.red, colour.green, colour.blue, colour.alpha)
+}
+
+//where it is on the screen rather than the page.
+pub fn rect(layout_info: LayoutInfo, scroll_y: f64) -> Rect {
+    Rect::new(
+        layout_info.x,
+        layout_info.y - scroll_y,
+        layout_info.x + layout_info.width,
+        layout_info.y - scroll_y + layout_info.height,
+    )
+}
+
+//percentages are of the border box. if the two corners on a side add up to more than it's long,
+//every corner gets scaled down by the same amount until they all fit.
+pub fn corner_radii(css: &CSSProps, border_box: Rect) -> Radii {
+    let (width, height) = (border_box.width(), border_box.height());
+    let radius = |radius: &CSSValue<CornerRadius>| match radius {
+        CSSValue::Value(radius) => match radius.resolve(width, height) {
+            //a corner's only round if it's round both ways.
+            (x, y) if x > 0. && y > 0. => Vec2::new(x, y),
+            _ => Vec2::ZERO,
+        },
+        _ => Vec2::ZERO,
+    };
+    let radii = [
+        radius(&css.border_top_left_radius),
+        radius(&css.border_top_right_radius),
+        radius(&css.border_bottom_right_radius),
+        radius(&css.border_bottom_left_radius),
+    ];
+    let sides = [
+        (radii[0].x + radii[1].x, width),
+        (radii[1].y + radii[2].y, height),
+        (radii[2].x + radii[3].x, width),
+        (radii[3].y + radii[0].y, height),
+    ];
+    let scale = sides
+        .iter()
+        .filter(|(sum, _)| *sum > 0.)
+        .map(|(sum, length)| length / sum)
+        .fold(1., f64::min);
+    radii.map(|radius| radius * scale)
+}
+
+//the radii for a box that's this much bigger on each side (top, left, right, bottom, like
+//LayoutInfo), or smaller if it's negative. square corners stay square.
+pub fn grow(radii: Radii, (top, left, right, bottom): (f64, f64, f64, f64)) -> Radii {
+    let grow = |radius: Vec2, x: f64, y: f64| match radius == Vec2::ZERO {
+        true => radius,
+        false => Vec2::new((radius.x + x).max(0.), (radius.y + y).max(0.)),
+    };
+    [
+        grow(radii[0], left, top),
+        grow(radii[1], right, top),
+        grow(radii[2], right, bottom),
+        grow(radii[3], left, bottom),
+    ]
+}
+
+//the padding box, with the border's inner curves, which is what overflow gets cut off at.
+pub fn padding_box_path(layout_info: &LayoutInfo, css: &CSSProps, scroll_y: f64) -> BezPath {
+    let border_box = rect(layout_info.border_box(), scroll_y);
+    let (top, left, right, bottom) = layout_info.border;
+    let radii = grow(
+        corner_radii(css, border_box),
+        (-top, -left, -right, -bottom),
+    );
+    rounded_rect(rect(layout_info.padding_box(), scroll_y), radii)
+}
+
+//clockwise from the top left, with elliptical corners.
+pub fn rounded_rect(rect: Rect, radii: Radii) -> BezPath {
+    let [top_left, top_right, bottom_right, bottom_left] = radii;
+    let mut path = BezPath::new();
+    let corner = |path: &mut BezPath, centre: Point, radius: Vec2, start: f64| {
+        if radius != Vec2::ZERO {
+            path.extend(Arc::new(centre, radius, start, PI / 2., 0.).append_iter(0.1));
+        }
+    };
+    path.move_to((rect.x0 + top_left.x, rect.y0));
+    path.line_to((rect.x1 - top_right.x, rect.y0));
+    let centre = Point::new(rect.x1 - top_right.x, rect.y0 + top_right.y);
+    corner(&mut path, centre, top_right, -PI / 2.);
+    path.line_to((rect.x1, rect.y1 - bottom_right.y));
+    let centre = Point::new(rect.x1 - bottom_right.x, rect.y1 - bottom_right.y);
+    corner(&mut path, centre, bottom_right, 0.);
+    path.line_to((rect.x0 + bottom_left.x, rect.y1));
+    let centre = Point::new(rect.x0 + bottom_left.x, rect.y1 - bottom_left.y);
+    corner(&mut path, centre, bottom_left, PI / 2.);
+    path.line_to((rect.x0, rect.y0 + top_left.y));
+    let centre = Point::new(rect.x0 + top_left.x, rect.y0 + top_left.y);
+    corner(&mut path, centre, top_left, PI);
+    path.close_path();
+    path
+}
+
+//each side (top, right, bottom, left) is strokes going round the whole box, clipped to that
+//side's own trapezoid so the corners meet on the diagonal. the strokes follow the corners if
+//they're round.
+pub fn draw_edges(
+    builder: &mut SceneBuilder,
+    transform: Affine,
+    outer: Rect,
+    radii: Radii,
+    edges: [Edge; 4],
+) {
+    let widths = edges.map(|edge| edge.width);
+    let corners = |r: Rect| {
+        [
+            Point::new(r.x0, r.y0),
+            Point::new(r.x1, r.y0),
+            Point::new(r.x1, r.y1),
+            Point::new(r.x0, r.y1),
+        ]
+    };
+    //a rect `fraction` of the way in from the outside of every side.
+    let inset = |fraction: f64| {
+        let [top, right, bottom, left] = widths.map(|w| w * fraction);
+        let r = Rect::new(
+            outer.x0 + left,
+            outer.y0 + top,
+            outer.x1 - right,
+            outer.y1 - bottom,
+        );
+        (r, grow(radii, (-top, -left, -right, -bottom)))
+    };
+    let (outside, inside) = (corners(outer), corners(inset(1.).0));
+    for (i, edge) in edges.into_iter().enumerate() {
+        let Edge {
+            width,
+            style,
+            colour: light,
+        } = edge;
+        if width <= 0. || light.real.alpha == 0 {
+            continue;
+        }
+        let dark = Colour::new(
+            (light.real.red as f64 * 2. / 3.) as u8,
+            (light.real.green as f64 * 2. / 3.) as u8,
+            (light.real.blue as f64 * 2. / 3.) as u8,
+            light.real.alpha,
+        );
+        //the 3d ones are lit from the top left.
+        let top_left = i == 0 || i == 3;
+        //how far across the side each stroke's middle is, how much of it it covers and what
+        //colour it is.
+        let strokes = match style {
+            BorderStyle::None | BorderStyle::Hidden => vec![],
+            BorderStyle::Solid | BorderStyle::Dashed | BorderStyle::Dotted => {
+                vec![(0.5, 1., light)]
+            }
+            BorderStyle::Double => vec![(1. / 6., 1. / 3., light), (5. / 6., 1. / 3., light)],
+            BorderStyle::Groove | BorderStyle::Ridge => {
+                let (outer, inner) = match top_left == (style == BorderStyle::Groove) {
+                    true => (dark, light),
+                    false => (light, dark),
+                };
+                vec![(0.25, 0.5, outer), (0.75, 0.5, inner)]
+            }
+            BorderStyle::Inset | BorderStyle::Outset => {
+                let shade = match top_left == (style == BorderStyle::Inset) {
+                    true => dark,
+                    false => light,
+                };
+                vec![(0.5, 1., shade)]
+            }
+        };
+        let mut clip = BezPath::new();
+        clip.move_to(outside[i]);
+        clip.line_to(outside[(i + 1) % 4]);
+        clip.line_to(inside[(i + 1) % 4]);
+        clip.line_to(inside[i]);
+        clip.close_path();
+        builder.push_layer(Mix::Clip, 1., transform, &clip);
+        for (depth, thickness, shade) in strokes {
+            let stroke = Stroke::new(width * thickness)
+                .with_caps(Cap::Butt)
+                .with_join(Join::Miter);
+            let stroke = match style {
+                BorderStyle::Dashed => stroke.with_dashes(0., [width * 3., width * 3.]),
+                BorderStyle::Dotted => stroke.with_dashes(0., [width, width]),
+                _ => stroke,
+            };
+            let (line, radii) = inset(depth);
+            builder.stroke(
+                &stroke,
+                transform,
+                BrushRef::Solid(colour(shade)),
+                None,
+                &rounded_rect(line, radii),
+            );
+        }
+        builder.pop_layer();
+    }
+}
+
+pub fn border_edges(css: &CSSProps, layout_info: &LayoutInfo) -> [Edge; 4] {
+    let border = layout_info.border;
+    let edge = |width, style: &CSSValue<BorderStyle>, colour: &CSSValue<Colour>| Edge {
+        width,
+        style: style.unwrap(),
+        colour: colour.unwrap(),
+    };
+    [
+        edge(border.0, &css.border_top_style, &css.border_top_color),
+        edge(border.2, &css.border_right_style, &css.border_right_color),
+        edge(border.3, &css.border_bottom_style, &css.border_bottom_color),
+        edge(border.1, &css.border_left_style, &css.border_left_color),
+    ]
+}
+
+//outset ones only show outside the border box and inset ones only inside the padding box, so
+//each gets drawn in a layer of its own and has the other side knocked back out of it.
+pub fn draw_shadows(
+    builder: &mut SceneBuilder,
+    transform: Affine,
+    shadows: &[Shadow],
+    inset: bool,
+    border_box: Rect,
+    radii: Radii,
+    border: (f64, f64, f64, f64),
+) {
+    let (top, left, right, bottom) = border;
+    let padding_box = Rect::new(
+        border_box.x0 + left,
+        border_box.y0 + top,
+        border_box.x1 - right,
+        border_box.y1 - bottom,
+    );
+    let padding_radii = grow(radii, (-top, -left, -right, -bottom));
+    //first one's on top, so it goes last.
+    for shadow in shadows.iter().rev().filter(|shadow| shadow.inset == inset) {
+        let px = |length: &Dimensionality| length.resolve(None).unwrap_or(0.);
+        let (offset, blur, spread) = (
+            Vec2::new(px(&shadow.x), px(&shadow.y)),
+            px(&shadow.blur),
+            px(&shadow.spread),
+        );
+        //inset ones are the shape of the padding box shrunk by the spread, with everything
+        //outside it shaded.
+        let (base, base_radii, spread) = match inset {
+            true => (padding_box, padding_radii, -spread),
+            false => (border_box, radii, spread),
+        };
+        let shape = base.inflate(spread, spread) + offset;
+        let shape_radii = grow(base_radii, (spread, spread, spread, spread));
+        //the blur takes it this far out, and then some.
+        let bounds = shape.inflate(blur * 2., blur * 2.).union(base);
+        let brush = colour(shadow.colour);
+        match inset {
+            false => {
+                builder.push_layer(Mix::Normal, 1., transform, &bounds);
+                fill_shape(builder, transform, shape, shape_radii, blur, brush);
+                builder.push_layer(
+                    BlendMode::new(Mix::Normal, Compose::DestOut),
+                    1.,
+                    transform,
+                    &base,
+                );
+                fill_shape(builder, transform, base, base_radii, 0., Color::BLACK);
+                builder.pop_layer();
+            }
+            true => {
+                let clip = rounded_rect(base, base_radii);
+                builder.push_layer(Mix::Clip, 1., transform, &clip);
+                builder.fill(Fill::NonZero, transform, brush, None, &base);
+                builder.push_layer(
+                    BlendMode::new(Mix::Normal, Compose::DestOut),
+                    1.,
+                    transform,
+                    &base,
+                );
+                fill_shape(builder, transform, shape, shape_radii, blur, Color::BLACK);
+                builder.pop_layer();
+            }
+        }
+        builder.pop_layer();
+    }
+}
+
+//vello's blurred rects only do the one circular radius, so blurred ones get the average.
+fn fill_shape(
+    builder: &mut SceneBuilder,
+    transform: Affine,
+    shape: Rect,
+    radii: Radii,
+    blur: f64,
+    brush: Color,
+) {
+    if shape.width() <= 0. || shape.height() <= 0. {
+        return;
+    }
+    match blur > 0. {
+        true => {
+            let radius = radii.iter().map(|r| r.x + r.y).sum::<f64>() / 8.;
+            //the blur radius is twice the standard deviation.
+            builder.draw_blurred_rounded_rect(transform, shape, brush, radius, blur / 2.);
+        }
+        false => builder.fill(
+            Fill::NonZero,
+            transform,
+            brush,
+            None,
+            &rounded_rect(shape, radii),
+        ),
+    }
+}
+
+//outside the border box and offset from it, following its corners.
+pub fn draw_outline(
+    builder: &mut SceneBuilder,
+    transform: Affine,
+    css: &CSSProps,
+    border_box: Rect,
+    radii: Radii,
+) {
+    let style = css.outline_style.unwrap().border_style();
+    let width = css.outline_width.unwrap().px();
+    let offset = match &css.outline_offset {
+        CSSValue::Value(offset) => offset.resolve(None).unwrap_or(0.),
+        _ => 0.,
+    };
+    if width <= 0. {
+        return;
+    }
+    let grown = offset + width;
+    draw_edges(
+        builder,
+        transform,
+        border_box.inflate(grown, grown),
+        grow(radii, (grown, grown, grown, grown)),
+        [Edge {
+            width,
+            style,
+            colour: css.outline_color.unwrap(),
+        }; 4],
+    );
+}
@@ -0,0 +1,494 @@
+use vello::kurbo::Affine;
+
+use crate::{
+    context::Viewport,
+    dom::{Element, Node},
+    layout::{inline::is_atomic, text::LaidoutText},
+    parser::css::{
+        properties::{Dimensionality, DisplayOutside, Isolation, Overflow, Position, ZIndex},
+        CSSValue,
+    },
+};
+
+//something to paint, with the scroll and transform it gets painted at, and what it gets clipped
+//to, outermost first.
+#[derive(Debug)]
+pub struct DisplayItem<'a> {
+    pub kind: ItemKind<'a>,
+    pub scroll_y: f64,
+    pub transform: Affine,
+    pub clips: Vec<Clip<'a>>,
+}
+
+//a box with overflow that isn't visible, which cuts off what's in it at its padding box.
+#[derive(Debug, Clone, Copy)]
+pub struct Clip<'a> {
+    pub el: &'a Element,
+    pub scroll_y: f64,
+    pub transform: Affine,
+}
+
+impl PartialEq for Clip<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.el, other.el)
+    }
+}
+
+#[derive(Debug)]
+pub enum ItemKind<'a> {
+    Box(&'a Element), //its shadows, background and border, for every box it's got
+    Outline(&'a Element),
+    Text(&'a LaidoutText),
+    //everything up to the pop gets painted on its own and then put down at this opacity.
+    PushOpacity(f32),
+    PopOpacity,
+}
+
+//what a box gets painted with, and the top and bottom of the nearest block's content box, which
+//sticky ones have to stay in.
+#[derive(Debug, Clone)]
+struct Context<'a> {
+    scroll_y: f64,
+    transform: Affine,
+    container: (f64, f64),
+    viewport: Viewport,
+    clips: Vec<Clip<'a>>,
+    //how many of the clips are from the nearest positioned box or above it, which are the only
+    //ones that still apply to anything absolutely positioned in there.
+    containing_clips: usize,
+}
+
+impl<'a> Context<'a> {
+    fn item(&self, kind: ItemKind<'a>) -> DisplayItem<'a> {
+        DisplayItem {
+            kind,
+            scroll_y: self.scroll_y,
+            transform: self.transform,
+            clips: self.clips.clone(),
+        }
+    }
+}
+
+//the steps of css 2.1 appendix e that don't come in z-index order, for one stacking context or
+//something painted like one.
+#[derive(Default)]
+struct Content<'a> {
+    blocks: Vec<DisplayItem<'a>>,
+    floats: Vec<DisplayItem<'a>>,
+    inlines: Vec<DisplayItem<'a>>,
+    outlines: Vec<DisplayItem<'a>>,
+}
+
+//the ones that do. positioned has the z-index 0 stacking contexts and the positioned boxes with
+//auto, in tree order.
+#[derive(Default)]
+struct Layers<'a> {
+    negative: Vec<(i32, Vec<DisplayItem<'a>>)>,
+    positioned: Vec<Vec<DisplayItem<'a>>>,
+    positive: Vec<(i32, Vec<DisplayItem<'a>>)>,
+}
+
+//everything in the document in the order it gets painted.
+pub fn build(nodes: &[Node], scroll_y: f64, viewport: Viewport) -> Vec<DisplayItem<'_>> {
+    let context = Context {
+        scroll_y,
+        transform: Affine::IDENTITY,
+        container: (f64::NEG_INFINITY, f64::INFINITY),
+        viewport,
+        clips: vec![],
+        containing_clips: 0,
+    };
+    //the root element always makes one.
+    let mut items = vec![];
+    for node in nodes {
+        if let Node::Element(el) = node {
+            if el.layout_info.laid_out {
+                stacking_context(el, enter(el, &context), &mut items);
+            }
+        }
+    }
+    items
+}
+
+//a stacking context paints its own box, then the ones with negative z-indexes, then blocks, then
+//floats, then inline content, then positioned boxes and the rest of the stacking contexts, then
+//outlines. nothing outside it can end up between any of that.
+fn stacking_context<'a>(el: &'a Element, context: Context<'a>, items: &mut Vec<DisplayItem<'a>>) {
+    let opacity = opacity(el);
+    if opacity < 1. {
+        items.push(context.item(ItemKind::PushOpacity(opacity as f32)));
+    }
+    items.push(context.item(ItemKind::Box(el)));
+    paint_children(&el.children, &inside(el, &context), items);
+    items.push(context.item(ItemKind::Outline(el)));
+    if opacity < 1. {
+        items.push(context.item(ItemKind::PopOpacity));
+    }
+}
+
+fn paint_children<'a>(nodes: &'a [Node], context: &Context<'a>, items: &mut Vec<DisplayItem<'a>>) {
+    let (mut content, mut layers) = (Content::default(), Layers::default());
+    gather(nodes, context, &mut content, &mut layers);
+    //sorting's stable, so ones with the same z-index stay in tree order.
+    layers.negative.sort_by_key(|(z, _)| *z);
+    layers.positive.sort_by_key(|(z, _)| *z);
+    items.extend(layers.negative.into_iter().flat_map(|(_, layer)| layer));
+    items.extend(content.blocks);
+    items.extend(content.floats);
+    items.extend(content.inlines);
+    items.extend(layers.positioned.into_iter().flatten());
+    items.extend(layers.positive.into_iter().flat_map(|(_, layer)| layer));
+    items.extend(content.outlines);
+}
+
+//painted as if it were a stacking context, except anything positioned or that really is one
+//goes to the stacking context it's in.
+fn pseudo_context<'a>(
+    el: &'a Element,
+    context: Context<'a>,
+    layers: &mut Layers<'a>,
+    items: &mut Vec<DisplayItem<'a>>,
+) {
+    let mut content = Content::default();
+    items.push(context.item(ItemKind::Box(el)));
+    gather(&el.children, &inside(el, &context), &mut content, layers);
+    items.extend(content.blocks);
+    items.extend(content.floats);
+    items.extend(content.inlines);
+    items.extend(content.outlines);
+    items.push(context.item(ItemKind::Outline(el)));
+}
+
+fn gather<'a>(
+    nodes: &'a [Node],
+    context: &Context<'a>,
+    content: &mut Content<'a>,
+    layers: &mut Layers<'a>,
+) {
+    for node in nodes {
+        let el = match node {
+            Node::Element(el) => el,
+            Node::LaidoutText(text) => {
+                content.inlines.push(context.item(ItemKind::Text(text)));
+                continue;
+            }
+            _ => continue,
+        };
+        //no box, but display: contents still has children that do.
+        if !el.layout_info.laid_out {
+            if let DisplayOutside::Contents = el.display().outside {
+                gather(&el.children, context, content, layers);
+            }
+            continue;
+        }
+        let context = enter(el, context);
+        let inline = matches!(el.display().outside, DisplayOutside::Inline);
+        if creates_stacking_context(el) {
+            let mut items = vec![];
+            stacking_context(el, context, &mut items);
+            match z_index(el).unwrap_or(0) {
+                z if z < 0 => layers.negative.push((z, items)),
+                0 => layers.positioned.push(items),
+                z => layers.positive.push((z, items)),
+            }
+        } else if el.position() != Position::Static {
+            //anything positioned in it comes after it in tree order, so it keeps its place.
+            let index = layers.positioned.len();
+            layers.positioned.push(vec![]);
+            let mut items = vec![];
+            pseudo_context(el, context, layers, &mut items);
+            layers.positioned[index] = items;
+        } else if el.is_floated() {
+            pseudo_context(el, context, layers, &mut content.floats);
+        } else if inline && is_atomic(el) {
+            pseudo_context(el, context, layers, &mut content.inlines);
+        } else {
+            let kind = ItemKind::Box(el);
+            match inline {
+                true => content.inlines.push(context.item(kind)),
+                false => content.blocks.push(context.item(kind)),
+            }
+            gather(&el.children, &inside(el, &context), content, layers);
+            content.outlines.push(context.item(ItemKind::Outline(el)));
+        }
+    }
+}
+
+//the context a box and everything in it gets painted in. fixed ones don't scroll at all and
+//sticky ones scroll less while they're stuck. absolutely positioned ones only get clipped by
+//their containing block and what's around that, and fixed ones by nothing.
+fn enter<'a>(el: &Element, context: &Context<'a>) -> Context<'a> {
+    let scroll_y = match el.position() {
+        Position::Fixed => 0.,
+        Position::Sticky => {
+            let offset = sticky_offset(el, context.scroll_y, context.container, context.viewport);
+            context.scroll_y - offset
+        }
+        _ => context.scroll_y,
+    };
+    //transforms go around the middle of the border box.
+    let transform = match transform(el) {
+        Some(matrix) => {
+            let border_box = el.layout_info.border_box();
+            let origin = (
+                border_box.x + border_box.width / 2.,
+                border_box.y - scroll_y + border_box.height / 2.,
+            );
+            context.transform
+                * Affine::translate(origin)
+                * Affine::new(matrix)
+                * Affine::translate((-origin.0, -origin.1))
+        }
+        None => context.transform,
+    };
+    let container = match !is_inline_box(el) {
+        true => (el.layout_info.y, el.layout_info.y + el.layout_info.height),
+        false => context.container,
+    };
+    let clips = match el.position() {
+        Position::Absolute => context.clips[..context.containing_clips].to_vec(),
+        Position::Fixed => vec![],
+        _ => context.clips.clone(),
+    };
+    Context {
+        scroll_y,
+        transform,
+        container,
+        containing_clips: context.containing_clips.min(clips.len()),
+        clips,
+        ..*context
+    }
+}
+
+//the context for what's in a box, which gets clipped to it if its overflow isn't visible.
+fn inside<'a>(el: &'a Element, context: &Context<'a>) -> Context<'a> {
+    let mut context = context.clone();
+    //it doesn't do anything for inlines, which could be split over lines.
+    if !is_inline_box(el)
+        && matches!(
+            el.css.overflow,
+            CSSValue::Value(Overflow::Hidden | Overflow::Clip | Overflow::Scroll | Overflow::Auto)
+        )
+    {
+        context.clips.push(Clip {
+            el,
+            scroll_y: context.scroll_y,
+            transform: context.transform,
+        });
+    }
+    if el.position() != Position::Static {
+        context.containing_clips = context.clips.len();
+    }
+    context
+}
+
+//an inline that isn't atomic, whose content goes on its container's lines.
+fn is_inline_box(el: &Element) -> bool {
+    matches!(el.display().outside, DisplayOutside::Inline) && !is_atomic(el)
+}
+
+fn creates_stacking_context(el: &Element) -> bool {
+    z_index(el).is_some()
+        || matches!(el.position(), Position::Fixed | Position::Sticky)
+        || opacity(el) < 1.
+        || transform(el).is_some()
+        || matches!(el.css.isolation, CSSValue::Value(Isolation::Isolate))
+}
+
+//only positioned boxes get one.
+fn z_index(el: &Element) -> Option<i32> {
+    match (el.position(), &el.css.z_index) {
+        (Position::Static, _) => None,
+        (_, CSSValue::Value(ZIndex::Integer(z))) => Some(*z),
+        _ => None,
+    }
+}
+
+fn opacity(el: &Element) -> f64 {
+    match el.css.opacity {
+        CSSValue::Value(opacity) => opacity.value,
+        _ => 1.,
+    }
+}
+
+//inline boxes can't be transformed, since they might be split up over lines.
+fn transform(el: &Element) -> Option<[f64; 6]> {
+    match &el.css.transform {
+        CSSValue::Value(transform) if !transform.functions.is_empty() && !is_inline_box(el) => {
+            let border_box = el.layout_info.border_box();
+            Some(transform.matrix(border_box.width, border_box.height))
+        }
+        _ => None,
+    }
+}
+
+//how far a sticky box gets pushed down to stay its top inset from the top of the viewport, or up
+//to stay its bottom inset from the bottom, without leaving its container. percentages are of the
+//viewport's height.
+fn sticky_offset(el: &Element, scroll_y: f64, container: (f64, f64), viewport: Viewport) -> f64 {
+    let height = viewport.height as f64;
+    let inset = |d: &CSSValue<Dimensionality>| match d {
+        CSSValue::Value(d) => d.resolve(Some(height)),
+        _ => None,
+    };
+    let layout_info = el.layout_info;
+    let margin_box = layout_info.border_box().expand(layout_info.margin);
+    let (y, size) = (margin_box.y, margin_box.height);
+    let mut stuck = y;
+    if let Some(top) = inset(&el.css.top) {
+        stuck = stuck.max(scroll_y + top).min(container.1 - size).max(y);
+    }
+    if let Some(bottom) = inset(&el.css.bottom) {
+        stuck = stuck
+            .min(scroll_y + height - bottom - size)
+            .max(container.0);
+    }
+    stuck - y
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::{Point, Shape};
+
+    use super::*;
+    use crate::{
+        context::Viewport,
+        layout::test_util::{find, laid_out},
+        renderer::decoration,
+    };
+
+    #[test]
+    fn test_painting_order() {
+        let document = laid_out(
+            "<b style=\"position: relative; z-index: 2\">a</b>\
+             <i style=\"position: absolute; z-index: -1\">b</i>\
+             <em style=\"opacity: 0.5\">c</em><p>d</p><u style=\"float: left\">f</u>\
+             <span style=\"position: relative\">e</span>",
+        );
+        let items = build(&document.children, 0., Viewport::new(800, 600));
+        let order = items
+            .iter()
+            .filter_map(|item| match item.kind {
+                ItemKind::Box(el) => Some(el.tag_name.clone()),
+                ItemKind::Text(text) => Some(format!("'{}'", text.text)),
+                ItemKind::PushOpacity(_) => Some(String::from("push")),
+                ItemKind::PopOpacity => Some(String::from("pop")),
+                ItemKind::Outline(_) => None,
+            })
+            .collect::<Vec<_>>();
+        //negative z-indexes go under the body's background, blocks and then floats go before
+        //what's on their lines, and positioned ones go on top in z-index order.
+        assert_eq!(
+            order,
+            [
+                "html", "i", "'b'", "body", "p", "u", "'f'", "'d'", "push", "em", "'c'", "pop",
+                "span", "'e'", "b", "'a'"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_stacking_contexts() {
+        let document = laid_out(
+            "<p style=\"z-index: 5\">a</p>\
+             <b style=\"position: relative; z-index: 1\">b<i style=\"position: absolute; \
+             z-index: -1\">c</i></b><em style=\"position: relative; z-index: 1\">d</em>",
+        );
+        let items = build(&document.children, 0., Viewport::new(800, 600));
+        let order = items
+            .iter()
+            .filter_map(|item| match item.kind {
+                ItemKind::Box(el) => Some(el.tag_name.clone()),
+                ItemKind::Text(text) => Some(format!("'{}'", text.text)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        //z-index does nothing for a static box, a negative one only goes under the rest of
+        //its own stacking context, and equal ones stay in tree order.
+        assert_eq!(
+            order,
+            ["html", "body", "p", "'a'", "b", "i", "'c'", "'b'", "em", "'d'"]
+        );
+    }
+
+    #[test]
+    fn test_sticky_stays_in_its_container() {
+        let document = laid_out(
+            "<p style=\"height: 100px\"><span style=\"display: block; position: sticky; \
+             top: 0px; height: 20px\"></span></p>\
+             <p style=\"height: 30px\"><em style=\"display: block; position: sticky; top: 0px; \
+             height: 50px\"></em></p>",
+        );
+        //where it ends up on the screen after scrolling that far.
+        let painted_at = |tag: &str, scroll_y| {
+            build(&document.children, scroll_y, Viewport::new(800, 600))
+                .iter()
+                .find_map(|item| match item.kind {
+                    ItemKind::Box(el) if el.tag_name == tag => {
+                        Some(el.layout_info.y - item.scroll_y)
+                    }
+                    _ => None,
+                })
+                .unwrap()
+        };
+        let span = find(&document.children, "span").unwrap();
+        let p = find(&document.children, "p").unwrap();
+        assert_eq!(painted_at("span", 0.), span.layout_info.y);
+        //stuck to the top of the viewport, until its container's bottom pushes it back up.
+        assert_eq!(painted_at("span", 40.), 0.);
+        assert_eq!(
+            painted_at("span", 1000.),
+            p.layout_info.y + 100. - 20. - 1000.
+        );
+        //one taller than its container has nowhere to go, so it just scrolls.
+        let em = find(&document.children, "em").unwrap();
+        assert_eq!(painted_at("em", 40.), em.layout_info.y - 40.);
+    }
+
+    #[test]
+    fn test_overflow_clips() {
+        let document = laid_out(
+            "<p style=\"width: auto; overflow: hidden; position: relative; height: 40px; \
+             padding-top: 5px; padding-left: 5px; border: 10px solid; border-radius: 30px\">\
+             <b>a</b><i style=\"position: fixed\">b</i><em style=\"position: absolute\">c</em></p>\
+             <center style=\"overflow: hidden\"><u style=\"position: absolute\">d</u></center>",
+        );
+        let items = build(&document.children, 0., Viewport::new(800, 600));
+        let clipped_by = |tag: &str| {
+            items
+                .iter()
+                .find_map(|item| match item.kind {
+                    ItemKind::Box(el) if el.tag_name == tag => Some(
+                        item.clips
+                            .iter()
+                            .map(|clip| clip.el.tag_name.clone())
+                            .collect::<Vec<_>>(),
+                    ),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        //a box's overflow only cuts off what's in it, and only what it contains: a fixed box
+        //escapes everything, and an absolute one everything under its containing block.
+        assert!(clipped_by("p").is_empty());
+        assert_eq!(clipped_by("b"), ["p"]);
+        assert!(clipped_by("i").is_empty());
+        assert_eq!(clipped_by("em"), ["p"]);
+        assert!(clipped_by("u").is_empty());
+
+        //the padding box, with the corners rounded by what's left of the radius inside the border.
+        let p = find(&document.children, "p").unwrap();
+        let path = decoration::padding_box_path(&p.layout_info, &p.css, 0.);
+        let padding_box = p.layout_info.padding_box();
+        let (x, y) = (padding_box.x as f64, padding_box.y as f64);
+        let bounds = path.bounding_box().round();
+        assert_eq!((bounds.x0, bounds.y0), (x, y));
+        assert_eq!(
+            (bounds.width(), bounds.height()),
+            (padding_box.width as f64, padding_box.height as f64)
+        );
+        assert!(!path.contains(Point::new(x + 1., y + 1.)));
+        assert!(path.contains(Point::new(x + 10., y + 10.)));
+        assert!(path.contains(Point::new(x + 20., y + 1.)));
+    }
+}
@@ -1,14 +1,19 @@
+pub mod decoration;
+pub mod display_list;
+
 use vello::{
     glyph::Glyph,
     kurbo::{Affine, Point, Rect, Shape, Stroke},
-    peniko::{Blob, BrushRef, Color, ColorStop, Font, Gradient},
+    peniko::{Blob, BrushRef, Color, ColorStop, Font, Gradient, Mix},
     SceneBuilder,
 };
 
+use self::display_list::{Clip, ItemKind};
 use crate::{
     context::Viewport,
     dom::{Element, Node},
-    parser::css::{properties::Colour, CSSValue, Numeric},
+    layout::LayoutInfo,
+    parser::css::Numeric,
 };
 
 #[derive(Debug, Default)]
@@ -23,65 +28,146 @@ impl PageRenderer {
         last_width: f64,
         render_info: RenderInfo,
     ) {
-        for node in nodes {
-            match node {
-                Node::Element(el) => {
-                    if el.layout_info.y + el.layout_info.height < render_info.scroll_y
-                        || el.layout_info.y > render_info.scroll_y + viewport.height as f64
-                    {
-                        continue;
+        let screen = Rect::new(0., 0., viewport.width as f64, viewport.height as f64);
+        let mut clips = vec![];
+        for item in display_list::build(nodes, render_info.scroll_y, viewport) {
+            let (scroll_y, transform) = (item.scroll_y, item.transform);
+            match item.kind {
+                ItemKind::PushOpacity(_) | ItemKind::PopOpacity => {
+                    set_clips(builder, &mut clips, &[])
+                }
+                _ => set_clips(builder, &mut clips, &item.clips),
+            }
+            match item.kind {
+                ItemKind::Box(el) => self.render_box(viewport, el, builder, scroll_y, transform),
+                ItemKind::Outline(el) => {
+                    //outlines go over everything, children included.
+                    for layout_info in boxes(el) {
+                        let border_box = decoration::rect(layout_info.border_box(), scroll_y);
+                        let radii = decoration::corner_radii(&el.css, border_box);
+                        decoration::draw_outline(builder, transform, &el.css, border_box, radii);
                     }
-                    let color = if let CSSValue::Value(c) = el.css.background_color {
-                        c.real
-                    } else {
-                        Colour::default().real
-                    };
-                    let shmop = el.layout_info.expand(el.layout_info.padding);
-                    builder.fill(
-                        vello::peniko::Fill::NonZero,
-                        Affine::IDENTITY,
-                        BrushRef::Solid(Color::rgb8(color.red, color.green, color.blue)),
-                        Some(Affine::IDENTITY),
-                        &Rect::new(
-                            shmop.x,
-                            shmop.y - render_info.scroll_y,
-                            shmop.x + shmop.width,
-                            shmop.y - render_info.scroll_y + shmop.height,
-                        ),
-                    );
-                    self.render(viewport, &el.children, builder, last_width, render_info);
                 }
-                Node::LaidoutText(text) => {
-                    let font_blob = Blob::new(text.font.copy_font_data().unwrap());
-                    let font = Font::new(font_blob, 0);
-                    let colour = text.colour.real;
-                    let mut text_builder = builder.draw_glyphs(&font);
-                    let mut text_builder = if text.axes.is_some() {
-                        text_builder.normalized_coords(text.axes.as_ref().unwrap().as_slice())
-                    } else {
-                        text_builder
-                    };
+                //one draw for each face the glyphs came from. the variation axes were worked out
+                //for the primary font, so the others are left at their defaults.
+                ItemKind::Text(text) => {
+                    for face in 0..text.font.face_count() {
+                        let glyphs = text
+                            .glyphs
+                            .iter()
+                            .filter(|v| v.glyph.face == face)
+                            .collect::<Vec<_>>();
+                        if glyphs.is_empty() {
+                            continue;
+                        }
+                        let shared = text.font.font(face);
+                        let font_blob = Blob::new(shared.data().clone());
+                        let font = Font::new(font_blob, shared.index());
+                        let colour = text.colour.real;
+                        let mut text_builder = builder.draw_glyphs(&font).transform(transform);
+                        let mut text_builder = match text.axes {
+                            Some(ref axes) if face == text.font.primary() => {
+                                text_builder.normalized_coords(axes.as_slice())
+                            }
+                            _ => text_builder,
+                        };
 
-                    text_builder
-                        .font_size(text.font_size as f32)
-                        .brush(BrushRef::Solid(Color::rgba8(
-                            colour.red,
-                            colour.green,
-                            colour.blue,
-                            colour.alpha,
-                        )))
-                        .draw(
-                            vello::peniko::Fill::NonZero,
-                            text.glyphs.iter().map(|v| Glyph {
-                                id: v.glyph.id as u32,
-                                x: v.x as f32,
-                                y: (v.y - render_info.scroll_y) as f32,
-                            }),
-                        );
+                        text_builder
+                            .font_size(text.font_size as f32)
+                            .brush(BrushRef::Solid(Color::rgba8(
+                                colour.red,
+                                colour.green,
+                                colour.blue,
+                                colour.alpha,
+                            )))
+                            .draw(
+                                vello::peniko::Fill::NonZero,
+                                glyphs.into_iter().map(|v| Glyph {
+                                    id: v.glyph.id as u32,
+                                    x: v.x as f32,
+                                    y: (v.y - scroll_y) as f32,
+                                }),
+                            );
+                    }
                 }
-                _ => {}
+                ItemKind::PushOpacity(alpha) => {
+                    builder.push_layer(Mix::Normal, alpha, Affine::IDENTITY, &screen)
+                }
+                ItemKind::PopOpacity => builder.pop_layer(),
             }
         }
+        set_clips(builder, &mut clips, &[]);
+    }
+
+    //its shadows, background and border. what's in it and its outline are items of their own.
+    fn render_box(
+        &mut self,
+        viewport: Viewport,
+        el: &Element,
+        builder: &mut SceneBuilder,
+        scroll_y: f64,
+        transform: Affine,
+    ) {
+        //inlines can be on lines anywhere, and transformed boxes could have been moved anywhere,
+        //so they're never skipped.
+        if el.fragments.is_empty()
+            && transform == Affine::IDENTITY
+            && (el.layout_info.y + el.layout_info.height < scroll_y
+                || el.layout_info.y > scroll_y + viewport.height as f64)
+        {
+            return;
+        }
+        let background = el.css.background_color.unwrap();
+        let shadows = el.css.box_shadow.unwrap().shadows;
+        for layout_info in boxes(el) {
+            let border_box = decoration::rect(layout_info.border_box(), scroll_y);
+            let radii = decoration::corner_radii(&el.css, border_box);
+            let (border, shadows) = (layout_info.border, shadows.as_slice());
+            decoration::draw_shadows(
+                builder, transform, shadows, false, border_box, radii, border,
+            );
+            //backgrounds go under the border too.
+            if background.real.alpha > 0 {
+                builder.fill(
+                    vello::peniko::Fill::NonZero,
+                    transform,
+                    BrushRef::Solid(decoration::colour(background)),
+                    Some(Affine::IDENTITY),
+                    &decoration::rounded_rect(border_box, radii),
+                );
+            }
+            decoration::draw_shadows(builder, transform, shadows, true, border_box, radii, border);
+            let edges = decoration::border_edges(&el.css, layout_info);
+            decoration::draw_edges(builder, transform, border_box, radii, edges);
+        }
+    }
+}
+
+//pops the clip layers that don't apply any more and pushes the ones that do now. opacity layers
+//only get pushed and popped with no clips on, so the two always nest.
+fn set_clips<'a>(builder: &mut SceneBuilder, pushed: &mut Vec<Clip<'a>>, wanted: &[Clip<'a>]) {
+    let common = pushed
+        .iter()
+        .zip(wanted)
+        .take_while(|(pushed, wanted)| pushed == wanted)
+        .count();
+    for _ in common..pushed.len() {
+        builder.pop_layer();
+    }
+    pushed.truncate(common);
+    for clip in &wanted[common..] {
+        let layout_info = &clip.el.layout_info;
+        let shape = decoration::padding_box_path(layout_info, &clip.el.css, clip.scroll_y);
+        builder.push_layer(Mix::Clip, 1., clip.transform, &shape);
+        pushed.push(*clip);
+    }
+}
+
+//an inline's fragments, or the one box everything else has.
+fn boxes(el: &Element) -> &[LayoutInfo] {
+    match el.fragments.is_empty() {
+        true => std::slice::from_ref(&el.layout_info),
+        false => el.fragments.as_slice(),
     }
 }
 
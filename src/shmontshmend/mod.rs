@@ -28,8 +28,9 @@ impl Frontend {
         let mut ctx = RenderContext::new().unwrap();
         let size = window.inner_size();
         context.resize(size.width as usize, size.height as usize);
+        context.set_scale_factor(window.scale_factor());
         context.load().await;
-        context.go();
+        context.go().await;
         let mut surface = ctx
             .create_surface(&window, size.width, size.height)
             .await
@@ -64,6 +65,12 @@ impl Frontend {
                             render_info.scroll_y -= pos.y as f64 * window.scale_factor();
                         }
                     },
+                    WindowEvent::Resized(size) => {
+                        context.resize(size.width as usize, size.height as usize);
+                    }
+                    WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                        context.set_scale_factor(scale_factor);
+                    }
                     _ => {}
                 },
                 Event::MainEventsCleared => {